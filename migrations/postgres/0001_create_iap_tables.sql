@@ -0,0 +1,27 @@
+-- Tables used by 'integrations::postgres::PostgresStore'. Safe to apply more
+-- than once. Can also be copied into an application's own migrations.
+
+-- Notifications claimed / handled by 'WebhookHandler' (dedupe store). Rows
+-- past 'expires_at' are ignored, and can be removed with
+-- 'PostgresStore::delete_expired_notifications'.
+CREATE TABLE IF NOT EXISTS iap_notification_dedupe (
+    notification_id TEXT PRIMARY KEY,
+    completed BOOLEAN NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS iap_notification_dedupe_expires_at_idx
+    ON iap_notification_dedupe (expires_at);
+
+-- Latest known state of each purchase (entitlement store).
+CREATE TABLE IF NOT EXISTS iap_entitlements (
+    platform TEXT NOT NULL,
+    purchase_id TEXT NOT NULL,
+    product_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    is_active BOOLEAN NOT NULL,
+    is_sandbox BOOLEAN NOT NULL,
+    expires_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (platform, purchase_id)
+);
@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+
+use crate::{capture::CapturedPayload, error_observer::IapPlatform};
+
+/// Hook retaining the original (signed) notification bodies handled by
+/// 'WebhookHandler', ex. for compliance. Registered through
+/// 'WebhookHandler::archive_sink'.
+///
+/// Notifications are archived once verified and parsed, before the
+/// 'NotificationCallback' runs. If archiving fails, the notification is
+/// reported as a retryable failure (without running the callback), so that the
+/// store redelivers it. Duplicates skipped by the dedupe store are not archived
+/// again.
+///
+/// See 'integrations::s3' for an S3-backed implementation.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ArchiveSink: Send + Sync {
+    async fn archive(&self, notification: &ArchivedNotification) -> Result<(), ServerError>;
+}
+
+/// A notification, as seen by an 'ArchiveSink'.
+#[derive(Debug, Clone)]
+pub struct ArchivedNotification {
+    pub platform: IapPlatform,
+    /// Same as 'IapUpdateNotification::notification_id' of the parsed
+    /// notification.
+    pub notification_id: String,
+    pub received_at: DateTime<Utc>,
+    /// The POST body exactly as received, including signatures.
+    pub body: String,
+    /// The decoded payloads of the notification (decoded JWS payloads, and
+    /// for Google, the body with the Pub/Sub message data decoded in place),
+    /// redacted as for a 'PayloadCapture'.
+    pub decoded_payloads: Vec<CapturedPayload>,
+}
+
+/// 'ArchiveSink' which discards notifications, ex. to disable archival
+/// depending on configuration without changing the handler's setup.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopArchiveSink;
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ArchiveSink for NoopArchiveSink {
+    async fn archive(&self, _notification: &ArchivedNotification) -> Result<(), ServerError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+//! Debugging tool for on-call investigation of store issues.
+//!
+//! Credentials are read from the environment (see 'Credentials'), so they do
+//! not end up in shell history.
+
+use std::{fs, path::PathBuf};
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine as _};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use fractic_iap::{
+    domain::entities::{
+        iap_product_id::{IapConsumableId, IapNonConsumableId, IapSubscriptionId},
+        iap_purchase_id::{AppleTransactionId, GooglePurchaseToken, IapPurchaseId},
+    },
+    util::IapUtil,
+};
+use fractic_server_error::ServerError;
+
+#[derive(Parser)]
+#[command(
+    name = "iap-cli",
+    about = "Debugging tool for App Store / Google Play purchases."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Verify a purchase and print its details.
+    Verify {
+        #[arg(value_enum)]
+        platform: Platform,
+        #[arg(value_enum)]
+        product_type: ProductType,
+        /// Product ID (SKU) as configured in the store.
+        product_id: String,
+        /// Apple transaction ID or Google purchase token.
+        purchase_id: String,
+        /// Also fetch price information.
+        #[arg(long)]
+        include_price_info: bool,
+        /// Print details even if the purchase is no longer active.
+        #[arg(long)]
+        allow_inactive: bool,
+        #[command(flatten)]
+        credentials: Credentials,
+    },
+    /// Decode a signed JWS (ex. an Apple signedTransactionInfo) and print its
+    /// header and payload, WITHOUT verifying the signature.
+    DecodeJws {
+        /// The JWS, or '-' to read it from stdin.
+        jws: String,
+    },
+    /// Request a TEST server-to-server notification from Apple.
+    AppleTestNotification {
+        #[arg(long)]
+        sandbox: bool,
+        #[command(flatten)]
+        credentials: Credentials,
+    },
+    /// Verify and parse a notification body saved to a file, and print the
+    /// parsed notification.
+    ParseNotification {
+        #[arg(value_enum)]
+        platform: Platform,
+        /// File containing the raw POST body of the notification.
+        file: PathBuf,
+        /// Authorization header sent with the Google Pub/Sub push request
+        /// (required for Google).
+        #[arg(long)]
+        authorization: Option<String>,
+        #[command(flatten)]
+        credentials: Credentials,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Platform {
+    Apple,
+    Google,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ProductType {
+    Consumable,
+    NonConsumable,
+    Subscription,
+}
+
+#[derive(Args)]
+struct Credentials {
+    #[arg(long, env = "IAP_APPLICATION_ID")]
+    application_id: String,
+    #[arg(long, env = "IAP_EXPECTED_AUD")]
+    expected_aud: String,
+    #[arg(long, env = "APPLE_API_KEY", hide_env_values = true)]
+    apple_api_key: String,
+    #[arg(long, env = "APPLE_KEY_ID")]
+    apple_key_id: String,
+    #[arg(long, env = "APPLE_ISSUER_ID")]
+    apple_issuer_id: String,
+    #[arg(long, env = "GOOGLE_API_KEY", hide_env_values = true)]
+    google_api_key: String,
+}
+
+impl Credentials {
+    async fn build(&self) -> Result<IapUtil, ServerError> {
+        IapUtil::from_values(
+            &self.application_id,
+            &self.expected_aud,
+            &self.apple_api_key,
+            &self.apple_key_id,
+            &self.apple_issuer_id,
+            &self.google_api_key,
+        )
+        .await
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ServerError> {
+    match Cli::parse().command {
+        Command::Verify {
+            platform,
+            product_type,
+            product_id,
+            purchase_id,
+            include_price_info,
+            allow_inactive,
+            credentials,
+        } => {
+            let iap_util = credentials.build().await?;
+            let purchase_id = match platform {
+                Platform::Apple => {
+                    IapPurchaseId::AppStoreTransactionId(AppleTransactionId::new(purchase_id)?)
+                }
+                Platform::Google => {
+                    IapPurchaseId::GooglePlayPurchaseToken(GooglePurchaseToken::new(purchase_id)?)
+                }
+            };
+            macro_rules! verify {
+                ($product_id:expr) => {
+                    if allow_inactive {
+                        println!(
+                            "{:#?}",
+                            iap_util
+                                .get_details_allow_inactive(
+                                    $product_id,
+                                    purchase_id,
+                                    include_price_info
+                                )
+                                .await?
+                        )
+                    } else {
+                        println!(
+                            "{:#?}",
+                            iap_util
+                                .verify_and_get_details(
+                                    $product_id,
+                                    purchase_id,
+                                    include_price_info
+                                )
+                                .await?
+                        )
+                    }
+                };
+            }
+            match product_type {
+                ProductType::Consumable => verify!(IapConsumableId(product_id)),
+                ProductType::NonConsumable => verify!(IapNonConsumableId(product_id)),
+                ProductType::Subscription => verify!(IapSubscriptionId(product_id)),
+            }
+        }
+        Command::DecodeJws { jws } => {
+            let jws = if jws == "-" {
+                std::io::read_to_string(std::io::stdin()).unwrap_or_default()
+            } else {
+                jws
+            };
+            let mut parts = jws.trim().split('.');
+            for label in ["Header", "Payload"] {
+                println!("{label}:");
+                match parts.next().map(|part| BASE64_URL_SAFE_NO_PAD.decode(part)) {
+                    Some(Ok(bytes)) => match serde_json::from_slice::<serde_json::Value>(&bytes) {
+                        Ok(json) => println!("{json:#}"),
+                        Err(_) => println!("{}", String::from_utf8_lossy(&bytes)),
+                    },
+                    Some(Err(e)) => println!("<invalid base64: {e}>"),
+                    None => println!("<missing>"),
+                }
+            }
+        }
+        Command::AppleTestNotification {
+            sandbox,
+            credentials,
+        } => {
+            let iap_util = credentials.build().await?;
+            let token = iap_util.request_apple_test_notification(sandbox).await?;
+            println!("Test notification token: {token}");
+        }
+        Command::ParseNotification {
+            platform,
+            file,
+            authorization,
+            credentials,
+        } => {
+            let body = fs::read_to_string(&file).unwrap_or_else(|e| {
+                eprintln!("Failed to read '{}': {e}", file.display());
+                std::process::exit(1);
+            });
+            let iap_util = credentials.build().await?;
+            let notification = match platform {
+                Platform::Apple => iap_util.parse_apple_notification(&body).await?,
+                Platform::Google => {
+                    let Some(authorization) = authorization else {
+                        eprintln!("--authorization is required for Google notifications.");
+                        std::process::exit(1);
+                    };
+                    iap_util
+                        .parse_google_notification(&authorization, &body)
+                        .await?
+                }
+            };
+            println!("{notification:#?}");
+        }
+    }
+    Ok(())
+}
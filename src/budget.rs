@@ -0,0 +1,111 @@
+use std::{cell::RefCell, collections::HashMap, future::Future, sync::Arc};
+
+use futures::future::{select, Either};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::scoped::{current, scope};
+
+/// Kind of work a platform API callout is made for. When a concurrency limit
+/// is set, slots can be reserved per class (see
+/// 'IapUtilBuilder::reserve_callouts'), so that ex. a backfill job can not
+/// starve live purchase verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CalloutClass {
+    /// Verification of purchases (ex. 'verify_and_get_details', 'consume').
+    /// The class used when none is set.
+    Verification,
+    /// Callouts made while parsing store notifications (ex. fetching the
+    /// purchase a Google RTDN refers to).
+    Notification,
+    /// Bulk or background work, such as backfills and reconciliation jobs.
+    Reconciliation,
+}
+
+thread_local! {
+    static CALLOUT_CLASS: RefCell<Option<CalloutClass>> = const { RefCell::new(None) };
+}
+
+/// Run 'future' with its platform API callouts counted against the given
+/// class.
+///
+/// ```ignore
+/// with_callout_class(CalloutClass::Reconciliation, async {
+///     for purchase_id in backlog {
+///         iap_util.get_details_allow_inactive(product_id, purchase_id, false).await?;
+///     }
+///     Ok(())
+/// })
+/// .await?;
+/// ```
+///
+/// NOTE: Like 'with_correlation_id', the class is scoped to the future
+/// itself, so tasks spawned from it do not inherit it.
+pub async fn with_callout_class<F: Future>(class: CalloutClass, future: F) -> F::Output {
+    scope(&CALLOUT_CLASS, class, future).await
+}
+
+/// Class of the enclosing 'with_callout_class' scope, or
+/// 'CalloutClass::Verification' if there is none.
+pub fn current_callout_class() -> CalloutClass {
+    current(&CALLOUT_CLASS).unwrap_or(CalloutClass::Verification)
+}
+
+/// Same as 'with_callout_class', but keeps the class of an enclosing scope
+/// if there is one.
+pub(crate) async fn with_default_callout_class<F: Future>(
+    class: CalloutClass,
+    future: F,
+) -> F::Output {
+    match current(&CALLOUT_CLASS) {
+        Some(_) => future.await,
+        None => with_callout_class(class, future).await,
+    }
+}
+
+/// Concurrency limit, partitioned into slots reserved for specific classes
+/// and a pool shared by all classes.
+pub(crate) struct CalloutBudget {
+    shared: Arc<Semaphore>,
+    reserved: HashMap<CalloutClass, Arc<Semaphore>>,
+}
+
+impl CalloutBudget {
+    /// Reservations are capped so that at least one slot of 'limit' remains
+    /// shared, and classes without a reservation can always make progress.
+    pub(crate) fn new(limit: usize, reservations: &[(CalloutClass, usize)]) -> Self {
+        let limit = limit.max(1);
+        let mut remaining = limit - 1;
+        let mut reserved = HashMap::new();
+        for (class, slots) in reservations {
+            let slots = (*slots).min(remaining);
+            remaining -= slots;
+            if slots > 0 {
+                reserved.insert(*class, Arc::new(Semaphore::new(slots)));
+            }
+        }
+        Self {
+            shared: Arc::new(Semaphore::new(remaining + 1)),
+            reserved,
+        }
+    }
+
+    /// Waits for a slot: one of the class's reserved slots if available,
+    /// otherwise whichever of a reserved or shared slot frees up first.
+    pub(crate) async fn acquire(&self, class: CalloutClass) -> Option<OwnedSemaphorePermit> {
+        // The semaphores are never closed, so acquiring can not fail.
+        let Some(reserved) = self.reserved.get(&class) else {
+            return self.shared.clone().acquire_owned().await.ok();
+        };
+        if let Ok(permit) = reserved.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        match select(
+            Box::pin(reserved.clone().acquire_owned()),
+            Box::pin(self.shared.clone().acquire_owned()),
+        )
+        .await
+        {
+            Either::Left((permit, _)) | Either::Right((permit, _)) => permit.ok(),
+        }
+    }
+}
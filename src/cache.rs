@@ -0,0 +1,63 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use fractic_server_error::ServerError;
+use web_time::Instant;
+
+/// Key-value store backing the caches used by 'IapUtil' (verification
+/// results, and product metadata used for price info).
+///
+/// By default, caches are kept in memory. Implement this trait (or use an
+/// adapter from 'integrations') to share caches across instances, and
+/// register it through 'IapUtilBuilder::cache_store'. Caches are still only
+/// enabled if a TTL is configured for them (ex.
+/// 'IapUtilBuilder::verification_cache_ttl').
+///
+/// Values are JSON. Failures are treated as cache misses, so a store outage
+/// slows down verification, but does not break it.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait CacheStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, ServerError>;
+
+    /// Store the value, replacing any existing value, until the TTL passes.
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), ServerError>;
+
+    async fn delete(&self, key: &str) -> Result<(), ServerError>;
+}
+
+/// Default 'CacheStore', local to the process.
+#[derive(Default)]
+pub(crate) struct InMemoryCacheStore {
+    entries: Mutex<HashMap<String, (Instant, Duration, String)>>,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl CacheStore for InMemoryCacheStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, ServerError> {
+        let Ok(entries) = self.entries.lock() else {
+            return Ok(None);
+        };
+        Ok(entries
+            .get(key)
+            .filter(|(inserted_at, ttl, _)| inserted_at.elapsed() < *ttl)
+            .map(|(_, _, value)| value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), ServerError> {
+        let Ok(mut entries) = self.entries.lock() else {
+            return Ok(());
+        };
+        entries.retain(|_, (inserted_at, ttl, _)| inserted_at.elapsed() < *ttl);
+        entries.insert(key.to_owned(), (Instant::now(), ttl, value.to_owned()));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), ServerError> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+        Ok(())
+    }
+}
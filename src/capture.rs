@@ -0,0 +1,162 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::scoped::{current, scope};
+
+const REDACTED: &str = "[REDACTED]";
+
+type Collector = Arc<Mutex<Vec<CapturedPayload>>>;
+
+thread_local! {
+    static COLLECTOR: RefCell<Option<Collector>> = const { RefCell::new(None) };
+}
+
+/// Hook receiving the payloads processed by 'IapUtil' (raw notification
+/// bodies, decoded JWS payloads, and platform API responses), ex. to archive
+/// them so failed verifications can be investigated after the fact.
+///
+/// Captures are registered through 'IapUtilBuilder::payload_capture'. Payloads
+/// are captured as they are received, before being validated, so payloads
+/// which later fail verification are captured too.
+///
+/// Signatures and tokens are redacted before payloads are passed to the hook:
+/// JWS strings (whose decoded payloads are captured separately) and the values
+/// of any fields with "token" or "signature" in their name are replaced with
+/// "[REDACTED]". The customer's Google profile reported for Subscribe with
+/// Google subscriptions ('subscribeWithGoogleInfo') is redacted as well.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait PayloadCapture: Send + Sync {
+    async fn capture(&self, payload: &CapturedPayload);
+}
+
+/// A payload, as seen by a 'PayloadCapture'.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedPayload {
+    pub kind: CapturedPayloadKind,
+    /// Where the payload came from: the platform API function (ex.
+    /// "GetTransactionInfo"), or the notification type (ex.
+    /// "AppStoreServerNotification"). For decoded JWS payloads, the name of
+    /// the field containing the JWS is appended (ex.
+    /// "GetTransactionInfo.signedTransactionInfo").
+    pub source: String,
+    /// The redacted payload, as JSON. Responses which are not valid JSON (ex.
+    /// error pages) are passed through as-is.
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapturedPayloadKind {
+    /// POST body of a server notification. For Google notifications, the
+    /// base64-encoded Pub/Sub message data is decoded in place.
+    NotificationBody,
+    /// Decoded payload of a JWS signed by Apple.
+    DecodedJws,
+    /// Response body of a platform API callout (including error responses).
+    ApiResponse,
+}
+
+/// The registered captures, shared by the datasources.
+#[derive(Clone, Default)]
+pub(crate) struct PayloadCaptures(Vec<Arc<dyn PayloadCapture>>);
+
+impl PayloadCaptures {
+    pub(crate) fn new(captures: Vec<Arc<dyn PayloadCapture>>) -> Self {
+        Self(captures)
+    }
+
+    /// Whether payloads can be skipped: no captures are registered, and no
+    /// payloads are being collected (see 'collect_payloads').
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty() && current(&COLLECTOR).is_none()
+    }
+
+    /// Capture a raw payload, redacting it if it is JSON.
+    pub(crate) async fn capture(&self, kind: CapturedPayloadKind, source: &str, raw: &str) {
+        if self.is_empty() {
+            return;
+        }
+        match serde_json::from_str::<Value>(raw) {
+            Ok(value) => self.capture_value(kind, source, value).await,
+            Err(_) => self.dispatch(kind, source, raw.to_owned()).await,
+        }
+    }
+
+    pub(crate) async fn capture_value(
+        &self,
+        kind: CapturedPayloadKind,
+        source: &str,
+        mut value: Value,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+        redact(&mut value);
+        self.dispatch(kind, source, value.to_string()).await;
+    }
+
+    async fn dispatch(&self, kind: CapturedPayloadKind, source: &str, body: String) {
+        let payload = CapturedPayload {
+            kind,
+            source: source.to_owned(),
+            body,
+        };
+        if let Some(collector) = current(&COLLECTOR) {
+            if let Ok(mut collected) = collector.lock() {
+                collected.push(payload.clone());
+            }
+        }
+        for capture in &self.0 {
+            capture.capture(&payload).await;
+        }
+    }
+}
+
+/// Run 'future', also returning the (redacted) payloads processed while it
+/// ran, regardless of whether any 'PayloadCapture' is registered.
+pub(crate) async fn collect_payloads<F: Future>(future: F) -> (F::Output, Vec<CapturedPayload>) {
+    let collector = Collector::default();
+    let output = scope(&COLLECTOR, collector.clone(), future).await;
+    let collected = collector
+        .lock()
+        .map(|mut collected| std::mem::take(&mut *collected))
+        .unwrap_or_default();
+    (output, collected)
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if key.contains("token")
+                    || key.contains("signature")
+                    || key == "subscribewithgoogleinfo"
+                {
+                    *value = Value::String(REDACTED.to_owned());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(s) if is_jws(s) => *s = REDACTED.to_owned(),
+        _ => {}
+    }
+}
+
+/// Compact JWS serialization: three base64url segments, with a JSON header
+/// (which always starts with "eyJ", the encoding of '{"').
+fn is_jws(s: &str) -> bool {
+    s.starts_with("eyJ")
+        && s.split('.').count() == 3
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '='))
+}
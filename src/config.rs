@@ -0,0 +1,563 @@
+use std::{cell::RefCell, future::Future, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "app-store-connect")]
+use crate::constants::APP_STORE_CONNECT_BASE_URL;
+#[cfg(feature = "native")]
+use crate::constants::GOOGLE_JWK_URL;
+#[cfg(feature = "steam")]
+use crate::constants::STEAM_PARTNER_BASE_URL;
+#[cfg(feature = "microsoft-store")]
+use crate::constants::{MICROSOFT_LOGIN_BASE_URL, MICROSOFT_STORE_COLLECTIONS_BASE_URL};
+#[cfg(feature = "store-simulator")]
+use crate::data::datasources::http_client::HttpTransport;
+use crate::{
+    budget::CalloutClass,
+    cache::CacheStore,
+    capture::PayloadCapture,
+    constants::{APPLE_PRODUCTION_BASE_URL, APPLE_SANDBOX_BASE_URL, GOOGLE_PLAY_BASE_URL},
+    deny_list::DenyListStore,
+    error_observer::ErrorObserver,
+    interceptor::CalloutInterceptor,
+    scoped::{current, scope},
+    secrets::SecretString,
+    verifier::SignatureVerifier,
+};
+
+/// Behavioural settings shared across the repository and datasources. Set
+/// through 'IapUtilBuilder'.
+#[derive(Clone)]
+pub(crate) struct IapConfig {
+    /// Grace window added to subscription expiry times before a subscription
+    /// is considered expired.
+    pub(crate) expiry_leeway: chrono::Duration,
+    /// Which subscription states count as active.
+    pub(crate) active_policy: ActivePolicy,
+    /// Retry behaviour for transient failures of platform API callouts.
+    pub(crate) retry_policy: RetryPolicy,
+    /// Maximum number of simultaneous callouts to the App Store Server API /
+    /// Google Play Developer API. Unlimited if not set.
+    pub(crate) apple_max_concurrent_callouts: Option<usize>,
+    pub(crate) google_max_concurrent_callouts: Option<usize>,
+    /// Slots of each concurrency limit reserved for callouts of a class.
+    pub(crate) reserved_callouts: Vec<(CalloutClass, usize)>,
+    /// How long successful verification results are cached for. Caching is
+    /// disabled if not set.
+    pub(crate) verification_cache_ttl: Option<Duration>,
+    /// How long Google Play product metadata (used for price info) is cached
+    /// for. Caching is disabled if not set.
+    pub(crate) product_cache_ttl: Option<Duration>,
+    /// Store backing the caches. In-memory if not set.
+    pub(crate) cache_store: Option<Arc<dyn CacheStore>>,
+    /// Purchases rejected by verification regardless of their state.
+    pub(crate) deny_list: Option<Arc<dyn DenyListStore>>,
+    /// Hooks called for every platform API callout.
+    pub(crate) interceptors: Vec<Arc<dyn CalloutInterceptor>>,
+    /// Whether platform API credentials (Apple JWT, Google access token) are
+    /// prepared on first use, rather than when the instance is built.
+    pub(crate) lazy_credentials: bool,
+    /// Hooks called for every failed operation.
+    pub(crate) error_observers: Vec<Arc<dyn ErrorObserver>>,
+    /// Key of the purchase ID hashes in error contexts. Purchase IDs are not
+    /// hashed if not set.
+    pub(crate) purchase_id_hash_key: Option<SecretString>,
+    /// User-Agent sent with platform API callouts (reqwest's default if not
+    /// set).
+    pub(crate) user_agent: Option<String>,
+    /// Additional headers sent with every platform API callout, parsed when
+    /// the client is built.
+    pub(crate) headers: Vec<(String, String)>,
+    /// Header the current correlation ID is sent in with platform API
+    /// callouts. Not sent if not set.
+    pub(crate) correlation_id_header: Option<String>,
+    /// Hooks receiving (redacted) notification bodies, decoded JWS payloads
+    /// and platform API responses.
+    pub(crate) payload_captures: Vec<Arc<dyn PayloadCapture>>,
+    /// Whether the Google profile of Subscribe with Google subscribers is
+    /// included in 'PlatformExtras::SubscribeWithGoogle'.
+    pub(crate) expose_subscribe_with_google_profile: bool,
+    /// Proxy used for platform API callouts, if any.
+    #[cfg(feature = "native")]
+    pub(crate) proxy: Option<ProxyConfig>,
+    /// Additional trusted root certificates for platform API callouts.
+    #[cfg(feature = "native")]
+    pub(crate) root_certificates: Vec<RootCertificate>,
+    /// Whether the TLS backend's built-in root certificates are trusted.
+    #[cfg(feature = "native")]
+    pub(crate) tls_built_in_root_certs: bool,
+    #[cfg(feature = "native")]
+    pub(crate) tls_backend: TlsBackend,
+    /// App Store Server API environments used for lookups.
+    pub(crate) environment: Environment,
+    /// Base URLs of the App Store Server API (production, sandbox) and the
+    /// Google Play Developer API, without trailing slashes.
+    pub(crate) apple_production_base_url: String,
+    pub(crate) apple_sandbox_base_url: String,
+    pub(crate) google_play_base_url: String,
+    /// Credentials for looking up App Store product prices. Price lookups
+    /// through App Store Connect are unavailable if not set.
+    #[cfg(feature = "app-store-connect")]
+    pub(crate) app_store_connect_credentials: Option<AppStoreConnectCredentials>,
+    /// Base URL of the App Store Connect API, without trailing slash.
+    #[cfg(feature = "app-store-connect")]
+    pub(crate) app_store_connect_base_url: String,
+    /// Credentials for verifying Microsoft Store purchases. Microsoft Store
+    /// purchases are rejected if not set.
+    #[cfg(feature = "microsoft-store")]
+    pub(crate) microsoft_store_credentials: Option<MicrosoftStoreCredentials>,
+    /// Base URLs of the Microsoft Store collections API and of Azure AD (for
+    /// access tokens), without trailing slashes.
+    #[cfg(feature = "microsoft-store")]
+    pub(crate) microsoft_store_collections_base_url: String,
+    #[cfg(feature = "microsoft-store")]
+    pub(crate) microsoft_login_base_url: String,
+    /// Credentials for verifying Steam microtransactions. Steam orders are
+    /// rejected if not set.
+    #[cfg(feature = "steam")]
+    pub(crate) steam_credentials: Option<SteamCredentials>,
+    /// Whether Steam orders are looked up through the sandbox interface
+    /// (ISteamMicroTxnSandbox).
+    #[cfg(feature = "steam")]
+    pub(crate) steam_sandbox: bool,
+    /// Base URL of the Steam partner Web API, without trailing slash.
+    #[cfg(feature = "steam")]
+    pub(crate) steam_base_url: String,
+    /// Secret of the Paddle notification destination, used to verify webhook
+    /// signatures. Paddle notifications are rejected if not set.
+    #[cfg(feature = "paddle")]
+    pub(crate) paddle_webhook_secret: Option<SecretString>,
+    /// Whether Paddle notifications come from the sandbox environment.
+    #[cfg(feature = "paddle")]
+    pub(crate) paddle_sandbox: bool,
+    /// Signing secret of the Stripe webhook endpoint, used to verify event
+    /// signatures. Stripe events are rejected if not set.
+    #[cfg(feature = "stripe")]
+    pub(crate) stripe_webhook_secret: Option<SecretString>,
+    /// JWK set used to verify Google OIDC tokens (by the native signature
+    /// verifier).
+    #[cfg(feature = "native")]
+    pub(crate) google_jwk_url: String,
+    /// How long keys fetched from 'google_jwk_url' are cached for.
+    #[cfg(feature = "native")]
+    pub(crate) google_jwks_cache_ttl: Duration,
+    /// Pre-seeded JWK set (JSON), used instead of fetching keys.
+    #[cfg(feature = "native")]
+    pub(crate) google_jwks: Option<String>,
+    /// Overrides the default (native) signature verification primitives.
+    pub(crate) signature_verifier: Option<Arc<dyn SignatureVerifier>>,
+    /// Replaces the network for platform API callouts (used by the store
+    /// simulator).
+    #[cfg(feature = "store-simulator")]
+    pub(crate) transport: Option<Arc<dyn HttpTransport>>,
+}
+
+impl Default for IapConfig {
+    fn default() -> Self {
+        Self {
+            expiry_leeway: chrono::Duration::zero(),
+            active_policy: ActivePolicy::default(),
+            retry_policy: RetryPolicy::none(),
+            apple_max_concurrent_callouts: None,
+            google_max_concurrent_callouts: None,
+            reserved_callouts: Vec::new(),
+            verification_cache_ttl: None,
+            product_cache_ttl: None,
+            cache_store: None,
+            deny_list: None,
+            interceptors: Vec::new(),
+            lazy_credentials: false,
+            error_observers: Vec::new(),
+            purchase_id_hash_key: None,
+            user_agent: None,
+            headers: Vec::new(),
+            correlation_id_header: None,
+            payload_captures: Vec::new(),
+            expose_subscribe_with_google_profile: false,
+            #[cfg(feature = "native")]
+            proxy: None,
+            #[cfg(feature = "native")]
+            root_certificates: Vec::new(),
+            #[cfg(feature = "native")]
+            tls_built_in_root_certs: true,
+            #[cfg(feature = "native")]
+            tls_backend: TlsBackend::default(),
+            environment: Environment::default(),
+            apple_production_base_url: APPLE_PRODUCTION_BASE_URL.to_owned(),
+            apple_sandbox_base_url: APPLE_SANDBOX_BASE_URL.to_owned(),
+            google_play_base_url: GOOGLE_PLAY_BASE_URL.to_owned(),
+            #[cfg(feature = "app-store-connect")]
+            app_store_connect_credentials: None,
+            #[cfg(feature = "app-store-connect")]
+            app_store_connect_base_url: APP_STORE_CONNECT_BASE_URL.to_owned(),
+            #[cfg(feature = "microsoft-store")]
+            microsoft_store_credentials: None,
+            #[cfg(feature = "microsoft-store")]
+            microsoft_store_collections_base_url: MICROSOFT_STORE_COLLECTIONS_BASE_URL.to_owned(),
+            #[cfg(feature = "microsoft-store")]
+            microsoft_login_base_url: MICROSOFT_LOGIN_BASE_URL.to_owned(),
+            #[cfg(feature = "steam")]
+            steam_credentials: None,
+            #[cfg(feature = "steam")]
+            steam_sandbox: false,
+            #[cfg(feature = "steam")]
+            steam_base_url: STEAM_PARTNER_BASE_URL.to_owned(),
+            #[cfg(feature = "paddle")]
+            paddle_webhook_secret: None,
+            #[cfg(feature = "paddle")]
+            paddle_sandbox: false,
+            #[cfg(feature = "stripe")]
+            stripe_webhook_secret: None,
+            #[cfg(feature = "native")]
+            google_jwk_url: GOOGLE_JWK_URL.to_owned(),
+            #[cfg(feature = "native")]
+            google_jwks_cache_ttl: Duration::from_secs(300),
+            #[cfg(feature = "native")]
+            google_jwks: None,
+            signature_verifier: None,
+            #[cfg(feature = "store-simulator")]
+            transport: None,
+        }
+    }
+}
+
+impl IapConfig {
+    pub(crate) fn set_expiry_leeway(&mut self, leeway: Duration) {
+        self.expiry_leeway = chrono::Duration::from_std(leeway).unwrap_or(chrono::Duration::MAX);
+    }
+
+    /// Whether the given expiry time has passed, taking the configured leeway
+    /// into account.
+    pub(crate) fn is_expired(&self, expiry: DateTime<Utc>) -> bool {
+        expiry
+            .checked_add_signed(self.expiry_leeway)
+            .map(|expiry| self.has_passed(expiry))
+            .unwrap_or(false)
+    }
+
+    /// Whether the given time has passed, as of the evaluation time (see
+    /// 'with_evaluation_time').
+    pub(crate) fn has_passed(&self, time: DateTime<Utc>) -> bool {
+        time <= current_evaluation_time().unwrap_or_else(Utc::now)
+    }
+
+    /// Whether the given time (ex. of a purchase) is after the evaluation
+    /// time of an enclosing 'with_evaluation_time' scope. Always false
+    /// outside of such scopes, so that clock skew does not affect purchases
+    /// made just now.
+    pub(crate) fn is_after_evaluation_time(&self, time: DateTime<Utc>) -> bool {
+        current_evaluation_time().is_some_and(|evaluation_time| evaluation_time < time)
+    }
+}
+
+/// Team API key used to call the App Store Connect API, and the app whose
+/// products are looked up:
+/// https://developer.apple.com/documentation/appstoreconnectapi/creating-api-keys-for-app-store-connect-api
+#[cfg(feature = "app-store-connect")]
+#[derive(Clone)]
+pub(crate) struct AppStoreConnectCredentials {
+    pub(crate) api_key: SecretString,
+    pub(crate) key_id: String,
+    pub(crate) issuer_id: String,
+    /// Apple ID of the app (ex. '1234567890'), as shown in App Store Connect.
+    pub(crate) app_apple_id: String,
+}
+
+/// Azure AD application used to call the Microsoft Store collections API:
+/// https://learn.microsoft.com/en-us/windows/uwp/monetize/view-and-grant-products-from-a-service#step-1
+#[cfg(feature = "microsoft-store")]
+#[derive(Clone)]
+pub(crate) struct MicrosoftStoreCredentials {
+    pub(crate) tenant_id: String,
+    pub(crate) client_id: String,
+    pub(crate) client_secret: SecretString,
+}
+
+/// Steam app and publisher Web API key used to call ISteamMicroTxn:
+/// https://partner.steamgames.com/doc/webapi_overview/auth#publisher-keys
+#[cfg(feature = "steam")]
+#[derive(Clone)]
+pub(crate) struct SteamCredentials {
+    pub(crate) app_id: u32,
+    pub(crate) publisher_key: SecretString,
+}
+
+/// Retry behaviour for platform API callouts (App Store Server API, Google Play
+/// Developer API) which fail with a transient error.
+///
+/// Delays grow exponentially: the n-th retry waits 'base_delay * 2^(n-1)',
+/// capped at 'max_delay'. If 'jitter' is enabled, a random delay between zero
+/// and that value is used instead ("full jitter"), to avoid many instances
+/// retrying in lock-step.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first. A value of 1 disables
+    /// retries.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+    pub retry_on: RetryOn,
+}
+
+/// Which kinds of failures are considered transient, and therefore retried.
+#[derive(Debug, Clone)]
+pub struct RetryOn {
+    /// Responses with a 5xx status code.
+    pub server_errors: bool,
+    /// Responses with a 429 (Too Many Requests) status code.
+    pub too_many_requests: bool,
+    /// Failures to connect, or requests which timed out before a response was
+    /// received.
+    pub connect_errors: bool,
+}
+
+impl RetryPolicy {
+    /// Never retry (the default used by 'IapUtil').
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the given retry (1-indexed).
+    pub(crate) fn delay_for_retry(&self, retry: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        let capped = exponential.min(self.max_delay);
+        if self.jitter {
+            capped.mul_f64(rand::random::<f64>())
+        } else {
+            capped
+        }
+    }
+
+    pub(crate) fn should_retry_status(&self, status: reqwest::StatusCode) -> bool {
+        (self.retry_on.server_errors && status.is_server_error())
+            || (self.retry_on.too_many_requests && status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    pub(crate) fn should_retry_error(&self, error: &reqwest::Error) -> bool {
+        self.retry_on.connect_errors && (error.is_connect() || error.is_timeout())
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Up to 3 attempts, starting at a 200ms delay, retrying on all transient
+    /// failure kinds.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+            retry_on: RetryOn::default(),
+        }
+    }
+}
+
+impl Default for RetryOn {
+    fn default() -> Self {
+        Self {
+            server_errors: true,
+            too_many_requests: true,
+            connect_errors: true,
+        }
+    }
+}
+
+/// Proxy through which platform API callouts are sent.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// Proxy URL, ex. "http://proxy.internal:3128". Used for both HTTP and HTTPS
+    /// traffic.
+    pub url: String,
+    /// Credentials for proxies requiring basic authentication.
+    pub basic_auth: Option<(String, String)>,
+    /// Comma-separated list of hosts which should bypass the proxy, in the
+    /// same format as the 'NO_PROXY' environment variable.
+    pub no_proxy: Option<String>,
+}
+
+#[cfg(feature = "native")]
+impl ProxyConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            basic_auth: None,
+            no_proxy: None,
+        }
+    }
+
+    pub fn with_basic_auth(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.basic_auth = Some((username.into(), password.into()));
+        self
+    }
+
+    pub fn with_no_proxy(mut self, no_proxy: impl Into<String>) -> Self {
+        self.no_proxy = Some(no_proxy.into());
+        self
+    }
+}
+
+/// Which App Store Server API environments purchases are looked up in.
+///
+/// Google Play has no separate sandbox API (test purchases are served by the
+/// regular API), so this only affects App Store callouts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Environment {
+    /// Query production first, and fall back to the sandbox if that fails (as
+    /// recommended by Apple, since TestFlight and App Review purchases are
+    /// sandbox purchases made against production builds).
+    #[default]
+    ProductionWithSandboxFallback,
+    /// Only query production. Sandbox purchases are rejected as not found.
+    ProductionOnly,
+    /// Only query the sandbox (ex. for staging deployments), avoiding a failed
+    /// production callout for every verification.
+    SandboxOnly,
+}
+
+thread_local! {
+    static ENVIRONMENT: RefCell<Option<Environment>> = const { RefCell::new(None) };
+}
+
+/// Run 'future' with App Store callouts made in the given environment,
+/// instead of the one configured through 'IapUtilBuilder::environment'. Ex.
+/// for internal QA tools checking sandbox purchases with a production
+/// 'IapUtil':
+///
+/// ```ignore
+/// let details = with_environment(Environment::SandboxOnly, async {
+///     iap_util.verify_and_get_details(product_id, purchase_id, false).await
+/// })
+/// .await?;
+/// ```
+///
+/// Cached verification results are neither used nor stored within the
+/// scope, since they may have been verified in another environment.
+///
+/// NOTE: Like 'with_correlation_id', the environment is scoped to the future
+/// itself, so tasks spawned from it do not inherit it.
+pub async fn with_environment<F: Future>(environment: Environment, future: F) -> F::Output {
+    scope(&ENVIRONMENT, environment, future).await
+}
+
+/// Environment of the enclosing 'with_environment' scope, if any.
+pub fn current_environment() -> Option<Environment> {
+    current(&ENVIRONMENT)
+}
+
+thread_local! {
+    static EVALUATION_TIME: RefCell<Option<DateTime<Utc>>> = const { RefCell::new(None) };
+}
+
+/// Run 'future' with purchase details evaluated as of the given time instead
+/// of now, ex. for historical audits and backfills reconstructing whether a
+/// purchase was active on a given date (also when replaying archived
+/// notification bodies, see 'ArchiveSink'):
+///
+/// ```ignore
+/// let details = with_evaluation_time(audit_time, async {
+///     iap_util.get_details_allow_inactive(product_id, purchase_id, false).await
+/// })
+/// .await?;
+/// let was_active = details.is_active;
+/// ```
+///
+/// 'IapDetails::is_active' then accounts for expiry (including the
+/// configured leeway, and Google Play subscriptions which expired since) and
+/// App Store revocations as of that time, and purchases made after it are
+/// not active. Platforms only report the current
+/// state of purchases though, so states which are not timestamped (ex. a
+/// Google Play subscription being paused, or a pending purchase) are
+/// evaluated as currently reported.
+///
+/// Cached verification results are neither used nor stored within the
+/// scope. Like 'with_environment', the evaluation time is scoped to the
+/// future itself, so tasks spawned from it do not inherit it.
+pub async fn with_evaluation_time<F: Future>(time: DateTime<Utc>, future: F) -> F::Output {
+    scope(&EVALUATION_TIME, time, future).await
+}
+
+/// Evaluation time of the enclosing 'with_evaluation_time' scope, if any.
+pub fn current_evaluation_time() -> Option<DateTime<Utc>> {
+    current(&EVALUATION_TIME)
+}
+
+/// Which subscription states still grant access ('IapDetails::is_active'),
+/// for states where this is a product decision. Subscriptions which expired
+/// (or were revoked) are never active, and canceled subscriptions remain
+/// active until they expire.
+///
+/// By default, all of these states count as active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivePolicy {
+    /// Google Play and App Store subscriptions in a grace period (renewal
+    /// payment failed, and the store is retrying it). App Store grace
+    /// periods are only known if the renewal info was retrieved (see
+    /// 'SubscriptionDetails::grace_period_expiration_time').
+    pub grace_period: bool,
+    /// Google Play subscriptions on account hold (renewal payment failed
+    /// after the grace period, and Google is still retrying it).
+    pub account_hold: bool,
+    /// Google Play subscriptions paused by the customer.
+    pub paused: bool,
+    /// Paddle and Stripe subscriptions which are past due (renewal payment
+    /// failed, and is being retried).
+    pub past_due: bool,
+}
+
+impl ActivePolicy {
+    /// Only grant access to subscriptions which are paid up: none of the
+    /// states above count as active.
+    pub fn strict() -> Self {
+        Self {
+            grace_period: false,
+            account_hold: false,
+            paused: false,
+            past_due: false,
+        }
+    }
+}
+
+impl Default for ActivePolicy {
+    fn default() -> Self {
+        Self {
+            grace_period: true,
+            account_hold: true,
+            paused: true,
+            past_due: true,
+        }
+    }
+}
+
+/// TLS implementation used for platform API callouts.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TlsBackend {
+    /// Rustls, with the Mozilla root certificates built in.
+    #[default]
+    Rustls,
+    /// The platform's native TLS implementation (OpenSSL on Linux, Secure
+    /// Transport on macOS, SChannel on Windows). Requires the 'native-tls'
+    /// feature.
+    #[cfg(feature = "native-tls")]
+    NativeTls,
+}
+
+/// A root certificate added through 'IapUtilBuilder', parsed when the client
+/// is built.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub(crate) enum RootCertificate {
+    Pem(Vec<u8>),
+    Der(Vec<u8>),
+}
@@ -1 +1,21 @@
+#[cfg(feature = "native")]
 pub(crate) const GOOGLE_JWK_URL: &'static str = "https://www.googleapis.com/oauth2/v3/certs";
+
+pub(crate) const APPLE_PRODUCTION_BASE_URL: &str = "https://api.storekit.itunes.apple.com";
+pub(crate) const APPLE_SANDBOX_BASE_URL: &str = "https://api.storekit-sandbox.itunes.apple.com";
+pub(crate) const GOOGLE_PLAY_BASE_URL: &str = "https://androidpublisher.googleapis.com";
+#[cfg(feature = "app-store-connect")]
+pub(crate) const APP_STORE_CONNECT_BASE_URL: &str = "https://api.appstoreconnect.apple.com";
+#[cfg(feature = "microsoft-store")]
+pub(crate) const MICROSOFT_STORE_COLLECTIONS_BASE_URL: &str =
+    "https://collections.mp.microsoft.com";
+#[cfg(feature = "microsoft-store")]
+pub(crate) const MICROSOFT_LOGIN_BASE_URL: &str = "https://login.microsoftonline.com";
+#[cfg(feature = "steam")]
+pub(crate) const STEAM_PARTNER_BASE_URL: &str = "https://partner.steam-api.com";
+
+#[cfg(feature = "gcp-secrets")]
+pub(crate) const GCP_METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+#[cfg(feature = "gcp-secrets")]
+pub(crate) const GCP_SECRET_MANAGER_URL: &str = "https://secretmanager.googleapis.com/v1";
@@ -0,0 +1,154 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+
+use crate::{
+    data::datasources::http_client::sleep,
+    domain::entities::{
+        iap_consumption::{ConsumptionInfo, ConsumptionRequestReason},
+        iap_purchase_id::{AppleTransactionId, IapPurchaseId},
+        iap_update_notification::{IapUpdateNotification, NotificationDetails},
+    },
+    error_observer::IapError,
+    util::IapUtil,
+};
+
+/// Supplies the information sent to Apple in response to a consumption
+/// request (ex. from the app's own records of the purchase and customer).
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ConsumptionDataProvider: Send + Sync {
+    /// Return None to leave the request unanswered (ex. if the customer has
+    /// not consented to sharing their data).
+    async fn consumption_info(
+        &self,
+        request: &ConsumptionRequest,
+    ) -> Result<Option<ConsumptionInfo>, ServerError>;
+}
+
+/// A consumption request, as passed to a 'ConsumptionDataProvider'.
+#[derive(Debug, Clone)]
+pub struct ConsumptionRequest {
+    pub application_id: String,
+    pub product_sku: String,
+    pub transaction_id: AppleTransactionId,
+    /// The customer's 'appAccountToken', if the app set one on the purchase.
+    pub account_id: Option<String>,
+    pub reason: Option<ConsumptionRequestReason>,
+    /// Information sent after this time is not considered by Apple.
+    pub deadline: DateTime<Utc>,
+}
+
+/// Result of 'ConsumptionResponder::respond'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsumptionOutcome {
+    /// The consumption information was sent to Apple.
+    Sent,
+    /// The provider chose not to answer the request.
+    Declined,
+    /// The notification is not a consumption request.
+    NotApplicable,
+}
+
+/// Answers App Store consumption requests: looks up the information with a
+/// 'ConsumptionDataProvider', and sends it to Apple, retrying transient
+/// failures for as long as the request's deadline allows.
+///
+/// Retries wait for the response, so 'respond' should be run in the
+/// background (ex. in a spawned task) rather than while Apple waits for the
+/// webhook response.
+pub struct ConsumptionResponder {
+    iap_util: IapUtil,
+    provider: Arc<dyn ConsumptionDataProvider>,
+    max_attempts: u32,
+    retry_delay: Duration,
+}
+
+impl ConsumptionResponder {
+    /// Up to 5 attempts are made by default, with delays doubling from 1
+    /// minute (see 'max_attempts' and 'retry_delay').
+    pub fn new(iap_util: IapUtil, provider: impl ConsumptionDataProvider + 'static) -> Self {
+        Self {
+            iap_util,
+            provider: Arc::new(provider),
+            max_attempts: 5,
+            retry_delay: Duration::from_secs(60),
+        }
+    }
+
+    /// Maximum number of attempts (including the first) to look up and send
+    /// the information. Attempts which would start after the deadline are
+    /// skipped regardless.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry, doubled for each subsequent retry.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Answer the notification if it is a consumption request. Only transient
+    /// failures (see 'IapError::is_transient') are retried; others, including
+    /// failures of the provider, are returned right away. Fails with the last
+    /// error if no attempt succeeded before the deadline.
+    pub async fn respond(
+        &self,
+        notification: &IapUpdateNotification,
+    ) -> Result<ConsumptionOutcome, IapError> {
+        let NotificationDetails::ConsumptionRequested {
+            application_id,
+            product_sku,
+            purchase_id: IapPurchaseId::AppStoreTransactionId(transaction_id),
+            account_id,
+            reason,
+            deadline,
+        } = &notification.details
+        else {
+            return Ok(ConsumptionOutcome::NotApplicable);
+        };
+        let request = ConsumptionRequest {
+            application_id: application_id.clone(),
+            product_sku: product_sku.clone(),
+            transaction_id: transaction_id.clone(),
+            account_id: account_id.clone(),
+            reason: reason.clone(),
+            deadline: *deadline,
+        };
+
+        let mut attempt = 1;
+        let mut delay = self.retry_delay;
+        loop {
+            let error = match self.attempt(&request).await {
+                Ok(outcome) => return Ok(outcome),
+                Err(e) => e,
+            };
+            let retry_at = chrono::Duration::from_std(delay)
+                .ok()
+                .and_then(|delay| Utc::now().checked_add_signed(delay));
+            if !error.is_transient()
+                || attempt >= self.max_attempts
+                || retry_at.is_none_or(|retry_at| retry_at >= request.deadline)
+            {
+                return Err(error);
+            }
+            sleep(delay).await;
+            attempt += 1;
+            delay = delay.saturating_mul(2);
+        }
+    }
+
+    async fn attempt(&self, request: &ConsumptionRequest) -> Result<ConsumptionOutcome, IapError> {
+        let Some(info) = self.provider.consumption_info(request).await? else {
+            return Ok(ConsumptionOutcome::Declined);
+        };
+        self.iap_util
+            .send_apple_consumption_info(request.transaction_id.clone(), &info)
+            .await?;
+        Ok(ConsumptionOutcome::Sent)
+    }
+}
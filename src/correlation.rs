@@ -0,0 +1,38 @@
+use std::{cell::RefCell, future::Future};
+
+use crate::scoped::{current, scope};
+
+thread_local! {
+    static CORRELATION_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run 'future' with the given correlation ID (ex. the ID of the user request
+/// being served). While it runs, the ID is:
+///
+/// - passed to interceptors ('CalloutRequest::correlation_id' /
+///   'CalloutResponse::correlation_id') and error observers
+///   ('IapErrorContext::correlation_id'), ex. to attach it to tracing spans
+///   or metrics,
+/// - sent with platform API callouts, if a header is configured through
+///   'IapUtilBuilder::correlation_id_header'.
+///
+/// ```ignore
+/// let details = with_correlation_id(request_id, async {
+///     iap_util.verify_and_get_details(product_id, purchase_id, false).await
+/// })
+/// .await?;
+/// ```
+///
+/// NOTE: The ID is scoped to the future itself, so tasks spawned from it do
+/// not inherit it.
+pub async fn with_correlation_id<F: Future>(
+    correlation_id: impl Into<String>,
+    future: F,
+) -> F::Output {
+    scope(&CORRELATION_ID, correlation_id.into(), future).await
+}
+
+/// Correlation ID of the enclosing 'with_correlation_id' scope, if any.
+pub fn current_correlation_id() -> Option<String> {
+    current(&CORRELATION_ID)
+}
@@ -0,0 +1,26 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine as _};
+use fractic_server_error::ServerError;
+use serde::de::DeserializeOwned;
+
+use crate::errors::InvalidJws;
+
+/// Decode the payload of a JWS (ex. an Apple signedTransactionInfo or
+/// signedPayload) WITHOUT validating its signature or certificate chain.
+///
+/// DANGER: The result can not be trusted, since anyone can produce a JWS with
+/// arbitrary contents. This is only intended for support tooling inspecting
+/// payloads which can no longer be verified (ex. because the certificate
+/// chain expired). Never use it to grant entitlements; use 'IapUtil' instead.
+///
+/// Decode into 'serde_json::Value' to inspect the raw payload.
+pub fn decode_jws_unverified<T: DeserializeOwned>(jws: &str) -> Result<T, ServerError> {
+    let payload = match jws.trim().split('.').collect::<Vec<_>>().as_slice() {
+        [_header, payload, _signature] => *payload,
+        _ => return Err(InvalidJws::new("expected three dot-separated segments")),
+    };
+    let decoded = BASE64_URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|e| InvalidJws::with_debug("failed to base64-decode payload", &e))?;
+    serde_json::from_slice(&decoded)
+        .map_err(|e| InvalidJws::with_debug("failed to parse JWS payload", &e))
+}
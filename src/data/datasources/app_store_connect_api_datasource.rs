@@ -0,0 +1,414 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use fractic_server_error::ServerError;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    cache::CacheStore,
+    capture::{CapturedPayloadKind, PayloadCaptures},
+    config::AppStoreConnectCredentials,
+    data::{
+        datasources::{callout_error::CalloutError, http_client::HttpClient},
+        models::app_store_connect_api::{
+            document_model::{DocumentModel, IncludedResourceModel, ResourceModel},
+            localization_model::LocalizationAttributes,
+            price_model::{InAppPurchasePriceAttributes, SubscriptionPriceAttributes},
+            product_model::{InAppPurchaseAttributes, SubscriptionAttributes},
+        },
+    },
+    domain::entities::iap_api_error::PlatformApiError,
+    error_observer::IapPlatform,
+    errors::{AppStoreConnectApiError, AppStoreConnectApiKeyInvalid},
+    secrets::SecretString,
+};
+
+/// Maximum page size of the App Store Connect API's list endpoints.
+const PAGE_LIMIT: usize = 200;
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub(crate) trait AppStoreConnectApiDatasource: Send + Sync {
+    /// List All In-App Purchases for an App:
+    /// https://developer.apple.com/documentation/appstoreconnectapi/get-v1-apps-_id_-inapppurchasesv2
+    ///
+    /// product_id:
+    ///   The product ID used in the app (the SKU). Auto-renewable
+    ///   subscriptions are not in-app purchases in App Store Connect (see
+    ///   'find_subscription').
+    async fn find_in_app_purchase(
+        &self,
+        product_id: &str,
+    ) -> Result<Option<ResourceModel<InAppPurchaseAttributes>>, CalloutError>;
+
+    /// List All Subscription Groups for an App, including their
+    /// subscriptions:
+    /// https://developer.apple.com/documentation/appstoreconnectapi/get-v1-apps-_id_-subscriptiongroups
+    ///
+    /// product_id:
+    ///   The product ID used in the app (the SKU).
+    async fn find_subscription(
+        &self,
+        product_id: &str,
+    ) -> Result<Option<ResourceModel<SubscriptionAttributes>>, CalloutError>;
+
+    /// List Manually Chosen / Automatically Generated Prices for an In-App
+    /// Purchase Price Schedule:
+    /// https://developer.apple.com/documentation/appstoreconnectapi/get-v1-inapppurchasepriceschedules-_id_-manualprices
+    /// https://developer.apple.com/documentation/appstoreconnectapi/get-v1-inapppurchasepriceschedules-_id_-automaticprices
+    ///
+    /// in_app_purchase_id:
+    ///   The App Store Connect ID of the in-app purchase (which is also the ID
+    ///   of its price schedule).
+    /// territory:
+    ///   ISO 3166-1 alpha-3 code of the territory to list prices for. All
+    ///   territories if not set.
+    ///
+    /// Returns both the manual and automatic prices, with their price points
+    /// and territories included.
+    async fn get_in_app_purchase_prices(
+        &self,
+        in_app_purchase_id: &str,
+        territory: Option<&str>,
+    ) -> Result<DocumentModel<InAppPurchasePriceAttributes>, CalloutError>;
+
+    /// List All Prices for a Subscription:
+    /// https://developer.apple.com/documentation/appstoreconnectapi/get-v1-subscriptions-_id_-prices
+    ///
+    /// subscription_id:
+    ///   The App Store Connect ID of the subscription.
+    /// territory:
+    ///   ISO 3166-1 alpha-3 code of the territory to list prices for. All
+    ///   territories if not set.
+    async fn get_subscription_prices(
+        &self,
+        subscription_id: &str,
+        territory: Option<&str>,
+    ) -> Result<DocumentModel<SubscriptionPriceAttributes>, CalloutError>;
+
+    /// List All Localizations for an In-App Purchase:
+    /// https://developer.apple.com/documentation/appstoreconnectapi/get-v2-inapppurchases-_id_-inapppurchaselocalizations
+    ///
+    /// in_app_purchase_id:
+    ///   The App Store Connect ID of the in-app purchase.
+    async fn get_in_app_purchase_localizations(
+        &self,
+        in_app_purchase_id: &str,
+    ) -> Result<DocumentModel<LocalizationAttributes>, CalloutError>;
+
+    /// List All Localizations for an Auto-Renewable Subscription:
+    /// https://developer.apple.com/documentation/appstoreconnectapi/get-v1-subscriptions-_id_-subscriptionlocalizations
+    ///
+    /// subscription_id:
+    ///   The App Store Connect ID of the subscription.
+    async fn get_subscription_localizations(
+        &self,
+        subscription_id: &str,
+    ) -> Result<DocumentModel<LocalizationAttributes>, CalloutError>;
+}
+
+pub(crate) struct AppStoreConnectApiDatasourceImpl {
+    credentials: AppStoreConnectCredentials,
+    base_url: String,
+    http_client: HttpClient,
+    captures: PayloadCaptures,
+    cache_store: Arc<dyn CacheStore>,
+    product_cache_ttl: Option<Duration>,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl AppStoreConnectApiDatasource for AppStoreConnectApiDatasourceImpl {
+    async fn find_in_app_purchase(
+        &self,
+        product_id: &str,
+    ) -> Result<Option<ResourceModel<InAppPurchaseAttributes>>, CalloutError> {
+        let app_id = &self.credentials.app_apple_id;
+        let path = format!("/v1/apps/{app_id}/inAppPurchasesV2?filter[productId]={product_id}");
+        let document: DocumentModel<InAppPurchaseAttributes> =
+            self.list(&path, "apps.inAppPurchasesV2.list").await?;
+        // The filter is documented as matching exactly, but is double-checked
+        // since a mismatch would silently report another product's prices.
+        Ok(document.data.into_iter().find(|resource| {
+            resource
+                .attributes
+                .as_ref()
+                .is_some_and(|attributes| attributes.product_id == product_id)
+        }))
+    }
+
+    async fn find_subscription(
+        &self,
+        product_id: &str,
+    ) -> Result<Option<ResourceModel<SubscriptionAttributes>>, CalloutError> {
+        let app_id = &self.credentials.app_apple_id;
+        let path = format!(
+            "/v1/apps/{app_id}/subscriptionGroups?include=subscriptions&limit[subscriptions]=50"
+        );
+        let document: DocumentModel<serde::de::IgnoredAny> =
+            self.list(&path, "apps.subscriptionGroups.list").await?;
+        Ok(document
+            .included
+            .into_iter()
+            .find_map(|resource| match resource {
+                IncludedResourceModel::Subscriptions { id, attributes }
+                    if attributes.product_id == product_id =>
+                {
+                    Some(ResourceModel {
+                        id,
+                        attributes: Some(attributes),
+                        relationships: Default::default(),
+                    })
+                }
+                _ => None,
+            }))
+    }
+
+    async fn get_in_app_purchase_prices(
+        &self,
+        in_app_purchase_id: &str,
+        territory: Option<&str>,
+    ) -> Result<DocumentModel<InAppPurchasePriceAttributes>, CalloutError> {
+        let query = Self::price_query("inAppPurchasePricePoint", territory);
+        let manual_path =
+            format!("/v1/inAppPurchasePriceSchedules/{in_app_purchase_id}/manualPrices?{query}");
+        let automatic_path =
+            format!("/v1/inAppPurchasePriceSchedules/{in_app_purchase_id}/automaticPrices?{query}");
+        let (mut manual, automatic) = futures::try_join!(
+            self.list::<InAppPurchasePriceAttributes>(
+                &manual_path,
+                "inAppPurchasePriceSchedules.manualPrices.list",
+            ),
+            self.list::<InAppPurchasePriceAttributes>(
+                &automatic_path,
+                "inAppPurchasePriceSchedules.automaticPrices.list",
+            ),
+        )?;
+        manual.data.extend(automatic.data);
+        manual.included.extend(automatic.included);
+        Ok(manual)
+    }
+
+    async fn get_subscription_prices(
+        &self,
+        subscription_id: &str,
+        territory: Option<&str>,
+    ) -> Result<DocumentModel<SubscriptionPriceAttributes>, CalloutError> {
+        let query = Self::price_query("subscriptionPricePoint", territory);
+        let path = format!("/v1/subscriptions/{subscription_id}/prices?{query}");
+        self.list(&path, "subscriptions.prices.list").await
+    }
+
+    async fn get_in_app_purchase_localizations(
+        &self,
+        in_app_purchase_id: &str,
+    ) -> Result<DocumentModel<LocalizationAttributes>, CalloutError> {
+        let path = format!(
+            "/v2/inAppPurchases/{in_app_purchase_id}/inAppPurchaseLocalizations?limit={PAGE_LIMIT}"
+        );
+        self.list(&path, "inAppPurchases.inAppPurchaseLocalizations.list")
+            .await
+    }
+
+    async fn get_subscription_localizations(
+        &self,
+        subscription_id: &str,
+    ) -> Result<DocumentModel<LocalizationAttributes>, CalloutError> {
+        let path = format!(
+            "/v1/subscriptions/{subscription_id}/subscriptionLocalizations?limit={PAGE_LIMIT}"
+        );
+        self.list(&path, "subscriptions.subscriptionLocalizations.list")
+            .await
+    }
+}
+
+impl AppStoreConnectApiDatasourceImpl {
+    pub(crate) fn new(
+        credentials: AppStoreConnectCredentials,
+        base_url: String,
+        http_client: HttpClient,
+        captures: PayloadCaptures,
+        cache_store: Arc<dyn CacheStore>,
+        product_cache_ttl: Option<Duration>,
+        lazy_credentials: bool,
+    ) -> Result<Self, ServerError> {
+        let datasource = Self {
+            credentials,
+            base_url,
+            http_client,
+            captures,
+            cache_store,
+            product_cache_ttl,
+        };
+        if !lazy_credentials {
+            datasource.warm_up()?;
+        }
+        Ok(datasource)
+    }
+
+    /// Checks that a JWT can be built from the configured key.
+    pub(crate) fn warm_up(&self) -> Result<(), ServerError> {
+        self.build_jwt_token().map(|_| ())
+    }
+
+    /// App Store Connect tokens are only valid for up to 20 minutes, and are
+    /// cheap to sign, so a fresh one is built for each callout.
+    fn build_jwt_token(&self) -> Result<SecretString, ServerError> {
+        // Build header.
+        let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::ES256);
+        header.kid = Some(self.credentials.key_id.clone());
+
+        // Build claims. Unlike for the App Store Server API, team keys are not
+        // bound to an app ('bid').
+        #[derive(Debug, Serialize)]
+        struct Claims<'a> {
+            iss: &'a str,
+            iat: usize,
+            exp: usize,
+            aud: &'a str,
+        }
+        let claims = Claims {
+            iss: &self.credentials.issuer_id,
+            iat: chrono::Utc::now().timestamp() as usize,
+            exp: (chrono::Utc::now() + chrono::Duration::minutes(10)).timestamp() as usize,
+            aud: "appstoreconnect-v1",
+        };
+
+        // Build token.
+        jsonwebtoken::encode(
+            &header,
+            &claims,
+            &jsonwebtoken::EncodingKey::from_ec_pem(
+                self.credentials.api_key.expose_secret().as_bytes(),
+            )
+            .map_err(|e| AppStoreConnectApiKeyInvalid::with_debug("invalid key format", &e))?,
+        )
+        .map(SecretString::new)
+        .map_err(|e| AppStoreConnectApiKeyInvalid::with_debug("failed to build JWT token", &e))
+    }
+
+    /// Query parameters for listing prices, including their price points and
+    /// territories.
+    fn price_query(price_point_relationship: &str, territory: Option<&str>) -> String {
+        let mut query = format!("include={price_point_relationship},territory&limit={PAGE_LIMIT}");
+        if let Some(territory) = territory {
+            query.push_str(&format!("&filter[territory]={territory}"));
+        }
+        query
+    }
+
+    /// Fetches all pages of a list endpoint, merged into one document.
+    ///
+    /// Product metadata rarely changes, so if a product cache TTL is
+    /// configured, the raw pages are cached (and parsed as usual on cache
+    /// hits).
+    async fn list<A: DeserializeOwned>(
+        &self,
+        path: &str,
+        function_name: &str,
+    ) -> Result<DocumentModel<A>, CalloutError> {
+        let cache_key = format!("iap:product:apple:{path}");
+        if self.product_cache_ttl.is_some() {
+            if let Ok(Some(cached)) = self.cache_store.get(&cache_key).await {
+                if let Ok(pages) = serde_json::from_str::<Vec<String>>(&cached) {
+                    if let Ok(document) = Self::merge_pages(function_name, &pages) {
+                        return Ok(document);
+                    }
+                }
+            }
+        }
+
+        let mut pages = Vec::new();
+        let mut url = Some(format!("{}{path}", self.base_url));
+        while let Some(next) = url {
+            let body = self.callout(&next, function_name).await?;
+            url =
+                Self::parse_response::<DocumentModel<serde::de::IgnoredAny>>(function_name, &body)?
+                    .links
+                    .and_then(|links| links.next);
+            pages.push(body);
+        }
+        let document = Self::merge_pages(function_name, &pages)?;
+        if let (Some(ttl), Ok(cached)) = (self.product_cache_ttl, serde_json::to_string(&pages)) {
+            let _ = self.cache_store.set(&cache_key, &cached, ttl).await;
+        }
+        Ok(document)
+    }
+
+    fn merge_pages<A: DeserializeOwned>(
+        function_name: &str,
+        pages: &[String],
+    ) -> Result<DocumentModel<A>, ServerError> {
+        let mut merged = DocumentModel {
+            data: Vec::new(),
+            included: Vec::new(),
+            links: None,
+        };
+        for page in pages {
+            let document: DocumentModel<A> = Self::parse_response(function_name, page)?;
+            merged.data.extend(document.data);
+            merged.included.extend(document.included);
+        }
+        Ok(merged)
+    }
+
+    fn parse_response<T: DeserializeOwned>(
+        function_name: &str,
+        body: &str,
+    ) -> Result<T, ServerError> {
+        serde_json::from_str(body).map_err(|e| {
+            AppStoreConnectApiError::with_debug(
+                function_name,
+                "failed to parse callout response",
+                &e,
+            )
+        })
+    }
+
+    /// Sends the request, failing on non-success status codes, and returns the
+    /// response body.
+    async fn callout(&self, url: &str, function_name: &str) -> Result<String, CalloutError> {
+        self.send_callout(url, function_name)
+            .await
+            .map_err(|e| e.at(IapPlatform::AppStore, function_name))
+    }
+
+    async fn send_callout(&self, url: &str, function_name: &str) -> Result<String, CalloutError> {
+        let builder = self
+            .http_client
+            .request(reqwest::Method::GET, url)
+            .bearer_auth(self.build_jwt_token()?.expose_secret());
+        let response = self
+            .http_client
+            .send(function_name, builder)
+            .await
+            .map_err(|e| {
+                AppStoreConnectApiError::with_debug(function_name, "callout failed to send", &e)
+            })?;
+
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            AppStoreConnectApiError::with_debug(
+                function_name,
+                "failed to read callout response",
+                &e,
+            )
+        })?;
+        self.captures
+            .capture(CapturedPayloadKind::ApiResponse, function_name, &body)
+            .await;
+
+        if !status.is_success() {
+            return Err(CalloutError::api(
+                AppStoreConnectApiError::with_debug(
+                    function_name,
+                    &format!("callout returned with {status} status code"),
+                    &body,
+                ),
+                PlatformApiError::from_app_store_connect_response(status.as_u16(), &body),
+            ));
+        }
+
+        Ok(body)
+    }
+}
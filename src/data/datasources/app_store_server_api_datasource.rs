@@ -1,27 +1,64 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use fractic_server_error::ServerError;
-use reqwest::header::AUTHORIZATION;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
+    capture::{CapturedPayloadKind, PayloadCaptures},
+    config::{current_environment, Environment},
     data::{
-        datasources::utils::validate_and_parse_apple_jws,
-        models::app_store_server_api::{
-            jws_transaction_decoded_payload_model::JwsTransactionDecodedPayloadModel,
-            send_test_notification_response::SendTestNotificationResponse,
-            transaction_info_response_model::TransactionInfoResponseModel,
+        datasources::{
+            cached_credential::CachedCredential, callout_error::CalloutError,
+            http_client::HttpClient, utils::validate_and_parse_apple_jws,
+        },
+        models::{
+            app_store_server_api::{
+                app_transaction_model::AppTransactionModel,
+                common::SubscriptionStatus,
+                consumption_request_model::ConsumptionRequestModel,
+                error_response_model::ErrorResponseModel,
+                extend_renewal_date_model::{
+                    ExtendRenewalDateRequestModel, ExtendRenewalDateResponseModel,
+                },
+                external_purchase_report_model::{
+                    ExternalPurchaseReportModel, ExternalPurchaseReportStatusModel,
+                },
+                history_response_model::{
+                    HistoryResponseModel, InAppOwnershipType, ProductType,
+                    TransactionHistoryRequestModel,
+                },
+                jws_renewal_info_decoded_payload_model::JwsRenewalInfoDecodedPayloadModel,
+                jws_transaction_decoded_payload_model::JwsTransactionDecodedPayloadModel,
+                notification_history_model::{
+                    NotificationHistoryRequestModel, NotificationHistoryResponseModel,
+                    SendAttemptItemModel,
+                },
+                send_test_notification_response::SendTestNotificationResponse,
+                status_response_model::StatusResponseModel,
+                transaction_info_response_model::TransactionInfoResponseModel,
+            },
+            app_store_server_notifications::response_body_v2_decoded_payload_model::ResponseBodyV2DecodedPayloadModel,
         },
     },
-    errors::{AppStoreServerApiError, AppStoreServerApiKeyInvalid},
+    domain::entities::iap_api_error::{AppStoreServerApiErrorCode, PlatformApiError},
+    error_observer::IapPlatform,
+    errors::{AppStoreServerApiError, AppStoreServerApiKeyInvalid, PurchaseNotFound},
+    secrets::SecretString,
+    verifier::SignatureVerifier,
 };
 
 #[derive(Debug, Clone, Copy)]
-enum Method {
+enum Method<'a> {
     Post,
+    PostJson(&'a serde_json::Value),
     Get,
+    Put(&'a serde_json::Value),
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub(crate) trait AppStoreServerApiDatasource: Send + Sync {
     /// Get Transaction Info:
     /// https://developer.apple.com/documentation/appstoreserverapi/get_transaction_info
@@ -32,29 +69,186 @@ pub(crate) trait AppStoreServerApiDatasource: Send + Sync {
     async fn get_transaction_info(
         &self,
         transaction_id: &str,
-    ) -> Result<JwsTransactionDecodedPayloadModel, ServerError>;
+    ) -> Result<JwsTransactionDecodedPayloadModel, CalloutError>;
+
+    /// Get All Subscription Statuses:
+    /// https://developer.apple.com/documentation/appstoreserverapi/get_all_subscription_statuses
+    ///
+    /// transactionId:
+    ///   The identifier of a transaction that belongs to the customer, and
+    ///   which may be an original transaction identifier.
+    ///
+    /// Returns the subscription group, status, latest transaction, and renewal
+    /// info of each of the customer's subscriptions (across all subscription
+    /// groups).
+    async fn get_all_subscription_statuses(
+        &self,
+        transaction_id: &str,
+    ) -> Result<
+        Vec<(
+            String,
+            SubscriptionStatus,
+            JwsTransactionDecodedPayloadModel,
+            JwsRenewalInfoDecodedPayloadModel,
+        )>,
+        CalloutError,
+    >;
+
+    /// Get Transaction History (one page):
+    /// https://developer.apple.com/documentation/appstoreserverapi/get_transaction_history
+    ///
+    /// transactionId:
+    ///   The identifier of a transaction that belongs to the customer, and
+    ///   which may be an original transaction identifier.
+    ///
+    /// revision:
+    ///   The revision returned with the previous page, or None for the first
+    ///   page.
+    ///
+    /// filters:
+    ///   Which transactions to include; the same filters must be passed for
+    ///   every page.
+    ///
+    /// Returns the customer's transactions in the app, oldest first, and the
+    /// revision to request the next page with (None if this is the last
+    /// page).
+    async fn get_transaction_history(
+        &self,
+        transaction_id: &str,
+        revision: Option<&str>,
+        filters: &TransactionHistoryRequestModel,
+    ) -> Result<(Vec<JwsTransactionDecodedPayloadModel>, Option<String>), CalloutError>;
+
+    /// Verify and decode a signed AppTransaction, sent by the app (no callout
+    /// is made):
+    /// https://developer.apple.com/documentation/storekit/apptransaction
+    ///
+    /// signed_app_transaction:
+    ///   The JWS representation of the AppTransaction ('jwsRepresentation').
+    async fn decode_app_transaction(
+        &self,
+        signed_app_transaction: &str,
+    ) -> Result<AppTransactionModel, ServerError>;
+
+    /// Get Notification History:
+    /// https://developer.apple.com/documentation/appstoreserverapi/get-notification-history
+    ///
+    /// pagination_token:
+    ///   The token returned with the previous page, or None for the first
+    ///   page.
+    ///
+    /// Returns the notifications sent in the requested period (signed and
+    /// decoded, with Apple's attempts to send each), and the token to request the next page with
+    /// (None if this is the last page).
+    async fn get_notification_history(
+        &self,
+        request: &NotificationHistoryRequestModel,
+        pagination_token: Option<&str>,
+        sandbox: bool,
+    ) -> Result<
+        (
+            Vec<(
+                String,
+                ResponseBodyV2DecodedPayloadModel,
+                Vec<SendAttemptItemModel>,
+            )>,
+            Option<String>,
+        ),
+        CalloutError,
+    >;
 
     /// Request a test notification from Apple.
     /// https://developer.apple.com/documentation/appstoreserverapi/request_a_test_notification
     async fn request_test_notification(&self, sandbox: bool) -> Result<String, ServerError>;
+
+    /// Builds a fresh JWT from the configured key, and checks that Apple
+    /// accepts it with an authenticated callout (looking up a transaction
+    /// which does not exist).
+    async fn check_credentials(&self) -> Result<(), ServerError>;
+
+    /// Checks that the configured key can be parsed, without any callout.
+    fn check_key(&self) -> Result<(), ServerError>;
+
+    /// Send Consumption Information:
+    /// https://developer.apple.com/documentation/appstoreserverapi/send_consumption_information
+    ///
+    /// transactionId:
+    ///   The transaction identifier for which the customer requested a refund
+    ///   (as found in the CONSUMPTION_REQUEST notification).
+    async fn send_consumption_information(
+        &self,
+        transaction_id: &str,
+        consumption: &ConsumptionRequestModel,
+    ) -> Result<(), CalloutError>;
+
+    /// Extend a Subscription Renewal Date:
+    /// https://developer.apple.com/documentation/appstoreserverapi/extend-a-subscription-renewal-date
+    ///
+    /// originalTransactionId:
+    ///   The original transaction identifier of the subscription.
+    /// sandbox:
+    ///   Whether the subscription was purchased in the sandbox environment.
+    async fn extend_subscription_renewal_date(
+        &self,
+        original_transaction_id: &str,
+        request: &ExtendRenewalDateRequestModel,
+        sandbox: bool,
+    ) -> Result<ExtendRenewalDateResponseModel, CalloutError>;
+
+    /// Send External Purchase Report:
+    /// https://developer.apple.com/documentation/externalpurchaseserverapi/send-external-purchase-report
+    ///
+    /// sandbox:
+    ///   Whether the reported tokens were created in the sandbox environment.
+    async fn send_external_purchase_report(
+        &self,
+        report: &ExternalPurchaseReportModel,
+        sandbox: bool,
+    ) -> Result<(), CalloutError>;
+
+    /// Retrieve External Purchase Report:
+    /// https://developer.apple.com/documentation/externalpurchaseserverapi/retrieve-external-purchase-report
+    ///
+    /// requestIdentifier:
+    ///   The UUID of a previously sent report.
+    async fn get_external_purchase_report(
+        &self,
+        request_identifier: &str,
+        sandbox: bool,
+    ) -> Result<ExternalPurchaseReportStatusModel, CalloutError>;
 }
 
 pub(crate) struct AppStoreServerApiDatasourceImpl {
-    jwt_token: String,
-    expected_aud: String,
+    api_key: SecretString,
+    key_id: String,
+    issuer_id: String,
+    bundle_id: String,
+    /// Built on first use if credentials are initialized lazily, and again
+    /// shortly before it expires.
+    jwt_token: CachedCredential,
+    expected_auds: Vec<String>,
+    production_base_url: String,
+    sandbox_base_url: String,
+    environment: Environment,
+    http_client: HttpClient,
+    signature_verifier: Arc<dyn SignatureVerifier>,
+    captures: PayloadCaptures,
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl AppStoreServerApiDatasource for AppStoreServerApiDatasourceImpl {
     async fn get_transaction_info(
         &self,
         transaction_id: &str,
-    ) -> Result<JwsTransactionDecodedPayloadModel, ServerError> {
+    ) -> Result<JwsTransactionDecodedPayloadModel, CalloutError> {
         let production_url = format!(
-            "https://api.storekit.itunes.apple.com/inApps/v1/transactions/{transaction_id}"
+            "{}/inApps/v1/transactions/{transaction_id}",
+            self.production_base_url
         );
         let sandbox_url = format!(
-            "https://api.storekit-sandbox.itunes.apple.com/inApps/v1/transactions/{transaction_id}"
+            "{}/inApps/v1/transactions/{transaction_id}",
+            self.sandbox_base_url
         );
         let response_wrapper: TransactionInfoResponseModel = self
             .callout_with_sandbox_fallback(
@@ -64,45 +258,380 @@ impl AppStoreServerApiDatasource for AppStoreServerApiDatasourceImpl {
                 Method::Get,
             )
             .await?;
-        validate_and_parse_apple_jws(
+        Ok(validate_and_parse_apple_jws(
+            self.signature_verifier.as_ref(),
             &response_wrapper.signed_transaction_info,
-            &self.expected_aud,
+            &self.expected_auds,
+            &self.captures,
+            "GetTransactionInfo.signedTransactionInfo",
+        )
+        .await?)
+    }
+
+    async fn get_all_subscription_statuses(
+        &self,
+        transaction_id: &str,
+    ) -> Result<
+        Vec<(
+            String,
+            SubscriptionStatus,
+            JwsTransactionDecodedPayloadModel,
+            JwsRenewalInfoDecodedPayloadModel,
+        )>,
+        CalloutError,
+    > {
+        let production_url = format!(
+            "{}/inApps/v1/subscriptions/{transaction_id}",
+            self.production_base_url
+        );
+        let sandbox_url = format!(
+            "{}/inApps/v1/subscriptions/{transaction_id}",
+            self.sandbox_base_url
+        );
+        let response: StatusResponseModel = self
+            .callout_with_sandbox_fallback(
+                &production_url,
+                &sandbox_url,
+                "GetAllSubscriptionStatuses",
+                Method::Get,
+            )
+            .await?;
+        let mut statuses = Vec::new();
+        for (subscription_group_identifier, last_transaction) in
+            response.data.into_iter().flat_map(|group| {
+                let subscription_group_identifier = group.subscription_group_identifier;
+                group
+                    .last_transactions
+                    .into_iter()
+                    .map(move |t| (subscription_group_identifier.clone(), t))
+            })
+        {
+            statuses.push((
+                subscription_group_identifier,
+                last_transaction.status,
+                validate_and_parse_apple_jws(
+                    self.signature_verifier.as_ref(),
+                    &last_transaction.signed_transaction_info,
+                    &self.expected_auds,
+                    &self.captures,
+                    "GetAllSubscriptionStatuses.signedTransactionInfo",
+                )
+                .await?,
+                validate_and_parse_apple_jws(
+                    self.signature_verifier.as_ref(),
+                    &last_transaction.signed_renewal_info,
+                    &self.expected_auds,
+                    &self.captures,
+                    "GetAllSubscriptionStatuses.signedRenewalInfo",
+                )
+                .await?,
+            ));
+        }
+        Ok(statuses)
+    }
+
+    async fn get_transaction_history(
+        &self,
+        transaction_id: &str,
+        revision: Option<&str>,
+        filters: &TransactionHistoryRequestModel,
+    ) -> Result<(Vec<JwsTransactionDecodedPayloadModel>, Option<String>), CalloutError> {
+        let mut path = format!("/inApps/v2/history/{transaction_id}?sort=ASCENDING");
+        if let Some(revision) = revision {
+            path.push_str(&format!("&revision={revision}"));
+        }
+        path.push_str(&transaction_history_query(filters));
+        let response: HistoryResponseModel = self
+            .callout_with_sandbox_fallback(
+                &format!("{}{path}", self.production_base_url),
+                &format!("{}{path}", self.sandbox_base_url),
+                "GetTransactionHistory",
+                Method::Get,
+            )
+            .await?;
+        let mut transactions = Vec::with_capacity(response.signed_transactions.len());
+        for signed_transaction in &response.signed_transactions {
+            transactions.push(
+                validate_and_parse_apple_jws(
+                    self.signature_verifier.as_ref(),
+                    signed_transaction,
+                    &self.expected_auds,
+                    &self.captures,
+                    "GetTransactionHistory.signedTransactions",
+                )
+                .await?,
+            );
+        }
+        let next_revision = match response.has_more {
+            true => response.revision,
+            false => None,
+        };
+        Ok((transactions, next_revision))
+    }
+
+    async fn decode_app_transaction(
+        &self,
+        signed_app_transaction: &str,
+    ) -> Result<AppTransactionModel, ServerError> {
+        validate_and_parse_apple_jws(
+            self.signature_verifier.as_ref(),
+            signed_app_transaction,
+            &self.expected_auds,
+            &self.captures,
+            "AppTransaction",
         )
         .await
     }
 
-    async fn request_test_notification(&self, sandbox: bool) -> Result<String, ServerError> {
-        let url = match sandbox {
-            false => "https://api.storekit.itunes.apple.com/inApps/v1/notifications/test",
-            true => "https://api.storekit-sandbox.itunes.apple.com/inApps/v1/notifications/test",
+    async fn get_notification_history(
+        &self,
+        request: &NotificationHistoryRequestModel,
+        pagination_token: Option<&str>,
+        sandbox: bool,
+    ) -> Result<
+        (
+            Vec<(
+                String,
+                ResponseBodyV2DecodedPayloadModel,
+                Vec<SendAttemptItemModel>,
+            )>,
+            Option<String>,
+        ),
+        CalloutError,
+    > {
+        let url = match pagination_token {
+            Some(token) => format!(
+                "{}/inApps/v1/notifications/history?paginationToken={token}",
+                self.base_url_for(sandbox)
+            ),
+            None => format!(
+                "{}/inApps/v1/notifications/history",
+                self.base_url_for(sandbox)
+            ),
         };
+        let body = serde_json::to_value(request).map_err(|e| {
+            AppStoreServerApiError::with_debug(
+                "GetNotificationHistory",
+                "failed to serialize request",
+                &e,
+            )
+        })?;
+        let response: NotificationHistoryResponseModel = self
+            .callout(&url, "GetNotificationHistory", Method::PostJson(&body))
+            .await?;
+        let mut notifications = Vec::with_capacity(response.notification_history.len());
+        for item in response.notification_history {
+            let payload = validate_and_parse_apple_jws(
+                self.signature_verifier.as_ref(),
+                &item.signed_payload,
+                &self.expected_auds,
+                &self.captures,
+                "GetNotificationHistory.signedPayload",
+            )
+            .await?;
+            notifications.push((item.signed_payload, payload, item.send_attempt_results));
+        }
+        let next_token = match response.has_more {
+            true => response.pagination_token,
+            false => None,
+        };
+        Ok((notifications, next_token))
+    }
+
+    async fn request_test_notification(&self, sandbox: bool) -> Result<String, ServerError> {
+        let url = format!(
+            "{}/inApps/v1/notifications/test",
+            self.base_url_for(sandbox)
+        );
         Ok(self
-            .callout::<SendTestNotificationResponse>(url, "RequestTestNotification", Method::Post)
+            .callout::<SendTestNotificationResponse>(&url, "RequestTestNotification", Method::Post)
             .await?
             .test_notification_token)
     }
+
+    async fn check_credentials(&self) -> Result<(), ServerError> {
+        let (jwt_token, _) = Self::build_jwt_token(
+            &self.api_key,
+            &self.key_id,
+            &self.issuer_id,
+            &self.bundle_id,
+        )
+        .await?;
+        let builder = self
+            .http_client
+            .request(
+                reqwest::Method::GET,
+                &format!("{}/inApps/v1/transactions/0", self.primary_base_url()),
+            )
+            .bearer_auth(jwt_token.expose_secret());
+        let response = self
+            .http_client
+            .send("HealthCheck", builder)
+            .await
+            .map_err(|e| {
+                AppStoreServerApiError::with_debug("HealthCheck", "callout failed to send", &e)
+            })?;
+
+        // Apple answers 401 if the JWT is rejected. Otherwise, the lookup is
+        // expected to fail with 400 / 404, since the transaction ID is bogus.
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(AppStoreServerApiKeyInvalid::new(
+                "JWT token rejected by the App Store Server API",
+            ));
+        }
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(AppStoreServerApiError::with_debug(
+                "HealthCheck",
+                &format!("callout returned with {status} status code"),
+                &response.text().await.unwrap_or_default(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_key(&self) -> Result<(), ServerError> {
+        jsonwebtoken::EncodingKey::from_ec_pem(self.api_key.expose_secret().as_bytes())
+            .map(|_| ())
+            .map_err(|e| AppStoreServerApiKeyInvalid::with_debug("invalid key format", &e))
+    }
+
+    async fn send_consumption_information(
+        &self,
+        transaction_id: &str,
+        consumption: &ConsumptionRequestModel,
+    ) -> Result<(), CalloutError> {
+        let path = format!("/inApps/v1/transactions/consumption/{transaction_id}");
+        let body = serde_json::to_value(consumption).map_err(|e| {
+            AppStoreServerApiError::with_debug(
+                "SendConsumptionInformation",
+                "failed to serialize consumption information",
+                &e,
+            )
+        })?;
+        self.callout_with_sandbox_fallback(
+            &format!("{}{path}", self.production_base_url),
+            &format!("{}{path}", self.sandbox_base_url),
+            "SendConsumptionInformation",
+            Method::Put(&body),
+        )
+        .await
+    }
+
+    async fn extend_subscription_renewal_date(
+        &self,
+        original_transaction_id: &str,
+        request: &ExtendRenewalDateRequestModel,
+        sandbox: bool,
+    ) -> Result<ExtendRenewalDateResponseModel, CalloutError> {
+        let url = format!(
+            "{}/inApps/v1/subscriptions/extend/{original_transaction_id}",
+            self.base_url_for(sandbox)
+        );
+        let body = serde_json::to_value(request).map_err(|e| {
+            AppStoreServerApiError::with_debug(
+                "ExtendSubscriptionRenewalDate",
+                "failed to serialize request",
+                &e,
+            )
+        })?;
+        self.callout(&url, "ExtendSubscriptionRenewalDate", Method::Put(&body))
+            .await
+    }
+
+    async fn send_external_purchase_report(
+        &self,
+        report: &ExternalPurchaseReportModel,
+        sandbox: bool,
+    ) -> Result<(), CalloutError> {
+        let url = format!("{}/externalPurchase/v1/reports", self.base_url_for(sandbox));
+        let body = serde_json::to_value(report).map_err(|e| {
+            AppStoreServerApiError::with_debug(
+                "SendExternalPurchaseReport",
+                "failed to serialize report",
+                &e,
+            )
+        })?;
+        self.callout(&url, "SendExternalPurchaseReport", Method::Put(&body))
+            .await
+    }
+
+    async fn get_external_purchase_report(
+        &self,
+        request_identifier: &str,
+        sandbox: bool,
+    ) -> Result<ExternalPurchaseReportStatusModel, CalloutError> {
+        let url = format!(
+            "{}/externalPurchase/v1/reports/{request_identifier}",
+            self.base_url_for(sandbox)
+        );
+        self.callout(&url, "RetrieveExternalPurchaseReport", Method::Get)
+            .await
+    }
 }
 
 impl AppStoreServerApiDatasourceImpl {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn new(
-        api_key: &str,
+        api_key: &SecretString,
         key_id: &str,
         issuer_id: &str,
         bundle_id: &str,
-        expected_aud: String,
+        expected_auds: Vec<String>,
+        production_base_url: String,
+        sandbox_base_url: String,
+        environment: Environment,
+        http_client: HttpClient,
+        signature_verifier: Arc<dyn SignatureVerifier>,
+        captures: PayloadCaptures,
+        lazy_credentials: bool,
     ) -> Result<Self, ServerError> {
         Ok(Self {
-            jwt_token: Self::build_jwt_token(api_key, key_id, issuer_id, bundle_id).await?,
-            expected_aud,
+            api_key: api_key.clone(),
+            key_id: key_id.to_owned(),
+            issuer_id: issuer_id.to_owned(),
+            bundle_id: bundle_id.to_owned(),
+            jwt_token: match lazy_credentials {
+                true => CachedCredential::new(),
+                false => CachedCredential::with(
+                    Self::build_jwt_token(api_key, key_id, issuer_id, bundle_id).await?,
+                ),
+            },
+            expected_auds,
+            production_base_url,
+            sandbox_base_url,
+            environment,
+            http_client,
+            signature_verifier,
+            captures,
         })
     }
 
+    /// Builds the JWT, if not done yet.
+    pub(crate) async fn warm_up(&self) -> Result<(), ServerError> {
+        self.jwt_token().await.map(|_| ())
+    }
+
+    async fn jwt_token(&self) -> Result<SecretString, ServerError> {
+        self.jwt_token
+            .get(|| {
+                Self::build_jwt_token(
+                    &self.api_key,
+                    &self.key_id,
+                    &self.issuer_id,
+                    &self.bundle_id,
+                )
+            })
+            .await
+    }
+
+    /// Returns the token and its expiry.
     async fn build_jwt_token(
-        api_key: &str,
+        api_key: &SecretString,
         key_id: &str,
         issuer_id: &str,
         bundle_id: &str,
-    ) -> Result<String, ServerError> {
+    ) -> Result<(SecretString, DateTime<Utc>), ServerError> {
         // Build header.
         let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::ES256);
         header.kid = Some(key_id.to_owned());
@@ -116,10 +645,12 @@ impl AppStoreServerApiDatasourceImpl {
             aud: String,
             bid: String,
         }
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::minutes(10);
         let claims = Claims {
             iss: issuer_id.to_owned(),
-            iat: chrono::Utc::now().timestamp() as usize,
-            exp: (chrono::Utc::now() + chrono::Duration::minutes(10)).timestamp() as usize,
+            iat: now.timestamp() as usize,
+            exp: expires_at.timestamp() as usize,
             aud: "appstoreconnect-v1".to_owned(),
             bid: bundle_id.to_owned(),
         };
@@ -128,27 +659,62 @@ impl AppStoreServerApiDatasourceImpl {
         jsonwebtoken::encode(
             &header,
             &claims,
-            &jsonwebtoken::EncodingKey::from_ec_pem(api_key.as_ref())
+            &jsonwebtoken::EncodingKey::from_ec_pem(api_key.expose_secret().as_bytes())
                 .map_err(|e| AppStoreServerApiKeyInvalid::with_debug("invalid key format", &e))?,
         )
+        .map(|token| (SecretString::new(token), expires_at))
         .map_err(|e| AppStoreServerApiKeyInvalid::with_debug("failed to build JWT token", &e))
     }
 
+    /// The configured environment, unless overridden for the current call
+    /// (see 'with_environment').
+    fn environment(&self) -> Environment {
+        current_environment().unwrap_or(self.environment)
+    }
+
+    /// Base URL of the environment queried first.
+    fn primary_base_url(&self) -> &str {
+        match self.environment() {
+            Environment::SandboxOnly => &self.sandbox_base_url,
+            _ => &self.production_base_url,
+        }
+    }
+
+    fn base_url_for(&self, sandbox: bool) -> &str {
+        match sandbox {
+            false => &self.production_base_url,
+            true => &self.sandbox_base_url,
+        }
+    }
+
     async fn callout_with_sandbox_fallback<T: DeserializeOwned>(
         &self,
         production_url: &str,
         sandbox_url: &str,
         function_name: &str,
-        method: Method,
-    ) -> Result<T, ServerError> {
+        method: Method<'_>,
+    ) -> Result<T, CalloutError> {
+        match self.environment() {
+            Environment::ProductionWithSandboxFallback => {}
+            Environment::ProductionOnly => {
+                return self.callout(production_url, function_name, method).await
+            }
+            Environment::SandboxOnly => {
+                return self.callout(sandbox_url, function_name, method).await
+            }
+        }
+
         // As per Apple's documentation, try production endpoint first. If it
         // fails, try checking the sandbox.
         //
-        // If both fail, we will return the error from the production callout.
+        // If both fail, we will return the error from the production callout,
+        // unless that only reported the transaction as not found (in which
+        // case the sandbox error is more relevant, ex. if it was transient).
         match self.callout(production_url, function_name, method).await {
             Ok(production_response) => Ok(production_response),
             Err(production_error) => match self.callout(sandbox_url, function_name, method).await {
                 Ok(sandbox_response) => Ok(sandbox_response),
+                Err(sandbox_error) if production_error.is_not_found() => Err(sandbox_error),
                 Err(_sandbox_error) => Err(production_error),
             },
         }
@@ -158,38 +724,143 @@ impl AppStoreServerApiDatasourceImpl {
         &self,
         url: &str,
         function_name: &str,
-        method: Method,
-    ) -> Result<T, ServerError> {
-        let client = reqwest::Client::new();
-        let builder = match method {
-            Method::Post => client.post(url),
-            Method::Get => client.get(url),
-        };
-        let response = builder
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt_token))
-            .send()
+        method: Method<'_>,
+    ) -> Result<T, CalloutError> {
+        self.send_callout(url, function_name, method)
             .await
-            .map_err(|e| {
-                AppStoreServerApiError::with_debug(function_name, "callout failed to send", &e)
-            })?;
+            .map_err(|e| e.at(IapPlatform::AppStore, function_name))
+    }
 
-        if !response.status().is_success() {
-            return Err(AppStoreServerApiError::with_debug(
-                function_name,
-                &format!(
-                    "callout returned with {} status code",
-                    response.status().to_string(),
+    async fn send_callout<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        function_name: &str,
+        method: Method<'_>,
+    ) -> Result<T, CalloutError> {
+        // A rejected token (ex. if the key was rotated) is replaced, and the
+        // request sent again, once.
+        let mut retried = false;
+        let response = loop {
+            let jwt_token = self.jwt_token().await?;
+            let builder = match method {
+                Method::Post => self.http_client.request(reqwest::Method::POST, url),
+                Method::PostJson(body) => self
+                    .http_client
+                    .request(reqwest::Method::POST, url)
+                    .json(body),
+                Method::Get => self.http_client.request(reqwest::Method::GET, url),
+                Method::Put(body) => self
+                    .http_client
+                    .request(reqwest::Method::PUT, url)
+                    .json(body),
+            };
+            let builder = builder.bearer_auth(jwt_token.expose_secret());
+            let response = self
+                .http_client
+                .send(function_name, builder)
+                .await
+                .map_err(|e| {
+                    AppStoreServerApiError::with_debug(function_name, "callout failed to send", &e)
+                })?;
+            if retried || response.status() != reqwest::StatusCode::UNAUTHORIZED {
+                break response;
+            }
+            self.jwt_token.invalidate(&jwt_token).await;
+            retried = true;
+        };
+
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            AppStoreServerApiError::with_debug(function_name, "failed to read callout response", &e)
+        })?;
+        self.captures
+            .capture(CapturedPayloadKind::ApiResponse, function_name, &body)
+            .await;
+
+        if !status.is_success() {
+            let error_response = serde_json::from_str::<ErrorResponseModel>(&body).ok();
+            let error_code = error_response
+                .as_ref()
+                .map(|r| AppStoreServerApiErrorCode::from_code(r.error_code));
+            let error = match error_code {
+                Some(code) if code.is_not_found() => PurchaseNotFound::with_debug(&body),
+                _ => AppStoreServerApiError::with_debug(
+                    function_name,
+                    &match &error_response {
+                        Some(r) => format!(
+                            "callout returned with {} status code (error {}: {})",
+                            status,
+                            r.error_code,
+                            r.error_message.as_deref().unwrap_or("no message")
+                        ),
+                        None => format!("callout returned with {} status code", status),
+                    },
+                    &body,
                 ),
-                &response.text().await.unwrap_or_default(),
+            };
+            return Err(CalloutError::api(
+                error,
+                PlatformApiError::AppStore {
+                    status: status.as_u16(),
+                    error_code,
+                    error_message: error_response.and_then(|r| r.error_message),
+                },
             ));
         }
 
-        response.json().await.map_err(|e| {
+        // Some endpoints (ex. Send External Purchase Report) answer with an
+        // empty body, which is parsed as null (ie. '()').
+        let body = match body.is_empty() {
+            true => "null",
+            false => &body,
+        };
+        serde_json::from_str(body).map_err(|e| {
             AppStoreServerApiError::with_debug(
                 function_name,
                 "failed to parse callout response",
                 &e,
             )
+            .into()
         })
     }
 }
+
+/// Query parameters for the filters of a Get Transaction History request,
+/// each prefixed with '&'.
+fn transaction_history_query(filters: &TransactionHistoryRequestModel) -> String {
+    let mut query = String::new();
+    if let Some(start_date) = filters.start_date {
+        query.push_str(&format!("&startDate={}", start_date.timestamp_millis()));
+    }
+    if let Some(end_date) = filters.end_date {
+        query.push_str(&format!("&endDate={}", end_date.timestamp_millis()));
+    }
+    for product_id in &filters.product_id {
+        query.push_str(&format!("&productId={product_id}"));
+    }
+    for product_type in &filters.product_type {
+        let product_type = match product_type {
+            ProductType::AutoRenewable => "AUTO_RENEWABLE",
+            ProductType::NonRenewable => "NON_RENEWABLE",
+            ProductType::Consumable => "CONSUMABLE",
+            ProductType::NonConsumable => "NON_CONSUMABLE",
+        };
+        query.push_str(&format!("&productType={product_type}"));
+    }
+    for subscription_group_identifier in &filters.subscription_group_identifier {
+        query.push_str(&format!(
+            "&subscriptionGroupIdentifier={subscription_group_identifier}"
+        ));
+    }
+    if let Some(in_app_ownership_type) = filters.in_app_ownership_type {
+        let in_app_ownership_type = match in_app_ownership_type {
+            InAppOwnershipType::FamilyShared => "FAMILY_SHARED",
+            InAppOwnershipType::Purchased => "PURCHASED",
+        };
+        query.push_str(&format!("&inAppOwnershipType={in_app_ownership_type}"));
+    }
+    if let Some(revoked) = filters.revoked {
+        query.push_str(&format!("&revoked={revoked}"));
+    }
+    query
+}
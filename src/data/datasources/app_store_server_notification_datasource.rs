@@ -1,7 +1,10 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use fractic_server_error::ServerError;
 
 use crate::{
+    capture::{CapturedPayloadKind, PayloadCaptures},
     data::{
         datasources::utils::validate_and_parse_apple_jws,
         models::{
@@ -16,9 +19,11 @@ use crate::{
         },
     },
     errors::AppStoreServerNotificationParseError,
+    verifier::SignatureVerifier,
 };
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub(crate) trait AppStoreServerNotificationDatasource: Send + Sync {
     /// Parse App Store Server Notification:
     /// https://developer.apple.com/documentation/appstoreservernotifications/app-store-server-notifications-v2
@@ -36,13 +41,23 @@ pub(crate) trait AppStoreServerNotificationDatasource: Send + Sync {
         ),
         ServerError,
     >;
+
+    /// Only verify the notification's signature (and audience), without
+    /// parsing its contents.
+    ///
+    /// body:
+    ///   The raw POST body of the notification.
+    async fn verify_notification_signature(&self, body: &str) -> Result<(), ServerError>;
 }
 
 pub(crate) struct AppStoreServerNotificationDatasourceImpl {
-    expected_aud: String,
+    expected_auds: Vec<String>,
+    signature_verifier: Arc<dyn SignatureVerifier>,
+    captures: PayloadCaptures,
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl AppStoreServerNotificationDatasource for AppStoreServerNotificationDatasourceImpl {
     async fn parse_notification(
         &self,
@@ -55,10 +70,23 @@ impl AppStoreServerNotificationDatasource for AppStoreServerNotificationDatasour
         ),
         ServerError,
     > {
+        self.captures
+            .capture(
+                CapturedPayloadKind::NotificationBody,
+                "AppStoreServerNotification",
+                body,
+            )
+            .await;
         let wrapper: ResponseBodyV2Model = serde_json::from_str(body)
             .map_err(|e| AppStoreServerNotificationParseError::with_debug(&e))?;
-        let decoded_payload: ResponseBodyV2DecodedPayloadModel =
-            validate_and_parse_apple_jws(&wrapper.signed_payload, &self.expected_aud).await?;
+        let decoded_payload: ResponseBodyV2DecodedPayloadModel = validate_and_parse_apple_jws(
+            self.signature_verifier.as_ref(),
+            &wrapper.signed_payload,
+            &self.expected_auds,
+            &self.captures,
+            "AppStoreServerNotification.signedPayload",
+        )
+        .await?;
         let decoded_transaction_info: Option<JwsTransactionDecodedPayloadModel> =
             match decoded_payload
                 .data
@@ -66,9 +94,16 @@ impl AppStoreServerNotificationDatasource for AppStoreServerNotificationDatasour
                 .map(|data| data.signed_transaction_info.as_ref())
                 .flatten()
             {
-                Some(transaction_info) => {
-                    Some(validate_and_parse_apple_jws(transaction_info, &self.expected_aud).await?)
-                }
+                Some(transaction_info) => Some(
+                    validate_and_parse_apple_jws(
+                        self.signature_verifier.as_ref(),
+                        transaction_info,
+                        &self.expected_auds,
+                        &self.captures,
+                        "AppStoreServerNotification.signedTransactionInfo",
+                    )
+                    .await?,
+                ),
                 None => None,
             };
         let decoded_renewal_info: Option<JwsRenewalInfoDecodedPayloadModel> = match decoded_payload
@@ -77,9 +112,16 @@ impl AppStoreServerNotificationDatasource for AppStoreServerNotificationDatasour
             .map(|data| data.signed_renewal_info.as_ref())
             .flatten()
         {
-            Some(renewal_info) => {
-                Some(validate_and_parse_apple_jws(renewal_info, &self.expected_aud).await?)
-            }
+            Some(renewal_info) => Some(
+                validate_and_parse_apple_jws(
+                    self.signature_verifier.as_ref(),
+                    renewal_info,
+                    &self.expected_auds,
+                    &self.captures,
+                    "AppStoreServerNotification.signedRenewalInfo",
+                )
+                .await?,
+            ),
             None => None,
         };
         Ok((
@@ -88,10 +130,34 @@ impl AppStoreServerNotificationDatasource for AppStoreServerNotificationDatasour
             decoded_renewal_info,
         ))
     }
+
+    async fn verify_notification_signature(&self, body: &str) -> Result<(), ServerError> {
+        let wrapper: ResponseBodyV2Model = serde_json::from_str(body)
+            .map_err(|e| AppStoreServerNotificationParseError::with_debug(&e))?;
+        // The nested JWS values (transaction and renewal info) are part of the
+        // signed payload, so checking the outer signature is sufficient.
+        validate_and_parse_apple_jws::<serde_json::Value>(
+            self.signature_verifier.as_ref(),
+            &wrapper.signed_payload,
+            &self.expected_auds,
+            &self.captures,
+            "AppStoreServerNotification.signedPayload",
+        )
+        .await?;
+        Ok(())
+    }
 }
 
 impl AppStoreServerNotificationDatasourceImpl {
-    pub(crate) fn new(expected_aud: String) -> Self {
-        Self { expected_aud }
+    pub(crate) fn new(
+        expected_auds: Vec<String>,
+        signature_verifier: Arc<dyn SignatureVerifier>,
+        captures: PayloadCaptures,
+    ) -> Self {
+        Self {
+            expected_auds,
+            signature_verifier,
+            captures,
+        }
     }
 }
@@ -0,0 +1,65 @@
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+use tokio::sync::Mutex;
+
+use crate::secrets::SecretString;
+
+/// How long before its expiry a credential is replaced, so that it does not
+/// expire while a callout is in flight.
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Credential (ex. an OAuth access token) minted on demand and reused until
+/// shortly before it expires, so that long-lived 'IapUtil' instances keep
+/// working after the first credential expired.
+pub(crate) struct CachedCredential {
+    current: Mutex<Option<(SecretString, DateTime<Utc>)>>,
+}
+
+impl CachedCredential {
+    pub(crate) fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Starts out with an already minted credential (see 'get').
+    pub(crate) fn with(credential: (SecretString, DateTime<Utc>)) -> Self {
+        Self {
+            current: Mutex::new(Some(credential)),
+        }
+    }
+
+    /// The current credential, minted through 'mint' (which returns the
+    /// credential and its expiry) if there is none yet or it is about to
+    /// expire. Concurrent callers wait for the same mint.
+    pub(crate) async fn get<F, Fut>(&self, mint: F) -> Result<SecretString, ServerError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(SecretString, DateTime<Utc>), ServerError>>,
+    {
+        let mut current = self.current.lock().await;
+        let refresh_at = Utc::now() + chrono::Duration::seconds(REFRESH_MARGIN_SECS);
+        if let Some((credential, expires_at)) = current.as_ref() {
+            if refresh_at < *expires_at {
+                return Ok(credential.clone());
+            }
+        }
+        let (credential, expires_at) = mint().await?;
+        *current = Some((credential.clone(), expires_at));
+        Ok(credential)
+    }
+
+    /// Drops 'rejected' (ex. after a 401 response), so that the next 'get'
+    /// mints a new credential. Kept if a concurrent caller already replaced it.
+    pub(crate) async fn invalidate(&self, rejected: &SecretString) {
+        let mut current = self.current.lock().await;
+        if current
+            .as_ref()
+            .is_some_and(|(credential, _)| credential.expose_secret() == rejected.expose_secret())
+        {
+            *current = None;
+        }
+    }
+}
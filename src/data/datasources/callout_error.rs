@@ -0,0 +1,70 @@
+use fractic_server_error::ServerError;
+
+use crate::{domain::entities::iap_api_error::PlatformApiError, error_observer::IapPlatform};
+
+/// Error returned by the platform API datasources. If the callout failed with
+/// a non-2xx response, the parsed response is kept, so that callers can tell
+/// transient failures (ex. quota exhausted) apart from permanent ones.
+#[derive(Debug)]
+pub(crate) struct CalloutError {
+    pub(crate) error: ServerError,
+    pub(crate) api_error: Option<PlatformApiError>,
+    /// Platform and API function of the failed callout, if the failure came
+    /// from a callout.
+    pub(crate) platform: Option<IapPlatform>,
+    pub(crate) endpoint: Option<String>,
+}
+
+impl CalloutError {
+    pub(crate) fn api(error: ServerError, api_error: PlatformApiError) -> Self {
+        Self {
+            api_error: Some(api_error),
+            ..error.into()
+        }
+    }
+
+    /// Attach the callout the error came from (unless already set).
+    pub(crate) fn at(mut self, platform: IapPlatform, endpoint: &str) -> Self {
+        if self.endpoint.is_none() {
+            self.platform = Some(platform);
+            self.endpoint = Some(endpoint.to_owned());
+        }
+        self
+    }
+
+    /// Whether the platform reported the requested purchase as not existing.
+    pub(crate) fn is_not_found(&self) -> bool {
+        matches!(
+            &self.api_error,
+            Some(PlatformApiError::AppStore {
+                error_code: Some(error_code),
+                ..
+            }) if error_code.is_not_found()
+        )
+    }
+
+    /// Failures without a platform response (ex. connection errors) are
+    /// considered transient.
+    pub(crate) fn is_transient(&self) -> bool {
+        self.api_error
+            .as_ref()
+            .is_none_or(PlatformApiError::is_transient)
+    }
+}
+
+impl From<ServerError> for CalloutError {
+    fn from(error: ServerError) -> Self {
+        Self {
+            error,
+            api_error: None,
+            platform: None,
+            endpoint: None,
+        }
+    }
+}
+
+impl From<CalloutError> for ServerError {
+    fn from(error: CalloutError) -> Self {
+        error.error
+    }
+}
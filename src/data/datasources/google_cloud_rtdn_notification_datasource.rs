@@ -1,8 +1,12 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use base64::{prelude::BASE64_STANDARD, Engine as _};
 use fractic_server_error::ServerError;
+use serde_json::Value;
 
 use crate::{
+    capture::{CapturedPayloadKind, PayloadCaptures},
     data::{
         datasources::utils::validate_google_header,
         models::google_cloud_rtdn_notifications::{
@@ -10,9 +14,11 @@ use crate::{
         },
     },
     errors::GoogleCloudRtdnNotificationParseError,
+    verifier::SignatureVerifier,
 };
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub(crate) trait GoogleCloudRtdnNotificationDatasource: Send + Sync {
     /// Parse Google Cloud RTDN Notification:
     /// https://developer.android.com/google/play/billing/rtdn-reference
@@ -24,45 +30,151 @@ pub(crate) trait GoogleCloudRtdnNotificationDatasource: Send + Sync {
         authorization_header: &str,
         body: &str,
     ) -> Result<(PubSubModel, DeveloperNotificationModel), ServerError>;
+
+    /// Parse an RTDN developer notification which was already unwrapped from
+    /// its Pub/Sub message (ex. by a pull subscriber).
+    ///
+    /// body:
+    ///   The notification JSON (the decoded 'message.data').
+    ///
+    /// NOTE: There is no signature to verify at this point, so the caller is
+    /// responsible for the notification's authenticity.
+    async fn parse_developer_notification(
+        &self,
+        body: &str,
+    ) -> Result<DeveloperNotificationModel, ServerError>;
+
+    /// Only verify the OIDC token in the push request's 'Authorization'
+    /// header (signature and audience), without parsing the body.
+    async fn verify_authorization(&self, authorization_header: &str) -> Result<(), ServerError>;
 }
 
 pub(crate) struct GoogleCloudRtdnNotificationDatasourceImpl {
-    expected_aud: String,
+    expected_auds: Vec<String>,
+    signature_verifier: Arc<dyn SignatureVerifier>,
+    captures: PayloadCaptures,
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl GoogleCloudRtdnNotificationDatasource for GoogleCloudRtdnNotificationDatasourceImpl {
     async fn parse_notification(
         &self,
         authorization_header: &str,
         body: &str,
     ) -> Result<(PubSubModel, DeveloperNotificationModel), ServerError> {
-        validate_google_header(authorization_header, &self.expected_aud).await?;
+        self.capture_body(body).await;
+        validate_google_header(
+            self.signature_verifier.as_ref(),
+            authorization_header,
+            &self.expected_auds,
+        )
+        .await?;
         let wrapper: PubSubModel = serde_json::from_str(body).map_err(|e| {
             GoogleCloudRtdnNotificationParseError::with_debug("failed to parse Pub/Sub wrapper", &e)
         })?;
-        let decoded_message = BASE64_STANDARD
-            .decode(wrapper.message.data.clone())
-            .map_err(|e| {
-                GoogleCloudRtdnNotificationParseError::with_debug(
-                    "failed to base64-decode notification struct",
-                    &e,
-                )
-            })?;
-        Ok((
-            wrapper,
-            serde_json::from_slice(&decoded_message).map_err(|e| {
+        let notification = Self::decode_message_data(&wrapper.message.data)?;
+        Ok((wrapper, notification))
+    }
+
+    async fn parse_developer_notification(
+        &self,
+        body: &str,
+    ) -> Result<DeveloperNotificationModel, ServerError> {
+        self.captures
+            .capture(
+                CapturedPayloadKind::NotificationBody,
+                "GoogleCloudRtdnDeveloperNotification",
+                body,
+            )
+            .await;
+        serde_json::from_str(body).map_err(|e| {
+            GoogleCloudRtdnNotificationParseError::with_debug(
+                "failed to parse notification struct",
+                &e,
+            )
+        })
+    }
+
+    async fn verify_authorization(&self, authorization_header: &str) -> Result<(), ServerError> {
+        validate_google_header(
+            self.signature_verifier.as_ref(),
+            authorization_header,
+            &self.expected_auds,
+        )
+        .await
+    }
+}
+
+impl GoogleCloudRtdnNotificationDatasourceImpl {
+    pub(crate) fn new(
+        expected_auds: Vec<String>,
+        signature_verifier: Arc<dyn SignatureVerifier>,
+        captures: PayloadCaptures,
+    ) -> Self {
+        Self {
+            expected_auds,
+            signature_verifier,
+            captures,
+        }
+    }
+
+    /// 'message.data' is normally base64-encoded, but is accepted as a plain
+    /// JSON object as well (ex. when testing locally).
+    fn decode_message_data(data: &Value) -> Result<DeveloperNotificationModel, ServerError> {
+        let Some(encoded) = data.as_str() else {
+            return serde_json::from_value(data.clone()).map_err(|e| {
                 GoogleCloudRtdnNotificationParseError::with_debug(
                     "failed to parse notification struct",
                     &e,
                 )
-            })?,
-        ))
+            });
+        };
+        let decoded_message = BASE64_STANDARD.decode(encoded).map_err(|e| {
+            GoogleCloudRtdnNotificationParseError::with_debug(
+                "failed to base64-decode notification struct",
+                &e,
+            )
+        })?;
+        serde_json::from_slice(&decoded_message).map_err(|e| {
+            GoogleCloudRtdnNotificationParseError::with_debug(
+                "failed to parse notification struct",
+                &e,
+            )
+        })
     }
-}
 
-impl GoogleCloudRtdnNotificationDatasourceImpl {
-    pub(crate) fn new(expected_aud: String) -> Self {
-        Self { expected_aud }
+    /// Captures the Pub/Sub envelope, with the notification (base64-encoded in
+    /// 'message.data') decoded in place, so it is readable in the capture.
+    async fn capture_body(&self, body: &str) {
+        if self.captures.is_empty() {
+            return;
+        }
+        let Ok(mut envelope) = serde_json::from_str::<Value>(body) else {
+            self.captures
+                .capture(
+                    CapturedPayloadKind::NotificationBody,
+                    "GoogleCloudRtdnNotification",
+                    body,
+                )
+                .await;
+            return;
+        };
+        if let Some(data) = envelope.pointer_mut("/message/data") {
+            if let Some(decoded) = data
+                .as_str()
+                .and_then(|encoded| BASE64_STANDARD.decode(encoded).ok())
+                .and_then(|decoded| serde_json::from_slice(&decoded).ok())
+            {
+                *data = decoded;
+            }
+        }
+        self.captures
+            .capture_value(
+                CapturedPayloadKind::NotificationBody,
+                "GoogleCloudRtdnNotification",
+                envelope,
+            )
+            .await;
     }
 }
@@ -1,26 +1,52 @@
-use std::any::TypeId;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use fractic_server_error::ServerError;
-use reqwest::header::{AUTHORIZATION, CONTENT_LENGTH};
+use reqwest::header::CONTENT_LENGTH;
 use serde::de::DeserializeOwned;
-use yup_oauth2::{parse_service_account_key, ServiceAccountAuthenticator};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    data::models::google_play_developer_api::{
-        in_app_product_model::InAppProductModel, product_purchase_model::ProductPurchaseModel,
-        subscription_purchase_v2_model::SubscriptionPurchaseV2Model,
+    cache::CacheStore,
+    capture::{CapturedPayloadKind, PayloadCaptures},
+    data::{
+        datasources::{
+            cached_credential::CachedCredential, callout_error::CalloutError,
+            http_client::HttpClient,
+        },
+        models::google_play_developer_api::{
+            external_transaction_model::{
+                ExternalTransactionModel, RefundExternalTransactionRequest,
+            },
+            in_app_product_model::InAppProductModel,
+            product_purchase_model::ProductPurchaseModel,
+            product_purchase_v2_model::ProductPurchaseV2Model,
+            subscription_deferral_model::{
+                SubscriptionPurchasesDeferRequest, SubscriptionPurchasesDeferResponse,
+            },
+            subscription_model::SubscriptionModel,
+            subscription_offer_model::{
+                ListSubscriptionOffersResponseModel, SubscriptionOfferModel,
+            },
+            subscription_purchase_v2_model::SubscriptionPurchaseV2Model,
+        },
     },
+    domain::entities::iap_api_error::PlatformApiError,
+    error_observer::IapPlatform,
     errors::{GooglePlayDeveloperApiError, GooglePlayDeveloperApiKeyInvalid},
+    secrets::{redacted_json_error, SecretString},
 };
 
 #[derive(Debug, Clone, Copy)]
-enum Method {
+enum Method<'a> {
     Post,
     Get,
+    PostJson(&'a serde_json::Value),
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub(crate) trait GooglePlayDeveloperApiDatasource: Send + Sync {
     /// purchases.products.get:
     /// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.products/get
@@ -38,7 +64,22 @@ pub(crate) trait GooglePlayDeveloperApiDatasource: Send + Sync {
         package_name: &str,
         product_id: &str,
         token: &str,
-    ) -> Result<ProductPurchaseModel, ServerError>;
+    ) -> Result<ProductPurchaseModel, CalloutError>;
+
+    /// purchases.productsv2.getproductpurchasev2:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.productsv2/getproductpurchasev2
+    ///
+    /// packageName:
+    ///   The package name of the application the inapp product was sold in (for
+    ///   example, 'com.some.thing').
+    /// token:
+    ///   The token provided to the user's device when the inapp product was
+    ///   purchased.
+    async fn get_product_purchase_v2(
+        &self,
+        package_name: &str,
+        token: &str,
+    ) -> Result<ProductPurchaseV2Model, CalloutError>;
 
     /// purchases.subscriptionsv2.get:
     /// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.subscriptionsv2/get
@@ -53,7 +94,7 @@ pub(crate) trait GooglePlayDeveloperApiDatasource: Send + Sync {
         &self,
         package_name: &str,
         token: &str,
-    ) -> Result<SubscriptionPurchaseV2Model, ServerError>;
+    ) -> Result<SubscriptionPurchaseV2Model, CalloutError>;
 
     /// inappproducts.get:
     /// https://developers.google.com/android-publisher/api-ref/rest/v3/inappproducts/get
@@ -66,7 +107,64 @@ pub(crate) trait GooglePlayDeveloperApiDatasource: Send + Sync {
         &self,
         package_name: &str,
         sku: &str,
-    ) -> Result<InAppProductModel, ServerError>;
+    ) -> Result<InAppProductModel, CalloutError>;
+
+    /// monetization.subscriptions.get:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/monetization.subscriptions/get
+    ///
+    /// packageName:
+    ///   The parent app (package name) of the subscription to get.
+    /// productId:
+    ///   The unique product ID of the subscription to get.
+    async fn get_subscription(
+        &self,
+        package_name: &str,
+        product_id: &str,
+    ) -> Result<SubscriptionModel, CalloutError>;
+
+    /// monetization.subscriptions.basePlans.offers.get:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/monetization.subscriptions.basePlans.offers/get
+    ///
+    /// packageName:
+    ///   The parent app (package name) of the offer to get.
+    /// productId:
+    ///   The parent subscription (ID) of the offer to get.
+    /// basePlanId:
+    ///   The parent base plan (ID) of the offer to get.
+    /// offerId:
+    ///   The unique offer ID of the offer to get.
+    async fn get_subscription_offer(
+        &self,
+        package_name: &str,
+        product_id: &str,
+        base_plan_id: &str,
+        offer_id: &str,
+    ) -> Result<SubscriptionOfferModel, CalloutError>;
+
+    /// monetization.subscriptions.basePlans.offers.list (one page):
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/monetization.subscriptions.basePlans.offers/list
+    ///
+    /// packageName:
+    ///   The parent app (package name) for which the subscriptions should be
+    ///   read.
+    /// productId:
+    ///   The parent subscription (ID) for which the offers should be read.
+    /// basePlanId:
+    ///   The parent base plan (ID) for which the offers should be read. May be
+    ///   specified as '-' to read all offers under the subscription.
+    /// pageToken:
+    ///   The token returned with the previous page, or None for the first
+    ///   page.
+    ///
+    /// Returns the offers, and the token to request the next page with (None
+    /// if this is the last page).
+    async fn list_subscription_offers(
+        &self,
+        package_name: &str,
+        product_id: &str,
+        base_plan_id: &str,
+        page_token: Option<&str>,
+    ) -> Result<(Vec<SubscriptionOfferModel>, Option<String>), CalloutError>;
 
     /// purchases.products.consume:
     /// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.products/consume
@@ -84,33 +182,130 @@ pub(crate) trait GooglePlayDeveloperApiDatasource: Send + Sync {
         package_name: &str,
         product_id: &str,
         token: &str,
-    ) -> Result<(), ServerError>;
+    ) -> Result<(), CalloutError>;
+
+    /// purchases.subscriptions.defer:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.subscriptions/defer
+    ///
+    /// packageName:
+    ///   The package of the application for which this subscription was
+    ///   purchased (for example, 'com.some.thing').
+    /// subscriptionId:
+    ///   The purchased subscription ID (for example, 'monthly001').
+    /// token:
+    ///   The token provided to the user's device when the subscription was
+    ///   purchased.
+    async fn defer_subscription_purchase(
+        &self,
+        package_name: &str,
+        subscription_id: &str,
+        token: &str,
+        request: &SubscriptionPurchasesDeferRequest,
+    ) -> Result<SubscriptionPurchasesDeferResponse, CalloutError>;
+
+    /// externaltransactions.createexternaltransaction:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/externaltransactions/createexternaltransaction
+    ///
+    /// parent:
+    ///   The parent resource where this external transaction will be created.
+    ///   Format: applications/{package_name}
+    /// externalTransactionId:
+    ///   The id to use for the external transaction. Must be unique across all
+    ///   other transactions for the app.
+    async fn create_external_transaction(
+        &self,
+        package_name: &str,
+        external_transaction_id: &str,
+        transaction: &ExternalTransactionModel,
+    ) -> Result<ExternalTransactionModel, CalloutError>;
+
+    /// externaltransactions.getexternaltransaction:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/externaltransactions/getexternaltransaction
+    ///
+    /// name:
+    ///   The name of the external transaction to retrieve. Format:
+    ///   applications/{package_name}/externalTransactions/{external_transaction}
+    async fn get_external_transaction(
+        &self,
+        package_name: &str,
+        external_transaction_id: &str,
+    ) -> Result<ExternalTransactionModel, CalloutError>;
+
+    /// externaltransactions.refundexternaltransaction:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/externaltransactions/refundexternaltransaction
+    ///
+    /// name:
+    ///   The name of the external transaction that will be refunded. Format:
+    ///   applications/{package_name}/externalTransactions/{external_transaction}
+    async fn refund_external_transaction(
+        &self,
+        package_name: &str,
+        external_transaction_id: &str,
+        refund: &RefundExternalTransactionRequest,
+    ) -> Result<ExternalTransactionModel, CalloutError>;
+
+    /// Mints a fresh access token from the configured service account key.
+    async fn check_credentials(&self) -> Result<(), ServerError>;
+
+    /// Checks that the configured service account key can be parsed, without
+    /// any callout.
+    fn check_key(&self) -> Result<(), ServerError>;
+
+    /// Checks that the service account may access the app's purchases, with
+    /// an authenticated callout (looking up a purchase which does not exist).
+    async fn check_permissions(&self, package_name: &str) -> Result<(), ServerError>;
 }
 
 pub(crate) struct GooglePlayDeveloperApiDatasourceImpl {
-    access_token: String,
+    api_key: SecretString,
+    /// Minted on first use if credentials are initialized lazily, and again
+    /// shortly before it expires.
+    access_token: CachedCredential,
+    base_url: String,
+    http_client: HttpClient,
+    captures: PayloadCaptures,
+    cache_store: Arc<dyn CacheStore>,
+    product_cache_ttl: Option<Duration>,
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl GooglePlayDeveloperApiDatasource for GooglePlayDeveloperApiDatasourceImpl {
     async fn get_product_purchase(
         &self,
         package_name: &str,
         product_id: &str,
         token: &str,
-    ) -> Result<ProductPurchaseModel, ServerError> {
-        let url = format!("https://androidpublisher.googleapis.com/androidpublisher/v3/applications/{package_name}/purchases/products/{product_id}/tokens/{token}");
-        self.callout(&url, "purchases.products.get", Method::Get)
+    ) -> Result<ProductPurchaseModel, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/purchases/products/{product_id}/tokens/{token}");
+        self.callout_json(&url, "purchases.products.get", Method::Get)
             .await
     }
 
+    async fn get_product_purchase_v2(
+        &self,
+        package_name: &str,
+        token: &str,
+    ) -> Result<ProductPurchaseV2Model, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/purchases/productsv2/tokens/{token}");
+        self.callout_json(
+            &url,
+            "purchases.productsv2.getproductpurchasev2",
+            Method::Get,
+        )
+        .await
+    }
+
     async fn get_subscription_purchase_v2(
         &self,
         package_name: &str,
         token: &str,
-    ) -> Result<SubscriptionPurchaseV2Model, ServerError> {
-        let url = format!("https://androidpublisher.googleapis.com/androidpublisher/v3/applications/{package_name}/purchases/subscriptionsv2/tokens/{token}");
-        self.callout(&url, "purchases.subscriptionsv2.get", Method::Get)
+    ) -> Result<SubscriptionPurchaseV2Model, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/purchases/subscriptionsv2/tokens/{token}");
+        self.callout_json(&url, "purchases.subscriptionsv2.get", Method::Get)
             .await
     }
 
@@ -118,9 +313,80 @@ impl GooglePlayDeveloperApiDatasource for GooglePlayDeveloperApiDatasourceImpl {
         &self,
         package_name: &str,
         sku: &str,
-    ) -> Result<InAppProductModel, ServerError> {
-        let url = format!("https://androidpublisher.googleapis.com/androidpublisher/v3/applications/{package_name}/inappproducts/{sku}");
-        self.callout(&url, "inappproducts.get", Method::Get).await
+    ) -> Result<InAppProductModel, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!(
+            "{base_url}/androidpublisher/v3/applications/{package_name}/inappproducts/{sku}"
+        );
+        let Some(ttl) = self.product_cache_ttl else {
+            return self
+                .callout_json(&url, "inappproducts.get", Method::Get)
+                .await;
+        };
+
+        // The raw response is cached, and parsed as usual on cache hits.
+        let cache_key = format!("iap:product:google:{package_name}:{sku}");
+        if let Ok(Some(body)) = self.cache_store.get(&cache_key).await {
+            if let Ok(product) = serde_json::from_str(&body) {
+                return Ok(product);
+            }
+        }
+        let body = self.callout(&url, "inappproducts.get", Method::Get).await?;
+        let product = Self::parse_response("inappproducts.get", &body)?;
+        let _ = self.cache_store.set(&cache_key, &body, ttl).await;
+        Ok(product)
+    }
+
+    async fn get_subscription(
+        &self,
+        package_name: &str,
+        product_id: &str,
+    ) -> Result<SubscriptionModel, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!(
+            "{base_url}/androidpublisher/v3/applications/{package_name}/subscriptions/{product_id}"
+        );
+        self.callout_json(&url, "monetization.subscriptions.get", Method::Get)
+            .await
+    }
+
+    async fn get_subscription_offer(
+        &self,
+        package_name: &str,
+        product_id: &str,
+        base_plan_id: &str,
+        offer_id: &str,
+    ) -> Result<SubscriptionOfferModel, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/subscriptions/{product_id}/basePlans/{base_plan_id}/offers/{offer_id}");
+        self.callout_json(
+            &url,
+            "monetization.subscriptions.basePlans.offers.get",
+            Method::Get,
+        )
+        .await
+    }
+
+    async fn list_subscription_offers(
+        &self,
+        package_name: &str,
+        product_id: &str,
+        base_plan_id: &str,
+        page_token: Option<&str>,
+    ) -> Result<(Vec<SubscriptionOfferModel>, Option<String>), CalloutError> {
+        let base_url = &self.base_url;
+        let url = match page_token {
+            Some(page_token) => format!("{base_url}/androidpublisher/v3/applications/{package_name}/subscriptions/{product_id}/basePlans/{base_plan_id}/offers?pageToken={page_token}"),
+            None => format!("{base_url}/androidpublisher/v3/applications/{package_name}/subscriptions/{product_id}/basePlans/{base_plan_id}/offers"),
+        };
+        let response: ListSubscriptionOffersResponseModel = self
+            .callout_json(
+                &url,
+                "monetization.subscriptions.basePlans.offers.list",
+                Method::Get,
+            )
+            .await?;
+        Ok((response.subscription_offers, response.next_page_token))
     }
 
     async fn consume_product_purchase(
@@ -128,99 +394,397 @@ impl GooglePlayDeveloperApiDatasource for GooglePlayDeveloperApiDatasourceImpl {
         package_name: &str,
         product_id: &str,
         token: &str,
-    ) -> Result<(), ServerError> {
-        let url = format!("https://androidpublisher.googleapis.com/androidpublisher/v3/applications/{package_name}/purchases/products/{product_id}/tokens/{token}:consume");
-        self.callout(&url, "purchases.products.consume", Method::Post)
+    ) -> Result<(), CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/purchases/products/{product_id}/tokens/{token}:consume");
+        self.callout_empty(&url, "purchases.products.consume", Method::Post)
+            .await
+    }
+
+    async fn defer_subscription_purchase(
+        &self,
+        package_name: &str,
+        subscription_id: &str,
+        token: &str,
+        request: &SubscriptionPurchasesDeferRequest,
+    ) -> Result<SubscriptionPurchasesDeferResponse, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/purchases/subscriptions/{subscription_id}/tokens/{token}:defer");
+        let body = Self::serialize_request("purchases.subscriptions.defer", request)?;
+        self.callout_json(
+            &url,
+            "purchases.subscriptions.defer",
+            Method::PostJson(&body),
+        )
+        .await
+    }
+
+    async fn create_external_transaction(
+        &self,
+        package_name: &str,
+        external_transaction_id: &str,
+        transaction: &ExternalTransactionModel,
+    ) -> Result<ExternalTransactionModel, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/externalTransactions?externalTransactionId={external_transaction_id}");
+        let body = Self::serialize_request("externaltransactions.create", transaction)?;
+        self.callout_json(&url, "externaltransactions.create", Method::PostJson(&body))
+            .await
+    }
+
+    async fn get_external_transaction(
+        &self,
+        package_name: &str,
+        external_transaction_id: &str,
+    ) -> Result<ExternalTransactionModel, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/externalTransactions/{external_transaction_id}");
+        self.callout_json(&url, "externaltransactions.get", Method::Get)
             .await
     }
+
+    async fn refund_external_transaction(
+        &self,
+        package_name: &str,
+        external_transaction_id: &str,
+        refund: &RefundExternalTransactionRequest,
+    ) -> Result<ExternalTransactionModel, CalloutError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/externalTransactions/{external_transaction_id}:refund");
+        let body = Self::serialize_request("externaltransactions.refund", refund)?;
+        self.callout_json(&url, "externaltransactions.refund", Method::PostJson(&body))
+            .await
+    }
+
+    async fn check_credentials(&self) -> Result<(), ServerError> {
+        Self::build_access_token(&self.api_key, &self.http_client)
+            .await
+            .map(|_| ())
+    }
+
+    fn check_key(&self) -> Result<(), ServerError> {
+        #[derive(Deserialize)]
+        struct ServiceAccountKey {
+            #[allow(dead_code)]
+            client_email: String,
+            private_key: SecretString,
+        }
+
+        let key: ServiceAccountKey =
+            serde_json::from_str(self.api_key.expose_secret()).map_err(|e| {
+                GooglePlayDeveloperApiKeyInvalid::with_debug(
+                    "Google Play API key could not be parsed",
+                    &redacted_json_error(&e),
+                )
+            })?;
+        jsonwebtoken::EncodingKey::from_rsa_pem(key.private_key.expose_secret().as_bytes())
+            .map(|_| ())
+            .map_err(|e| {
+                GooglePlayDeveloperApiKeyInvalid::with_debug(
+                    "Google Play API private key could not be parsed",
+                    &e,
+                )
+            })
+    }
+
+    async fn check_permissions(&self, package_name: &str) -> Result<(), ServerError> {
+        let base_url = &self.base_url;
+        let url = format!("{base_url}/androidpublisher/v3/applications/{package_name}/purchases/subscriptionsv2/tokens/0");
+        let builder = self
+            .http_client
+            .request(reqwest::Method::GET, &url)
+            .header(CONTENT_LENGTH, "0")
+            .bearer_auth(self.access_token().await?.expose_secret());
+        let response = self
+            .http_client
+            .send("PermissionCheck", builder)
+            .await
+            .map_err(|e| {
+                GooglePlayDeveloperApiError::with_debug(
+                    "PermissionCheck",
+                    "callout failed to send",
+                    &e,
+                )
+            })?;
+
+        // Google answers 401 / 403 if the service account has no access to
+        // the app. Otherwise, the lookup is expected to fail with 400 / 404,
+        // since the purchase token is bogus.
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(GooglePlayDeveloperApiKeyInvalid::with_debug(
+                "service account may not access the app's purchases (grant it access to the app, including financial data, in the Play Console)",
+                &response.text().await.unwrap_or_default(),
+            ));
+        }
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(GooglePlayDeveloperApiError::with_debug(
+                "PermissionCheck",
+                &format!("callout returned with {status} status code"),
+                &response.text().await.unwrap_or_default(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl GooglePlayDeveloperApiDatasourceImpl {
-    pub(crate) async fn new(api_key: &str) -> Result<Self, ServerError> {
+    pub(crate) async fn new(
+        api_key: &SecretString,
+        base_url: String,
+        http_client: HttpClient,
+        captures: PayloadCaptures,
+        cache_store: Arc<dyn CacheStore>,
+        product_cache_ttl: Option<Duration>,
+        lazy_credentials: bool,
+    ) -> Result<Self, ServerError> {
         Ok(Self {
-            access_token: Self::build_access_token(api_key).await?,
+            api_key: api_key.clone(),
+            access_token: match lazy_credentials {
+                true => CachedCredential::new(),
+                false => {
+                    CachedCredential::with(Self::build_access_token(api_key, &http_client).await?)
+                }
+            },
+            base_url,
+            http_client,
+            captures,
+            cache_store,
+            product_cache_ttl,
         })
     }
 
-    async fn build_access_token(api_key: &str) -> Result<String, ServerError> {
-        let key = parse_service_account_key(api_key).map_err(|e| {
-            GooglePlayDeveloperApiKeyInvalid::with_debug(
-                "Google Play API key could not be parsed",
-                &e,
-            )
-        })?;
-        let authenticator = ServiceAccountAuthenticator::builder(key)
-            .build()
+    /// Mints the access token, if not done yet.
+    pub(crate) async fn warm_up(&self) -> Result<(), ServerError> {
+        self.access_token().await.map(|_| ())
+    }
+
+    async fn access_token(&self) -> Result<SecretString, ServerError> {
+        self.access_token
+            .get(|| Self::build_access_token(&self.api_key, &self.http_client))
             .await
-            .map_err(|e| {
+    }
+
+    /// Service account OAuth flow, sent through the shared HTTP client (so
+    /// through the configured proxy, if any):
+    /// https://developers.google.com/identity/protocols/oauth2/service-account#httprest
+    ///
+    /// Returns the token and its expiry.
+    async fn build_access_token(
+        api_key: &SecretString,
+        http_client: &HttpClient,
+    ) -> Result<(SecretString, DateTime<Utc>), ServerError> {
+        #[derive(Deserialize)]
+        struct ServiceAccountKey {
+            client_email: String,
+            private_key: SecretString,
+            token_uri: String,
+        }
+        #[derive(Serialize)]
+        struct Claims<'a> {
+            iss: &'a str,
+            scope: &'a str,
+            aud: &'a str,
+            iat: i64,
+            exp: i64,
+        }
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            access_token: SecretString,
+            /// Lifetime of the token, in seconds.
+            expires_in: Option<i64>,
+        }
+
+        let key: ServiceAccountKey =
+            serde_json::from_str(api_key.expose_secret()).map_err(|e| {
                 GooglePlayDeveloperApiKeyInvalid::with_debug(
-                    "Google Play API service account authenticator could not be built",
-                    &e,
+                    "Google Play API key could not be parsed",
+                    &redacted_json_error(&e),
                 )
             })?;
+        let now = Utc::now();
+        let assertion = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            &Claims {
+                iss: &key.client_email,
+                scope: "https://www.googleapis.com/auth/androidpublisher",
+                aud: &key.token_uri,
+                iat: now.timestamp(),
+                exp: now.timestamp() + 3600,
+            },
+            &jsonwebtoken::EncodingKey::from_rsa_pem(key.private_key.expose_secret().as_bytes())
+                .map_err(|e| {
+                    GooglePlayDeveloperApiKeyInvalid::with_debug(
+                        "Google Play API private key could not be parsed",
+                        &e,
+                    )
+                })?,
+        )
+        .map(SecretString::new)
+        .map_err(|e| {
+            GooglePlayDeveloperApiKeyInvalid::with_debug(
+                "Google Play API service account assertion could not be signed",
+                &e,
+            )
+        })?;
 
-        let scopes = &["https://www.googleapis.com/auth/androidpublisher"];
-        Ok(authenticator
-            .token(scopes)
+        let builder = http_client
+            .request(reqwest::Method::POST, &key.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.expose_secret()),
+            ]);
+        let response = http_client
+            .send("GetAccessToken", builder)
             .await
+            .and_then(|response| response.error_for_status())
             .map_err(|e| {
                 GooglePlayDeveloperApiKeyInvalid::with_debug(
                     "Google Play API service account token could not be built",
                     &e,
                 )
-            })?
-            .token()
-            .ok_or(GooglePlayDeveloperApiKeyInvalid::new(
-                "Google Play API service account token is empty",
-            ))?
-            .to_string())
+            })?;
+        let token = response.json::<TokenResponse>().await.map_err(|e| {
+            GooglePlayDeveloperApiKeyInvalid::with_debug(
+                "Google Play API service account token response could not be parsed",
+                &e,
+            )
+        })?;
+        let expires_at = now + chrono::Duration::seconds(token.expires_in.unwrap_or(3600));
+        Ok((token.access_token, expires_at))
     }
 
-    async fn callout<T: DeserializeOwned + 'static>(
+    async fn callout_json<T: DeserializeOwned>(
         &self,
         url: &str,
         function_name: &str,
-        method: Method,
+        method: Method<'_>,
+    ) -> Result<T, CalloutError> {
+        let body = self.callout(url, function_name, method).await?;
+        Ok(Self::parse_response(function_name, &body)?)
+    }
+
+    fn serialize_request<T: Serialize>(
+        function_name: &str,
+        request: &T,
+    ) -> Result<serde_json::Value, ServerError> {
+        serde_json::to_value(request).map_err(|e| {
+            GooglePlayDeveloperApiError::with_debug(
+                function_name,
+                "failed to serialize request",
+                &e,
+            )
+        })
+    }
+
+    fn parse_response<T: DeserializeOwned>(
+        function_name: &str,
+        body: &str,
     ) -> Result<T, ServerError> {
-        let client = reqwest::Client::new();
-        let builder = match method {
-            Method::Post => client.post(url),
-            Method::Get => client.get(url),
-        };
-        let response = builder
-            .header(AUTHORIZATION, format!("Bearer {}", self.access_token))
-            .header(CONTENT_LENGTH, "0")
-            .send()
+        serde_json::from_str(body).map_err(|e| {
+            GooglePlayDeveloperApiError::with_debug(
+                function_name,
+                "failed to parse callout response",
+                &e,
+            )
+        })
+    }
+
+    /// For endpoints with an empty response body (ex. 200 with '{}', or 204),
+    /// which is discarded.
+    async fn callout_empty(
+        &self,
+        url: &str,
+        function_name: &str,
+        method: Method<'_>,
+    ) -> Result<(), CalloutError> {
+        self.callout(url, function_name, method).await.map(|_| ())
+    }
+
+    /// Sends the request, failing on non-success status codes, and returns the
+    /// response body.
+    async fn callout(
+        &self,
+        url: &str,
+        function_name: &str,
+        method: Method<'_>,
+    ) -> Result<String, CalloutError> {
+        self.send_callout(url, function_name, method)
             .await
-            .map_err(|e| {
-                GooglePlayDeveloperApiError::with_debug(function_name, "callout failed to send", &e)
-            })?;
+            .map_err(|e| e.at(IapPlatform::GooglePlay, function_name))
+    }
 
-        if !response.status().is_success() {
-            return Err(GooglePlayDeveloperApiError::with_debug(
-                function_name,
-                &format!(
-                    "callout returned with {} status code",
-                    response.status().to_string(),
-                ),
-                &response.text().await.unwrap_or_default(),
-            ));
-        }
+    async fn send_callout(
+        &self,
+        url: &str,
+        function_name: &str,
+        method: Method<'_>,
+    ) -> Result<String, CalloutError> {
+        // A rejected token (ex. if it was revoked) is replaced, and the
+        // request sent again, once.
+        let mut retried = false;
+        let response = loop {
+            let access_token = self.access_token().await?;
+            let builder = match method {
+                Method::Post => self
+                    .http_client
+                    .request(reqwest::Method::POST, url)
+                    .header(CONTENT_LENGTH, "0"),
+                Method::Get => self
+                    .http_client
+                    .request(reqwest::Method::GET, url)
+                    .header(CONTENT_LENGTH, "0"),
+                Method::PostJson(body) => self
+                    .http_client
+                    .request(reqwest::Method::POST, url)
+                    .json(body),
+            };
+            let builder = builder.bearer_auth(access_token.expose_secret());
+            let response = self
+                .http_client
+                .send(function_name, builder)
+                .await
+                .map_err(|e| {
+                    GooglePlayDeveloperApiError::with_debug(
+                        function_name,
+                        "callout failed to send",
+                        &e,
+                    )
+                })?;
+            if retried || response.status() != reqwest::StatusCode::UNAUTHORIZED {
+                break response;
+            }
+            self.access_token.invalidate(&access_token).await;
+            retried = true;
+        };
 
         // NOTE:
         //   Response from callout does not contain Authorization header (for
         //   Google, only server-to-server notifications do).
 
-        if TypeId::of::<T>() == TypeId::of::<()>() {
-            return Ok(unsafe { std::mem::zeroed() }); // Safe because () has no data.
-        }
-
-        response.json().await.map_err(|e| {
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
             GooglePlayDeveloperApiError::with_debug(
                 function_name,
-                "failed to parse callout response",
+                "failed to read callout response",
                 &e,
             )
-        })
+        })?;
+        self.captures
+            .capture(CapturedPayloadKind::ApiResponse, function_name, &body)
+            .await;
+
+        if !status.is_success() {
+            return Err(CalloutError::api(
+                GooglePlayDeveloperApiError::with_debug(
+                    function_name,
+                    &format!("callout returned with {} status code", status.to_string()),
+                    &body,
+                ),
+                PlatformApiError::from_google_play_response(status.as_u16(), &body),
+            ));
+        }
+
+        Ok(body)
     }
 }
@@ -0,0 +1,301 @@
+use std::sync::Arc;
+
+#[cfg(feature = "store-simulator")]
+use async_trait::async_trait;
+use fractic_server_error::ServerError;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, PROXY_AUTHORIZATION, USER_AGENT},
+    Method, RequestBuilder, Response,
+};
+use web_time::Instant;
+
+use crate::{
+    budget::{current_callout_class, CalloutBudget, CalloutClass},
+    config::{IapConfig, RetryPolicy},
+    correlation::current_correlation_id,
+    errors::HttpClientConfigInvalid,
+    interceptor::{CalloutInterceptor, CalloutRequest, CalloutResponse},
+};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Header carrying the Steam publisher Web API key.
+#[cfg(feature = "steam")]
+pub(crate) const STEAM_WEB_API_KEY_HEADER: &str = "x-webapi-key";
+
+/// Executes requests in place of the network, ex. to answer callouts from an
+/// in-process store simulator.
+#[cfg(feature = "store-simulator")]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub(crate) trait HttpTransport: Send + Sync {
+    async fn execute(&self, request: reqwest::Request) -> Result<Response, reqwest::Error>;
+}
+
+/// HTTP client shared by the platform API datasources, applying the
+/// configured retry policy and interceptors to every callout.
+#[derive(Clone)]
+pub(crate) struct HttpClient {
+    client: reqwest::Client,
+    default_headers: HeaderMap,
+    retry_policy: RetryPolicy,
+    interceptors: Vec<Arc<dyn CalloutInterceptor>>,
+    /// Header the correlation ID is sent in, if any.
+    correlation_id_header: Option<HeaderName>,
+    /// Limits the number of simultaneous requests (shared by clones).
+    concurrency_limit: Option<Arc<CalloutBudget>>,
+    #[cfg(feature = "store-simulator")]
+    transport: Option<Arc<dyn HttpTransport>>,
+}
+
+impl HttpClient {
+    pub(crate) fn new(config: &IapConfig) -> Result<Self, ServerError> {
+        Ok(Self {
+            client: Self::build_client(config)?,
+            default_headers: Self::build_default_headers(config)?,
+            retry_policy: config.retry_policy.clone(),
+            interceptors: config.interceptors.clone(),
+            correlation_id_header: config
+                .correlation_id_header
+                .as_ref()
+                .map(|name| {
+                    HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+                        HttpClientConfigInvalid::with_debug(
+                            "correlation ID header name is not valid",
+                            &e,
+                        )
+                    })
+                })
+                .transpose()?,
+            concurrency_limit: None,
+            #[cfg(feature = "store-simulator")]
+            transport: config.transport.clone(),
+        })
+    }
+
+    /// Copy of this client which allows at most 'limit' simultaneous requests
+    /// (across its clones), or no limit if 'None'. Requests over the limit
+    /// wait for a slot. 'reservations' sets aside slots of the limit for
+    /// requests of the given classes.
+    pub(crate) fn with_concurrency_limit(
+        &self,
+        limit: Option<usize>,
+        reservations: &[(CalloutClass, usize)],
+    ) -> Self {
+        Self {
+            concurrency_limit: limit.map(|limit| Arc::new(CalloutBudget::new(limit, reservations))),
+            ..self.clone()
+        }
+    }
+
+    /// Client applying the configured proxy and TLS settings, also used for
+    /// requests not sent through 'HttpClient' (ex. fetching Google's keys).
+    #[cfg(feature = "native")]
+    pub(crate) fn build_client(config: &IapConfig) -> Result<reqwest::Client, ServerError> {
+        use crate::config::{RootCertificate, TlsBackend};
+
+        let mut builder = match config.tls_backend {
+            TlsBackend::Rustls => reqwest::Client::builder().use_rustls_tls(),
+            #[cfg(feature = "native-tls")]
+            TlsBackend::NativeTls => reqwest::Client::builder().use_native_tls(),
+        }
+        .tls_built_in_root_certs(config.tls_built_in_root_certs);
+
+        if let Some(proxy_config) = &config.proxy {
+            let mut proxy = reqwest::Proxy::all(&proxy_config.url).map_err(|e| {
+                HttpClientConfigInvalid::with_debug("proxy URL could not be parsed", &e)
+            })?;
+            if let Some((username, password)) = &proxy_config.basic_auth {
+                proxy = proxy.basic_auth(username, password);
+            }
+            if let Some(no_proxy) = &proxy_config.no_proxy {
+                proxy = proxy.no_proxy(reqwest::NoProxy::from_string(no_proxy));
+            }
+            builder = builder.proxy(proxy);
+        }
+
+        for root_certificate in &config.root_certificates {
+            let certificate = match root_certificate {
+                RootCertificate::Pem(pem) => reqwest::Certificate::from_pem(pem),
+                RootCertificate::Der(der) => reqwest::Certificate::from_der(der),
+            }
+            .map_err(|e| {
+                HttpClientConfigInvalid::with_debug("root certificate could not be parsed", &e)
+            })?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        builder
+            .build()
+            .map_err(|e| HttpClientConfigInvalid::with_debug("client could not be built", &e))
+    }
+
+    /// Without the 'native' feature, the platform's default client is used
+    /// (on WASM, this is the fetch API).
+    #[cfg(not(feature = "native"))]
+    pub(crate) fn build_client(_config: &IapConfig) -> Result<reqwest::Client, ServerError> {
+        reqwest::Client::builder()
+            .build()
+            .map_err(|e| HttpClientConfigInvalid::with_debug("client could not be built", &e))
+    }
+
+    /// The configured User-Agent and additional headers. These are added to
+    /// each request (rather than set on the client), since not all targets
+    /// support client-level defaults.
+    fn build_default_headers(config: &IapConfig) -> Result<HeaderMap, ServerError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| HttpClientConfigInvalid::with_debug("header name is not valid", &e))?;
+            let value = HeaderValue::from_str(value).map_err(|e| {
+                HttpClientConfigInvalid::with_debug("header value is not valid", &e)
+            })?;
+            headers.append(name, value);
+        }
+        if let Some(user_agent) = &config.user_agent {
+            let value = HeaderValue::from_str(user_agent)
+                .map_err(|e| HttpClientConfigInvalid::with_debug("user agent is not valid", &e))?;
+            headers.insert(USER_AGENT, value);
+        }
+        Ok(headers)
+    }
+
+    pub(crate) fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client
+            .request(method, url)
+            .headers(self.default_headers.clone())
+    }
+
+    /// Sends the request, retrying transient failures according to the retry
+    /// policy.
+    ///
+    /// If all attempts fail, the last response (which may have a non-success
+    /// status code) or error is returned, so callers can handle it as usual.
+    pub(crate) async fn send(
+        &self,
+        function_name: &str,
+        builder: RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let mut request = builder.build()?;
+        let correlation_id = current_correlation_id();
+        let class = current_callout_class();
+        if let (Some(name), Some(value)) = (
+            &self.correlation_id_header,
+            correlation_id
+                .as_ref()
+                .and_then(|id| HeaderValue::from_str(id).ok()),
+        ) {
+            request.headers_mut().insert(name.clone(), value);
+        }
+
+        if !self.interceptors.is_empty() {
+            let mut view = CalloutRequest::new(
+                function_name,
+                request.method().to_string(),
+                request.url().to_string(),
+                request
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = if is_credential_header(name) {
+                            REDACTED.to_owned()
+                        } else {
+                            value.to_str().unwrap_or(REDACTED).to_owned()
+                        };
+                        (name.to_string(), value)
+                    })
+                    .collect(),
+                correlation_id.clone(),
+            );
+            for interceptor in &self.interceptors {
+                interceptor.on_request(&mut view).await;
+            }
+            for (name, value) in view.added_headers() {
+                // Headers which are not valid HTTP are ignored.
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.as_bytes()),
+                    HeaderValue::from_str(value),
+                ) {
+                    request.headers_mut().insert(name, value);
+                }
+            }
+        }
+
+        let mut attempt = 1;
+        loop {
+            // Requests without a streaming body can always be cloned. If not,
+            // fall back to a single attempt.
+            let (current, next) = match request.try_clone() {
+                Some(next) if attempt < self.retry_policy.max_attempts => (next, Some(request)),
+                _ => (request, None),
+            };
+            let (method, url) = (current.method().to_string(), current.url().to_string());
+            let start = Instant::now();
+            let result = self.execute(current, class).await;
+            if !self.interceptors.is_empty() {
+                let view = CalloutResponse {
+                    function_name: function_name.to_owned(),
+                    method,
+                    url,
+                    attempt,
+                    status: result.as_ref().ok().map(|r| r.status().as_u16()),
+                    error: result.as_ref().err().map(|e| e.to_string()),
+                    elapsed: start.elapsed(),
+                    correlation_id: correlation_id.clone(),
+                };
+                for interceptor in &self.interceptors {
+                    interceptor.on_response(&view).await;
+                }
+            }
+            let Some(next) = next else {
+                return result;
+            };
+            let should_retry = match &result {
+                Ok(response) => self.retry_policy.should_retry_status(response.status()),
+                Err(e) => self.retry_policy.should_retry_error(e),
+            };
+            if !should_retry {
+                return result;
+            }
+            sleep(self.retry_policy.delay_for_retry(attempt)).await;
+            request = next;
+            attempt += 1;
+        }
+    }
+
+    async fn execute(
+        &self,
+        request: reqwest::Request,
+        class: CalloutClass,
+    ) -> Result<Response, reqwest::Error> {
+        // The slot is only held for the attempt itself, not while waiting to
+        // retry.
+        let _permit = match &self.concurrency_limit {
+            Some(budget) => budget.acquire(class).await,
+            None => None,
+        };
+        #[cfg(feature = "store-simulator")]
+        if let Some(transport) = &self.transport {
+            return transport.execute(request).await;
+        }
+        self.client.execute(request).await
+    }
+}
+
+fn is_credential_header(name: &HeaderName) -> bool {
+    #[cfg(feature = "steam")]
+    if name == STEAM_WEB_API_KEY_HEADER {
+        return true;
+    }
+    name == AUTHORIZATION || name == PROXY_AUTHORIZATION
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: std::time::Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: std::time::Duration) {
+    gloo_timers::future::sleep(duration).await;
+}
@@ -0,0 +1,338 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    capture::{CapturedPayloadKind, PayloadCaptures},
+    config::MicrosoftStoreCredentials,
+    data::{
+        datasources::{
+            cached_credential::CachedCredential, callout_error::CalloutError,
+            http_client::HttpClient,
+        },
+        models::microsoft_store_collections_api::collections_query_response_model::{
+            CollectionItemModel, CollectionsQueryResponseModel,
+        },
+    },
+    domain::entities::iap_api_error::PlatformApiError,
+    error_observer::IapPlatform,
+    errors::{MicrosoftStoreApiError, MicrosoftStoreCredentialsInvalid},
+    secrets::SecretString,
+};
+
+/// Audience of the Azure AD access token required by the collections API.
+const COLLECTIONS_RESOURCE: &str = "https://onestore.microsoft.com";
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub(crate) trait MicrosoftStoreCollectionsApiDatasource: Send + Sync {
+    /// Query for products:
+    /// https://learn.microsoft.com/en-us/windows/uwp/monetize/query-for-products
+    ///
+    /// store_id_key:
+    ///   The Microsoft Store ID key of the user, generated on the client.
+    /// product_id:
+    ///   The Store ID of the product (ex. '9NBLGGH4R315').
+    async fn query_collections(
+        &self,
+        store_id_key: &str,
+        product_id: &str,
+    ) -> Result<Vec<CollectionItemModel>, CalloutError>;
+
+    /// Report consumable products as fulfilled:
+    /// https://learn.microsoft.com/en-us/windows/uwp/monetize/report-consumable-products-as-fulfilled
+    ///
+    /// store_id_key:
+    ///   The Microsoft Store ID key of the user, generated on the client.
+    /// product_id:
+    ///   The Store ID of the consumable product.
+    /// tracking_id:
+    ///   GUID identifying the fulfillment. Retrying with the same ID does not
+    ///   consume the product again.
+    async fn consume(
+        &self,
+        store_id_key: &str,
+        product_id: &str,
+        tracking_id: &str,
+    ) -> Result<(), CalloutError>;
+}
+
+pub(crate) struct MicrosoftStoreCollectionsApiDatasourceImpl {
+    credentials: MicrosoftStoreCredentials,
+    /// Requested on first use if credentials are initialized lazily, and
+    /// again shortly before it expires.
+    access_token: CachedCredential,
+    collections_base_url: String,
+    login_base_url: String,
+    http_client: HttpClient,
+    captures: PayloadCaptures,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Beneficiary<'a> {
+    identitytype: &'a str,
+    identity_value: &'a str,
+    local_ticket_reference: &'a str,
+}
+
+impl<'a> Beneficiary<'a> {
+    fn b2b(store_id_key: &'a str) -> Self {
+        Self {
+            identitytype: "b2b",
+            identity_value: store_id_key,
+            local_ticket_reference: "",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProductSkuId<'a> {
+    product_id: &'a str,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl MicrosoftStoreCollectionsApiDatasource for MicrosoftStoreCollectionsApiDatasourceImpl {
+    async fn query_collections(
+        &self,
+        store_id_key: &str,
+        product_id: &str,
+    ) -> Result<Vec<CollectionItemModel>, CalloutError> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct QueryRequest<'a> {
+            beneficiaries: [Beneficiary<'a>; 1],
+            continuation_token: Option<&'a str>,
+            product_sku_ids: [ProductSkuId<'a>; 1],
+            validity_type: &'a str,
+        }
+
+        let base_url = &self.collections_base_url;
+        let url = format!("{base_url}/v6.0/collections/query");
+        let mut items = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let request = QueryRequest {
+                beneficiaries: [Beneficiary::b2b(store_id_key)],
+                continuation_token: continuation_token.as_deref(),
+                product_sku_ids: [ProductSkuId { product_id }],
+                // Include expired and revoked items, so they can be reported
+                // as inactive rather than as missing.
+                validity_type: "All",
+            };
+            let page: CollectionsQueryResponseModel = self
+                .callout_json(&url, "collections.query", &request)
+                .await?;
+            items.extend(page.items);
+            match page.continuation_token {
+                Some(token) if !token.is_empty() => continuation_token = Some(token),
+                _ => return Ok(items),
+            }
+        }
+    }
+
+    async fn consume(
+        &self,
+        store_id_key: &str,
+        product_id: &str,
+        tracking_id: &str,
+    ) -> Result<(), CalloutError> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ConsumeRequest<'a> {
+            beneficiary: Beneficiary<'a>,
+            product_id: &'a str,
+            tracking_id: &'a str,
+        }
+
+        let base_url = &self.collections_base_url;
+        let url = format!("{base_url}/v6.0/collections/consume");
+        let request = ConsumeRequest {
+            beneficiary: Beneficiary::b2b(store_id_key),
+            product_id,
+            tracking_id,
+        };
+        self.callout(&url, "collections.consume", &request)
+            .await
+            .map(|_| ())
+    }
+}
+
+impl MicrosoftStoreCollectionsApiDatasourceImpl {
+    pub(crate) async fn new(
+        credentials: MicrosoftStoreCredentials,
+        collections_base_url: String,
+        login_base_url: String,
+        http_client: HttpClient,
+        captures: PayloadCaptures,
+        lazy_credentials: bool,
+    ) -> Result<Self, ServerError> {
+        let datasource = Self {
+            credentials,
+            access_token: CachedCredential::new(),
+            collections_base_url,
+            login_base_url,
+            http_client,
+            captures,
+        };
+        if !lazy_credentials {
+            datasource.warm_up().await?;
+        }
+        Ok(datasource)
+    }
+
+    /// Requests the access token, if not done yet.
+    pub(crate) async fn warm_up(&self) -> Result<(), ServerError> {
+        self.access_token().await.map(|_| ())
+    }
+
+    async fn access_token(&self) -> Result<SecretString, ServerError> {
+        self.access_token.get(|| self.build_access_token()).await
+    }
+
+    /// Azure AD client credentials flow, for the collections API audience:
+    /// https://learn.microsoft.com/en-us/windows/uwp/monetize/view-and-grant-products-from-a-service#step-3
+    ///
+    /// Returns the token and its expiry.
+    async fn build_access_token(&self) -> Result<(SecretString, DateTime<Utc>), ServerError> {
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            access_token: SecretString,
+            /// Lifetime of the token, in seconds.
+            expires_in: Option<ExpiresIn>,
+        }
+        /// The v1 endpoint returns the lifetime as a string (ex. "3599").
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum ExpiresIn {
+            Number(i64),
+            Text(String),
+        }
+
+        let now = Utc::now();
+        let url = format!(
+            "{}/{}/oauth2/token",
+            self.login_base_url, self.credentials.tenant_id
+        );
+        let builder = self
+            .http_client
+            .request(reqwest::Method::POST, &url)
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", self.credentials.client_id.as_str()),
+                (
+                    "client_secret",
+                    self.credentials.client_secret.expose_secret(),
+                ),
+                ("resource", COLLECTIONS_RESOURCE),
+            ]);
+        let response = self
+            .http_client
+            .send("GetAccessToken", builder)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| {
+                MicrosoftStoreCredentialsInvalid::with_debug(
+                    "Azure AD access token could not be requested",
+                    &e,
+                )
+            })?;
+        let token = response.json::<TokenResponse>().await.map_err(|e| {
+            MicrosoftStoreCredentialsInvalid::with_debug(
+                "Azure AD access token response could not be parsed",
+                &e,
+            )
+        })?;
+        let expires_in = match token.expires_in {
+            Some(ExpiresIn::Number(seconds)) => Some(seconds),
+            Some(ExpiresIn::Text(seconds)) => seconds.parse().ok(),
+            None => None,
+        };
+        let expires_at = now + chrono::Duration::seconds(expires_in.unwrap_or(3600));
+        Ok((token.access_token, expires_at))
+    }
+
+    async fn callout_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        function_name: &str,
+        request: &impl Serialize,
+    ) -> Result<T, CalloutError> {
+        let body = self.callout(url, function_name, request).await?;
+        Ok(serde_json::from_str(&body).map_err(|e| {
+            MicrosoftStoreApiError::with_debug(
+                function_name,
+                "failed to parse callout response",
+                &e,
+            )
+        })?)
+    }
+
+    /// Sends the request, failing on non-success status codes, and returns the
+    /// response body.
+    async fn callout(
+        &self,
+        url: &str,
+        function_name: &str,
+        request: &impl Serialize,
+    ) -> Result<String, CalloutError> {
+        self.send_callout(url, function_name, request)
+            .await
+            .map_err(|e| e.at(IapPlatform::MicrosoftStore, function_name))
+    }
+
+    async fn send_callout(
+        &self,
+        url: &str,
+        function_name: &str,
+        request: &impl Serialize,
+    ) -> Result<String, CalloutError> {
+        // A rejected token (ex. if the client secret was rotated) is replaced,
+        // and the request sent again, once.
+        let mut retried = false;
+        let response = loop {
+            let access_token = self.access_token().await?;
+            let builder = self
+                .http_client
+                .request(reqwest::Method::POST, url)
+                .bearer_auth(access_token.expose_secret())
+                .json(request);
+            let response = self
+                .http_client
+                .send(function_name, builder)
+                .await
+                .map_err(|e| {
+                    MicrosoftStoreApiError::with_debug(function_name, "callout failed to send", &e)
+                })?;
+            if retried || response.status() != reqwest::StatusCode::UNAUTHORIZED {
+                break response;
+            }
+            self.access_token.invalidate(&access_token).await;
+            retried = true;
+        };
+
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            MicrosoftStoreApiError::with_debug(function_name, "failed to read callout response", &e)
+        })?;
+        self.captures
+            .capture(CapturedPayloadKind::ApiResponse, function_name, &body)
+            .await;
+
+        if !status.is_success() {
+            return Err(CalloutError::api(
+                MicrosoftStoreApiError::with_debug(
+                    function_name,
+                    &format!("callout returned with {status} status code"),
+                    &body,
+                ),
+                PlatformApiError::from_microsoft_store_response(status.as_u16(), &body),
+            ));
+        }
+
+        Ok(body)
+    }
+}
@@ -0,0 +1,333 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use fractic_server_error::{CriticalError, ServerError};
+use jwtk::{
+    jwk::{JwkSet, JwkSetVerifier},
+    OneOrMany,
+};
+use once_cell::sync::Lazy;
+use openssl::{
+    asn1::Asn1Time,
+    error::ErrorStack,
+    stack::Stack,
+    x509::{
+        store::{X509Store, X509StoreBuilder},
+        X509StoreContext, X509,
+    },
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::IapConfig,
+    data::datasources::http_client::HttpClient,
+    errors::{GoogleJwksInvalid, InvalidAppleSignature, InvalidGoogleSignature},
+    verifier::SignatureVerifier,
+};
+
+static APPLE_TRUST_STORE: Lazy<Result<X509Store, ErrorStack>> = Lazy::new(|| {
+    let mut store_builder = X509StoreBuilder::new()?;
+    X509::from_der(include_bytes!("../../../res/trust/AppleRootCA-G2.cer"))
+        .and_then(|cert| store_builder.add_cert(cert))?;
+    X509::from_der(include_bytes!("../../../res/trust/AppleRootCA-G3.cer"))
+        .and_then(|cert| store_builder.add_cert(cert))?;
+    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG2.cer"))
+        .and_then(|cert| store_builder.add_cert(cert))?;
+    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG3.cer"))
+        .and_then(|cert| store_builder.add_cert(cert))?;
+    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG4.cer"))
+        .and_then(|cert| store_builder.add_cert(cert))?;
+    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG5.cer"))
+        .and_then(|cert| store_builder.add_cert(cert))?;
+    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG6.cer"))
+        .and_then(|cert| store_builder.add_cert(cert))?;
+    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG8.cer"))
+        .and_then(|cert| store_builder.add_cert(cert))?;
+    Ok(store_builder.build())
+});
+
+/// Number of validated Apple certificate chains kept. Apple only signs with a
+/// few leaf certificates at a time, so this is plenty.
+const APPLE_CHAIN_CACHE_CAPACITY: usize = 16;
+
+/// Default 'SignatureVerifier', backed by OpenSSL and jwtk.
+pub(crate) struct NativeSignatureVerifier {
+    google_keys: GoogleKeys,
+    apple_chains: AppleChainCache,
+}
+
+/// Successfully validated Apple x5c chains, keyed by their SHA-256
+/// fingerprint, so that the same chain is not decoded and validated again for
+/// every JWS (ex. during notification bursts). Least recently used chains are
+/// evicted first.
+#[derive(Default)]
+struct AppleChainCache {
+    inner: Mutex<AppleChainCacheInner>,
+}
+
+#[derive(Default)]
+struct AppleChainCacheInner {
+    entries: HashMap<[u8; 32], ValidatedChain>,
+    /// Incremented on every access, to order entries by last use.
+    tick: u64,
+}
+
+struct ValidatedChain {
+    /// The leaf certificate's public key, PEM-encoded.
+    public_key_pem: Vec<u8>,
+    /// Earliest expiry of the chain's certificates (UNIX timestamp), after
+    /// which the chain must be validated again (and fail).
+    not_after: i64,
+    last_used: u64,
+}
+
+impl AppleChainCache {
+    fn fingerprint(x5c_chain: &[Vec<u8>]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for der in x5c_chain {
+            hasher.update((der.len() as u64).to_be_bytes());
+            hasher.update(der);
+        }
+        hasher.finalize().into()
+    }
+
+    fn get(&self, fingerprint: &[u8; 32]) -> Option<Vec<u8>> {
+        let mut inner = self.inner.lock().ok()?;
+        inner.tick += 1;
+        let tick = inner.tick;
+        let now = chrono::Utc::now().timestamp();
+        match inner.entries.get_mut(fingerprint) {
+            Some(chain) if now < chain.not_after => {
+                chain.last_used = tick;
+                Some(chain.public_key_pem.clone())
+            }
+            Some(_) => {
+                inner.entries.remove(fingerprint);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, fingerprint: [u8; 32], public_key_pem: Vec<u8>, not_after: i64) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.tick += 1;
+        let tick = inner.tick;
+        if inner.entries.len() >= APPLE_CHAIN_CACHE_CAPACITY
+            && !inner.entries.contains_key(&fingerprint)
+        {
+            if let Some(oldest) = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, chain)| chain.last_used)
+                .map(|(fingerprint, _)| *fingerprint)
+            {
+                inner.entries.remove(&oldest);
+            }
+        }
+        inner.entries.insert(
+            fingerprint,
+            ValidatedChain {
+                public_key_pem,
+                not_after,
+                last_used: tick,
+            },
+        );
+    }
+}
+
+/// Minimum age of the cached Google keys before a failed verification fetches
+/// them again (in case Google rotated its keys), so that invalid tokens can
+/// not trigger a fetch each.
+const GOOGLE_KEYS_MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+
+type CachedGoogleKeys = Option<(Arc<JwkSetVerifier>, Instant)>;
+
+enum GoogleKeys {
+    /// Fetched from the JWK URL (through the configured HTTP client, so
+    /// through the proxy, if any), and cached for 'cache_ttl'.
+    Remote {
+        url: String,
+        cache_ttl: Duration,
+        client: reqwest::Client,
+        cached: RwLock<CachedGoogleKeys>,
+    },
+    /// Pre-seeded through 'IapUtilBuilder::google_jwks'.
+    Static(JwkSetVerifier),
+}
+
+impl NativeSignatureVerifier {
+    pub(crate) fn new(config: &IapConfig) -> Result<Self, ServerError> {
+        let google_keys = match &config.google_jwks {
+            Some(jwks) => GoogleKeys::Static(
+                serde_json::from_str::<JwkSet>(jwks)
+                    .map_err(|e| GoogleJwksInvalid::with_debug("failed to parse JWK set", &e))?
+                    .verifier(),
+            ),
+            None => GoogleKeys::Remote {
+                url: config.google_jwk_url.clone(),
+                cache_ttl: config.google_jwks_cache_ttl,
+                client: HttpClient::build_client(config)?,
+                cached: RwLock::new(None),
+            },
+        };
+        Ok(Self {
+            google_keys,
+            apple_chains: AppleChainCache::default(),
+        })
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl SignatureVerifier for NativeSignatureVerifier {
+    async fn verify_apple_certificate_chain(
+        &self,
+        x5c_chain: &[Vec<u8>],
+    ) -> Result<Vec<u8>, ServerError> {
+        let fingerprint = AppleChainCache::fingerprint(x5c_chain);
+        if let Some(public_key_pem) = self.apple_chains.get(&fingerprint) {
+            return Ok(public_key_pem);
+        }
+
+        let certs = x5c_chain
+            .iter()
+            .map(|der| {
+                X509::from_der(der).map_err(|e| {
+                    InvalidAppleSignature::with_debug("failed to decode x5c certs", &e)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Validate certificate chain.
+        let mut chain = Stack::new()
+            .map_err(|e| CriticalError::with_debug("failed to create X509 stack", &e))?;
+        let mut certs_iter = certs.into_iter();
+        let leaf_cert = certs_iter
+            .next()
+            .ok_or(InvalidAppleSignature::new("empty x5c chain"))?;
+        for cert in certs_iter {
+            chain
+                .push(cert.clone())
+                .map_err(|e| CriticalError::with_debug("failed to push cert to X509 stack", &e))?;
+        }
+        let mut cxt = X509StoreContext::new()
+            .map_err(|e| CriticalError::with_debug("failed to create X509 store context", &e))?;
+        let trust_store = APPLE_TRUST_STORE
+            .as_ref()
+            .map_err(|e| CriticalError::with_debug("failed to build Apple trust store", e))?;
+        let valid = cxt
+            .init(&trust_store, &leaf_cert, &chain, |cxt| cxt.verify_cert())
+            .map_err(|e| InvalidAppleSignature::with_debug("failed to validate x5c chain", &e))?;
+        if !valid {
+            return Err(InvalidAppleSignature::new("invalid x5c chain"));
+        }
+
+        // Calculate public key used to sign JWS.
+        let public_key = leaf_cert.public_key().map_err(|e| {
+            InvalidAppleSignature::with_debug("couldn't get public key from leaf cert", &e)
+        })?;
+        let public_key_pem = public_key.public_key_to_pem().map_err(|e| {
+            InvalidAppleSignature::with_debug("couldn't convert public key to PEM", &e)
+        })?;
+
+        // Only cached if the expiry of the chain is known.
+        if let Some(not_after) = chain_not_after(&leaf_cert, &chain) {
+            self.apple_chains
+                .insert(fingerprint, public_key_pem.clone(), not_after);
+        }
+        Ok(public_key_pem)
+    }
+
+    async fn verify_google_token(&self, token: &str) -> Result<Vec<String>, ServerError> {
+        let result = match &self.google_keys {
+            GoogleKeys::Remote {
+                url,
+                cache_ttl,
+                client,
+                cached,
+            } => {
+                // Cloned out of the lock, so that it is not held while the keys
+                // are fetched.
+                let current = cached
+                    .read()
+                    .map_err(|_| CriticalError::new("Google JWK verifier lock poisoned"))?
+                    .clone()
+                    .filter(|(_, fetched_at)| fetched_at.elapsed() < *cache_ttl);
+                let (verifier, fetched_at) = match current {
+                    Some(current) => current,
+                    None => fetch_google_keys(url, client, cached).await?,
+                };
+                match verifier.verify::<serde_json::Map<String, serde_json::Value>>(token) {
+                    Err(_) if fetched_at.elapsed() >= GOOGLE_KEYS_MIN_REFETCH_INTERVAL => {
+                        fetch_google_keys(url, client, cached)
+                            .await?
+                            .0
+                            .verify::<serde_json::Map<String, serde_json::Value>>(token)
+                    }
+                    result => result,
+                }
+            }
+            GoogleKeys::Static(verifier) => {
+                verifier.verify::<serde_json::Map<String, serde_json::Value>>(token)
+            }
+        }
+        .map_err(|e| InvalidGoogleSignature::with_debug("token", &e))?;
+        Ok(match &result.claims().aud {
+            OneOrMany::One(aud) => vec![aud.clone()],
+            OneOrMany::Vec(auds) => auds.clone(),
+        })
+    }
+
+    async fn refresh_google_keys(&self) -> Result<(), ServerError> {
+        if let GoogleKeys::Remote { cached, .. } = &self.google_keys {
+            *cached
+                .write()
+                .map_err(|_| CriticalError::new("Google JWK verifier lock poisoned"))? = None;
+        }
+        Ok(())
+    }
+}
+
+/// Fetch Google's keys, replacing the cached ones.
+async fn fetch_google_keys(
+    url: &str,
+    client: &reqwest::Client,
+    cached: &RwLock<CachedGoogleKeys>,
+) -> Result<(Arc<JwkSetVerifier>, Instant), ServerError> {
+    let jwks = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| GoogleJwksInvalid::with_debug("failed to fetch JWK set", &e))?
+        .json::<JwkSet>()
+        .await
+        .map_err(|e| GoogleJwksInvalid::with_debug("failed to parse JWK set", &e))?;
+    let fetched = (Arc::new(jwks.verifier()), Instant::now());
+    *cached
+        .write()
+        .map_err(|_| CriticalError::new("Google JWK verifier lock poisoned"))? =
+        Some(fetched.clone());
+    Ok(fetched)
+}
+
+/// Earliest expiry (UNIX timestamp) of the given certificates.
+fn chain_not_after(leaf_cert: &X509, chain: &Stack<X509>) -> Option<i64> {
+    let epoch = Asn1Time::from_unix(0).ok()?;
+    std::iter::once(leaf_cert.as_ref())
+        .chain(chain.iter())
+        .map(|cert| {
+            let diff = epoch.diff(cert.not_after()).ok()?;
+            Some(i64::from(diff.days) * 86_400 + i64::from(diff.secs))
+        })
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .min()
+}
@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use fractic_server_error::ServerError;
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use crate::{
+    capture::{CapturedPayloadKind, PayloadCaptures},
+    data::models::paddle_billing_webhooks::event_model::EventModel,
+    errors::{InvalidPaddleSignature, PaddleWebhookParseError},
+    secrets::SecretString,
+};
+
+/// Maximum age of the signature timestamp, to limit replays of intercepted
+/// notifications. Paddle signs each delivery attempt separately, so
+/// redeliveries are not affected.
+const MAX_SIGNATURE_AGE_SECS: i64 = 5 * 60;
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub(crate) trait PaddleWebhookDatasource: Send + Sync {
+    /// Parse Paddle Billing webhook notification:
+    /// https://developer.paddle.com/webhooks/signature-verification
+    ///
+    /// signature_header:
+    ///   The value of the 'Paddle-Signature' header.
+    /// body:
+    ///   The raw POST body of the notification (the signature covers the
+    ///   exact bytes, so it must not be re-serialized).
+    async fn parse_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<EventModel, ServerError>;
+}
+
+pub(crate) struct PaddleWebhookDatasourceImpl {
+    webhook_secret: SecretString,
+    captures: PayloadCaptures,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl PaddleWebhookDatasource for PaddleWebhookDatasourceImpl {
+    async fn parse_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<EventModel, ServerError> {
+        self.captures
+            .capture(
+                CapturedPayloadKind::NotificationBody,
+                "PaddleNotification",
+                body,
+            )
+            .await;
+        self.verify_signature(signature_header, body)?;
+        serde_json::from_str(body).map_err(|e| {
+            PaddleWebhookParseError::with_debug("failed to parse notification struct", &e)
+        })
+    }
+}
+
+impl PaddleWebhookDatasourceImpl {
+    pub(crate) fn new(webhook_secret: SecretString, captures: PayloadCaptures) -> Self {
+        Self {
+            webhook_secret,
+            captures,
+        }
+    }
+
+    /// The header has the format 'ts=<unix timestamp>;h1=<hex signature>',
+    /// where the signature is an HMAC-SHA256 of '<timestamp>:<body>', keyed
+    /// with the notification destination's secret. While the secret is being
+    /// rotated, the header contains an 'h1' value for each secret.
+    fn verify_signature(&self, signature_header: &str, body: &str) -> Result<(), ServerError> {
+        let mut timestamp = None;
+        let mut signatures = Vec::new();
+        for part in signature_header.split(';') {
+            match part.trim().split_once('=') {
+                Some(("ts", value)) => timestamp = Some(value),
+                Some(("h1", value)) => signatures.push(value),
+                _ => {}
+            }
+        }
+        let timestamp = timestamp.ok_or_else(|| InvalidPaddleSignature::new("timestamp"))?;
+        let signed_at: i64 = timestamp
+            .parse()
+            .map_err(|_| InvalidPaddleSignature::new("timestamp"))?;
+        if (Utc::now().timestamp() - signed_at).abs() > MAX_SIGNATURE_AGE_SECS {
+            return Err(InvalidPaddleSignature::new("timestamp"));
+        }
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(self.webhook_secret.expose_secret().as_bytes())
+                .map_err(|_| InvalidPaddleSignature::new("secret"))?;
+        mac.update(timestamp.as_bytes());
+        mac.update(b":");
+        mac.update(body.as_bytes());
+        // 'verify_slice' compares in constant time.
+        let valid = signatures.iter().any(|signature| {
+            hex::decode(signature)
+                .map(|signature| mac.clone().verify_slice(&signature).is_ok())
+                .unwrap_or(false)
+        });
+        match valid {
+            true => Ok(()),
+            false => Err(InvalidPaddleSignature::new("signature")),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    capture::{CapturedPayloadKind, PayloadCaptures},
+    config::SteamCredentials,
+    data::{
+        datasources::{
+            callout_error::CalloutError,
+            http_client::{HttpClient, STEAM_WEB_API_KEY_HEADER},
+        },
+        models::steam_micro_txn_api::query_txn_response_model::{
+            FinalizeTxnModel, QueryTxnModel, SteamResponseModel,
+        },
+    },
+    domain::entities::iap_api_error::PlatformApiError,
+    error_observer::IapPlatform,
+    errors::SteamMicroTxnApiError,
+};
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub(crate) trait SteamMicroTxnApiDatasource: Send + Sync {
+    /// ISteamMicroTxn/QueryTxn:
+    /// https://partner.steamgames.com/doc/webapi/ISteamMicroTxn#QueryTxn
+    ///
+    /// orderid:
+    ///   The developer-chosen ID of the order.
+    async fn query_txn(&self, orderid: u64) -> Result<QueryTxnModel, CalloutError>;
+
+    /// ISteamMicroTxn/FinalizeTxn:
+    /// https://partner.steamgames.com/doc/webapi/ISteamMicroTxn#FinalizeTxn
+    ///
+    /// orderid:
+    ///   The developer-chosen ID of the order, which the user has approved.
+    async fn finalize_txn(&self, orderid: u64) -> Result<(), CalloutError>;
+}
+
+pub(crate) struct SteamMicroTxnApiDatasourceImpl {
+    credentials: SteamCredentials,
+    /// Base URL of the interface (ISteamMicroTxn or ISteamMicroTxnSandbox).
+    interface_url: String,
+    http_client: HttpClient,
+    captures: PayloadCaptures,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl SteamMicroTxnApiDatasource for SteamMicroTxnApiDatasourceImpl {
+    async fn query_txn(&self, orderid: u64) -> Result<QueryTxnModel, CalloutError> {
+        let url = format!("{}/QueryTxn/v3/", self.interface_url);
+        let builder = self
+            .http_client
+            .request(reqwest::Method::GET, &url)
+            .query(&[
+                ("appid", self.credentials.app_id.to_string()),
+                ("orderid", orderid.to_string()),
+            ]);
+        self.callout(builder, "QueryTxn").await
+    }
+
+    async fn finalize_txn(&self, orderid: u64) -> Result<(), CalloutError> {
+        let url = format!("{}/FinalizeTxn/v2/", self.interface_url);
+        let builder = self
+            .http_client
+            .request(reqwest::Method::POST, &url)
+            .form(&[
+                ("appid", self.credentials.app_id.to_string()),
+                ("orderid", orderid.to_string()),
+            ]);
+        self.callout::<FinalizeTxnModel>(builder, "FinalizeTxn")
+            .await
+            .map(|_| ())
+    }
+}
+
+impl SteamMicroTxnApiDatasourceImpl {
+    pub(crate) fn new(
+        credentials: SteamCredentials,
+        base_url: &str,
+        sandbox: bool,
+        http_client: HttpClient,
+        captures: PayloadCaptures,
+    ) -> Self {
+        let interface = match sandbox {
+            true => "ISteamMicroTxnSandbox",
+            false => "ISteamMicroTxn",
+        };
+        Self {
+            credentials,
+            interface_url: format!("{base_url}/{interface}"),
+            http_client,
+            captures,
+        }
+    }
+
+    /// Sends the request, and returns the response's 'params'. Failures are
+    /// reported both through non-success status codes, and through
+    /// "Failure" results in successful responses.
+    async fn callout<T: DeserializeOwned>(
+        &self,
+        builder: RequestBuilder,
+        function_name: &str,
+    ) -> Result<T, CalloutError> {
+        self.send_callout(builder, function_name)
+            .await
+            .map_err(|e| e.at(IapPlatform::Steam, function_name))
+    }
+
+    async fn send_callout<T: DeserializeOwned>(
+        &self,
+        builder: RequestBuilder,
+        function_name: &str,
+    ) -> Result<T, CalloutError> {
+        // The key is sent as a header rather than a query parameter, so that
+        // it is not part of the URL seen by interceptors.
+        let builder = builder.header(
+            STEAM_WEB_API_KEY_HEADER,
+            self.credentials.publisher_key.expose_secret(),
+        );
+        let response = self
+            .http_client
+            .send(function_name, builder)
+            .await
+            .map_err(|e| {
+                SteamMicroTxnApiError::with_debug(function_name, "callout failed to send", &e)
+            })?;
+
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            SteamMicroTxnApiError::with_debug(function_name, "failed to read callout response", &e)
+        })?;
+        self.captures
+            .capture(CapturedPayloadKind::ApiResponse, function_name, &body)
+            .await;
+
+        if !status.is_success() {
+            return Err(CalloutError::api(
+                SteamMicroTxnApiError::with_debug(
+                    function_name,
+                    &format!("callout returned with {status} status code"),
+                    &body,
+                ),
+                PlatformApiError::from_steam_response(status.as_u16(), &body),
+            ));
+        }
+
+        let parsed: SteamResponseModel<T> = serde_json::from_str(&body).map_err(|e| {
+            SteamMicroTxnApiError::with_debug(function_name, "failed to parse callout response", &e)
+        })?;
+        match parsed.response.params {
+            Some(params) if parsed.response.result == "OK" => Ok(params),
+            _ => Err(CalloutError::api(
+                SteamMicroTxnApiError::with_debug(function_name, "callout failed", &body),
+                PlatformApiError::from_steam_response(status.as_u16(), &body),
+            )),
+        }
+    }
+}
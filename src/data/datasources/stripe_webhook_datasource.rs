@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use fractic_server_error::ServerError;
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use crate::{
+    capture::{CapturedPayloadKind, PayloadCaptures},
+    data::models::stripe_webhooks::event_model::EventModel,
+    errors::{InvalidStripeSignature, StripeWebhookParseError},
+    secrets::SecretString,
+};
+
+/// Maximum age of the signature timestamp, to limit replays of intercepted
+/// events (same as the default tolerance of Stripe's libraries). Stripe signs
+/// each delivery attempt separately, so retries are not affected.
+const MAX_SIGNATURE_AGE_SECS: i64 = 5 * 60;
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub(crate) trait StripeWebhookDatasource: Send + Sync {
+    /// Parse Stripe webhook event:
+    /// https://docs.stripe.com/webhooks#verify-manually
+    ///
+    /// signature_header:
+    ///   The value of the 'Stripe-Signature' header.
+    /// body:
+    ///   The raw POST body of the event (the signature covers the exact
+    ///   bytes, so it must not be re-serialized).
+    async fn parse_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<EventModel, ServerError>;
+}
+
+pub(crate) struct StripeWebhookDatasourceImpl {
+    webhook_secret: SecretString,
+    captures: PayloadCaptures,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl StripeWebhookDatasource for StripeWebhookDatasourceImpl {
+    async fn parse_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<EventModel, ServerError> {
+        self.captures
+            .capture(
+                CapturedPayloadKind::NotificationBody,
+                "StripeNotification",
+                body,
+            )
+            .await;
+        self.verify_signature(signature_header, body)?;
+        serde_json::from_str(body)
+            .map_err(|e| StripeWebhookParseError::with_debug("failed to parse event struct", &e))
+    }
+}
+
+impl StripeWebhookDatasourceImpl {
+    pub(crate) fn new(webhook_secret: SecretString, captures: PayloadCaptures) -> Self {
+        Self {
+            webhook_secret,
+            captures,
+        }
+    }
+
+    /// The header has the format 't=<unix timestamp>,v1=<hex signature>',
+    /// where the signature is an HMAC-SHA256 of '<timestamp>.<body>', keyed
+    /// with the endpoint's signing secret. While the secret is being rolled,
+    /// the header contains a 'v1' value for each secret. Other schemes (ex.
+    /// 'v0', used for test events) are ignored.
+    fn verify_signature(&self, signature_header: &str, body: &str) -> Result<(), ServerError> {
+        let mut timestamp = None;
+        let mut signatures = Vec::new();
+        for part in signature_header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = Some(value),
+                Some(("v1", value)) => signatures.push(value),
+                _ => {}
+            }
+        }
+        let timestamp = timestamp.ok_or_else(|| InvalidStripeSignature::new("timestamp"))?;
+        let signed_at: i64 = timestamp
+            .parse()
+            .map_err(|_| InvalidStripeSignature::new("timestamp"))?;
+        if (Utc::now().timestamp() - signed_at).abs() > MAX_SIGNATURE_AGE_SECS {
+            return Err(InvalidStripeSignature::new("timestamp"));
+        }
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(self.webhook_secret.expose_secret().as_bytes())
+                .map_err(|_| InvalidStripeSignature::new("secret"))?;
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body.as_bytes());
+        // 'verify_slice' compares in constant time.
+        let valid = signatures.iter().any(|signature| {
+            hex::decode(signature)
+                .map(|signature| mac.clone().verify_slice(&signature).is_ok())
+                .unwrap_or(false)
+        });
+        match valid {
+            true => Ok(()),
+            false => Err(InvalidStripeSignature::new("signature")),
+        }
+    }
+}
@@ -1,56 +1,47 @@
-use std::time::Duration;
-
-use base64::{prelude::BASE64_STANDARD, Engine as _};
-use fractic_server_error::{CriticalError, ServerError};
-use jsonwebtoken::decode_header;
-use jwtk::{jwk::RemoteJwksVerifier, OneOrMany};
-use once_cell::sync::Lazy;
-use openssl::{
-    error::ErrorStack,
-    stack::Stack,
-    x509::{
-        store::{X509Store, X509StoreBuilder},
-        X509StoreContext, X509,
-    },
+use base64::{
+    prelude::{BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD},
+    Engine as _,
 };
+use fractic_server_error::ServerError;
+use jsonwebtoken::decode_header;
 use serde::de::DeserializeOwned;
 
 use crate::{
-    constants::GOOGLE_JWK_URL,
+    capture::{CapturedPayloadKind, PayloadCaptures},
     errors::{InvalidAppleSignature, InvalidGoogleSignature, InvalidJws},
+    verifier::SignatureVerifier,
 };
 
-static APPLE_TRUST_STORE: Lazy<Result<X509Store, ErrorStack>> = Lazy::new(|| {
-    let mut store_builder = X509StoreBuilder::new()?;
-    X509::from_der(include_bytes!("../../../res/trust/AppleRootCA-G2.cer"))
-        .and_then(|cert| store_builder.add_cert(cert))?;
-    X509::from_der(include_bytes!("../../../res/trust/AppleRootCA-G3.cer"))
-        .and_then(|cert| store_builder.add_cert(cert))?;
-    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG2.cer"))
-        .and_then(|cert| store_builder.add_cert(cert))?;
-    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG3.cer"))
-        .and_then(|cert| store_builder.add_cert(cert))?;
-    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG4.cer"))
-        .and_then(|cert| store_builder.add_cert(cert))?;
-    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG5.cer"))
-        .and_then(|cert| store_builder.add_cert(cert))?;
-    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG6.cer"))
-        .and_then(|cert| store_builder.add_cert(cert))?;
-    X509::from_der(include_bytes!("../../../res/trust/AppleWWDRCAG8.cer"))
-        .and_then(|cert| store_builder.add_cert(cert))?;
-    Ok(store_builder.build())
-});
-
-static GOOGLE_JWK_VERIFIER: Lazy<RemoteJwksVerifier> = Lazy::new(|| {
-    RemoteJwksVerifier::new(GOOGLE_JWK_URL.to_owned(), None, Duration::from_secs(300))
-});
-
 /// Validates that the jws is signed by Apple, and returns the payload parsed as
-/// type T from JSON.
+/// type T from JSON. If the payload has an 'aud' claim, it must be one of
+/// 'expected_auds'.
+///
+/// The decoded payload is passed to the captures (labelled with 'source')
+/// before the signature is checked, so payloads failing verification are
+/// captured too.
 pub(crate) async fn validate_and_parse_apple_jws<T: DeserializeOwned>(
+    verifier: &dyn SignatureVerifier,
     jws: &str,
-    expected_aud: &str,
+    expected_auds: &[String],
+    captures: &PayloadCaptures,
+    source: &str,
 ) -> Result<T, ServerError> {
+    if !captures.is_empty() {
+        if let Some(decoded) = jws
+            .split('.')
+            .nth(1)
+            .and_then(|payload| BASE64_URL_SAFE_NO_PAD.decode(payload).ok())
+        {
+            captures
+                .capture(
+                    CapturedPayloadKind::DecodedJws,
+                    source,
+                    &String::from_utf8_lossy(&decoded),
+                )
+                .await;
+        }
+    }
+
     // Parse x5c cert chain from JWS header.
     let header =
         decode_header(jws).map_err(|e| InvalidJws::with_debug("failed to parse JWS header", &e))?;
@@ -60,54 +51,43 @@ pub(crate) async fn validate_and_parse_apple_jws<T: DeserializeOwned>(
     let certs = x5c_chain
         .into_iter()
         .map(|x5c| {
-            X509::from_der(&BASE64_STANDARD.decode(x5c.as_bytes()).map_err(|e| {
+            BASE64_STANDARD.decode(x5c.as_bytes()).map_err(|e| {
                 InvalidAppleSignature::with_debug("failed to base64 decode x5c certs", &e)
-            })?)
-            .map_err(|e| InvalidAppleSignature::with_debug("failed to decode x5c certs", &e))
+            })
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    // Validate certificate chain.
-    let mut chain =
-        Stack::new().map_err(|e| CriticalError::with_debug("failed to create X509 stack", &e))?;
-    let mut certs_iter = certs.into_iter();
-    let leaf_cert = certs_iter
-        .next()
-        .ok_or(InvalidAppleSignature::new("empty x5c chain"))?;
-    for cert in certs_iter {
-        chain
-            .push(cert.clone())
-            .map_err(|e| CriticalError::with_debug("failed to push cert to X509 stack", &e))?;
-    }
-    let mut cxt = X509StoreContext::new()
-        .map_err(|e| CriticalError::with_debug("failed to create X509 store context", &e))?;
-    let trust_store = APPLE_TRUST_STORE
-        .as_ref()
-        .map_err(|e| CriticalError::with_debug("failed to build Apple trust store", e))?;
-    let valid = cxt
-        .init(&trust_store, &leaf_cert, &chain, |cxt| cxt.verify_cert())
-        .map_err(|e| InvalidAppleSignature::with_debug("failed to validate x5c chain", &e))?;
-    if !valid {
-        return Err(InvalidAppleSignature::new("invalid x5c chain"));
-    }
-
-    // Calculate public key used to sign JWS.
-    let public_key = leaf_cert.public_key().map_err(|e| {
-        InvalidAppleSignature::with_debug("couldn't get public key from leaf cert", &e)
-    })?;
-    let public_key_pem = public_key
-        .public_key_to_pem()
-        .map_err(|e| InvalidAppleSignature::with_debug("couldn't convert public key to PEM", &e))?;
+    // Validate certificate chain, and get public key used to sign JWS.
+    let public_key_pem = verifier.verify_apple_certificate_chain(&certs).await?;
 
     // Verify JWS signature.
     let decoding_key = jsonwebtoken::DecodingKey::from_ec_pem(&public_key_pem)
         .map_err(|e| InvalidAppleSignature::with_debug("failed to create decoding key", &e))?;
     let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::ES256);
     validation.required_spec_claims = Default::default();
-    validation.set_audience(&[expected_aud]);
+    validation.validate_aud = false;
     let payload = jsonwebtoken::decode::<serde_json::Value>(jws, &decoding_key, &validation)
         .map_err(|e| InvalidAppleSignature::with_debug("failed to verify JWS signature", &e))?;
 
+    // Validate audience (checked here rather than by 'jsonwebtoken', so the
+    // error can include the received audience).
+    if let Some(aud) = payload.claims.get("aud") {
+        let auds = match aud {
+            serde_json::Value::String(aud) => vec![aud.clone()],
+            serde_json::Value::Array(auds) => auds
+                .iter()
+                .filter_map(|aud| aud.as_str().map(str::to_owned))
+                .collect(),
+            _ => Vec::new(),
+        };
+        if !auds.iter().any(|aud| expected_auds.contains(aud)) {
+            return Err(InvalidAppleSignature::with_debug(
+                "audience",
+                &audience_mismatch(&auds, expected_auds),
+            ));
+        }
+    }
+
     // Parse payload.
     //
     // Since this is a JWT library, it expects the data to be JWT 'claims'.
@@ -118,23 +98,21 @@ pub(crate) async fn validate_and_parse_apple_jws<T: DeserializeOwned>(
 
 /// Validates that the jwt is signed by Google.
 pub(crate) async fn validate_google_header(
+    verifier: &dyn SignatureVerifier,
     authentication_header: &str,
-    expected_aud: &str,
+    expected_auds: &[String],
 ) -> Result<(), ServerError> {
     let token = authentication_header.trim_start_matches("Bearer ").trim();
-    let result = GOOGLE_JWK_VERIFIER
-        .verify::<serde_json::Map<String, serde_json::Value>>(token)
-        .await
-        .map_err(|e| InvalidGoogleSignature::with_debug("token", &e))?;
-    let valid_aud = match result.claims().aud {
-        OneOrMany::One(ref aud) => aud == expected_aud,
-        OneOrMany::Vec(ref auds) => auds.iter().any(|aud| aud == expected_aud),
-    };
-    if !valid_aud {
+    let auds = verifier.verify_google_token(token).await?;
+    if !auds.iter().any(|aud| expected_auds.contains(aud)) {
         return Err(InvalidGoogleSignature::with_debug(
             "audience",
-            &result.claims(),
+            &audience_mismatch(&auds, expected_auds),
         ));
     }
     Ok(())
 }
+
+fn audience_mismatch(received: &[String], expected: &[String]) -> String {
+    format!("received audience {received:?}, expected one of {expected:?}")
+}
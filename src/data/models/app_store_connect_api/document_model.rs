@@ -0,0 +1,86 @@
+#![allow(dead_code)]
+
+use serde::Deserialize;
+
+use super::{
+    price_model::{PricePointAttributes, TerritoryAttributes},
+    product_model::SubscriptionAttributes,
+};
+
+/// JSON:API document returned by the App Store Connect API's list endpoints.
+///
+/// https://developer.apple.com/documentation/appstoreconnectapi/fetching-data
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DocumentModel<A> {
+    pub(crate) data: Vec<ResourceModel<A>>,
+    /// Related resources requested with the 'include' parameter.
+    #[serde(default)]
+    pub(crate) included: Vec<IncludedResourceModel>,
+    pub(crate) links: Option<LinksModel>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ResourceModel<A> {
+    /// Opaque identifier of the resource (unique per resource type).
+    pub(crate) id: String,
+    pub(crate) attributes: Option<A>,
+    #[serde(default)]
+    pub(crate) relationships: RelationshipsModel,
+}
+
+/// Relationships of the resources requested by this crate (absent unless the
+/// related resources are included).
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RelationshipsModel {
+    pub(crate) territory: Option<RelationshipModel>,
+    pub(crate) in_app_purchase_price_point: Option<RelationshipModel>,
+    pub(crate) subscription_price_point: Option<RelationshipModel>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RelationshipModel {
+    pub(crate) data: Option<ResourceIdentifierModel>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ResourceIdentifierModel {
+    #[serde(rename = "type")]
+    pub(crate) resource_type: String,
+    pub(crate) id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum IncludedResourceModel {
+    InAppPurchasePricePoints {
+        id: String,
+        attributes: PricePointAttributes,
+    },
+    SubscriptionPricePoints {
+        id: String,
+        attributes: PricePointAttributes,
+    },
+    Territories {
+        id: String,
+        attributes: TerritoryAttributes,
+    },
+    Subscriptions {
+        id: String,
+        attributes: SubscriptionAttributes,
+    },
+
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LinksModel {
+    /// Full URL of the next page, if there is one.
+    pub(crate) next: Option<String>,
+}
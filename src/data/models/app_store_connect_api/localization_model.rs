@@ -0,0 +1,20 @@
+#![allow(dead_code)]
+
+use serde::Deserialize;
+
+/// Attributes of an in-app purchase or subscription localization (the
+/// display name and description shown to customers in one locale).
+///
+/// https://developer.apple.com/documentation/appstoreconnectapi/inapppurchaselocalization/attributes-data.dictionary
+/// https://developer.apple.com/documentation/appstoreconnectapi/subscriptionlocalization/attributes-data.dictionary
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LocalizationAttributes {
+    /// Locale of the localization (ex. 'en-US').
+    pub(crate) locale: String,
+    /// Display name of the product.
+    pub(crate) name: Option<String>,
+    pub(crate) description: Option<String>,
+    /// Review state (ex. 'APPROVED', 'PREPARE_FOR_SUBMISSION').
+    pub(crate) state: Option<String>,
+}
@@ -0,0 +1,57 @@
+#![allow(dead_code)]
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+/// Attributes of a price in an in-app purchase's price schedule.
+///
+/// https://developer.apple.com/documentation/appstoreconnectapi/inapppurchaseprice/attributes-data.dictionary
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InAppPurchasePriceAttributes {
+    /// First day the price applies. Not set for the price which applied when
+    /// the schedule was created.
+    pub(crate) start_date: Option<NaiveDate>,
+    /// First day the price no longer applies. Not set for the latest price.
+    pub(crate) end_date: Option<NaiveDate>,
+    /// Whether the price was set manually, rather than equalized from the
+    /// base territory's price.
+    pub(crate) manual: Option<bool>,
+}
+
+/// Attributes of a price of an auto-renewable subscription. Each price
+/// applies until the next price (by start date) in the same territory.
+///
+/// https://developer.apple.com/documentation/appstoreconnectapi/subscriptionprice/attributes-data.dictionary
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionPriceAttributes {
+    /// First day the price applies. Not set for the initial price.
+    pub(crate) start_date: Option<NaiveDate>,
+    /// Whether existing subscribers keep paying their previous price.
+    pub(crate) preserved: Option<bool>,
+}
+
+/// Attributes of an in-app purchase or subscription price point.
+///
+/// https://developer.apple.com/documentation/appstoreconnectapi/inapppurchasepricepointv2/attributes-data.dictionary
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PricePointAttributes {
+    /// The price charged to customers, as a decimal string in the territory's
+    /// currency (ex. '9.99').
+    pub(crate) customer_price: Option<String>,
+    /// The developer's proceeds, as a decimal string.
+    pub(crate) proceeds: Option<String>,
+}
+
+/// Attributes of an App Store territory (identified by its ISO 3166-1
+/// alpha-3 code).
+///
+/// https://developer.apple.com/documentation/appstoreconnectapi/territory/attributes-data.dictionary
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TerritoryAttributes {
+    /// ISO 4217 code of the territory's currency.
+    pub(crate) currency: Option<String>,
+}
@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+use serde::Deserialize;
+
+/// Attributes of an in-app purchase (consumable, non-consumable, or
+/// non-renewing subscription).
+///
+/// https://developer.apple.com/documentation/appstoreconnectapi/inapppurchasev2/attributes-data.dictionary
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InAppPurchaseAttributes {
+    /// The reference name of the in-app purchase.
+    pub(crate) name: Option<String>,
+    /// The product ID used in the app (the SKU).
+    pub(crate) product_id: String,
+    pub(crate) in_app_purchase_type: Option<InAppPurchaseType>,
+    /// Review state (ex. 'APPROVED', 'MISSING_METADATA').
+    pub(crate) state: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum InAppPurchaseType {
+    Consumable,
+    NonConsumable,
+    NonRenewingSubscription,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// Attributes of an auto-renewable subscription.
+///
+/// https://developer.apple.com/documentation/appstoreconnectapi/subscription/attributes-data.dictionary
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionAttributes {
+    /// The reference name of the subscription.
+    pub(crate) name: Option<String>,
+    /// The product ID used in the app (the SKU).
+    pub(crate) product_id: String,
+    /// Billing period (ex. 'ONE_MONTH').
+    pub(crate) subscription_period: Option<String>,
+    /// Review state (ex. 'APPROVED', 'MISSING_METADATA').
+    pub(crate) state: Option<String>,
+}
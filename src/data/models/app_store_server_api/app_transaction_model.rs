@@ -0,0 +1,52 @@
+#![allow(dead_code)]
+
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
+};
+use serde::Deserialize;
+
+use super::common::Environment;
+
+type AppleIdType = u64;
+
+/// Data structure for the decoded payload of a signed AppTransaction, which
+/// the app obtains through StoreKit as proof that the customer purchased (or
+/// downloaded) the app itself.
+///
+/// https://developer.apple.com/documentation/storekit/apptransaction
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppTransactionModel {
+    /// The server environment that signs the app transaction.
+    pub(crate) receipt_type: Environment,
+    /// The unique identifier the App Store uses to identify the app.
+    pub(crate) app_apple_id: Option<AppleIdType>,
+    /// The bundle identifier that the app transaction applies to.
+    pub(crate) bundle_id: String,
+    /// The app version that the app transaction applies to.
+    pub(crate) application_version: String,
+    /// The version number of the app that the customer originally purchased.
+    /// On iOS, this is the build number (CFBundleVersion); on macOS, the
+    /// version string (CFBundleShortVersionString).
+    pub(crate) original_application_version: String,
+    /// The date the customer originally purchased the app.
+    #[serde(with = "ts_milliseconds")]
+    pub(crate) original_purchase_date: DateTime<Utc>,
+    /// The date the App Store created the app transaction.
+    #[serde(default, with = "ts_milliseconds_option")]
+    pub(crate) receipt_creation_date: Option<DateTime<Utc>>,
+    /// The date the customer placed an order for the app before it became
+    /// available. Only present if the customer pre-ordered the app and it
+    /// has not been released yet.
+    #[serde(default, with = "ts_milliseconds_option")]
+    pub(crate) preorder_date: Option<DateTime<Utc>>,
+    /// The unique identifier of the app download transaction.
+    pub(crate) app_transaction_id: Option<String>,
+    /// The platform on which the customer originally purchased the app (ex.
+    /// "iOS", "macOS").
+    pub(crate) original_platform: Option<String>,
+    /// Used to verify the app transaction on the device.
+    pub(crate) device_verification: Option<String>,
+    pub(crate) device_verification_nonce: Option<String>,
+}
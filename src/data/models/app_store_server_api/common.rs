@@ -14,7 +14,7 @@ pub(crate) enum Environment {
     Unknown(String),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub(crate) enum OfferDiscountType {
     /// A payment mode of a product discount that indicates a free trial.
@@ -29,7 +29,7 @@ pub(crate) enum OfferDiscountType {
     Unknown(String),
 }
 
-#[derive(Debug, Deserialize_repr)]
+#[derive(Debug, Deserialize_repr, PartialEq)]
 #[repr(u8)]
 pub(crate) enum OfferType {
     /// An introductory offer.
@@ -41,3 +41,18 @@ pub(crate) enum OfferType {
     /// A win-back offer.
     WinBack = 4,
 }
+
+#[derive(Debug, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub(crate) enum SubscriptionStatus {
+    /// The auto-renewable subscription is active.
+    Active = 1,
+    /// The auto-renewable subscription is expired.
+    Expired = 2,
+    /// The auto-renewable subscription is in a billing retry period.
+    BillingRetry = 3,
+    /// The auto-renewable subscription is in a Billing Grace Period.
+    BillingGracePeriod = 4,
+    /// The auto-renewable subscription is revoked.
+    Revoked = 5,
+}
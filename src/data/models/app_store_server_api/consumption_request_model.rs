@@ -0,0 +1,42 @@
+#![allow(dead_code)]
+
+use serde::Serialize;
+
+/// Request body of the App Store Server API's Send Consumption Information
+/// endpoint. Apart from the booleans and the account token, fields are codes
+/// as defined by Apple, where 0 means undeclared.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/consumptionrequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConsumptionRequestModel {
+    /// Whether the customer consented to provide consumption data.
+    pub(crate) customer_consented: bool,
+    /// 0: undeclared, 1: not consumed, 2: partially consumed, 3: fully
+    /// consumed.
+    pub(crate) consumption_status: u8,
+    /// 0: undeclared, 1: Apple platform, 2: non-Apple platform.
+    pub(crate) platform: u8,
+    pub(crate) sample_content_provided: bool,
+    /// 0: delivered and working properly, 1: quality issue, 2: wrong item, 3:
+    /// server outage, 4: in-game currency change, 5: other reason.
+    pub(crate) delivery_status: u8,
+    /// The UUID set at purchase time, or an empty string if there is none.
+    pub(crate) app_account_token: String,
+    /// 0: undeclared, 1-7: 0-3 days, 3-10 days, 10-30 days, 30-90 days,
+    /// 90-180 days, 180-365 days, over 365 days.
+    pub(crate) account_tenure: u8,
+    /// 0: undeclared, 1-7: 0-5 minutes, 5-60 minutes, 1-6 hours, 6-24 hours,
+    /// 1-4 days, 4-16 days, over 16 days.
+    pub(crate) play_time: u8,
+    /// 0: undeclared, 1-7: 0 USD, 0.01-49.99 USD, 50-99.99 USD, 100-499.99
+    /// USD, 500-999.99 USD, 1000-1999.99 USD, over 2000 USD.
+    pub(crate) lifetime_dollars_refunded: u8,
+    /// As 'lifetime_dollars_refunded'.
+    pub(crate) lifetime_dollars_purchased: u8,
+    /// 0: undeclared, 1: active, 2: suspended, 3: terminated, 4: limited
+    /// access.
+    pub(crate) user_status: u8,
+    /// 0: undeclared, 1: prefer grant, 2: prefer decline, 3: no preference.
+    pub(crate) refund_preference: u8,
+}
@@ -0,0 +1,16 @@
+#![allow(dead_code)]
+
+use serde::Deserialize;
+
+/// Data structure returned by the App Store Server API for failed requests.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/error_codes
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ErrorResponseModel {
+    /// Numeric code identifying the error (ex. 4040010 for
+    /// TransactionIdNotFoundError).
+    pub(crate) error_code: u32,
+    /// Human-readable description of the error.
+    pub(crate) error_message: Option<String>,
+}
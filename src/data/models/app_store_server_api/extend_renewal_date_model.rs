@@ -0,0 +1,45 @@
+#![allow(dead_code)]
+
+use chrono::{serde::ts_milliseconds_option, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Request body for extending the renewal date of a customer's active
+/// subscription.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/extendrenewaldaterequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExtendRenewalDateRequestModel {
+    /// The number of days to extend the subscription renewal date (at most
+    /// 90).
+    pub(crate) extend_by_days: u32,
+    /// 0: undeclared, 1: customer satisfaction, 2: other, 3: service issue or
+    /// outage.
+    pub(crate) extend_reason_code: u8,
+    /// A string that contains a unique identifier you provide to track each
+    /// subscription-renewal-date extension request. The maximum length is 128
+    /// characters.
+    pub(crate) request_identifier: String,
+}
+
+/// Data structure returned by the App Store Server API when extending the
+/// renewal date of a subscription.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/extendrenewaldateresponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExtendRenewalDateResponseModel {
+    /// The original transaction identifier of a purchase.
+    pub(crate) original_transaction_id: Option<String>,
+    /// The unique identifier of subscription-purchase events across devices,
+    /// including renewals.
+    pub(crate) web_order_line_item_id: Option<String>,
+    /// A Boolean value that indicates whether the subscription-renewal-date
+    /// extension succeeded.
+    #[serde(default)]
+    pub(crate) success: bool,
+    /// The new subscription expiration date for a subscription-renewal
+    /// extension.
+    #[serde(default, with = "ts_milliseconds_option")]
+    pub(crate) effective_date: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,78 @@
+#![allow(dead_code)]
+
+use chrono::serde::ts_milliseconds;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Request body of the External Purchase Server API's Send External Purchase
+/// Report endpoint.
+///
+/// https://developer.apple.com/documentation/externalpurchaseserverapi/send-external-purchase-report
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExternalPurchaseReportModel {
+    /// A UUID that uniquely identifies the report.
+    pub(crate) request_identifier: String,
+    pub(crate) line_items: Vec<LineItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LineItem {
+    /// The external purchase ID from the external purchase token.
+    pub(crate) external_purchase_id: String,
+    /// The developer's unique identifier of the transaction.
+    pub(crate) line_item_id: String,
+    pub(crate) event_type: EventType,
+    /// The UNIX time, in milliseconds, of the transaction.
+    #[serde(with = "ts_milliseconds")]
+    pub(crate) event_date: DateTime<Utc>,
+    /// The amount of the transaction, in milliunits of the currency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) amount_taxes_included: Option<i64>,
+    /// 3-letter ISO 4217 currency code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) currency: Option<String>,
+    /// 3-letter ISO 3166-1 code of the customer's storefront.
+    pub(crate) storefront: String,
+    pub(crate) is_subscription: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum EventType {
+    Purchase,
+    Refund,
+    NoLineItem,
+}
+
+/// Data structure returned by the Retrieve External Purchase Report endpoint.
+///
+/// https://developer.apple.com/documentation/externalpurchaseserverapi/retrieve-external-purchase-report
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExternalPurchaseReportStatusModel {
+    pub(crate) request_identifier: String,
+    pub(crate) status: ReportStatus,
+    #[serde(default)]
+    pub(crate) errors: Vec<ReportError>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum ReportStatus {
+    Pending,
+    Processed,
+    Failed,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReportError {
+    pub(crate) line_item_id: Option<String>,
+    pub(crate) error_code: Option<i64>,
+    pub(crate) error_message: Option<String>,
+}
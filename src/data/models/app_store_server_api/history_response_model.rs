@@ -0,0 +1,82 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::common::Environment;
+
+type AppleIdType = u64;
+type JWSTransaction = String;
+
+/// Query parameters for querying a customer's transaction history. Lists are
+/// sent as repeated parameters, and empty lists and None values are omitted.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/get-transaction-history
+#[derive(Debug, Default)]
+pub(crate) struct TransactionHistoryRequestModel {
+    /// An optional start date of the timespan for the transaction history
+    /// records you're requesting, in UNIX time, in milliseconds.
+    pub(crate) start_date: Option<DateTime<Utc>>,
+    /// An optional end date of the timespan for the transaction history
+    /// records you're requesting, in UNIX time, in milliseconds.
+    pub(crate) end_date: Option<DateTime<Utc>>,
+    /// An optional filter that indicates the product identifier to include in
+    /// the transaction history.
+    pub(crate) product_id: Vec<String>,
+    /// An optional filter that indicates the product type to include in the
+    /// transaction history.
+    pub(crate) product_type: Vec<ProductType>,
+    /// An optional filter that indicates the subscription group identifier to
+    /// include in the transaction history.
+    pub(crate) subscription_group_identifier: Vec<String>,
+    /// An optional filter that limits the transaction history by the in-app
+    /// ownership type.
+    pub(crate) in_app_ownership_type: Option<InAppOwnershipType>,
+    /// An optional Boolean value that indicates whether the response includes
+    /// only revoked transactions when the value is true, or contains only
+    /// nonrevoked transactions when the value is false.
+    pub(crate) revoked: Option<bool>,
+}
+
+/// Product types accepted by the 'productType' filter.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ProductType {
+    AutoRenewable,
+    NonRenewable,
+    Consumable,
+    NonConsumable,
+}
+
+/// Ownership types accepted by the 'inAppOwnershipType' filter.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum InAppOwnershipType {
+    FamilyShared,
+    Purchased,
+}
+
+/// Data structure returned by the App Store Server API when querying for a
+/// customer's transaction history.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/historyresponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HistoryResponseModel {
+    /// A token you use in a query to request the next set of transactions for
+    /// the customer.
+    pub(crate) revision: Option<String>,
+    /// A Boolean value indicating whether the App Store has more transaction
+    /// data.
+    #[serde(default)]
+    pub(crate) has_more: bool,
+    /// The bundle identifier of an app.
+    pub(crate) bundle_id: String,
+    /// The unique identifier of an app in the App Store.
+    pub(crate) app_apple_id: Option<AppleIdType>,
+    /// The server environment in which you're making the request, whether
+    /// sandbox or production.
+    pub(crate) environment: Environment,
+    /// An array of in-app purchase transactions for the customer, signed by
+    /// Apple, in JSON Web Signature format.
+    #[serde(default)]
+    pub(crate) signed_transactions: Vec<JWSTransaction>,
+}
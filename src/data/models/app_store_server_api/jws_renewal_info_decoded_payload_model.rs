@@ -18,7 +18,7 @@ use super::common::{Environment, OfferDiscountType, OfferType};
 /// reference, so reasonable assumptions are made.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub(crate) struct JwsRenewalInfoDecodedPayloadModel {
+pub struct JwsRenewalInfoDecodedPayloadModel {
     /// The identifier of the product that renews at the next billing period.
     pub(crate) auto_renew_product_id: String,
     /// The renewal status of the auto-renewable subscription.
@@ -0,0 +1,74 @@
+#![allow(dead_code)]
+
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+type JWSNotification = String;
+
+/// Request body for querying the notifications the App Store sent for the
+/// app.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/notificationhistoryrequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NotificationHistoryRequestModel {
+    /// The start date of the timespan for the requested notification history
+    /// records, in UNIX time, in milliseconds.
+    #[serde(with = "ts_milliseconds")]
+    pub(crate) start_date: DateTime<Utc>,
+    /// The end date of the timespan for the requested notification history
+    /// records, in UNIX time, in milliseconds.
+    #[serde(with = "ts_milliseconds")]
+    pub(crate) end_date: DateTime<Utc>,
+    /// A Boolean value you set to true to request only the notifications that
+    /// haven't reached your server successfully.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) only_failures: Option<bool>,
+}
+
+/// Data structure returned by the App Store Server API when querying for the
+/// notification history.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/notificationhistoryresponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NotificationHistoryResponseModel {
+    /// The pagination token you pass to the Get Notification History endpoint
+    /// to request the next set of notifications.
+    pub(crate) pagination_token: Option<String>,
+    /// A Boolean value indicating whether the App Store has more notification
+    /// history records to send.
+    #[serde(default)]
+    pub(crate) has_more: bool,
+    /// An array of App Store server notification history records.
+    #[serde(default)]
+    pub(crate) notification_history: Vec<NotificationHistoryResponseItemModel>,
+}
+
+/// https://developer.apple.com/documentation/appstoreserverapi/notificationhistoryresponseitem
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NotificationHistoryResponseItemModel {
+    /// The cryptographically signed payload, in JSON Web Signature (JWS)
+    /// format, containing the original response body of a version 2
+    /// notification.
+    pub(crate) signed_payload: JWSNotification,
+    /// An array of information the App Store server records for its attempts
+    /// to send a notification to your server. The maximum number of entries
+    /// in the array is six.
+    #[serde(default)]
+    pub(crate) send_attempt_results: Vec<SendAttemptItemModel>,
+}
+
+/// https://developer.apple.com/documentation/appstoreserverapi/sendattemptitem
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SendAttemptItemModel {
+    /// The date the App Store server attempts to send a notification.
+    #[serde(with = "ts_milliseconds")]
+    pub(crate) attempt_date: DateTime<Utc>,
+    /// The success or error information the App Store server records when it
+    /// attempts to send an App Store server notification to your server (ex.
+    /// "SUCCESS", "TIMED_OUT", "UNSUCCESSFUL_HTTP_RESPONSE_CODE").
+    pub(crate) send_attempt_result: String,
+}
@@ -0,0 +1,62 @@
+#![allow(dead_code)]
+
+use serde::Deserialize;
+
+use super::common::{Environment, SubscriptionStatus};
+
+type AppleIdType = u64;
+type JWSTransaction = String;
+type JWSRenewalInfo = String;
+
+/// Data structure returned by the App Store Server API when querying for all
+/// subscription statuses.
+///
+/// https://developer.apple.com/documentation/appstoreserverapi/statusresponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StatusResponseModel {
+    /// An array of information for auto-renewable subscriptions, including App
+    /// Store-signed transaction information and App Store-signed renewal
+    /// information.
+    #[serde(default)]
+    pub(crate) data: Vec<SubscriptionGroupIdentifierItem>,
+    /// The server environment, sandbox or production, in which the App Store
+    /// generated the response.
+    pub(crate) environment: Environment,
+    /// The unique identifier of an app in the App Store.
+    pub(crate) app_apple_id: Option<AppleIdType>,
+    /// The bundle identifier of an app.
+    pub(crate) bundle_id: String,
+}
+
+/// Information for auto-renewable subscriptions, including signed transaction
+/// information and signed renewal information, for one subscription group.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionGroupIdentifierItem {
+    /// The identifier of the subscription group that the subscription belongs
+    /// to.
+    pub(crate) subscription_group_identifier: String,
+    /// An array of the most recent App Store-signed transaction information
+    /// and App Store-signed renewal information for all auto-renewable
+    /// subscriptions in the subscription group.
+    #[serde(default)]
+    pub(crate) last_transactions: Vec<LastTransactionsItem>,
+}
+
+/// The most recent App Store-signed transaction information and App
+/// Store-signed renewal information for an auto-renewable subscription.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LastTransactionsItem {
+    /// The status of the auto-renewable subscription.
+    pub(crate) status: SubscriptionStatus,
+    /// The original transaction identifier of a purchase.
+    pub(crate) original_transaction_id: String,
+    /// Transaction information signed by the App Store, in JSON Web Signature
+    /// (JWS) format.
+    pub(crate) signed_transaction_info: JWSTransaction,
+    /// Subscription renewal information, signed by the App Store, in JSON Web
+    /// Signature (JWS) format.
+    pub(crate) signed_renewal_info: JWSRenewalInfo,
+}
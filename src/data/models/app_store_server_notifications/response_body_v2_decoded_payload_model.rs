@@ -3,9 +3,8 @@
 use chrono::serde::ts_milliseconds;
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
-use serde_repr::Deserialize_repr;
 
-use crate::data::models::app_store_server_api::common::Environment;
+use crate::data::models::app_store_server_api::common::{Environment, SubscriptionStatus};
 
 type AppleIdType = u64;
 type JWSTransaction = String;
@@ -445,18 +444,3 @@ pub enum ConsumptionRequestReason {
     #[serde(untagged)]
     Unknown(String),
 }
-
-#[derive(Debug, Deserialize_repr)]
-#[repr(u8)]
-pub enum SubscriptionStatus {
-    /// The auto-renewable subscription is active.
-    Active = 1,
-    /// The auto-renewable subscription is expired.
-    Expired = 2,
-    /// The auto-renewable subscription is in a billing retry period.
-    BillingRetry = 3,
-    /// The auto-renewable subscription is in a Billing Grace Period.
-    BillingGracePeriod = 4,
-    /// The auto-renewable subscription is revoked.
-    Revoked = 5,
-}
@@ -49,6 +49,10 @@ pub(crate) struct DeveloperNotificationModel {
     /// oneTimeProductNotification, subscriptionNotification, and
     /// voidedPurchaseNotification.
     pub(crate) test_notification: Option<TestNotification>,
+    /// Top-level fields not modelled above, such as notification categories
+    /// added by Google after this model was written.
+    #[serde(flatten)]
+    pub(crate) other: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 
 use serde::Deserialize;
+use serde_with::DefaultOnNull;
 
 /// Data structure returned for all Google Cloud Pub/Sub topic notifications.
 ///
@@ -10,19 +11,55 @@ use serde::Deserialize;
 ///
 /// Whether fields are nullable is not documented explicitly in the API
 /// reference, so reasonable assumptions are made.
+///
+/// Parsing is lenient towards variations seen in practice, or when testing
+/// locally: a missing 'subscription', missing or null 'attributes', the
+/// message ID sent as 'messageId' and / or 'message_id', and 'data' sent as
+/// the (unencoded) notification object instead of base64.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct PubSubModel {
     pub(crate) message: Message,
+    #[serde(default)]
     pub(crate) subscription: String,
 }
 
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(try_from = "MessageFields")]
 pub(crate) struct Message {
-    #[serde(default)]
     pub(crate) attributes: HashMap<String, String>,
-    /// Main data. Base64-encoded JSON object.
-    pub(crate) data: String,
+    /// Main data. Base64-encoded JSON object (or, when testing locally, the
+    /// JSON object itself).
+    pub(crate) data: serde_json::Value,
     pub(crate) message_id: String,
 }
+
+/// Push deliveries include both casings of the message ID, so they can not be
+/// handled with an alias (which rejects duplicates).
+#[serde_with::serde_as]
+#[derive(Deserialize)]
+struct MessageFields {
+    #[serde_as(as = "DefaultOnNull")]
+    #[serde(default)]
+    attributes: HashMap<String, String>,
+    data: serde_json::Value,
+    #[serde(rename = "messageId")]
+    message_id_camel_case: Option<String>,
+    #[serde(rename = "message_id")]
+    message_id_snake_case: Option<String>,
+}
+
+impl TryFrom<MessageFields> for Message {
+    type Error = &'static str;
+
+    fn try_from(fields: MessageFields) -> Result<Self, Self::Error> {
+        Ok(Self {
+            attributes: fields.attributes,
+            data: fields.data,
+            message_id: fields
+                .message_id_camel_case
+                .or(fields.message_id_snake_case)
+                .ok_or("missing field `messageId`")?,
+        })
+    }
+}
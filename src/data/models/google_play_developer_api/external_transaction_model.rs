@@ -0,0 +1,169 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Data structure sent to and returned by the Google Play Developer API's
+/// externaltransactions endpoints.
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/externaltransactions#ExternalTransaction
+///
+/// Output-only fields are skipped when creating a transaction.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExternalTransactionModel {
+    /// Output only. The resource name of the external transaction. The package
+    /// name of the application the inapp products were sold (for example,
+    /// 'com.some.app').
+    #[serde(skip_serializing)]
+    pub(crate) package_name: Option<String>,
+    /// Output only. The id of this transaction. All transaction ids under the
+    /// same package name must be unique. Set when creating the external
+    /// transaction.
+    #[serde(skip_serializing)]
+    pub(crate) external_transaction_id: Option<String>,
+    /// Required. The original transaction amount before taxes. This represents
+    /// the pre-tax amount originally notified to Google before any refunds were
+    /// applied.
+    pub(crate) original_pre_tax_amount: Option<Price>,
+    /// Required. The original tax amount. This represents the tax amount
+    /// originally notified to Google before any refunds were applied.
+    pub(crate) original_tax_amount: Option<Price>,
+    /// Output only. The current transaction amount before tax. This represents
+    /// the current pre-tax amount including any refunds that may have been
+    /// applied to this transaction.
+    #[serde(skip_serializing)]
+    pub(crate) current_pre_tax_amount: Option<Price>,
+    /// Output only. The current tax amount. This represents the current tax
+    /// amount including any refunds that may have been applied to this
+    /// transaction.
+    #[serde(skip_serializing)]
+    pub(crate) current_tax_amount: Option<Price>,
+    /// Output only. If set, this transaction was a test purchase. Google will
+    /// not charge for a test transaction.
+    #[serde(skip_serializing)]
+    pub(crate) test_purchase: Option<serde_json::Value>,
+    /// Required. The time when the transaction was completed.
+    pub(crate) transaction_time: Option<DateTime<Utc>>,
+    /// Output only. The time when this transaction was created. This is the
+    /// time when Google was notified of the transaction.
+    #[serde(skip_serializing)]
+    pub(crate) create_time: Option<DateTime<Utc>>,
+    /// Output only. The current state of the transaction.
+    #[serde(skip_serializing)]
+    pub(crate) transaction_state: Option<TransactionState>,
+    /// Required. User address for tax computation.
+    pub(crate) user_tax_address: Option<ExternalTransactionAddress>,
+    /// This is a one-time transaction and not part of a subscription.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) one_time_transaction: Option<OneTimeExternalTransaction>,
+    /// This transaction is part of a recurring series of transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) recurring_transaction: Option<RecurringExternalTransaction>,
+}
+
+/// Definition of a price, i.e. currency and units.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Price {
+    /// Price in 1/million of the currency base unit, represented as a string.
+    pub(crate) price_micros: String,
+    /// 3 letter Currency code, as defined by ISO 4217.
+    pub(crate) currency: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum TransactionState {
+    /// Unspecified transaction state. Not used.
+    TransactionStateUnspecified,
+    /// The transaction has been successfully reported to Google.
+    TransactionReported,
+    /// The transaction has been fully refunded.
+    TransactionCanceled,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExternalTransactionAddress {
+    /// Required. Two letter region code based on ISO-3166-1 Alpha-2 (UN region
+    /// codes).
+    pub(crate) region_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OneTimeExternalTransaction {
+    /// Input only. Provided during the call to Create. Retrieved from the
+    /// client when the alternative billing flow is launched.
+    pub(crate) external_transaction_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecurringExternalTransaction {
+    /// Input only. Provided during the call to Create. Retrieved from the
+    /// client when the alternative billing flow is launched. Required only for
+    /// the initial purchase.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) external_transaction_token: Option<String>,
+    /// The external transaction id of the first transaction of this recurring
+    /// series of transactions. For example, for a subscription this would be
+    /// the transaction id of the first payment. Required when creating
+    /// recurring external transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) initial_external_transaction_id: Option<String>,
+    /// Details of an external subscription.
+    pub(crate) external_subscription: Option<ExternalSubscription>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExternalSubscription {
+    /// Required. The type of the external subscription.
+    pub(crate) subscription_type: SubscriptionType,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum SubscriptionType {
+    /// This is a recurring subscription where the user is charged every
+    /// billing cycle.
+    Recurring,
+    /// This is a prepaid subscription where the user pays up front.
+    Prepaid,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// Request body of externaltransactions.refundexternaltransaction.
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/externaltransactions/refundexternaltransaction
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RefundExternalTransactionRequest {
+    /// Required. The time that the transaction was refunded.
+    pub(crate) refund_time: DateTime<Utc>,
+    /// A full-amount refund.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) full_refund: Option<serde_json::Value>,
+    /// A partial refund.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) partial_refund: Option<PartialRefund>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PartialRefund {
+    /// Required. A unique id distinguishing this partial refund. If the refund
+    /// is successful, subsequent refunds with the same id will fail. Must be
+    /// unique across refunds for one individual transaction.
+    pub(crate) refund_id: String,
+    /// Required. The pre-tax amount of the partial refund. Should be less than
+    /// the remaining pre-tax amount of the transaction.
+    pub(crate) refund_pre_tax_amount: Price,
+}
@@ -49,19 +49,15 @@ pub struct InAppProductModel {
     /// days), P3D (three days), P7D (seven days), P14D (14 days), and P30D (30
     /// days).
     pub(crate) grace_period: Option<String>,
-    //
-    // Can implement if needed in future:
-    // // Union field TaxAndComplianceType can be only one of the following:
-    // // --
-    // /// Details about taxes and legal compliance. Only applicable to
-    // /// subscription products.
-    // pub(crate) subscription_taxes_and_compliance_settings:
-    //     Option<SubscriptionTaxesAndComplianceSettings>,
-    // /// Details about taxes and legal compliance. Only applicable to managed
-    // /// products.
-    // pub(crate) managed_product_taxes_and_compliance_settings:
-    //     Option<ManagedProductTaxesAndComplianceSettings>,
-    // // --
+    // Union field TaxAndComplianceType can be only one of the following:
+    // --
+    /// Details about taxes and legal compliance. Only applicable to
+    /// subscription products.
+    pub(crate) subscription_taxes_and_compliance_settings: Option<TaxesAndComplianceSettings>,
+    /// Details about taxes and legal compliance. Only applicable to managed
+    /// products.
+    pub(crate) managed_product_taxes_and_compliance_settings: Option<TaxesAndComplianceSettings>,
+    // --
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -107,3 +103,99 @@ pub struct InAppProductListing {
     #[serde(default)]
     pub(crate) benefits: Vec<String>,
 }
+
+/// Details about taxation and legal compliance, as reported for both managed
+/// products (ManagedProductTaxAndComplianceSettings) and subscriptions
+/// (SubscriptionTaxAndComplianceSettings).
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/inappproducts#managedproducttaxandcompliancesettings
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TaxesAndComplianceSettings {
+    /// Digital content or service classification for products distributed to
+    /// users in the European Economic Area (EEA). The withdrawal regime under
+    /// EEA consumer laws depends on this classification.
+    pub(crate) eea_withdrawal_right_type: Option<EeaWithdrawalRightType>,
+    /// A mapping from region code to tax rate details. The keys are region
+    /// codes as defined by Unicode's "CLDR".
+    #[serde(default)]
+    pub(crate) tax_rate_info_by_region_code: HashMap<String, RegionalTaxRateInfo>,
+    /// Whether this in-app product is declared as a product representing a
+    /// tokenized digital asset.
+    #[serde(default)]
+    pub(crate) is_tokenized_digital_asset: bool,
+    /// Product tax category code to assign to the in-app product. Product tax
+    /// category determines the transaction tax rates applied to the product.
+    pub(crate) product_tax_category_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum EeaWithdrawalRightType {
+    WithdrawalRightTypeUnspecified,
+    WithdrawalRightDigitalContent,
+    WithdrawalRightService,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// Specified details about taxation in a given geographical region.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RegionalTaxRateInfo {
+    /// Tax tier to specify reduced tax rate. Developers who sell digital news,
+    /// magazines, newspapers, books, or audiobooks in various regions may be
+    /// eligible for reduced tax rates.
+    pub(crate) tax_tier: Option<TaxTier>,
+    /// You must tell us if your app contains streaming products to correctly
+    /// charge US state and local sales tax. Field only supported in the United
+    /// States.
+    #[serde(default)]
+    pub(crate) eligible_for_streaming_service_tax_rate: bool,
+    /// To collect communications or amusement taxes in the United States,
+    /// choose the appropriate tax category.
+    pub(crate) streaming_tax_type: Option<StreamingTaxType>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum TaxTier {
+    TaxTierUnspecified,
+    TaxTierBooks1,
+    TaxTierNews1,
+    TaxTierNews2,
+    TaxTierMusicOrAudio1,
+    TaxTierLiveOrBroadcast1,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum StreamingTaxType {
+    /// No telecommunications tax collected.
+    StreamingTaxTypeUnspecified,
+    /// US-specific telecommunications tax tier for video streaming, on
+    /// demand, rentals / subscriptions / pay-per-view.
+    StreamingTaxTypeTelcoVideoRental,
+    /// US-specific telecommunications tax tier for video streaming of pre-
+    /// recorded content like movies, tv shows.
+    StreamingTaxTypeTelcoVideoSales,
+    /// US-specific telecommunications tax tier for video streaming of
+    /// multi-channel programming.
+    StreamingTaxTypeTelcoVideoMultiChannel,
+    /// US-specific telecommunications tax tier for audio streaming, rental /
+    /// subscription.
+    StreamingTaxTypeTelcoAudioRental,
+    /// US-specific telecommunications tax tier for audio streaming, sale /
+    /// permanent download.
+    StreamingTaxTypeTelcoAudioSales,
+    /// US-specific telecommunications tax tier for multi channel audio
+    /// streaming like radio.
+    StreamingTaxTypeTelcoAudioMultiChannel,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
@@ -0,0 +1,146 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// Data structure returned by the Google Play Developer API when querying for a
+/// one-time product purchase through the productsv2 API.
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.productsv2#ProductPurchaseV2
+///
+/// Whether fields are nullable is not documented explicitly in the API
+/// reference, so reasonable assumptions are made.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductPurchaseV2Model {
+    /// This kind represents a ProductPurchaseV2 object in the androidpublisher
+    /// service.
+    pub(crate) kind: Option<String>,
+    /// Contains item-level info for a ProductPurchaseV2.
+    #[serde(default)]
+    pub(crate) product_line_item: Vec<ProductLineItem>,
+    /// Information about the purchase state of the purchase.
+    pub(crate) purchase_state_context: Option<PurchaseStateContext>,
+    /// Information related to test purchases. This will only be set for test
+    /// purchases.
+    pub(crate) test_purchase_context: Option<TestPurchaseContext>,
+    /// The order id associated with the purchase of the product. May not be
+    /// set if there is no order associated with the purchase.
+    pub(crate) order_id: Option<String>,
+    /// An obfuscated version of the id that is uniquely associated with the
+    /// user's account in your app. Only present if specified using
+    /// BillingFlowParams.Builder#setObfuscatedAccountId when the purchase was
+    /// made.
+    pub(crate) obfuscated_external_account_id: Option<String>,
+    /// An obfuscated version of the id that is uniquely associated with the
+    /// user's profile in your app. Only present if specified using
+    /// BillingFlowParams.Builder#setObfuscatedProfileId when the purchase was
+    /// made.
+    pub(crate) obfuscated_external_profile_id: Option<String>,
+    /// ISO 3166-1 alpha-2 billing region code of the user at the time the
+    /// product was granted.
+    pub(crate) region_code: String,
+    /// The time when the purchase was successful, i.e., when the
+    /// PurchaseState has changed to PURCHASED. This field will not be present
+    /// until the payment is complete.
+    pub(crate) purchase_completion_time: Option<DateTime<Utc>>,
+    /// Output only. The acknowledgement state of the purchase.
+    pub(crate) acknowledgement_state: AcknowledgementState,
+}
+
+/// Contains item-level info for a ProductPurchaseV2.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProductLineItem {
+    /// The purchased product ID (for example, 'monthly001').
+    pub(crate) product_id: String,
+    /// The offer details for this item.
+    pub(crate) product_offer_details: Option<ProductOfferDetails>,
+}
+
+/// Offer details information related to a purchase line item.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProductOfferDetails {
+    /// The latest offer tags associated with the offer. It includes tags
+    /// inherited from the purchase option.
+    #[serde(default)]
+    pub(crate) offer_tags: Vec<String>,
+    /// The offer ID. Only present for offers.
+    pub(crate) offer_id: Option<String>,
+    /// The purchase option ID.
+    pub(crate) purchase_option_id: Option<String>,
+    /// The per-transaction offer token used to make this purchase line item.
+    pub(crate) offer_token: Option<String>,
+    /// The quantity associated with the purchase of the inapp product.
+    pub(crate) quantity: Option<i32>,
+    /// The quantity eligible for refund, i.e. quantity that hasn't been
+    /// refunded. The value reflects quantity-based partial refunds and full
+    /// refunds.
+    pub(crate) refundable_quantity: Option<i32>,
+    /// Output only. The consumption state of the purchase.
+    pub(crate) consumption_state: Option<ConsumptionState>,
+}
+
+/// Context about the purchase state.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PurchaseStateContext {
+    /// Output only. The purchase state of the purchase.
+    pub(crate) purchase_state: PurchaseState,
+}
+
+/// Context about a test purchase.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TestPurchaseContext {
+    /// The fop type of the test purchase.
+    pub(crate) fop_type: Option<String>,
+}
+
+/// The possible purchase states for a one-time product purchase.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum PurchaseState {
+    /// Purchase state unspecified. This value should never be set.
+    PurchaseStateUnspecified,
+    /// Purchased successfully.
+    Purchased,
+    /// Purchase was cancelled.
+    Cancelled,
+    /// The purchase is in a pending state and has not yet been completed.
+    Pending,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// The possible consumption states for a one-time product purchase.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum ConsumptionState {
+    /// Consumption state unspecified. This value should never be set.
+    ConsumptionStateUnspecified,
+    /// Yet to be consumed.
+    ConsumptionStateYetToBeConsumed,
+    /// Consumed already.
+    ConsumptionStateConsumed,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// The possible acknowledgement states for a one-time product purchase.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum AcknowledgementState {
+    /// Unspecified acknowledgement state.
+    AcknowledgementStateUnspecified,
+    /// The purchase is not acknowledged yet.
+    AcknowledgementStatePending,
+    /// The purchase is acknowledged.
+    AcknowledgementStateAcknowledged,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
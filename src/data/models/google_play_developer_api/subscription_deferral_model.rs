@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::formats::Flexible;
+use serde_with::TimestampMilliSeconds;
+
+/// Request body of purchases.subscriptions.defer.
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.subscriptions/defer
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionPurchasesDeferRequest {
+    /// The information about the new desired expiry time for the
+    /// subscription.
+    pub(crate) deferral_info: SubscriptionDeferralInfo,
+}
+
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.subscriptions/defer#SubscriptionDeferralInfo
+#[serde_with::serde_as]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionDeferralInfo {
+    /// The expected expiry time for the subscription. If the current expiry
+    /// time for the subscription is not the value specified here, the
+    /// deferral will not occur.
+    #[serde_as(as = "TimestampMilliSeconds<String>")]
+    pub(crate) expected_expiry_time_millis: DateTime<Utc>,
+    /// The desired next expiry time to assign to the subscription. The time
+    /// must be later than the current expiry time for the subscription.
+    #[serde_as(as = "TimestampMilliSeconds<String>")]
+    pub(crate) desired_expiry_time_millis: DateTime<Utc>,
+}
+
+/// Response body of purchases.subscriptions.defer.
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/SubscriptionPurchasesDeferResponse
+#[serde_with::serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionPurchasesDeferResponse {
+    /// The new expiry time for the subscription in milliseconds since the
+    /// Epoch.
+    #[serde_as(as = "TimestampMilliSeconds<String, Flexible>")]
+    pub(crate) new_expiry_time_millis: DateTime<Utc>,
+}
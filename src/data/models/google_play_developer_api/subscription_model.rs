@@ -0,0 +1,121 @@
+#![allow(dead_code)]
+
+use serde::Deserialize;
+
+use super::subscription_purchase_v2_model::Money;
+
+/// Data structure returned by the Google Play Developer API when querying for
+/// a subscription product (as configured in the Play Console, with its base
+/// plans).
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/monetization.subscriptions#Subscription
+///
+/// Only the fields used by this library are included.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionModel {
+    /// Package name of the parent app.
+    pub(crate) package_name: String,
+    /// Unique product ID of the product. Unique within the parent app.
+    pub(crate) product_id: String,
+    /// The set of base plans for this subscription. Represents the prices and
+    /// duration of the subscription if no other offers apply.
+    #[serde(default)]
+    pub(crate) base_plans: Vec<BasePlan>,
+}
+
+/// A single base plan for a subscription.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BasePlan {
+    /// Immutable. The unique identifier of this base plan. Must be unique
+    /// within the subscription, and conform with RFC-1034.
+    pub(crate) base_plan_id: String,
+    /// Output only. The state of the base plan, i.e. whether it's active.
+    /// Draft and inactive base plans can be activated or deleted. Active base
+    /// plans can be made inactive. Inactive base plans can be canceled. This
+    /// field cannot be changed by updating the resource. Use the dedicated
+    /// endpoints instead.
+    pub(crate) state: Option<BasePlanState>,
+    /// List of up to 20 custom tags specified for this base plan, and
+    /// returned to the app through the billing library. Subscription offers
+    /// for this base plan will also receive these offer tags in the billing
+    /// library.
+    #[serde(default)]
+    pub(crate) offer_tags: Vec<OfferTag>,
+
+    // Union field base_plan_type can be only one of the following:
+    // --
+    /// Set when the base plan automatically renews at a regular interval.
+    pub(crate) auto_renewing_base_plan_type: Option<AutoRenewingBasePlanType>,
+    /// Set when the base plan does not automatically renew at the end of the
+    /// billing period.
+    pub(crate) prepaid_base_plan_type: Option<PrepaidBasePlanType>,
+    // --
+    /// Region-specific information for this base plan.
+    #[serde(default)]
+    pub(crate) regional_configs: Vec<RegionalBasePlanConfig>,
+}
+
+/// Configuration for a base plan specific to a region.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RegionalBasePlanConfig {
+    /// Required. Region code this configuration applies to, as defined by ISO
+    /// 3166-2, e.g. "US".
+    pub(crate) region_code: String,
+    /// The price of the base plan in the specified region. Must be set if the
+    /// base plan is available to new subscribers. Must be set in the currency
+    /// that is linked to the specified region.
+    pub(crate) price: Option<Money>,
+}
+
+/// Represents a base plan that automatically renews at the end of its
+/// subscription period.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AutoRenewingBasePlanType {
+    /// Required. Immutable. Subscription period, specified in ISO 8601
+    /// format. For a list of acceptable billing periods, refer to the help
+    /// center. The duration is immutable after the base plan is created.
+    pub(crate) billing_period_duration: String,
+}
+
+/// Represents a base plan that does not automatically renew at the end of the
+/// base plan, and must be manually renewed by the user.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PrepaidBasePlanType {
+    /// Required. Immutable. Subscription period, specified in ISO 8601
+    /// format. For a list of acceptable billing periods, refer to the help
+    /// center. The duration is immutable after the base plan is created.
+    pub(crate) billing_period_duration: String,
+}
+
+/// The state of a base plan.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum BasePlanState {
+    /// Unspecified state.
+    StateUnspecified,
+    /// The base plan is currently in a draft state, and hasn't been activated.
+    /// It can be safely deleted at this point.
+    Draft,
+    /// The base plan is active and available for new subscribers.
+    Active,
+    /// The base plan is inactive and only available for existing subscribers.
+    Inactive,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// Represents a custom tag specified for base plans and subscription offers.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OfferTag {
+    /// Must conform with RFC-1034. That is, this string can only contain
+    /// lower-case letters (a-z), numbers (0-9), and hyphens (-), and be at
+    /// most 20 characters.
+    pub(crate) tag: String,
+}
@@ -0,0 +1,131 @@
+#![allow(dead_code)]
+
+use serde::Deserialize;
+
+use super::{subscription_model::OfferTag, subscription_purchase_v2_model::Money};
+
+/// Data structure returned by the Google Play Developer API when listing the
+/// offers of a subscription's base plans.
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/monetization.subscriptions.basePlans.offers/list
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ListSubscriptionOffersResponseModel {
+    /// The subscription offers from the specified subscription.
+    #[serde(default)]
+    pub(crate) subscription_offers: Vec<SubscriptionOfferModel>,
+    /// A token, which can be sent as pageToken to retrieve the next page. If
+    /// this field is omitted, there are no subsequent pages.
+    pub(crate) next_page_token: Option<String>,
+}
+
+/// Data structure returned by the Google Play Developer API when querying for
+/// an offer of a subscription's base plan.
+///
+/// https://developers.google.com/android-publisher/api-ref/rest/v3/monetization.subscriptions.basePlans.offers#SubscriptionOffer
+///
+/// Only the fields used by this library are included.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionOfferModel {
+    /// Required. Immutable. The package name of the app the parent
+    /// subscription belongs to.
+    pub(crate) package_name: String,
+    /// Required. Immutable. The ID of the parent subscription this offer
+    /// belongs to.
+    pub(crate) product_id: String,
+    /// Required. Immutable. The ID of the base plan to which this offer is an
+    /// extension.
+    pub(crate) base_plan_id: String,
+    /// Required. Immutable. Unique ID of this subscription offer. Must be
+    /// unique within the base plan.
+    pub(crate) offer_id: String,
+    /// Output only. The current state of this offer. Can be changed using
+    /// Activate and Deactivate actions. NB: the base plan state supersedes
+    /// this state, so an active offer may not be available if the base plan
+    /// is not active.
+    pub(crate) state: Option<SubscriptionOfferState>,
+    /// List of up to 20 custom tags specified for this offer, and returned to
+    /// the app through the billing library.
+    #[serde(default)]
+    pub(crate) offer_tags: Vec<OfferTag>,
+    /// Required. The phases of this subscription offer. Must contain at least
+    /// one and at most two entries. Users will always receive all these phases
+    /// in the specified order.
+    #[serde(default)]
+    pub(crate) phases: Vec<SubscriptionOfferPhase>,
+}
+
+/// The current state of a subscription offer.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum SubscriptionOfferState {
+    /// Default value, should never be used.
+    StateUnspecified,
+    /// The subscription offer is not and has never been available to users.
+    Draft,
+    /// The subscription offer is available to new and existing users.
+    Active,
+    /// The subscription offer is not available to new users. Existing users
+    /// retain access.
+    Inactive,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// A single phase of a subscription offer.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionOfferPhase {
+    /// Required. The number of times this phase repeats. If this offer phase
+    /// is not free, each recurrence charges the user the price of this offer
+    /// phase.
+    pub(crate) recurrence_count: i32,
+    /// Required. The duration of a single recurrence of this phase. Specified
+    /// in ISO 8601 format.
+    pub(crate) duration: String,
+    /// Required. The region-specific configuration of this offer phase. This
+    /// list must contain exactly one entry for each region for which the
+    /// subscription offer has a regional config.
+    #[serde(default)]
+    pub(crate) regional_configs: Vec<RegionalSubscriptionOfferPhaseConfig>,
+}
+
+/// Configuration for a single phase of a subscription offer in a single
+/// region.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RegionalSubscriptionOfferPhaseConfig {
+    /// Required. Immutable. The region to which this config applies.
+    pub(crate) region_code: String,
+
+    // Union field price_override can be only one of the following:
+    // --
+    /// The absolute price the user pays for this offer phase. The price must
+    /// not be smaller than the minimum price allowed for this region.
+    pub(crate) price: Option<Money>,
+    /// The fraction of the base plan price prorated over the phase duration
+    /// that the user pays for this offer phase. For example, if the base plan
+    /// price for this region is $12 for a period of 1 year, then a 50%
+    /// discount for a phase of a duration of 3 months would correspond to a
+    /// price of $1.50. The discount must be specified as a fraction strictly
+    /// larger than 0 and strictly smaller than 1. The resulting price will be
+    /// rounded to the nearest billable unit (e.g. cents for USD).
+    pub(crate) relative_discount: Option<f64>,
+    /// The absolute amount of money subtracted from the base plan price
+    /// prorated over the phase duration that the user pays for this offer
+    /// phase. For example, if the base plan price for this region is $12 for
+    /// a period of 1 year, then a $1 absolute discount for a phase of a
+    /// duration of 3 months would correspond to a price of $2.
+    pub(crate) absolute_discount: Option<Money>,
+    /// Set to specify this offer is free to obtain.
+    pub(crate) free: Option<RegionalSubscriptionOfferPhaseFreePriceOverride>,
+    // --
+}
+
+/// Represents the free price override configuration for a single phase of a
+/// subscription offer.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RegionalSubscriptionOfferPhaseFreePriceOverride {}
@@ -275,6 +275,8 @@ pub(crate) struct SubscriptionPurchaseLineItem {
     pub(crate) expiry_time: DateTime<Utc>,
     /// The offer details for this item.
     pub(crate) offer_details: Option<OfferDetails>,
+    /// The pricing phase for the billing period funded by this order.
+    pub(crate) offer_phase: Option<OfferPhase>,
     /// Information for deferred item replacement.
     pub(crate) deferred_item_replacement: Option<DeferredItemReplacement>,
 
@@ -295,6 +297,9 @@ pub(crate) struct AutoRenewingPlan {
     /// not canceled the subscription
     #[serde(default)]
     pub(crate) auto_renew_enabled: bool,
+    /// The current recurring price of the auto renewing plan. Note that the
+    /// price does not take into account discounts and taxes.
+    pub(crate) recurring_price: Option<Money>,
     /// The information of the last price change for the item since subscription
     /// signup.
     pub(crate) price_change_details: Option<SubscriptionItemPriceChangeDetails>,
@@ -412,6 +417,28 @@ pub(crate) struct OfferDetails {
     pub(crate) offer_id: Option<String>,
 }
 
+/// Offer phase details.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OfferPhase {
+    // Union field phase can be only one of the following:
+    // ---
+    /// Set when the offer phase is a base plan pricing phase.
+    pub(crate) base_price: Option<OfferPhaseDetails>,
+    /// Set when the offer phase is an introductory pricing phase.
+    pub(crate) introductory_price: Option<OfferPhaseDetails>,
+    /// Set when the offer phase is a free trial.
+    pub(crate) free_trial: Option<OfferPhaseDetails>,
+    /// Set when the offer phase is a proration period.
+    pub(crate) proration_period: Option<OfferPhaseDetails>,
+    // ---
+}
+
+/// Details of a pricing phase (not used).
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OfferPhaseDetails {}
+
 /// Information related to deferred item replacement.
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
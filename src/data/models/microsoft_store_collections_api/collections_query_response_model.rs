@@ -0,0 +1,78 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// Data structure returned by the Microsoft Store collections API when
+/// querying for a user's products.
+///
+/// https://learn.microsoft.com/en-us/windows/uwp/monetize/query-for-products#response
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CollectionsQueryResponseModel {
+    /// If there are more products than fit in one page, the token to pass to
+    /// the next query.
+    pub(crate) continuation_token: Option<String>,
+    /// The products owned by the user, matching the query.
+    #[serde(default)]
+    pub(crate) items: Vec<CollectionItemModel>,
+}
+
+/// A product owned by the user.
+///
+/// https://learn.microsoft.com/en-us/windows/uwp/monetize/query-for-products#collectionitemcontractv6
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItemModel {
+    /// The date the user acquired the item.
+    pub(crate) acquired_date: DateTime<Utc>,
+    /// The end date of the item (far in the future for products which do not
+    /// expire, ex. durables).
+    pub(crate) end_date: DateTime<Utc>,
+    /// The start date of the item.
+    pub(crate) start_date: Option<DateTime<Utc>>,
+    /// The date the item was last modified.
+    pub(crate) modified_date: Option<DateTime<Utc>>,
+    /// ID identifying this item among the user's items.
+    pub(crate) item_id: String,
+    /// The Store ID of the product (ex. '9NBLGGH4R315').
+    pub(crate) product_id: String,
+    /// The SKU of the product.
+    pub(crate) sku_id: Option<String>,
+    /// The kind of product (ex. 'Durable', 'UnmanagedConsumable').
+    pub(crate) product_type: ProductType,
+    /// Country (ISO 3166-1 alpha-2) in which the item was purchased.
+    pub(crate) purchased_country: Option<String>,
+    /// The quantity of the item. For consumables, the quantity which has not
+    /// been consumed yet.
+    pub(crate) quantity: Option<i64>,
+    /// The status of the item.
+    pub(crate) status: ItemStatus,
+    /// The ID of the transaction the item was acquired in.
+    pub(crate) transaction_id: Option<String>,
+    /// The ID of the order the item was acquired in.
+    pub(crate) order_id: Option<String>,
+    /// How the user owns the item (ex. 'OwnedByBeneficiary').
+    pub(crate) ownership_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub(crate) enum ProductType {
+    Application,
+    Durable,
+    UnmanagedConsumable,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub(crate) enum ItemStatus {
+    Active,
+    Expired,
+    Revoked,
+    Banned,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
@@ -0,0 +1,67 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Data structure for Paddle Billing webhook notifications.
+///
+/// https://developer.paddle.com/webhooks/overview
+#[derive(Debug, Deserialize)]
+pub(crate) struct EventModel {
+    /// Unique ID of the event (ex. 'evt_01h04vsc5dh8m0hvbbd2m5wdnh').
+    pub(crate) event_id: String,
+    /// Type of the event, which determines the shape of 'data'.
+    pub(crate) event_type: EventType,
+    /// When the event occurred.
+    pub(crate) occurred_at: DateTime<Utc>,
+    /// Unique ID of the notification (ex. 'ntf_01h04vsc83j63b8phxrtv7s3vk').
+    /// Unlike 'event_id', this is specific to the notification destination,
+    /// and does not change when the notification is redelivered.
+    pub(crate) notification_id: Option<String>,
+    /// The entity the event is about (ex. a subscription, for
+    /// 'subscription.*' events).
+    pub(crate) data: Value,
+}
+
+/// https://developer.paddle.com/webhooks/overview#events
+#[derive(Debug, Deserialize, PartialEq)]
+pub(crate) enum EventType {
+    /// A subscription was created, either directly (ex. through checkout) or
+    /// from a transaction.
+    #[serde(rename = "subscription.created")]
+    SubscriptionCreated,
+    /// A subscription was imported (ex. when migrating from another billing
+    /// platform).
+    #[serde(rename = "subscription.imported")]
+    SubscriptionImported,
+    /// A subscription's status changed to 'active' (ex. after the trial
+    /// ended, and the first payment was collected).
+    #[serde(rename = "subscription.activated")]
+    SubscriptionActivated,
+    /// A subscription's status changed to 'trialing'.
+    #[serde(rename = "subscription.trialing")]
+    SubscriptionTrialing,
+    /// A subscription was updated (ex. renewed, or items / scheduled changes
+    /// modified).
+    #[serde(rename = "subscription.updated")]
+    SubscriptionUpdated,
+    /// A subscription's status changed to 'past_due', since a renewal payment
+    /// failed. Paddle retries the payment (dunning), after which the
+    /// subscription is either reactivated, paused or canceled.
+    #[serde(rename = "subscription.past_due")]
+    SubscriptionPastDue,
+    /// A subscription's status changed to 'paused'.
+    #[serde(rename = "subscription.paused")]
+    SubscriptionPaused,
+    /// A paused subscription was resumed.
+    #[serde(rename = "subscription.resumed")]
+    SubscriptionResumed,
+    /// A subscription's status changed to 'canceled'. Scheduled cancellations
+    /// are only reported once they take effect.
+    #[serde(rename = "subscription.canceled")]
+    SubscriptionCanceled,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
@@ -0,0 +1,94 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// Paddle Billing subscription entity, as included in 'subscription.*'
+/// webhook notifications.
+///
+/// https://developer.paddle.com/api-reference/subscriptions/overview
+#[derive(Debug, Deserialize)]
+pub(crate) struct SubscriptionModel {
+    /// Unique ID of the subscription (ex. 'sub_01h04vsc0qhwtsbsxh3422wjs4').
+    pub(crate) id: String,
+    pub(crate) status: SubscriptionStatus,
+    /// ID of the customer the subscription belongs to.
+    pub(crate) customer_id: String,
+    /// ISO 4217 currency code of the subscription's prices.
+    pub(crate) currency_code: String,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) updated_at: DateTime<Utc>,
+    /// When the subscription started. Null for subscriptions which have not
+    /// started yet (ex. created with a future billing date).
+    pub(crate) started_at: Option<DateTime<Utc>>,
+    /// When the subscription was first billed. Null for subscriptions which
+    /// are trialing.
+    pub(crate) first_billed_at: Option<DateTime<Utc>>,
+    /// When the subscription is next billed. Null for paused and canceled
+    /// subscriptions.
+    pub(crate) next_billed_at: Option<DateTime<Utc>>,
+    pub(crate) paused_at: Option<DateTime<Utc>>,
+    pub(crate) canceled_at: Option<DateTime<Utc>>,
+    /// The period the customer was last billed for. Null for paused and
+    /// canceled subscriptions.
+    pub(crate) current_billing_period: Option<TimePeriodModel>,
+    /// Change which takes effect at the end of the current billing period
+    /// (ex. a cancellation requested by the customer).
+    pub(crate) scheduled_change: Option<ScheduledChangeModel>,
+    /// Recurring items on the subscription.
+    pub(crate) items: Vec<SubscriptionItemModel>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SubscriptionStatus {
+    Active,
+    Canceled,
+    /// A renewal payment failed, and is being retried.
+    PastDue,
+    Paused,
+    Trialing,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TimePeriodModel {
+    pub(crate) starts_at: DateTime<Utc>,
+    pub(crate) ends_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ScheduledChangeModel {
+    pub(crate) action: ScheduledChangeAction,
+    pub(crate) effective_at: DateTime<Utc>,
+    /// For scheduled pauses, when the subscription resumes (if set).
+    pub(crate) resume_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ScheduledChangeAction {
+    Cancel,
+    Pause,
+    Resume,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SubscriptionItemModel {
+    pub(crate) quantity: i64,
+    pub(crate) price: PriceModel,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PriceModel {
+    /// Unique ID of the price (ex. 'pri_01gsz8x8sawmvhz1pv30nge1ke').
+    pub(crate) id: String,
+    /// Unique ID of the product the price is for (ex.
+    /// 'pro_01gsz4t5hdjse780zja8vvr7jg').
+    pub(crate) product_id: String,
+}
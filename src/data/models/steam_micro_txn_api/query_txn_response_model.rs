@@ -0,0 +1,113 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_with::{DisplayFromStr, PickFirst};
+
+/// Envelope of all ISteamMicroTxn responses. Failures are reported with a 200
+/// status code, and 'result' set to "Failure".
+///
+/// https://partner.steamgames.com/doc/webapi/ISteamMicroTxn
+#[derive(Debug, Deserialize)]
+pub(crate) struct SteamResponseModel<T> {
+    pub(crate) response: SteamResponseBodyModel<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SteamResponseBodyModel<T> {
+    /// "OK" or "Failure".
+    pub(crate) result: String,
+    /// Present if 'result' is "OK".
+    pub(crate) params: Option<T>,
+    /// Present if 'result' is "Failure".
+    pub(crate) error: Option<SteamErrorModel>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SteamErrorModel {
+    /// Numeric error code (sent as a number or a string).
+    pub(crate) errorcode: serde_json::Value,
+    pub(crate) errordesc: Option<String>,
+}
+
+/// Data structure returned by ISteamMicroTxn/QueryTxn.
+///
+/// https://partner.steamgames.com/doc/webapi/ISteamMicroTxn#QueryTxn
+#[serde_with::serde_as]
+#[derive(Debug, Deserialize)]
+pub(crate) struct QueryTxnModel {
+    /// Unique 64-bit ID for the order, chosen by the developer.
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub(crate) orderid: u64,
+    /// Unique 64-bit transaction ID, assigned by Steam.
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub(crate) transid: u64,
+    /// Steam ID of the user who placed the order.
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub(crate) steamid: u64,
+    /// The status of the order.
+    pub(crate) status: TxnStatus,
+    /// ISO 4217 currency code of the prices.
+    pub(crate) currency: String,
+    /// Time of the transaction.
+    pub(crate) time: DateTime<Utc>,
+    /// ISO 3166-1 alpha-2 country code of the user.
+    pub(crate) country: String,
+    /// US state, for purchases from the US.
+    pub(crate) usstate: Option<String>,
+    /// Time the order was created.
+    pub(crate) timecreated: Option<DateTime<Utc>>,
+    /// Items in the order.
+    #[serde(default)]
+    pub(crate) items: Vec<TxnItemModel>,
+}
+
+#[serde_with::serde_as]
+#[derive(Debug, Deserialize)]
+pub struct TxnItemModel {
+    /// Developer-defined ID of the item.
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub(crate) itemid: u64,
+    /// Quantity of the item purchased.
+    pub(crate) qty: i64,
+    /// Total cost of the item (in cents), not including VAT.
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub(crate) amount: i64,
+    /// VAT on the item (in cents).
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    #[serde(default)]
+    pub(crate) vat: Option<i64>,
+    /// The status of the item (same values as the order's status).
+    pub(crate) itemstatus: Option<TxnStatus>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub(crate) enum TxnStatus {
+    /// The order was created, but not yet approved by the user.
+    Init,
+    /// The user approved the order, but it has not been finalized.
+    Approved,
+    /// The order was finalized, and the user charged.
+    Succeeded,
+    Failed,
+    Refunded,
+    PartialRefund,
+    Chargedback,
+    RefundedSuspectedFraud,
+    RefundedFriendlyFraud,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// Data structure returned by ISteamMicroTxn/FinalizeTxn.
+///
+/// https://partner.steamgames.com/doc/webapi/ISteamMicroTxn#FinalizeTxn
+#[serde_with::serde_as]
+#[derive(Debug, Deserialize)]
+pub(crate) struct FinalizeTxnModel {
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub(crate) orderid: u64,
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub(crate) transid: u64,
+}
@@ -0,0 +1,62 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use serde_with::TimestampSeconds;
+
+/// Data structure for Stripe webhook events.
+///
+/// https://docs.stripe.com/api/events/object
+#[serde_with::serde_as]
+#[derive(Debug, Deserialize)]
+pub(crate) struct EventModel {
+    /// Unique ID of the event (ex. 'evt_1NG8Du2eZvKYlo2CUI79vXWy'). Retried
+    /// deliveries of the same event have the same ID.
+    pub(crate) id: String,
+    /// Type of the event, which determines the shape of 'data.object'.
+    #[serde(rename = "type")]
+    pub(crate) event_type: EventType,
+    /// When the event was created.
+    #[serde_as(as = "TimestampSeconds<i64>")]
+    pub(crate) created: DateTime<Utc>,
+    /// Whether the event occurred in live mode (as opposed to test mode).
+    pub(crate) livemode: bool,
+    pub(crate) data: EventDataModel,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct EventDataModel {
+    /// The object the event is about (ex. a subscription, for
+    /// 'customer.subscription.*' events).
+    pub(crate) object: Value,
+    /// For '*.updated' events, the previous values of the changed attributes.
+    pub(crate) previous_attributes: Option<Value>,
+}
+
+/// https://docs.stripe.com/api/events/types
+#[derive(Debug, Deserialize, PartialEq)]
+pub(crate) enum EventType {
+    #[serde(rename = "customer.subscription.created")]
+    SubscriptionCreated,
+    /// A subscription changed (ex. renewed, status changed, or cancellation
+    /// scheduled).
+    #[serde(rename = "customer.subscription.updated")]
+    SubscriptionUpdated,
+    /// A subscription ended, either immediately or at the end of the period
+    /// (after a scheduled cancellation).
+    #[serde(rename = "customer.subscription.deleted")]
+    SubscriptionDeleted,
+    /// A subscription's status changed to 'paused' (ex. when a trial ended
+    /// without a payment method).
+    #[serde(rename = "customer.subscription.paused")]
+    SubscriptionPaused,
+    #[serde(rename = "customer.subscription.resumed")]
+    SubscriptionResumed,
+    /// An invoice was paid (including invoices for subscription renewals).
+    #[serde(rename = "invoice.paid")]
+    InvoicePaid,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
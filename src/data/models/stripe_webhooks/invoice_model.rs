@@ -0,0 +1,105 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_with::TimestampSeconds;
+
+use super::subscription_model::ListModel;
+
+/// Stripe invoice object, as included in 'invoice.*' events.
+///
+/// https://docs.stripe.com/api/invoices/object
+///
+/// Since API version 2025-03-31, the subscription and prices are nested
+/// under 'parent' and 'pricing' respectively, so both layouts are accepted.
+#[derive(Debug, Deserialize)]
+pub(crate) struct InvoiceModel {
+    /// Unique ID of the invoice (ex. 'in_1MtHbELkdIwHu7ixl4OzzPMv').
+    pub(crate) id: String,
+    pub(crate) livemode: bool,
+    pub(crate) billing_reason: Option<BillingReason>,
+    /// ID of the subscription the invoice is for (before API version
+    /// 2025-03-31).
+    pub(crate) subscription: Option<String>,
+    pub(crate) parent: Option<InvoiceParentModel>,
+    pub(crate) lines: ListModel<InvoiceLineModel>,
+}
+
+impl InvoiceModel {
+    pub(crate) fn subscription_id(&self) -> Option<&str> {
+        self.subscription.as_deref().or(self
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.subscription_details.as_ref())
+            .map(|details| details.subscription.as_str()))
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BillingReason {
+    /// The subscription was created.
+    SubscriptionCreate,
+    /// The subscription renewed.
+    SubscriptionCycle,
+    /// The subscription was updated (ex. prorations).
+    SubscriptionUpdate,
+    Subscription,
+    Manual,
+    Upcoming,
+    SubscriptionThreshold,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct InvoiceParentModel {
+    pub(crate) subscription_details: Option<SubscriptionDetailsModel>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SubscriptionDetailsModel {
+    pub(crate) subscription: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct InvoiceLineModel {
+    pub(crate) period: PeriodModel,
+    /// Before API version 2025-03-31.
+    pub(crate) price: Option<LinePriceModel>,
+    /// Since API version 2025-03-31.
+    pub(crate) pricing: Option<LinePricingModel>,
+}
+
+impl InvoiceLineModel {
+    pub(crate) fn product_id(&self) -> Option<&str> {
+        self.price
+            .as_ref()
+            .map(|price| price.product.as_str())
+            .or(self
+                .pricing
+                .as_ref()
+                .and_then(|pricing| pricing.price_details.as_ref())
+                .map(|details| details.product.as_str()))
+    }
+}
+
+#[serde_with::serde_as]
+#[derive(Debug, Deserialize)]
+pub(crate) struct PeriodModel {
+    #[serde_as(as = "TimestampSeconds<i64>")]
+    pub(crate) start: DateTime<Utc>,
+    #[serde_as(as = "TimestampSeconds<i64>")]
+    pub(crate) end: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LinePriceModel {
+    pub(crate) product: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LinePricingModel {
+    pub(crate) price_details: Option<LinePriceModel>,
+}
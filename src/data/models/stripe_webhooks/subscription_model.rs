@@ -0,0 +1,115 @@
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_with::TimestampSeconds;
+
+/// Stripe subscription object, as included in 'customer.subscription.*'
+/// events.
+///
+/// https://docs.stripe.com/api/subscriptions/object
+///
+/// Since API version 2025-03-31, the current period is reported per item
+/// rather than for the whole subscription, so both are accepted.
+#[serde_with::serde_as]
+#[derive(Debug, Deserialize)]
+pub(crate) struct SubscriptionModel {
+    /// Unique ID of the subscription (ex. 'sub_1MowQVLkdIwHu7ixeRlqHVzs').
+    pub(crate) id: String,
+    pub(crate) status: SubscriptionStatus,
+    /// ID of the customer the subscription belongs to.
+    pub(crate) customer: String,
+    /// ISO 4217 currency code (lowercase).
+    pub(crate) currency: String,
+    pub(crate) livemode: bool,
+    #[serde_as(as = "TimestampSeconds<i64>")]
+    pub(crate) created: DateTime<Utc>,
+    /// When the subscription started (may be backdated, so can be before
+    /// 'created').
+    #[serde_as(as = "TimestampSeconds<i64>")]
+    pub(crate) start_date: DateTime<Utc>,
+    /// End of the current period (before API version 2025-03-31).
+    #[serde_as(as = "Option<TimestampSeconds<i64>>")]
+    #[serde(default)]
+    pub(crate) current_period_end: Option<DateTime<Utc>>,
+    /// Whether the subscription is canceled at the end of the current period.
+    #[serde(default)]
+    pub(crate) cancel_at_period_end: bool,
+    #[serde_as(as = "Option<TimestampSeconds<i64>>")]
+    #[serde(default)]
+    pub(crate) canceled_at: Option<DateTime<Utc>>,
+    /// When the subscription ended, if it has.
+    #[serde_as(as = "Option<TimestampSeconds<i64>>")]
+    #[serde(default)]
+    pub(crate) ended_at: Option<DateTime<Utc>>,
+    #[serde_as(as = "Option<TimestampSeconds<i64>>")]
+    #[serde(default)]
+    pub(crate) trial_end: Option<DateTime<Utc>>,
+    pub(crate) cancellation_details: Option<CancellationDetailsModel>,
+    pub(crate) items: ListModel<SubscriptionItemModel>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SubscriptionStatus {
+    /// The first payment has not been made yet.
+    Incomplete,
+    /// The first payment was not made within 23 hours.
+    IncompleteExpired,
+    Trialing,
+    Active,
+    /// A renewal payment failed, and is being retried.
+    PastDue,
+    Canceled,
+    /// Retries of a renewal payment were exhausted, without canceling the
+    /// subscription.
+    Unpaid,
+    Paused,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CancellationDetailsModel {
+    pub(crate) reason: Option<CancellationReason>,
+    /// Feedback chosen by the customer when cancelling (ex. 'too_expensive').
+    pub(crate) feedback: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CancellationReason {
+    CancellationRequested,
+    PaymentDisputed,
+    PaymentFailed,
+
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// Stripe list object.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ListModel<T> {
+    pub(crate) data: Vec<T>,
+}
+
+#[serde_with::serde_as]
+#[derive(Debug, Deserialize)]
+pub(crate) struct SubscriptionItemModel {
+    pub(crate) id: String,
+    pub(crate) price: PriceModel,
+    pub(crate) quantity: Option<i64>,
+    /// End of the item's current period (since API version 2025-03-31).
+    #[serde_as(as = "Option<TimestampSeconds<i64>>")]
+    #[serde(default)]
+    pub(crate) current_period_end: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PriceModel {
+    /// Unique ID of the price (ex. 'price_1MowQULkdIwHu7ixraBm864M').
+    pub(crate) id: String,
+    /// ID of the product the price is for (ex. 'prod_NWjs8kKbJWmuuc').
+    pub(crate) product: String,
+}
@@ -1,7 +1,15 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
 use async_trait::async_trait;
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
 use fractic_server_error::ServerError;
 
 use crate::{
+    budget::{with_default_callout_class, CalloutClass},
+    cache::InMemoryCacheStore,
+    capture::{collect_payloads, PayloadCaptures},
+    config::{current_environment, current_evaluation_time, ActivePolicy, IapConfig},
     data::{
         datasources::{
             app_store_server_api_datasource::{
@@ -10,48 +18,177 @@ use crate::{
             app_store_server_notification_datasource::{
                 AppStoreServerNotificationDatasource, AppStoreServerNotificationDatasourceImpl,
             },
+            callout_error::CalloutError,
             google_cloud_rtdn_notification_datasource::{
                 GoogleCloudRtdnNotificationDatasource, GoogleCloudRtdnNotificationDatasourceImpl,
             },
             google_play_developer_api_datasource::{
                 GooglePlayDeveloperApiDatasource, GooglePlayDeveloperApiDatasourceImpl,
             },
+            http_client::HttpClient,
         },
         models::{
-            app_store_server_api::{self, jws_transaction_decoded_payload_model as at},
+            app_store_server_api::{
+                self, app_transaction_model as aa, consumption_request_model as ac,
+                extend_renewal_date_model as ax, external_purchase_report_model as ae,
+                history_response_model as ahr, jws_renewal_info_decoded_payload_model as ar,
+                jws_transaction_decoded_payload_model as at, notification_history_model as ah,
+            },
             app_store_server_notifications::response_body_v2_decoded_payload_model as an,
             google_cloud_rtdn_notifications::developer_notification_model as gn,
             google_play_developer_api::{
-                in_app_product_model as gi, product_purchase_model as gp,
-                subscription_purchase_v2_model as gs,
+                external_transaction_model as ge, in_app_product_model as gi,
+                product_purchase_model as gp, product_purchase_v2_model as gp2,
+                subscription_deferral_model as gd, subscription_model as gm,
+                subscription_offer_model as go, subscription_purchase_v2_model as gs,
             },
         },
+        verification_cache::VerificationCache,
     },
     domain::{
         entities::{
+            iap_app_purchase::AppPurchaseDetails,
+            iap_audit::{AuditCheck, AuditCheckOutcome, IapAuditRecord},
+            iap_billing_period::BillingPeriod,
+            iap_consumption::{
+                ConsumeResult, ConsumptionInfo, ConsumptionRequestReason, ConsumptionStatus,
+                DeliveryStatus, RefundPreference, UserStatus,
+            },
             iap_details::{
-                ConsumableDetails, IapDetails, IapTypeSpecificDetails, MaybeKnown,
-                NonConsumableDetails, PriceInfo, SubscriptionDetails,
+                ConsumableDetails, ExpirationIntent, IapDetails, IapTypeSpecificDetails,
+                MaybeKnown, NonConsumableDetails, PlatformExtras, PriceInfo, PriceOffer,
+                PriceOfferType, PurchaseSource, SubscribeWithGoogleProfile, SubscriptionDetails,
+            },
+            iap_entitlement_extension::{EntitlementExtension, ExtensionReason},
+            iap_external_purchase::{
+                AppleExternalPurchaseToken, ExternalPurchaseEventType, ExternalPurchaseReport,
+                ExternalPurchaseReportState, ExternalPurchaseReportStatus,
+                GoogleExternalTransaction, GoogleExternalTransactionDetails,
+                GoogleExternalTransactionKind, GoogleExternalTransactionRefund,
+                GoogleExternalTransactionState,
             },
+            iap_health_report::{
+                CredentialCheck, CredentialCheckKind, CredentialReport, IapHealthReport,
+            },
+            iap_notification_history::{AppleNotificationHistoryEntry, NotificationSendAttempt},
             iap_product_id::{
-                private::{IapProductId, _ProductIdType},
+                private::{_ProductIdType, IapProductId},
                 IapConsumableId, IapNonConsumableId, IapSubscriptionId,
             },
-            iap_purchase_id::IapPurchaseId,
+            iap_product_listing::{
+                EeaWithdrawalRight, ProductListing, RegionalTaxSettings, StreamingTaxType,
+                TaxSettings, TaxTier,
+            },
+            iap_purchase_id::{
+                AppleExternalPurchaseId, AppleTransactionId, CanonicalPurchaseId,
+                GoogleExternalTransactionId, GooglePurchaseToken, IapPurchaseId,
+            },
+            iap_refund_risk::{RefundReason, RefundRiskProfile},
+            iap_subscription_plan::{
+                PricingPhase, PricingPhaseKind, SubscriptionBasePlan, SubscriptionOffer,
+            },
+            iap_subscription_status::{
+                AppleSubscriptionGroupStatus, AppleSubscriptionState, AppleSubscriptionStatus,
+            },
+            iap_transaction_history::{
+                AppleProductType, AppleTransaction, AppleTransactionHistoryFilters,
+            },
             iap_update_notification::{
-                IapUpdateNotification, NotificationDetails, SubscriptionEndReason,
+                IapUpdateNotification, NotificationDetails, OneTimePurchaseState, PlanChangeTiming,
+                SubscriptionEndReason, SubscriptionStartKind,
             },
         },
         repositories::iap_repository::{IapRepository, TypedProductId},
     },
+    error_observer::{IapError, IapErrorContext, IapPlatform, IapWarning},
+    errors::{
+        AppStoreServerApiInvalidResponse, EntitlementExtensionNotAvailable,
+        GoogleCloudRtdnNotificationParseError, GooglePlayDeveloperApiInvalidResponse,
+        InvalidAppleAppTransaction, InvalidAppleExternalPurchaseToken, InvalidEntitlementExtension,
+        InvalidGoogleExternalTransaction, InvalidPurchaseId, NotActive, ProductListingNotAvailable,
+        ProductTypeMismatch, PurchaseDenied, PurchaseNotConsumable,
+    },
+    region,
+    secrets::SecretString,
+    verifier::SignatureVerifier,
+};
+
+#[cfg(feature = "native")]
+use crate::data::datasources::native_signature_verifier::NativeSignatureVerifier;
+#[cfg(any(feature = "microsoft-store", feature = "steam"))]
+use crate::errors::PurchaseNotFound;
+#[cfg(not(feature = "native"))]
+use crate::errors::SignatureVerifierMissing;
+#[cfg(feature = "app-store-connect")]
+use crate::{
+    data::{
+        datasources::app_store_connect_api_datasource::{
+            AppStoreConnectApiDatasource, AppStoreConnectApiDatasourceImpl,
+        },
+        models::app_store_connect_api::{
+            document_model::{DocumentModel, IncludedResourceModel, RelationshipModel},
+            localization_model::LocalizationAttributes,
+            price_model as cp,
+        },
+    },
+    domain::entities::iap_price_schedule::ScheduledPrice,
     errors::{
-        AppStoreServerApiInvalidResponse, GoogleCloudRtdnNotificationParseError,
-        GooglePlayDeveloperApiInvalidResponse, NotActive,
+        AppStoreConnectApiInvalidResponse, AppStoreConnectNotConfigured, AppStoreProductNotFound,
+    },
+};
+#[cfg(feature = "microsoft-store")]
+use crate::{
+    data::{
+        datasources::microsoft_store_collections_api_datasource::{
+            MicrosoftStoreCollectionsApiDatasource, MicrosoftStoreCollectionsApiDatasourceImpl,
+        },
+        models::microsoft_store_collections_api::collections_query_response_model as mc,
+    },
+    domain::entities::iap_purchase_id::MicrosoftStoreIdKey,
+    errors::{MicrosoftStoreApiInvalidResponse, MicrosoftStoreNotConfigured},
+};
+#[cfg(feature = "paddle")]
+use crate::{
+    data::{
+        datasources::paddle_webhook_datasource::{
+            PaddleWebhookDatasource, PaddleWebhookDatasourceImpl,
+        },
+        models::paddle_billing_webhooks::{event_model as pe, subscription_model as ps},
+    },
+    domain::entities::iap_purchase_id::PaddleSubscriptionId,
+    errors::{PaddleNotConfigured, PaddleWebhookParseError},
+};
+#[cfg(feature = "steam")]
+use crate::{
+    data::{
+        datasources::steam_micro_txn_api_datasource::{
+            SteamMicroTxnApiDatasource, SteamMicroTxnApiDatasourceImpl,
+        },
+        models::steam_micro_txn_api::query_txn_response_model as st,
+    },
+    domain::entities::iap_purchase_id::SteamOrderId,
+    errors::{SteamMicroTxnApiInvalidResponse, SteamNotConfigured},
+};
+#[cfg(feature = "stripe")]
+use crate::{
+    data::{
+        datasources::stripe_webhook_datasource::{
+            StripeWebhookDatasource, StripeWebhookDatasourceImpl,
+        },
+        models::stripe_webhooks::{
+            event_model as se, invoice_model as si, subscription_model as ss,
+        },
     },
+    domain::entities::iap_purchase_id::StripeSubscriptionId,
+    errors::{StripeNotConfigured, StripeWebhookParseError},
 };
 
 use MaybeKnown::*;
 
+/// Limit on the earlier Google Play purchases followed when resolving linked
+/// purchase tokens (see 'canonical_purchase_id').
+const MAX_LINKED_PURCHASE_TOKENS: usize = 10;
+
 pub(crate) struct IapRepositoryImpl<
     A: AppStoreServerApiDatasource,
     B: AppStoreServerNotificationDatasource,
@@ -62,10 +199,56 @@ pub(crate) struct IapRepositoryImpl<
     app_store_server_notification_datasource: B,
     google_play_developer_api_datasource: C,
     google_cloud_rtdn_notification_datasource: D,
+    /// Only set if App Store Connect credentials are configured.
+    #[cfg(feature = "app-store-connect")]
+    app_store_connect_api_datasource: Option<AppStoreConnectApiDatasourceImpl>,
+    /// Only set if Microsoft Store credentials are configured.
+    #[cfg(feature = "microsoft-store")]
+    microsoft_store_collections_api_datasource: Option<MicrosoftStoreCollectionsApiDatasourceImpl>,
+    /// Only set if Steam credentials are configured.
+    #[cfg(feature = "steam")]
+    steam_micro_txn_api_datasource: Option<SteamMicroTxnApiDatasourceImpl>,
+    /// Only set if a Paddle webhook secret is configured.
+    #[cfg(feature = "paddle")]
+    paddle_webhook_datasource: Option<PaddleWebhookDatasourceImpl>,
+    /// Only set if a Stripe webhook secret is configured.
+    #[cfg(feature = "stripe")]
+    stripe_webhook_datasource: Option<StripeWebhookDatasourceImpl>,
     application_id: String,
+    config: IapConfig,
+    verification_cache: Option<VerificationCache>,
+    signature_verifier: Arc<dyn SignatureVerifier>,
+}
+
+/// Notification parsing failure, classified by whether redelivery of the same
+/// notification could succeed (ex. a platform API callout failed), or would
+/// fail again (ex. invalid signature or malformed body).
+pub(crate) struct NotificationError {
+    pub(crate) error: CalloutError,
+    pub(crate) retryable: bool,
+}
+
+impl NotificationError {
+    fn permanent(error: ServerError) -> Self {
+        Self {
+            error: error.into(),
+            retryable: false,
+        }
+    }
+
+    /// Failed platform API callouts are retryable, unless the platform's
+    /// response indicates the failure would recur (ex. the purchase does not
+    /// exist).
+    fn from_callout(error: CalloutError) -> Self {
+        Self {
+            retryable: error.is_transient(),
+            error,
+        }
+    }
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl<
         A: AppStoreServerApiDatasource,
         B: AppStoreServerNotificationDatasource,
@@ -78,52 +261,68 @@ impl<
         product_id: T,
         purchase_id: IapPurchaseId,
         include_price_info: bool,
-    ) -> Result<IapDetails<T::DetailsType>, ServerError> {
-        let iap_details = match &purchase_id {
-            IapPurchaseId::AppStoreTransactionId(transaction_id) => {
-                let m = self
-                    .app_store_server_api_datasource
-                    .get_transaction_info(&transaction_id)
-                    .await?;
-                IapDetails::from_apple_transaction::<T>(m, include_price_info)?
-            }
-            IapPurchaseId::GooglePlayPurchaseToken(token) => {
-                match T::product_type() {
-                    _ProductIdType::Consumable | _ProductIdType::NonConsumable => {
-                        let m = self
-                            .google_play_developer_api_datasource
-                            .get_product_purchase(&self.application_id, product_id.sku(), token)
-                            .await?;
-                        let p = if include_price_info {
-                            Some(
-                                self.google_play_developer_api_datasource
-                                    .get_in_app_product(&self.application_id, product_id.sku())
-                                    .await?,
-                            )
-                        } else {
-                            None
-                        };
-                        IapDetails::from_google_product_purchase::<T>(purchase_id, m, p)?
-                    }
-                    _ProductIdType::Subscription => {
-                        let m = self
-                            .google_play_developer_api_datasource
-                            .get_subscription_purchase_v2(&self.application_id, token)
-                            .await?;
-                        // Price info not available for subscriptions.
-                        //
-                        // This would technically be possible with the
-                        // monetization.subscriptions API, but would be quite
-                        // complex as it requires determining which base plan is
-                        // purchased.
-                        let p = None;
-                        IapDetails::from_google_subscription_purchase::<T>(purchase_id, m, p)?
-                    }
+    ) -> Result<IapDetails<T::DetailsType>, IapError> {
+        // Results verified in an overridden environment are not cached.
+        let verification_cache = self
+            .verification_cache
+            .as_ref()
+            .filter(|_| current_environment().is_none() && current_evaluation_time().is_none());
+        if let Some(cache) = verification_cache {
+            if let Some(cached) = cache
+                .get(&purchase_id, product_id.sku(), include_price_info)
+                .await
+            {
+                // Purchases may have been denied since they were cached.
+                if self.is_denied(&purchase_id, &cached).await? {
+                    return Err(PurchaseDenied::new().into());
                 }
+                return Ok(cached);
             }
-        };
+        }
+        let sku = product_id.sku().to_owned();
+        let result = self
+            .get_details(product_id, purchase_id.clone(), include_price_info, false)
+            .await;
+        let iap_details = self
+            .observed("verify_and_get_details", &purchase_id, result)
+            .await?;
+        if self.is_denied(&purchase_id, &iap_details).await? {
+            return Err(PurchaseDenied::new().into());
+        }
         if !iap_details.is_active {
-            return Err(NotActive::new());
+            return Err(NotActive::new().into());
+        }
+        if let Some(deadline) = iap_details.acknowledgement_deadline {
+            self.notify_warning_observers(
+                "verify_and_get_details",
+                &purchase_id,
+                &IapWarning::AcknowledgementPending { deadline },
+            )
+            .await;
+        }
+        if let Some(cache) = verification_cache {
+            cache
+                .insert(purchase_id, &sku, include_price_info, &iap_details)
+                .await;
+        }
+        Ok(iap_details)
+    }
+
+    async fn get_details_allow_inactive<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+    ) -> Result<IapDetails<T::DetailsType>, IapError> {
+        let result = self
+            .get_details(product_id, purchase_id.clone(), include_price_info, true)
+            .await;
+        let mut iap_details = self
+            .observed("get_details_allow_inactive", &purchase_id, result)
+            .await?;
+        if self.is_denied(&purchase_id, &iap_details).await? {
+            iap_details.is_active = false;
+            iap_details.is_pending = false;
         }
         Ok(iap_details)
     }
@@ -132,41 +331,320 @@ impl<
         &self,
         product_id: IapConsumableId,
         purchase_id: IapPurchaseId,
-    ) -> Result<(), ServerError> {
-        match purchase_id {
+    ) -> Result<ConsumeResult, IapError> {
+        match &purchase_id {
             IapPurchaseId::GooglePlayPurchaseToken(token) => {
-                self.google_play_developer_api_datasource
-                    .consume_product_purchase(&self.application_id, product_id.sku(), &token)
-                    .await
+                let result = self
+                    .google_play_developer_api_datasource
+                    .get_product_purchase(&self.application_id, product_id.sku(), token.as_str())
+                    .await;
+                let purchase = self.observed("consume", &purchase_id, result).await?;
+                match purchase.purchase_state {
+                    gp::PurchaseState::Purchased => {}
+                    gp::PurchaseState::Canceled => {
+                        return Err(PurchaseNotConsumable::new("canceled").into())
+                    }
+                    gp::PurchaseState::Pending => {
+                        return Err(PurchaseNotConsumable::new("pending").into())
+                    }
+                }
+                if purchase.consumption_state == gp::ConsumptionState::Consumed {
+                    return Ok(ConsumeResult::AlreadyConsumed);
+                }
+                let result = self
+                    .google_play_developer_api_datasource
+                    .consume_product_purchase(
+                        &self.application_id,
+                        product_id.sku(),
+                        token.as_str(),
+                    )
+                    .await;
+                self.observed("consume", &purchase_id, result).await?;
+                Ok(ConsumeResult::Consumed)
+            }
+            #[cfg(feature = "microsoft-store")]
+            IapPurchaseId::MicrosoftStoreIdKey(key) => {
+                let result = self.consume_microsoft_store_product(key, &product_id).await;
+                self.observed("consume", &purchase_id, result).await?;
+                Ok(ConsumeResult::Consumed)
             }
-            _ => Ok(()),
+            _ => Ok(ConsumeResult::Consumed),
         }
     }
 
     async fn parse_apple_notification(
         &self,
         body: &str,
-    ) -> Result<IapUpdateNotification, ServerError> {
-        let (notification, transaction_info, _subscription_renewal_info) = self
+    ) -> Result<IapUpdateNotification, IapError> {
+        let result = self.try_parse_apple_notification(body).await;
+        self.observed_notification("parse_apple_notification", IapPlatform::AppStore, result)
+            .await
+    }
+
+    async fn parse_google_notification(
+        &self,
+        authorization_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        let result = self
+            .try_parse_google_notification(authorization_header, body)
+            .await;
+        self.observed_notification("parse_google_notification", IapPlatform::GooglePlay, result)
+            .await
+    }
+
+    async fn parse_google_developer_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        let result = self.try_parse_google_developer_notification(body).await;
+        self.observed_notification(
+            "parse_google_developer_notification",
+            IapPlatform::GooglePlay,
+            result,
+        )
+        .await
+    }
+
+    async fn verify_apple_notification_signature(&self, body: &str) -> Result<(), IapError> {
+        Ok(self
+            .app_store_server_notification_datasource
+            .verify_notification_signature(body)
+            .await?)
+    }
+
+    async fn verify_google_notification_auth(
+        &self,
+        authorization_header: &str,
+    ) -> Result<(), IapError> {
+        Ok(self
+            .google_cloud_rtdn_notification_datasource
+            .verify_authorization(authorization_header)
+            .await?)
+    }
+
+    async fn request_apple_test_notification(&self, sandbox: bool) -> Result<String, IapError> {
+        Ok(self
+            .app_store_server_api_datasource
+            .request_test_notification(sandbox)
+            .await?)
+    }
+
+    async fn health_check(&self) -> IapHealthReport {
+        let (apple, google) = futures::join!(
+            self.app_store_server_api_datasource.check_credentials(),
+            self.google_play_developer_api_datasource
+                .check_credentials(),
+        );
+        IapHealthReport {
+            apple: apple.into(),
+            google: google.into(),
+        }
+    }
+}
+
+impl<
+        A: AppStoreServerApiDatasource,
+        B: AppStoreServerNotificationDatasource,
+        C: GooglePlayDeveloperApiDatasource,
+        D: GoogleCloudRtdnNotificationDatasource,
+    > IapRepositoryImpl<A, B, C, D>
+{
+    /// Paddle notifications are not part of 'IapRepository', since they are
+    /// only available with the 'paddle' feature.
+    #[cfg(feature = "paddle")]
+    pub(crate) async fn parse_paddle_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        let result = self
+            .try_parse_paddle_notification(signature_header, body)
+            .await;
+        self.observed_notification("parse_paddle_notification", IapPlatform::Paddle, result)
+            .await
+    }
+
+    /// Stripe events are not part of 'IapRepository', since they are only
+    /// available with the 'stripe' feature.
+    #[cfg(feature = "stripe")]
+    pub(crate) async fn parse_stripe_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        let result = self
+            .try_parse_stripe_notification(signature_header, body)
+            .await;
+        self.observed_notification("parse_stripe_notification", IapPlatform::Stripe, result)
+            .await
+    }
+
+    async fn try_parse_apple_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, NotificationError> {
+        let (notification, transaction_info, subscription_renewal_info) = self
             .app_store_server_notification_datasource
             .parse_notification(body)
-            .await?;
+            .await
+            .map_err(NotificationError::permanent)?;
+        let notification_id = notification.notification_uuid.clone();
+        let time = notification.signed_date.clone();
+        let is_offer_conversion = with_default_callout_class(
+            CalloutClass::Notification,
+            self.apple_offer_conversion(&notification.notification_type, transaction_info.as_ref()),
+        )
+        .await;
+        let billing_periods = with_default_callout_class(
+            CalloutClass::Notification,
+            self.apple_plan_change_periods(
+                &notification,
+                transaction_info.as_ref(),
+                subscription_renewal_info.as_ref(),
+            ),
+        )
+        .await;
+        let details = NotificationDetails::from_apple_notification(
+            notification,
+            transaction_info,
+            subscription_renewal_info,
+            is_offer_conversion,
+            billing_periods,
+            &self.config,
+        )
+        .map_err(NotificationError::permanent)?;
+        self.invalidate_cached(&details).await;
         Ok(IapUpdateNotification {
-            notification_id: notification.notification_uuid.clone(),
-            time: notification.signed_date.clone(),
-            details: NotificationDetails::from_apple_notification(notification, transaction_info)?,
+            notification_id,
+            time,
+            details,
         })
     }
 
-    async fn parse_google_notification(
+    async fn try_parse_google_notification(
         &self,
         authorization_header: &str,
         body: &str,
-    ) -> Result<IapUpdateNotification, ServerError> {
+    ) -> Result<IapUpdateNotification, NotificationError> {
         let (wrapper, notification) = self
             .google_cloud_rtdn_notification_datasource
             .parse_notification(authorization_header, body)
-            .await?;
+            .await
+            .map_err(NotificationError::permanent)?;
+        with_default_callout_class(
+            CalloutClass::Notification,
+            self.google_update_notification(wrapper.message.message_id, notification),
+        )
+        .await
+    }
+
+    #[cfg(feature = "paddle")]
+    async fn try_parse_paddle_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, NotificationError> {
+        let event = self
+            .paddle_webhook_datasource
+            .as_ref()
+            .ok_or_else(PaddleNotConfigured::new)
+            .map_err(NotificationError::permanent)?
+            .parse_notification(signature_header, body)
+            .await
+            .map_err(NotificationError::permanent)?;
+        let notification_id = event
+            .notification_id
+            .clone()
+            .unwrap_or_else(|| event.event_id.clone());
+        let time = event.occurred_at;
+        let details = NotificationDetails::from_paddle_event(
+            event,
+            self.application_id.clone(),
+            &self.config,
+        )
+        .map_err(NotificationError::permanent)?;
+        self.invalidate_cached(&details).await;
+        Ok(IapUpdateNotification {
+            notification_id,
+            time,
+            details,
+        })
+    }
+
+    #[cfg(feature = "stripe")]
+    async fn try_parse_stripe_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, NotificationError> {
+        let event = self
+            .stripe_webhook_datasource
+            .as_ref()
+            .ok_or_else(StripeNotConfigured::new)
+            .map_err(NotificationError::permanent)?
+            .parse_notification(signature_header, body)
+            .await
+            .map_err(NotificationError::permanent)?;
+        let notification_id = event.id.clone();
+        let time = event.created;
+        let details = NotificationDetails::from_stripe_event(
+            event,
+            self.application_id.clone(),
+            &self.config,
+        )
+        .map_err(NotificationError::permanent)?;
+        self.invalidate_cached(&details).await;
+        Ok(IapUpdateNotification {
+            notification_id,
+            time,
+            details,
+        })
+    }
+
+    async fn try_parse_google_developer_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, NotificationError> {
+        let notification = self
+            .google_cloud_rtdn_notification_datasource
+            .parse_developer_notification(body)
+            .await
+            .map_err(NotificationError::permanent)?;
+        // Without the Pub/Sub message ID, derive a stable ID from the
+        // notification itself, so that redeliveries can still be detected.
+        let purchase_token = notification
+            .subscription_notification
+            .as_ref()
+            .map(|n| n.purchase_token.as_str())
+            .or(notification
+                .one_time_product_notification
+                .as_ref()
+                .map(|n| n.purchase_token.as_str()))
+            .or(notification
+                .voided_purchase_notification
+                .as_ref()
+                .map(|n| n.purchase_token.as_str()))
+            .or(notification.other.keys().next().map(String::as_str))
+            .unwrap_or("test");
+        let notification_id = format!(
+            "{}:{}:{}",
+            notification.package_name,
+            notification.event_time_millis.timestamp_millis(),
+            purchase_token
+        );
+        with_default_callout_class(
+            CalloutClass::Notification,
+            self.google_update_notification(notification_id, notification),
+        )
+        .await
+    }
+
+    async fn google_update_notification(
+        &self,
+        notification_id: String,
+        notification: gn::DeveloperNotificationModel,
+    ) -> Result<IapUpdateNotification, NotificationError> {
         let application_id = notification.package_name.clone();
         let details = if let Some(_) = notification.test_notification {
             NotificationDetails::Test
@@ -175,195 +653,3336 @@ impl<
                 subscription_notification,
                 application_id,
                 &self.google_play_developer_api_datasource,
+                &self.config,
             )
-            .await?
+            .await
+            .map_err(NotificationError::from_callout)?
         } else if let Some(voided_purchase_notification) = notification.voided_purchase_notification
         {
             NotificationDetails::from_google_voided_purchase_notification(
                 voided_purchase_notification,
                 application_id,
                 &self.google_play_developer_api_datasource,
+                &self.config,
             )
-            .await?
-        } else if let Some(_) = notification.one_time_product_notification {
-            NotificationDetails::Other
+            .await
+            .map_err(NotificationError::from_callout)?
+        } else if let Some(one_time_product_notification) =
+            notification.one_time_product_notification
+        {
+            NotificationDetails::from_google_one_time_product_notification(
+                one_time_product_notification,
+                application_id,
+                &self.google_play_developer_api_datasource,
+            )
+            .await
+            .map_err(NotificationError::from_callout)?
+        } else if !notification.other.is_empty() {
+            // Notification categories added by Google since, which should not
+            // be rejected (or they would be redelivered indefinitely).
+            NotificationDetails::Other {
+                raw: Some(serde_json::Value::Object(notification.other)),
+            }
         } else {
-            return Err(GoogleCloudRtdnNotificationParseError::new(
+            return Err(NotificationError::permanent(GoogleCloudRtdnNotificationParseError::new(
                 "notification did not have one of the recognized types (subscription, one-time purchase, voided purchase, or test)",
-            ));
+            )));
         };
+        self.invalidate_cached(&details).await;
         Ok(IapUpdateNotification {
-            notification_id: wrapper.message.message_id,
+            notification_id,
             time: notification.event_time_millis,
             details,
         })
     }
 
-    async fn request_apple_test_notification(&self, sandbox: bool) -> Result<String, ServerError> {
-        self.app_store_server_api_datasource
-            .request_test_notification(sandbox)
-            .await
+    /// Reports a failed operation concerning the given purchase to the error
+    /// observers.
+    async fn observed<T>(
+        &self,
+        operation: &'static str,
+        purchase_id: &IapPurchaseId,
+        result: Result<T, CalloutError>,
+    ) -> Result<T, IapError> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                // Only failed callouts may be transient; anything else (ex. an
+                // invalid signature) would recur.
+                let transient = e.endpoint.is_some() && e.is_transient();
+                Err(self
+                    .observed_error(operation, None, Some(purchase_id), e, transient)
+                    .await)
+            }
+        }
     }
-}
 
-impl
-    IapRepositoryImpl<
-        AppStoreServerApiDatasourceImpl,
-        AppStoreServerNotificationDatasourceImpl,
-        GooglePlayDeveloperApiDatasourceImpl,
-        GoogleCloudRtdnNotificationDatasourceImpl,
-    >
-{
-    pub(crate) async fn new(
-        application_id: impl Into<String>,
-        expected_aud: impl Into<String>,
-        apple_api_key: &str,
-        apple_key_id: &str,
-        apple_issuer_id: &str,
-        google_api_key: &str,
-    ) -> Result<Self, ServerError> {
-        let application_id = application_id.into();
-        let expected_aud = expected_aud.into();
-        Ok(Self {
-            app_store_server_api_datasource: AppStoreServerApiDatasourceImpl::new(
-                apple_api_key,
-                apple_key_id,
-                apple_issuer_id,
-                &application_id,
-                expected_aud.clone(),
-            )
-            .await?,
-            app_store_server_notification_datasource: AppStoreServerNotificationDatasourceImpl::new(
-                expected_aud.clone(),
-            ),
-            google_play_developer_api_datasource: GooglePlayDeveloperApiDatasourceImpl::new(
-                google_api_key,
-            )
-            .await?,
-            google_cloud_rtdn_notification_datasource:
-                GoogleCloudRtdnNotificationDatasourceImpl::new(expected_aud),
-            application_id,
-        })
+    /// Reports a failed notification to the error observers.
+    async fn observed_notification<T>(
+        &self,
+        operation: &'static str,
+        platform: IapPlatform,
+        result: Result<T, NotificationError>,
+    ) -> Result<T, IapError> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(e) => Err(self
+                .observed_error(operation, Some(platform), None, e.error, e.retryable)
+                .await),
+        }
     }
-}
 
-impl<U: IapTypeSpecificDetails> IapDetails<U> {
-    fn from_apple_transaction<T: TypedProductId<DetailsType = U>>(
-        m: at::JwsTransactionDecodedPayloadModel,
-        include_price_info: bool,
-    ) -> Result<Self, ServerError> {
-        Ok(IapDetails {
-            cannonical_id: IapPurchaseId::AppStoreTransactionId(m.original_transaction_id.clone()),
-            // NOTE: For subscriptions, we should also check the expiry date.
-            // This field is only present for subscriptions, so assume true if
-            // it is not present (its presence for subscriptions is validated by
-            // subscription-specific parsing logic later on).
-            is_active: m.revocation_date.is_none()
-                && m.revocation_reason.is_none()
-                && m.expires_date
-                    .map(|expiry| expiry > chrono::Utc::now())
-                    .unwrap_or(true),
-            is_sandbox: m.environment == app_store_server_api::common::Environment::Sandbox,
-            is_finalized_by_client: Unknown,
-            purchase_time: m.purchase_date,
-            region_iso3166_alpha_3: m.storefront.clone(), // Already in ISO 3166-1 alpha-3 format.
-            price_info: if include_price_info {
-                Some(PriceInfo {
-                    price_micros: m.price.ok_or_else(|| {
-                        AppStoreServerApiInvalidResponse::new(
-                            "transaction did not contain price info",
-                        )
-                    })? * 1000,
-                    currency_iso_4217: m.currency.clone().ok_or_else(|| {
-                        AppStoreServerApiInvalidResponse::new(
-                            "transaction did not contain currency info",
-                        )
-                    })?, // Already in ISO 4217 format.
-                })
-            } else {
-                None
-            },
-            type_specific_details: T::extract_details_from_apple_transaction(&m)?,
-        })
-    }
+    /// Attaches the structured context to the error, and reports it to the
+    /// error observers.
+    async fn observed_error(
+        &self,
+        operation: &'static str,
+        platform: Option<IapPlatform>,
+        purchase_id: Option<&IapPurchaseId>,
+        error: CalloutError,
+        transient: bool,
+    ) -> IapError {
+        let context = IapErrorContext::new(
+            operation,
+            platform.or(error.platform),
+            error.endpoint,
+            error.api_error,
+            purchase_id,
+            self.config.purchase_id_hash_key.as_ref(),
+            transient,
+        );
+        for observer in &self.config.error_observers {
+            observer.on_error(&context, &error.error).await;
+        }
+        IapError::new(error.error, context)
+    }
+
+    async fn notify_warning_observers(
+        &self,
+        operation: &'static str,
+        purchase_id: &IapPurchaseId,
+        warning: &IapWarning,
+    ) {
+        if self.config.error_observers.is_empty() {
+            return;
+        }
+        let context = IapErrorContext::new(
+            operation,
+            None,
+            None,
+            None,
+            Some(purchase_id),
+            self.config.purchase_id_hash_key.as_ref(),
+            false,
+        );
+        for observer in &self.config.error_observers {
+            observer.on_warning(&context, warning).await;
+        }
+    }
+
+    /// Apple renewal transactions only describe the offer of the new period,
+    /// so whether a renewal ended an introductory offer is determined from the
+    /// subscription's first transaction (which is where the introductory offer
+    /// is redeemed). To avoid a callout for every renewal, this is only looked
+    /// up for renewals within the first year of the subscription.
+    async fn apple_offer_conversion(
+        &self,
+        notification_type: &an::NotificationType,
+        transaction_info: Option<&at::JwsTransactionDecodedPayloadModel>,
+    ) -> MaybeKnown<bool> {
+        use app_store_server_api::common::{OfferDiscountType, OfferType};
+
+        let Some(t) = transaction_info else {
+            return Unknown;
+        };
+        if *notification_type != an::NotificationType::DidRenew
+            || t.offer_type.is_some()
+            || t.transaction_id == t.original_transaction_id
+        {
+            return Known(false);
+        }
+        let Some(period) = t.expires_date.map(|expiry| expiry - t.purchase_date) else {
+            return Unknown;
+        };
+        if t.original_purchase_date.is_some_and(|original_purchase| {
+            t.purchase_date - original_purchase > chrono::Duration::days(366) + period
+        }) {
+            return Known(false);
+        }
+        let Ok(original) = self
+            .app_store_server_api_datasource
+            .get_transaction_info(&t.original_transaction_id)
+            .await
+        else {
+            return Unknown;
+        };
+        match (&original.offer_type, &original.offer_discount_type) {
+            (
+                Some(OfferType::Introductory),
+                Some(OfferDiscountType::FreeTrial | OfferDiscountType::PayUpFront),
+            ) => match original.expires_date {
+                // Single-period offers end with the first renewal after them.
+                Some(offer_end) => Known(t.purchase_date < offer_end + period),
+                None => Unknown,
+            },
+            // Pay-as-you-go offers span several periods, which are not
+            // distinguishable from the renewal transactions.
+            (Some(OfferType::Introductory), _) => Unknown,
+            _ => Known(false),
+        }
+    }
+
+    /// Billing periods before and after an App Store plan change (see
+    /// 'NotificationDetails::SubscriptionPlanChanged'), as far as known. The
+    /// previous product of upgrades is not reported by Apple.
+    async fn apple_plan_change_periods(
+        &self,
+        notification: &an::ResponseBodyV2DecodedPayloadModel,
+        transaction_info: Option<&at::JwsTransactionDecodedPayloadModel>,
+        renewal_info: Option<&ar::JwsRenewalInfoDecodedPayloadModel>,
+    ) -> (Option<BillingPeriod>, Option<BillingPeriod>) {
+        let Some(t) = transaction_info else {
+            return (None, None);
+        };
+        match (&notification.notification_type, &notification.subtype) {
+            (
+                an::NotificationType::DidChangeRenewalPref | an::NotificationType::OfferRedeemed,
+                Some(an::NotificationSubtype::Upgrade),
+            ) => (None, self.apple_billing_period(&t.product_id).await),
+            (
+                an::NotificationType::DidChangeRenewalPref | an::NotificationType::OfferRedeemed,
+                Some(an::NotificationSubtype::Downgrade),
+            ) => {
+                let previous = self.apple_billing_period(&t.product_id).await;
+                let current = match renewal_info {
+                    Some(r) => self.apple_billing_period(&r.auto_renew_product_id).await,
+                    None => None,
+                };
+                (previous, current)
+            }
+            _ => (None, None),
+        }
+    }
+
+    /// Billing period of the subscription product, only looked up if App
+    /// Store Connect credentials are configured.
+    #[cfg(feature = "app-store-connect")]
+    async fn apple_billing_period(&self, sku: &str) -> Option<BillingPeriod> {
+        let subscription = self
+            .app_store_connect_datasource()
+            .ok()?
+            .find_subscription(sku)
+            .await
+            .ok()??;
+        BillingPeriod::from_app_store_connect_period(&subscription.attributes?.subscription_period?)
+    }
+
+    #[cfg(not(feature = "app-store-connect"))]
+    async fn apple_billing_period(&self, _sku: &str) -> Option<BillingPeriod> {
+        None
+    }
+
+    /// Whether the purchase is on the configured deny list, by the verified
+    /// ID or the canonical ID.
+    async fn is_denied<T: IapTypeSpecificDetails>(
+        &self,
+        purchase_id: &IapPurchaseId,
+        details: &IapDetails<T>,
+    ) -> Result<bool, ServerError> {
+        let Some(deny_list) = &self.config.deny_list else {
+            return Ok(false);
+        };
+        if deny_list.is_denied(purchase_id).await? {
+            return Ok(true);
+        }
+        if details.canonical_id != *purchase_id {
+            return deny_list.is_denied(&details.canonical_id).await;
+        }
+        Ok(false)
+    }
+
+    async fn invalidate_cached(&self, details: &NotificationDetails) {
+        if let (Some(cache), Some(purchase_id)) = (&self.verification_cache, details.purchase_id())
+        {
+            cache.invalidate(purchase_id).await;
+        }
+    }
+
+    async fn get_details<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+        allow_inactive: bool,
+    ) -> Result<IapDetails<T::DetailsType>, CalloutError> {
+        Ok(match &purchase_id {
+            IapPurchaseId::AppStoreTransactionId(transaction_id) => {
+                let m = self
+                    .app_store_server_api_datasource
+                    .get_transaction_info(transaction_id.as_str())
+                    .await?;
+                // The transaction itself does not say why a subscription
+                // lapsed, so if it has expired, fetch the latest renewal info
+                // (which carries the expiration intent). This is skipped when
+                // inactive purchases would be rejected anyway. The renewal
+                // info also carries the price of pay-as-you-go offers, which
+                // older transactions do not report themselves.
+                let r = if (allow_inactive
+                    && m.revocation_date.is_none()
+                    && m.expires_date
+                        .map(|expiry| self.config.is_expired(expiry))
+                        .unwrap_or(false))
+                    || (include_price_info && m.price.is_none() && m.offer_type.is_some())
+                {
+                    self.app_store_server_api_datasource
+                        .get_all_subscription_statuses(transaction_id.as_str())
+                        .await?
+                        .into_iter()
+                        .find(|(_, _, t, _)| t.original_transaction_id == m.original_transaction_id)
+                        .map(|(_, _, _, r)| r)
+                } else {
+                    None
+                };
+                #[cfg(feature = "app-store-connect")]
+                let catalog_price = self
+                    .apple_catalog_price(&product_id, &m, include_price_info)
+                    .await?;
+                #[cfg(not(feature = "app-store-connect"))]
+                let catalog_price: Option<PriceInfo> = None;
+                let mut details = IapDetails::from_apple_transaction::<T>(
+                    m,
+                    r.as_ref(),
+                    include_price_info && catalog_price.is_none(),
+                    &self.config,
+                )?;
+                if catalog_price.is_some() {
+                    details.price_info = catalog_price;
+                }
+                details
+            }
+            IapPurchaseId::GooglePlayPurchaseToken(token) => {
+                match T::product_type() {
+                    _ProductIdType::Consumable | _ProductIdType::NonConsumable => {
+                        // The purchase and price lookups are independent, so
+                        // they are sent concurrently.
+                        let ((m, platform_extras), p) = futures::try_join!(
+                            self.google_product_purchase(product_id.sku(), token.as_str()),
+                            async {
+                                match include_price_info {
+                                    true => self
+                                        .google_play_developer_api_datasource
+                                        .get_in_app_product(&self.application_id, product_id.sku())
+                                        .await
+                                        .map(Some),
+                                    false => Ok(None),
+                                }
+                            },
+                        )?;
+                        IapDetails::from_google_product_purchase::<T>(
+                            purchase_id,
+                            m,
+                            p,
+                            platform_extras,
+                            &self.config,
+                        )?
+                    }
+                    _ProductIdType::Subscription => {
+                        let m = self
+                            .google_play_developer_api_datasource
+                            .get_subscription_purchase_v2(&self.application_id, token.as_str())
+                            .await?;
+                        let price_info = match include_price_info {
+                            true => self.google_subscription_price(&m).await?,
+                            false => None,
+                        };
+                        let linked_token = m.linked_purchase_token.clone();
+                        let mut details = IapDetails::from_google_subscription_purchase::<T>(
+                            purchase_id,
+                            m,
+                            price_info,
+                            &self.config,
+                        )?;
+                        // The customer first subscribed with the earliest
+                        // purchase this one (transitively) replaced.
+                        if linked_token.is_some() {
+                            let linked = self.google_linked_purchases(linked_token).await?;
+                            details.original_purchase_time = linked
+                                .iter()
+                                .rev()
+                                .find_map(|(_, start_time)| *start_time)
+                                .or(Some(details.purchase_time));
+                        }
+                        details
+                    }
+                }
+            }
+            IapPurchaseId::AppleExternalPurchaseId(_) => {
+                return Err(InvalidPurchaseId::new(
+                    "Apple external purchases have no App Store transaction to verify",
+                )
+                .into());
+            }
+            IapPurchaseId::GoogleExternalTransactionId(_) => {
+                return Err(InvalidPurchaseId::new(
+                    "Google external transactions are not processed by Google Play, so can not be verified",
+                )
+                .into());
+            }
+            #[cfg(feature = "microsoft-store")]
+            IapPurchaseId::MicrosoftStoreIdKey(key) => {
+                let m = self.microsoft_store_item(key, product_id.sku()).await?;
+                // Price info not available through the collections API.
+                IapDetails::from_microsoft_collection_item::<T>(key.clone(), m, &self.config)?
+            }
+            #[cfg(feature = "steam")]
+            IapPurchaseId::SteamOrderId(order_id) => {
+                if let _ProductIdType::Subscription = T::product_type() {
+                    return Err(InvalidPurchaseId::new(
+                        "Steam orders can not be verified as subscriptions",
+                    )
+                    .into());
+                }
+                let m = self.steam_datasource()?.query_txn(order_id.value()).await?;
+                IapDetails::from_steam_txn::<T>(
+                    m,
+                    product_id.sku(),
+                    include_price_info,
+                    &self.config,
+                )?
+            }
+            #[cfg(feature = "paddle")]
+            IapPurchaseId::PaddleSubscriptionId(_) => {
+                return Err(InvalidPurchaseId::new(
+                    "Paddle subscriptions can only be tracked through webhook notifications",
+                )
+                .into());
+            }
+            #[cfg(feature = "stripe")]
+            IapPurchaseId::StripeSubscriptionId(_) => {
+                return Err(InvalidPurchaseId::new(
+                    "Stripe subscriptions can only be tracked through webhook events",
+                )
+                .into());
+            }
+        })
+    }
+
+    #[cfg(feature = "steam")]
+    fn steam_datasource(&self) -> Result<&SteamMicroTxnApiDatasourceImpl, ServerError> {
+        self.steam_micro_txn_api_datasource
+            .as_ref()
+            .ok_or_else(SteamNotConfigured::new)
+    }
+
+    #[cfg(feature = "steam")]
+    pub(crate) async fn finalize_steam_order(
+        &self,
+        order_id: SteamOrderId,
+    ) -> Result<(), IapError> {
+        let result = match self.steam_datasource() {
+            Ok(datasource) => datasource.finalize_txn(order_id.value()).await,
+            Err(e) => Err(e.into()),
+        };
+        let purchase_id = IapPurchaseId::SteamOrderId(order_id);
+        self.observed("finalize_steam_order", &purchase_id, result)
+            .await?;
+        if let Some(cache) = &self.verification_cache {
+            cache.invalidate(&purchase_id).await;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn decode_apple_external_purchase_token(
+        &self,
+        token: &str,
+    ) -> Result<AppleExternalPurchaseToken, IapError> {
+        // StoreKit returns the token as Base64URL, but tolerate the standard
+        // alphabet and padding in case it was re-encoded on the way.
+        let normalized = token
+            .trim()
+            .trim_end_matches('=')
+            .replace('+', "-")
+            .replace('/', "_");
+        let decoded = BASE64_URL_SAFE_NO_PAD
+            .decode(normalized)
+            .map_err(|e| InvalidAppleExternalPurchaseToken::with_debug("not valid base64", &e))?;
+        let m: an::ExternalPurchaseToken = serde_json::from_slice(&decoded).map_err(|e| {
+            InvalidAppleExternalPurchaseToken::with_debug("failed to parse token payload", &e)
+        })?;
+        if m.bundle_id != self.application_id {
+            return Err(InvalidAppleExternalPurchaseToken::new(&format!(
+                "token was issued for bundle '{}'",
+                m.bundle_id
+            ))
+            .into());
+        }
+        Ok(AppleExternalPurchaseToken::from_apple_token(m)?)
+    }
+
+    pub(crate) async fn verify_apple_app_transaction(
+        &self,
+        signed_app_transaction: &str,
+    ) -> Result<AppPurchaseDetails, IapError> {
+        let result = self
+            .try_verify_apple_app_transaction(signed_app_transaction)
+            .await;
+        self.observed_platform(
+            "verify_apple_app_transaction",
+            IapPlatform::AppStore,
+            result,
+        )
+        .await
+    }
+
+    async fn try_verify_apple_app_transaction(
+        &self,
+        signed_app_transaction: &str,
+    ) -> Result<AppPurchaseDetails, CalloutError> {
+        let m = self
+            .app_store_server_api_datasource
+            .decode_app_transaction(signed_app_transaction)
+            .await?;
+        if m.bundle_id != self.application_id {
+            return Err(InvalidAppleAppTransaction::new(&format!(
+                "app transaction was issued for bundle '{}'",
+                m.bundle_id
+            ))
+            .into());
+        }
+        Ok(AppPurchaseDetails::from_apple_app_transaction(m))
+    }
+
+    pub(crate) async fn send_apple_consumption_info(
+        &self,
+        transaction_id: AppleTransactionId,
+        info: &ConsumptionInfo,
+    ) -> Result<(), IapError> {
+        let result = self
+            .app_store_server_api_datasource
+            .send_consumption_information(
+                transaction_id.as_str(),
+                &ac::ConsumptionRequestModel::from(info),
+            )
+            .await;
+        self.observed(
+            "send_apple_consumption_info",
+            &IapPurchaseId::AppStoreTransactionId(transaction_id),
+            result,
+        )
+        .await
+    }
+
+    pub(crate) async fn extend_entitlement(
+        &self,
+        purchase_id: IapPurchaseId,
+        extension: &EntitlementExtension,
+    ) -> Result<DateTime<Utc>, IapError> {
+        let result = match &purchase_id {
+            IapPurchaseId::AppStoreTransactionId(transaction_id) => {
+                self.extend_apple_subscription(transaction_id.as_str(), extension)
+                    .await
+            }
+            IapPurchaseId::GooglePlayPurchaseToken(token) => {
+                self.defer_google_subscription(token.as_str(), extension)
+                    .await
+            }
+            _ => Err(EntitlementExtensionNotAvailable::new().into()),
+        };
+        let new_expiry = self
+            .observed("extend_entitlement", &purchase_id, result)
+            .await?;
+        if let Some(cache) = &self.verification_cache {
+            cache.invalidate(&purchase_id).await;
+        }
+        Ok(new_expiry)
+    }
+
+    async fn extend_apple_subscription(
+        &self,
+        transaction_id: &str,
+        extension: &EntitlementExtension,
+    ) -> Result<DateTime<Utc>, CalloutError> {
+        let days = extension.duration.num_days();
+        if extension.duration != chrono::Duration::days(days) || !(1..=90).contains(&days) {
+            return Err(InvalidEntitlementExtension::new(
+                "App Store subscriptions can only be extended by 1 to 90 whole days",
+            )
+            .into());
+        }
+        let m = self
+            .app_store_server_api_datasource
+            .get_transaction_info(transaction_id)
+            .await?;
+        if m.expires_date.is_none() {
+            return Err(InvalidEntitlementExtension::new("purchase is not a subscription").into());
+        }
+        let request = ax::ExtendRenewalDateRequestModel {
+            extend_by_days: days as u32,
+            extend_reason_code: match extension.reason {
+                ExtensionReason::Undeclared => 0,
+                ExtensionReason::CustomerSatisfaction => 1,
+                ExtensionReason::Other => 2,
+                ExtensionReason::ServiceIssue => 3,
+            },
+            request_identifier: extension.request_identifier.clone(),
+        };
+        let response = self
+            .app_store_server_api_datasource
+            .extend_subscription_renewal_date(
+                &m.original_transaction_id,
+                &request,
+                m.environment == app_store_server_api::common::Environment::Sandbox,
+            )
+            .await?;
+        match response.effective_date {
+            Some(effective_date) if response.success => Ok(effective_date),
+            _ => Err(AppStoreServerApiInvalidResponse::new(
+                "subscription renewal date extension did not succeed",
+            )
+            .into()),
+        }
+    }
+
+    async fn defer_google_subscription(
+        &self,
+        token: &str,
+        extension: &EntitlementExtension,
+    ) -> Result<DateTime<Utc>, CalloutError> {
+        if extension.duration <= chrono::Duration::zero() {
+            return Err(InvalidEntitlementExtension::new("duration must be positive").into());
+        }
+        let m = self
+            .google_play_developer_api_datasource
+            .get_subscription_purchase_v2(&self.application_id, token)
+            .await?;
+        let Some(line_item) = m.line_items.iter().max_by_key(|item| item.expiry_time) else {
+            return Err(GooglePlayDeveloperApiInvalidResponse::new(
+                "subscription did not have any line items",
+            )
+            .into());
+        };
+        let request = gd::SubscriptionPurchasesDeferRequest {
+            deferral_info: gd::SubscriptionDeferralInfo {
+                expected_expiry_time_millis: line_item.expiry_time,
+                desired_expiry_time_millis: line_item.expiry_time + extension.duration,
+            },
+        };
+        let response = self
+            .google_play_developer_api_datasource
+            .defer_subscription_purchase(
+                &self.application_id,
+                &line_item.product_id,
+                token,
+                &request,
+            )
+            .await?;
+        Ok(response.new_expiry_time_millis)
+    }
+
+    pub(crate) async fn canonical_purchase_id<T: TypedProductId>(
+        &self,
+        purchase_id: IapPurchaseId,
+    ) -> Result<CanonicalPurchaseId, IapError> {
+        let result = self.resolve_canonical_purchase_id::<T>(&purchase_id).await;
+        self.observed("canonical_purchase_id", &purchase_id, result)
+            .await
+    }
+
+    async fn resolve_canonical_purchase_id<T: TypedProductId>(
+        &self,
+        purchase_id: &IapPurchaseId,
+    ) -> Result<CanonicalPurchaseId, CalloutError> {
+        let mut linked_ids = Vec::new();
+        let canonical_id = match purchase_id {
+            IapPurchaseId::AppStoreTransactionId(transaction_id) => {
+                let m = self
+                    .app_store_server_api_datasource
+                    .get_transaction_info(transaction_id.as_str())
+                    .await?;
+                IapPurchaseId::AppStoreTransactionId(AppleTransactionId::new_unchecked(
+                    m.original_transaction_id,
+                ))
+            }
+            IapPurchaseId::GooglePlayPurchaseToken(token)
+                if T::product_type() == _ProductIdType::Subscription =>
+            {
+                let linked_token = self
+                    .google_play_developer_api_datasource
+                    .get_subscription_purchase_v2(&self.application_id, token.as_str())
+                    .await?
+                    .linked_purchase_token;
+                linked_ids = self
+                    .google_linked_purchases(linked_token)
+                    .await?
+                    .into_iter()
+                    .map(|(linked_id, _)| linked_id)
+                    .collect();
+                purchase_id.clone()
+            }
+            // IDs of the other platforms are already canonical.
+            _ => purchase_id.clone(),
+        };
+        Ok(CanonicalPurchaseId {
+            canonical_id,
+            linked_ids,
+        })
+    }
+
+    /// Follows the chain of earlier Google Play purchases a subscription
+    /// replaced, starting at its 'linked_token', returning them most recent
+    /// first, with their start times. Purchases no longer known to Google end
+    /// the chain (without a start time).
+    async fn google_linked_purchases(
+        &self,
+        mut linked_token: Option<String>,
+    ) -> Result<Vec<(IapPurchaseId, Option<DateTime<Utc>>)>, CalloutError> {
+        let mut linked = Vec::new();
+        while let Some(token) = linked_token.take() {
+            let linked_id = IapPurchaseId::GooglePlayPurchaseToken(
+                GooglePurchaseToken::new_unchecked(token.clone()),
+            );
+            if linked.len() >= MAX_LINKED_PURCHASE_TOKENS
+                || linked.iter().any(|(id, _)| *id == linked_id)
+            {
+                break;
+            }
+            let start_time = match self
+                .google_play_developer_api_datasource
+                .get_subscription_purchase_v2(&self.application_id, &token)
+                .await
+            {
+                Ok(m) => {
+                    linked_token = m.linked_purchase_token;
+                    m.start_time
+                }
+                // Old purchases may no longer be available.
+                Err(e) if !e.is_transient() => None,
+                Err(e) => return Err(e),
+            };
+            linked.push((linked_id, start_time));
+        }
+        Ok(linked)
+    }
+
+    /// Price the customer pays for the current period of a Google Play
+    /// subscription: the base plan's price, or the offer's during free trials
+    /// and introductory phases. None if it can not be determined (ex. during
+    /// proration periods, for offers with several paid phases, or if the base
+    /// plan or offer was deleted).
+    async fn google_subscription_price(
+        &self,
+        m: &gs::SubscriptionPurchaseV2Model,
+    ) -> Result<Option<PriceInfo>, CalloutError> {
+        let Some(line_item) = m.line_items.last() else {
+            return Ok(None);
+        };
+        let (base_plan_id, offer_id) = match &line_item.offer_details {
+            Some(od) => (od.base_plan_id.as_deref(), od.offer_id.as_deref()),
+            None => (None, None),
+        };
+        let phase = line_item.offer_phase.as_ref();
+        let is_free_trial = phase.is_some_and(|p| p.free_trial.is_some());
+        let is_introductory = phase.is_some_and(|p| p.introductory_price.is_some());
+        let is_base_price = match phase {
+            Some(p) => p.base_price.is_some(),
+            None => offer_id.is_none(),
+        };
+        if !is_base_price && !is_free_trial && !is_introductory {
+            return Ok(None);
+        }
+
+        // The recurring price is only reported for auto-renewing plans, so
+        // prepaid plans are priced from the base plan.
+        let recurring_price = line_item
+            .auto_renewing_plan
+            .as_ref()
+            .and_then(|p| p.recurring_price.as_ref())
+            .map(PriceInfo::from_google_money);
+        let base_plan = match (recurring_price.is_none() || is_introductory, base_plan_id) {
+            (true, Some(base_plan_id)) => match self
+                .google_play_developer_api_datasource
+                .get_subscription(&self.application_id, &line_item.product_id)
+                .await
+            {
+                Ok(subscription) => subscription
+                    .base_plans
+                    .into_iter()
+                    .find(|bp| bp.base_plan_id == base_plan_id),
+                Err(e) if !e.is_transient() => None,
+                Err(e) => return Err(e),
+            },
+            _ => None,
+        };
+        let Some(base_price) = recurring_price.or_else(|| {
+            base_plan
+                .as_ref()?
+                .regional_configs
+                .iter()
+                .find(|c| c.region_code == m.region_code)?
+                .price
+                .as_ref()
+                .map(PriceInfo::from_google_money)
+        }) else {
+            return Ok(None);
+        };
+        if is_base_price {
+            return Ok(Some(base_price));
+        }
+        let offer = Some(PriceOffer {
+            offer_type: PriceOfferType::GooglePlay,
+            offer_id: offer_id.map(str::to_owned),
+            is_free_trial,
+        });
+        if is_free_trial {
+            return Ok(Some(PriceInfo {
+                price_micros: 0,
+                offer,
+                ..base_price
+            }));
+        }
+
+        let (Some(base_plan), Some(base_plan_id), Some(offer_id)) =
+            (base_plan, base_plan_id, offer_id)
+        else {
+            return Ok(None);
+        };
+        let subscription_offer = match self
+            .google_play_developer_api_datasource
+            .get_subscription_offer(
+                &self.application_id,
+                &line_item.product_id,
+                base_plan_id,
+                offer_id,
+            )
+            .await
+        {
+            Ok(subscription_offer) => subscription_offer,
+            Err(e) if !e.is_transient() => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        // Which phase the customer is in is not reported, so it is only known
+        // if the offer has a single paid phase.
+        let mut paid_phases = subscription_offer.phases.iter().filter_map(|phase| {
+            let config = phase
+                .regional_configs
+                .iter()
+                .find(|c| c.region_code == m.region_code)?;
+            config.free.is_none().then_some((phase, config))
+        });
+        let (Some((phase, config)), None) = (paid_phases.next(), paid_phases.next()) else {
+            return Ok(None);
+        };
+        Ok(google_offer_phase_price(
+            config,
+            BillingPeriod::parse_iso8601(&phase.duration),
+            &base_price,
+            google_base_plan_period(&base_plan),
+            m.start_time.unwrap_or_else(Utc::now),
+        )
+        .map(|price| PriceInfo { offer, ..price }))
+    }
+
+    pub(crate) async fn get_google_subscription_plans(
+        &self,
+        product_id: IapSubscriptionId,
+    ) -> Result<Vec<SubscriptionBasePlan>, IapError> {
+        let result = self.google_subscription_plans(product_id.sku()).await;
+        self.observed_platform(
+            "get_google_subscription_plans",
+            IapPlatform::GooglePlay,
+            result,
+        )
+        .await
+    }
+
+    async fn google_subscription_plans(
+        &self,
+        product_id: &str,
+    ) -> Result<Vec<SubscriptionBasePlan>, CalloutError> {
+        let (subscription, mut offers) = futures::try_join!(
+            self.google_play_developer_api_datasource
+                .get_subscription(&self.application_id, product_id),
+            async {
+                let mut offers = Vec::new();
+                let mut page_token = None;
+                loop {
+                    let (page, next_page_token) = self
+                        .google_play_developer_api_datasource
+                        .list_subscription_offers(
+                            &self.application_id,
+                            product_id,
+                            "-",
+                            page_token.as_deref(),
+                        )
+                        .await?;
+                    offers.extend(page);
+                    match next_page_token {
+                        Some(next_page_token) => page_token = Some(next_page_token),
+                        None => return Ok::<_, CalloutError>(offers),
+                    }
+                }
+            },
+        )?;
+        offers.retain(|offer| offer.state == Some(go::SubscriptionOfferState::Active));
+        let now = Utc::now();
+        Ok(subscription
+            .base_plans
+            .into_iter()
+            .filter(|bp| bp.state == Some(gm::BasePlanState::Active))
+            .map(|bp| SubscriptionBasePlan::from_google_base_plan(bp, &offers, now))
+            .collect())
+    }
+
+    pub(crate) async fn verify_and_get_details_audited<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+    ) -> (
+        Result<IapDetails<T::DetailsType>, IapError>,
+        IapAuditRecord<T::DetailsType>,
+    ) {
+        let product_sku = product_id.sku().to_owned();
+        let verified_at = Utc::now();
+        let (result, responses) = collect_payloads(self.get_details(
+            product_id,
+            purchase_id.clone(),
+            include_price_info,
+            false,
+        ))
+        .await;
+        let result = self
+            .observed("verify_and_get_details_audited", &purchase_id, result)
+            .await;
+
+        let mut checks = Vec::new();
+        let (result, details) = match result {
+            Ok(details) => {
+                checks.push(AuditCheck::new(
+                    "platform_verification",
+                    AuditCheckOutcome::Passed,
+                    None,
+                ));
+                let denied = match self.is_denied(&purchase_id, &details).await {
+                    Ok(true) => Err(PurchaseDenied::new()),
+                    Ok(false) => Ok(()),
+                    Err(error) => Err(error),
+                };
+                checks.push(match &denied {
+                    Ok(()) => AuditCheck::new("deny_list", AuditCheckOutcome::Passed, None),
+                    Err(error) => {
+                        AuditCheck::new("deny_list", AuditCheckOutcome::Failed, error.to_string())
+                    }
+                });
+                let result = match (denied, details.is_active) {
+                    (Err(error), _) => {
+                        checks.push(AuditCheck::new(
+                            "is_active",
+                            AuditCheckOutcome::Skipped,
+                            None,
+                        ));
+                        Err(error)
+                    }
+                    (Ok(()), true) => {
+                        checks.push(AuditCheck::new(
+                            "is_active",
+                            AuditCheckOutcome::Passed,
+                            None,
+                        ));
+                        Ok(details.clone())
+                    }
+                    (Ok(()), false) => {
+                        let error = NotActive::new();
+                        checks.push(AuditCheck::new(
+                            "is_active",
+                            AuditCheckOutcome::Failed,
+                            error.to_string(),
+                        ));
+                        Err(error)
+                    }
+                };
+                if let Some(deadline) = details.acknowledgement_deadline {
+                    checks.push(AuditCheck::new(
+                        "acknowledged",
+                        AuditCheckOutcome::Warning,
+                        format!("must be acknowledged by {deadline}"),
+                    ));
+                    self.notify_warning_observers(
+                        "verify_and_get_details_audited",
+                        &purchase_id,
+                        &IapWarning::AcknowledgementPending { deadline },
+                    )
+                    .await;
+                }
+                (result.map_err(IapError::from), Some(details))
+            }
+            Err(error) => {
+                checks.push(AuditCheck::new(
+                    "platform_verification",
+                    AuditCheckOutcome::Failed,
+                    error.to_string(),
+                ));
+                checks.push(AuditCheck::new(
+                    "deny_list",
+                    AuditCheckOutcome::Skipped,
+                    None,
+                ));
+                checks.push(AuditCheck::new(
+                    "is_active",
+                    AuditCheckOutcome::Skipped,
+                    None,
+                ));
+                (Err(error), None)
+            }
+        };
+        let record = IapAuditRecord {
+            purchase_id,
+            product_sku,
+            verified_at,
+            granted: result.is_ok(),
+            checks,
+            responses,
+            details,
+        };
+        (result, record)
+    }
+
+    pub(crate) async fn get_apple_refund_risk_profile(
+        &self,
+        transaction_id: AppleTransactionId,
+    ) -> Result<RefundRiskProfile, IapError> {
+        let result = self
+            .apple_transaction_history(
+                transaction_id.as_str(),
+                &ahr::TransactionHistoryRequestModel::default(),
+            )
+            .await;
+        let purchase_id = IapPurchaseId::AppStoreTransactionId(transaction_id);
+        let transactions = self
+            .observed("get_apple_refund_risk_profile", &purchase_id, result)
+            .await?;
+        Ok(RefundRiskProfile::from_apple_transactions(
+            purchase_id,
+            transactions,
+        ))
+    }
+
+    pub(crate) async fn get_subscription_statuses(
+        &self,
+        original_transaction_id: AppleTransactionId,
+    ) -> Result<Vec<AppleSubscriptionGroupStatus>, IapError> {
+        let result = self
+            .apple_subscription_statuses(original_transaction_id.as_str())
+            .await;
+        let purchase_id = IapPurchaseId::AppStoreTransactionId(original_transaction_id);
+        self.observed("get_subscription_statuses", &purchase_id, result)
+            .await
+    }
+
+    async fn apple_subscription_statuses(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<AppleSubscriptionGroupStatus>, CalloutError> {
+        use app_store_server_api::common::SubscriptionStatus;
+
+        let mut groups: Vec<AppleSubscriptionGroupStatus> = Vec::new();
+        for (subscription_group_id, status, t, r) in self
+            .app_store_server_api_datasource
+            .get_all_subscription_statuses(transaction_id)
+            .await?
+        {
+            let subscription = AppleSubscriptionStatus {
+                status: match status {
+                    SubscriptionStatus::Active => AppleSubscriptionState::Active,
+                    SubscriptionStatus::Expired => AppleSubscriptionState::Expired,
+                    SubscriptionStatus::BillingRetry => AppleSubscriptionState::BillingRetry,
+                    SubscriptionStatus::BillingGracePeriod => {
+                        AppleSubscriptionState::BillingGracePeriod
+                    }
+                    SubscriptionStatus::Revoked => AppleSubscriptionState::Revoked,
+                },
+                product_id: IapSubscriptionId(t.product_id.clone()),
+                will_auto_renew: matches!(r.auto_renew_status, ar::AutoRenewStatus::On),
+                auto_renew_product_id: IapSubscriptionId(r.auto_renew_product_id.clone()),
+                renewal_price: PriceInfo::from_apple_renewal_info(&r),
+                grace_period_expiration_time: r.grace_period_expires_date,
+                details: {
+                    // Older transactions do not report their price.
+                    let price_info = PriceInfo::from_apple_transaction(&t, Some(&r));
+                    let mut details = IapDetails::from_apple_transaction::<IapSubscriptionId>(
+                        t,
+                        Some(&r),
+                        false,
+                        &self.config,
+                    )?;
+                    details.price_info = price_info;
+                    details
+                },
+            };
+            match groups
+                .iter_mut()
+                .find(|group| group.subscription_group_id == subscription_group_id)
+            {
+                Some(group) => group.subscriptions.push(subscription),
+                None => groups.push(AppleSubscriptionGroupStatus {
+                    subscription_group_id,
+                    subscriptions: vec![subscription],
+                }),
+            }
+        }
+        Ok(groups)
+    }
+
+    pub(crate) async fn get_apple_transaction_history(
+        &self,
+        transaction_id: AppleTransactionId,
+        filters: AppleTransactionHistoryFilters,
+    ) -> Result<Vec<AppleTransaction>, IapError> {
+        let result = self
+            .apple_transaction_history(transaction_id.as_str(), &(&filters).into())
+            .await
+            .map(|transactions| {
+                transactions
+                    .into_iter()
+                    .map(AppleTransaction::from_apple_transaction)
+                    .collect()
+            });
+        let purchase_id = IapPurchaseId::AppStoreTransactionId(transaction_id);
+        self.observed("get_apple_transaction_history", &purchase_id, result)
+            .await
+    }
+
+    /// All pages of the customer's transaction history.
+    async fn apple_transaction_history(
+        &self,
+        transaction_id: &str,
+        filters: &ahr::TransactionHistoryRequestModel,
+    ) -> Result<Vec<at::JwsTransactionDecodedPayloadModel>, CalloutError> {
+        let mut transactions = Vec::new();
+        let mut revision = None;
+        loop {
+            let (page, next_revision) = self
+                .app_store_server_api_datasource
+                .get_transaction_history(transaction_id, revision.as_deref(), filters)
+                .await?;
+            transactions.extend(page);
+            match next_revision {
+                Some(next_revision) => revision = Some(next_revision),
+                None => return Ok(transactions),
+            }
+        }
+    }
+
+    pub(crate) async fn get_apple_notification_history(
+        &self,
+        range: Range<DateTime<Utc>>,
+        sandbox: bool,
+    ) -> Result<Vec<AppleNotificationHistoryEntry>, IapError> {
+        let result = self.apple_notification_history(range, sandbox).await;
+        self.observed_platform(
+            "get_apple_notification_history",
+            IapPlatform::AppStore,
+            result,
+        )
+        .await
+    }
+
+    async fn apple_notification_history(
+        &self,
+        range: Range<DateTime<Utc>>,
+        sandbox: bool,
+    ) -> Result<Vec<AppleNotificationHistoryEntry>, CalloutError> {
+        let request = ah::NotificationHistoryRequestModel {
+            start_date: range.start,
+            end_date: range.end,
+            only_failures: None,
+        };
+        let mut entries = Vec::new();
+        let mut pagination_token = None;
+        loop {
+            let (page, next_token) = self
+                .app_store_server_api_datasource
+                .get_notification_history(&request, pagination_token.as_deref(), sandbox)
+                .await?;
+            entries.extend(
+                page.into_iter()
+                    .map(|(signed_payload, notification, attempts)| {
+                        AppleNotificationHistoryEntry::from_apple_history_item(
+                            signed_payload,
+                            notification,
+                            attempts,
+                        )
+                    }),
+            );
+            match next_token {
+                Some(next_token) => pagination_token = Some(next_token),
+                None => return Ok(entries),
+            }
+        }
+    }
+
+    pub(crate) async fn report_apple_external_purchases(
+        &self,
+        report: &ExternalPurchaseReport,
+        sandbox: bool,
+    ) -> Result<(), IapError> {
+        let result = self
+            .app_store_server_api_datasource
+            .send_external_purchase_report(&ae::ExternalPurchaseReportModel::from(report), sandbox)
+            .await;
+        self.observed_platform(
+            "report_apple_external_purchases",
+            IapPlatform::AppStore,
+            result,
+        )
+        .await
+    }
+
+    pub(crate) async fn get_apple_external_purchase_report(
+        &self,
+        request_identifier: &str,
+        sandbox: bool,
+    ) -> Result<ExternalPurchaseReportStatus, IapError> {
+        let result = self
+            .app_store_server_api_datasource
+            .get_external_purchase_report(request_identifier, sandbox)
+            .await;
+        self.observed_platform(
+            "get_apple_external_purchase_report",
+            IapPlatform::AppStore,
+            result,
+        )
+        .await
+        .map(ExternalPurchaseReportStatus::from_apple_model)
+    }
+
+    pub(crate) async fn report_google_external_transaction(
+        &self,
+        transaction: &GoogleExternalTransaction,
+    ) -> Result<GoogleExternalTransactionDetails, IapError> {
+        let result =
+            match ge::ExternalTransactionModel::from_google_external_transaction(transaction) {
+                Ok(m) => {
+                    self.google_play_developer_api_datasource
+                        .create_external_transaction(
+                            &self.application_id,
+                            transaction.external_transaction_id.as_str(),
+                            &m,
+                        )
+                        .await
+                }
+                Err(e) => Err(e.into()),
+            };
+        let transaction = self
+            .observed(
+                "report_google_external_transaction",
+                &transaction.purchase_id(),
+                result,
+            )
+            .await?;
+        Ok(GoogleExternalTransactionDetails::from_google_model(
+            transaction,
+        )?)
+    }
+
+    pub(crate) async fn get_google_external_transaction(
+        &self,
+        external_transaction_id: GoogleExternalTransactionId,
+    ) -> Result<GoogleExternalTransactionDetails, IapError> {
+        let result = self
+            .google_play_developer_api_datasource
+            .get_external_transaction(&self.application_id, external_transaction_id.as_str())
+            .await;
+        let purchase_id = IapPurchaseId::GoogleExternalTransactionId(external_transaction_id);
+        let transaction = self
+            .observed("get_google_external_transaction", &purchase_id, result)
+            .await?;
+        Ok(GoogleExternalTransactionDetails::from_google_model(
+            transaction,
+        )?)
+    }
+
+    pub(crate) async fn refund_google_external_transaction(
+        &self,
+        external_transaction_id: GoogleExternalTransactionId,
+        refund: &GoogleExternalTransactionRefund,
+        refund_time: DateTime<Utc>,
+    ) -> Result<GoogleExternalTransactionDetails, IapError> {
+        let result = self
+            .google_play_developer_api_datasource
+            .refund_external_transaction(
+                &self.application_id,
+                external_transaction_id.as_str(),
+                &ge::RefundExternalTransactionRequest::from_google_refund(refund, refund_time),
+            )
+            .await;
+        let purchase_id = IapPurchaseId::GoogleExternalTransactionId(external_transaction_id);
+        let transaction = self
+            .observed("refund_google_external_transaction", &purchase_id, result)
+            .await?;
+        Ok(GoogleExternalTransactionDetails::from_google_model(
+            transaction,
+        )?)
+    }
+
+    /// For operations which do not concern a single purchase (ex. external
+    /// purchase reports, or product lookups), failures are only attributed to
+    /// the platform.
+    async fn observed_platform<T, E: Into<CalloutError>>(
+        &self,
+        operation: &'static str,
+        platform: IapPlatform,
+        result: Result<T, E>,
+    ) -> Result<T, IapError> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                let e = e.into();
+                let transient = e.endpoint.is_some() && e.is_transient();
+                Err(self
+                    .observed_error(operation, Some(platform), None, e, transient)
+                    .await)
+            }
+        }
+    }
+
+    #[cfg(feature = "app-store-connect")]
+    fn app_store_connect_datasource(
+        &self,
+    ) -> Result<&AppStoreConnectApiDatasourceImpl, ServerError> {
+        self.app_store_connect_api_datasource
+            .as_ref()
+            .ok_or_else(AppStoreConnectNotConfigured::new)
+    }
+
+    /// Prices of the product, per storefront, in the given storefront only if
+    /// set.
+    #[cfg(feature = "app-store-connect")]
+    async fn apple_price_schedule<T: TypedProductId>(
+        &self,
+        product_id: &T,
+        region_iso3166_alpha_3: Option<&str>,
+    ) -> Result<Vec<ScheduledPrice>, CalloutError> {
+        let datasource = self.app_store_connect_datasource()?;
+        let sku = product_id.sku();
+        match T::product_type() {
+            _ProductIdType::Subscription => {
+                let subscription = datasource
+                    .find_subscription(sku)
+                    .await?
+                    .ok_or_else(|| AppStoreProductNotFound::new(sku))?;
+                let prices = datasource
+                    .get_subscription_prices(&subscription.id, region_iso3166_alpha_3)
+                    .await?;
+                Ok(ScheduledPrice::from_app_store_connect_subscription_prices(
+                    prices,
+                )?)
+            }
+            _ProductIdType::Consumable | _ProductIdType::NonConsumable => {
+                let in_app_purchase = datasource
+                    .find_in_app_purchase(sku)
+                    .await?
+                    .ok_or_else(|| AppStoreProductNotFound::new(sku))?;
+                let prices = datasource
+                    .get_in_app_purchase_prices(&in_app_purchase.id, region_iso3166_alpha_3)
+                    .await?;
+                Ok(ScheduledPrice::from_app_store_connect_in_app_purchase_prices(prices)?)
+            }
+        }
+    }
+
+    /// Localizations of the product, and its billing period if it is a
+    /// subscription.
+    #[cfg(feature = "app-store-connect")]
+    async fn apple_localizations<T: TypedProductId>(
+        &self,
+        product_id: &T,
+    ) -> Result<(DocumentModel<LocalizationAttributes>, Option<String>), CalloutError> {
+        let datasource = self.app_store_connect_datasource()?;
+        let sku = product_id.sku();
+        match T::product_type() {
+            _ProductIdType::Subscription => {
+                let subscription = datasource
+                    .find_subscription(sku)
+                    .await?
+                    .ok_or_else(|| AppStoreProductNotFound::new(sku))?;
+                let localizations = datasource
+                    .get_subscription_localizations(&subscription.id)
+                    .await?;
+                let subscription_period = subscription
+                    .attributes
+                    .and_then(|attributes| attributes.subscription_period);
+                Ok((localizations, subscription_period))
+            }
+            _ProductIdType::Consumable | _ProductIdType::NonConsumable => {
+                let in_app_purchase = datasource
+                    .find_in_app_purchase(sku)
+                    .await?
+                    .ok_or_else(|| AppStoreProductNotFound::new(sku))?;
+                let localizations = datasource
+                    .get_in_app_purchase_localizations(&in_app_purchase.id)
+                    .await?;
+                Ok((localizations, None))
+            }
+        }
+    }
+
+    /// If price info was requested but the transaction does not carry it (ex.
+    /// transactions signed before Apple added prices), the price scheduled in
+    /// the transaction's storefront on the day of the purchase. Only looked up
+    /// if App Store Connect credentials are configured.
+    #[cfg(feature = "app-store-connect")]
+    async fn apple_catalog_price<T: TypedProductId>(
+        &self,
+        product_id: &T,
+        m: &at::JwsTransactionDecodedPayloadModel,
+        include_price_info: bool,
+    ) -> Result<Option<PriceInfo>, CalloutError> {
+        // Catalog prices do not account for offers, so they are not used for
+        // transactions bought with one.
+        if !include_price_info
+            || (m.price.is_some() && m.currency.is_some())
+            || m.offer_type.is_some()
+            || self.app_store_connect_api_datasource.is_none()
+        {
+            return Ok(None);
+        }
+        let date = m.purchase_date.date_naive();
+        self.apple_price_schedule(product_id, Some(&m.storefront))
+            .await?
+            .into_iter()
+            .find(|price| price.region_iso3166_alpha_3 == m.storefront && price.applies_on(date))
+            .map(|price| {
+                Some(PriceInfo {
+                    quantity: m.quantity.map(|q| q as i64).unwrap_or(1),
+                    ..price.price_info
+                })
+            })
+            .ok_or_else(|| {
+                AppStoreConnectApiInvalidResponse::new(&format!(
+                    "no price scheduled for product '{}' in storefront '{}' on {date}",
+                    product_id.sku(),
+                    m.storefront
+                ))
+                .into()
+            })
+    }
+
+    #[cfg(feature = "app-store-connect")]
+    pub(crate) async fn get_apple_price_schedule<T: TypedProductId>(
+        &self,
+        product_id: T,
+        region_iso3166_alpha_3: Option<&str>,
+    ) -> Result<Vec<ScheduledPrice>, IapError> {
+        let result = self
+            .apple_price_schedule(&product_id, region_iso3166_alpha_3)
+            .await;
+        self.observed_platform("get_apple_price_schedule", IapPlatform::AppStore, result)
+            .await
+    }
+
+    pub(crate) async fn get_product_listing<T: TypedProductId>(
+        &self,
+        product_id: T,
+        platform: IapPlatform,
+        locale: &str,
+    ) -> Result<Option<ProductListing>, IapError> {
+        let result =
+            match platform {
+                IapPlatform::GooglePlay => self
+                    .google_play_developer_api_datasource
+                    .get_in_app_product(&self.application_id, product_id.sku())
+                    .await
+                    .and_then(|p| Ok(ProductListing::from_google_in_app_product_model(p, locale)?)),
+                #[cfg(feature = "app-store-connect")]
+                IapPlatform::AppStore => self.apple_localizations(&product_id).await.and_then(
+                    |(d, subscription_period)| {
+                        Ok(ProductListing::from_app_store_connect_localizations(
+                            d,
+                            subscription_period,
+                            locale,
+                        )?)
+                    },
+                ),
+                // Unreachable if no other platform's feature is enabled.
+                #[allow(unreachable_patterns)]
+                _ => return Err(ProductListingNotAvailable::new(&format!("{platform:?}")).into()),
+            };
+        self.observed_platform("get_product_listing", platform, result)
+            .await
+    }
+
+    /// Google Play one-time purchase, preferably from purchases.productsv2
+    /// (which also reports the purchase option and offer). Falls back to
+    /// purchases.products if the productsv2 lookup fails, or does not report
+    /// everything the details depend on (ex. pending purchases have no
+    /// completion time yet), keeping the purchase option and offer if known.
+    async fn google_product_purchase(
+        &self,
+        sku: &str,
+        token: &str,
+    ) -> Result<(gp::ProductPurchaseModel, PlatformExtras), CalloutError> {
+        let platform_extras = match self
+            .google_play_developer_api_datasource
+            .get_product_purchase_v2(&self.application_id, token)
+            .await
+        {
+            Ok(m) => {
+                let platform_extras = google_product_purchase_extras(&m, sku);
+                if let Some(purchase) = google_product_purchase_from_v2(m, sku, token) {
+                    return Ok((purchase, platform_extras));
+                }
+                platform_extras
+            }
+            // Failures which matter for the purchase itself recur below.
+            Err(_) => PlatformExtras::None,
+        };
+        let m = self
+            .google_play_developer_api_datasource
+            .get_product_purchase(&self.application_id, sku, token)
+            .await?;
+        Ok((m, platform_extras))
+    }
+
+    #[cfg(feature = "microsoft-store")]
+    fn microsoft_store_datasource(
+        &self,
+    ) -> Result<&MicrosoftStoreCollectionsApiDatasourceImpl, ServerError> {
+        self.microsoft_store_collections_api_datasource
+            .as_ref()
+            .ok_or_else(MicrosoftStoreNotConfigured::new)
+    }
+
+    /// The user's most recently acquired item for the product.
+    #[cfg(feature = "microsoft-store")]
+    async fn microsoft_store_item(
+        &self,
+        key: &MicrosoftStoreIdKey,
+        product_id: &str,
+    ) -> Result<mc::CollectionItemModel, CalloutError> {
+        self.microsoft_store_datasource()?
+            .query_collections(key.as_str(), product_id)
+            .await?
+            .into_iter()
+            .filter(|item| item.product_id.eq_ignore_ascii_case(product_id))
+            .max_by_key(|item| item.acquired_date)
+            .ok_or_else(|| PurchaseNotFound::new().into())
+    }
+
+    /// The tracking ID is derived from the item's transaction, so that
+    /// retries do not consume further quantity, while later purchases of the
+    /// same product can still be consumed.
+    #[cfg(feature = "microsoft-store")]
+    async fn consume_microsoft_store_product(
+        &self,
+        key: &MicrosoftStoreIdKey,
+        product_id: &IapConsumableId,
+    ) -> Result<(), CalloutError> {
+        let item = self.microsoft_store_item(key, product_id.sku()).await?;
+        let tracking_id = tracking_guid(&format!(
+            "{}:{}",
+            item.item_id,
+            item.transaction_id.as_deref().unwrap_or_default()
+        ));
+        self.microsoft_store_datasource()?
+            .consume(key.as_str(), product_id.sku(), &tracking_id)
+            .await
+    }
+}
+
+/// GUID-formatted hash of the given value.
+#[cfg(feature = "microsoft-store")]
+fn tracking_guid(value: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let hex: String = Sha256::digest(value.as_bytes())[..16]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+impl
+    IapRepositoryImpl<
+        AppStoreServerApiDatasourceImpl,
+        AppStoreServerNotificationDatasourceImpl,
+        GooglePlayDeveloperApiDatasourceImpl,
+        GoogleCloudRtdnNotificationDatasourceImpl,
+    >
+{
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn new(
+        application_id: impl Into<String>,
+        apple_expected_auds: Vec<String>,
+        google_expected_auds: Vec<String>,
+        apple_api_key: &SecretString,
+        apple_key_id: &str,
+        apple_issuer_id: &str,
+        google_api_key: &SecretString,
+        config: IapConfig,
+    ) -> Result<Self, ServerError> {
+        let application_id = application_id.into();
+        let http_client = HttpClient::new(&config)?;
+        let signature_verifier = Self::signature_verifier(&config)?;
+        let captures = PayloadCaptures::new(config.payload_captures.clone());
+        let cache_store = config
+            .cache_store
+            .clone()
+            .unwrap_or_else(|| Arc::new(InMemoryCacheStore::default()));
+        Ok(Self {
+            app_store_server_api_datasource: AppStoreServerApiDatasourceImpl::new(
+                apple_api_key,
+                apple_key_id,
+                apple_issuer_id,
+                &application_id,
+                apple_expected_auds.clone(),
+                config.apple_production_base_url.clone(),
+                config.apple_sandbox_base_url.clone(),
+                config.environment,
+                http_client.with_concurrency_limit(
+                    config.apple_max_concurrent_callouts,
+                    &config.reserved_callouts,
+                ),
+                signature_verifier.clone(),
+                captures.clone(),
+                config.lazy_credentials,
+            )
+            .await?,
+            app_store_server_notification_datasource: AppStoreServerNotificationDatasourceImpl::new(
+                apple_expected_auds,
+                signature_verifier.clone(),
+                captures.clone(),
+            ),
+            google_play_developer_api_datasource: GooglePlayDeveloperApiDatasourceImpl::new(
+                google_api_key,
+                config.google_play_base_url.clone(),
+                http_client.with_concurrency_limit(
+                    config.google_max_concurrent_callouts,
+                    &config.reserved_callouts,
+                ),
+                captures.clone(),
+                cache_store.clone(),
+                config.product_cache_ttl,
+                config.lazy_credentials,
+            )
+            .await?,
+            #[cfg(feature = "app-store-connect")]
+            app_store_connect_api_datasource: match &config.app_store_connect_credentials {
+                Some(credentials) => Some(AppStoreConnectApiDatasourceImpl::new(
+                    credentials.clone(),
+                    config.app_store_connect_base_url.clone(),
+                    http_client.clone(),
+                    captures.clone(),
+                    cache_store.clone(),
+                    config.product_cache_ttl,
+                    config.lazy_credentials,
+                )?),
+                None => None,
+            },
+            #[cfg(feature = "microsoft-store")]
+            microsoft_store_collections_api_datasource: match &config.microsoft_store_credentials {
+                Some(credentials) => Some(
+                    MicrosoftStoreCollectionsApiDatasourceImpl::new(
+                        credentials.clone(),
+                        config.microsoft_store_collections_base_url.clone(),
+                        config.microsoft_login_base_url.clone(),
+                        http_client.clone(),
+                        captures.clone(),
+                        config.lazy_credentials,
+                    )
+                    .await?,
+                ),
+                None => None,
+            },
+            #[cfg(feature = "steam")]
+            steam_micro_txn_api_datasource: config.steam_credentials.as_ref().map(|credentials| {
+                SteamMicroTxnApiDatasourceImpl::new(
+                    credentials.clone(),
+                    &config.steam_base_url,
+                    config.steam_sandbox,
+                    http_client.clone(),
+                    captures.clone(),
+                )
+            }),
+            #[cfg(feature = "paddle")]
+            paddle_webhook_datasource: config
+                .paddle_webhook_secret
+                .as_ref()
+                .map(|secret| PaddleWebhookDatasourceImpl::new(secret.clone(), captures.clone())),
+            #[cfg(feature = "stripe")]
+            stripe_webhook_datasource: config
+                .stripe_webhook_secret
+                .as_ref()
+                .map(|secret| StripeWebhookDatasourceImpl::new(secret.clone(), captures.clone())),
+            google_cloud_rtdn_notification_datasource:
+                GoogleCloudRtdnNotificationDatasourceImpl::new(
+                    google_expected_auds,
+                    signature_verifier.clone(),
+                    captures,
+                ),
+            application_id,
+            verification_cache: config
+                .verification_cache_ttl
+                .map(|ttl| VerificationCache::new(cache_store, ttl)),
+            config,
+            signature_verifier,
+        })
+    }
+
+    pub(crate) async fn validate_credentials(&self) -> CredentialReport {
+        use CredentialCheckKind::*;
+
+        // Each check is only run if the ones it depends on passed.
+        let apple = async {
+            let key = CredentialCheck::new(
+                AppleKeyParse,
+                self.app_store_server_api_datasource.check_key(),
+            );
+            let jwt = match key.passed() {
+                true => CredentialCheck::new(
+                    AppleJwtAccepted,
+                    self.app_store_server_api_datasource
+                        .check_credentials()
+                        .await,
+                ),
+                false => CredentialCheck::skipped(AppleJwtAccepted),
+            };
+            [key, jwt]
+        };
+        let google = async {
+            let key = CredentialCheck::new(
+                GoogleKeyParse,
+                self.google_play_developer_api_datasource.check_key(),
+            );
+            let token = match key.passed() {
+                true => CredentialCheck::new(
+                    GoogleTokenMint,
+                    self.google_play_developer_api_datasource
+                        .check_credentials()
+                        .await,
+                ),
+                false => CredentialCheck::skipped(GoogleTokenMint),
+            };
+            let permission = match token.passed() {
+                true => CredentialCheck::new(
+                    GooglePublisherPermission,
+                    self.google_play_developer_api_datasource
+                        .check_permissions(&self.application_id)
+                        .await,
+                ),
+                false => CredentialCheck::skipped(GooglePublisherPermission),
+            };
+            [key, token, permission]
+        };
+        let (apple, google) = futures::join!(apple, google);
+        CredentialReport {
+            checks: apple.into_iter().chain(google).collect(),
+        }
+    }
+
+    pub(crate) async fn warm_up(&self) -> Result<(), IapError> {
+        futures::try_join!(
+            async {
+                let result = self.app_store_server_api_datasource.warm_up().await;
+                self.observed_platform("warm_up", IapPlatform::AppStore, result)
+                    .await
+            },
+            async {
+                let result = self.google_play_developer_api_datasource.warm_up().await;
+                self.observed_platform("warm_up", IapPlatform::GooglePlay, result)
+                    .await
+            },
+            async {
+                #[cfg(feature = "app-store-connect")]
+                if let Some(datasource) = &self.app_store_connect_api_datasource {
+                    let result = datasource.warm_up();
+                    self.observed_platform("warm_up", IapPlatform::AppStore, result)
+                        .await?;
+                }
+                #[cfg(feature = "microsoft-store")]
+                if let Some(datasource) = &self.microsoft_store_collections_api_datasource {
+                    let result = datasource.warm_up().await;
+                    self.observed_platform("warm_up", IapPlatform::MicrosoftStore, result)
+                        .await?;
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+
+    pub(crate) async fn refresh_google_keys(&self) -> Result<(), IapError> {
+        let result = self.signature_verifier.refresh_google_keys().await;
+        self.observed_platform("refresh_google_keys", IapPlatform::GooglePlay, result)
+            .await
+    }
+
+    fn signature_verifier(config: &IapConfig) -> Result<Arc<dyn SignatureVerifier>, ServerError> {
+        match &config.signature_verifier {
+            Some(signature_verifier) => Ok(signature_verifier.clone()),
+            #[cfg(feature = "native")]
+            None => Ok(Arc::new(NativeSignatureVerifier::new(config)?)),
+            #[cfg(not(feature = "native"))]
+            None => Err(SignatureVerifierMissing::new()),
+        }
+    }
+}
+
+impl<U: IapTypeSpecificDetails> IapDetails<U> {
+    fn from_apple_transaction<T: TypedProductId<DetailsType = U>>(
+        m: at::JwsTransactionDecodedPayloadModel,
+        r: Option<&ar::JwsRenewalInfoDecodedPayloadModel>,
+        include_price_info: bool,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        let actual_type = match m.transaction_type {
+            at::TransactionType::AutoRenewableSubscription => Some(_ProductIdType::Subscription),
+            at::TransactionType::Consumable => Some(_ProductIdType::Consumable),
+            at::TransactionType::NonConsumable => Some(_ProductIdType::NonConsumable),
+            // Non-renewing subscriptions are tracked by the app itself, so
+            // may be modelled as any product type.
+            at::TransactionType::NonRenewableSubscription | at::TransactionType::Unknown(_) => None,
+        };
+        if let Some(actual_type) = actual_type {
+            check_product_type::<T>(actual_type == T::product_type(), actual_type.name())?;
+        }
+        let purchase_source = PurchaseSource::sandbox_if(
+            m.environment == app_store_server_api::common::Environment::Sandbox,
+        );
+        Ok(IapDetails {
+            canonical_id: IapPurchaseId::AppStoreTransactionId(AppleTransactionId::new_unchecked(
+                m.original_transaction_id.clone(),
+            )),
+            // NOTE: For subscriptions, we should also check the expiry date.
+            // This field is only present for subscriptions, so assume true if
+            // it is not present (its presence for subscriptions is validated by
+            // subscription-specific parsing logic later on).
+            is_active: match m.revocation_date {
+                Some(revocation_date) => !config.has_passed(revocation_date),
+                None => m.revocation_reason.is_none(),
+            } && m
+                .expires_date
+                .map(|expiry| {
+                    let grace = apple_grace_period_expiration(r, &config.active_policy);
+                    !config.is_expired(grace.map_or(expiry, |grace| grace.max(expiry)))
+                })
+                .unwrap_or(true)
+                && !config.is_after_evaluation_time(m.purchase_date),
+            is_pending: false,
+            is_sandbox: purchase_source.is_test(),
+            purchase_source,
+            is_finalized_by_client: Unknown,
+            purchase_time: m.purchase_date,
+            original_purchase_time: m.original_purchase_date,
+            region_iso3166_alpha_2: region::alpha3_to_alpha2(&m.storefront)
+                .unwrap_or_default()
+                .to_string(),
+            region_iso3166_alpha_3: m.storefront.clone(), // Already in ISO 3166-1 alpha-3 format.
+            price_info: include_price_info
+                .then(|| PriceInfo::from_apple_transaction(&m, r))
+                .flatten(),
+            acknowledgement_deadline: None,
+            account_id: m
+                .app_account_token
+                .clone()
+                .filter(|token| !token.is_empty()),
+            platform_extras: PlatformExtras::Apple {
+                storefront: m.storefront.clone(),
+                storefront_id: m.storefront_id.clone(),
+            },
+            type_specific_details: T::extract_details_from_apple_transaction(
+                &m,
+                r,
+                &config.active_policy,
+            )?,
+        })
+    }
+
+    fn from_google_product_purchase<T: TypedProductId<DetailsType = U>>(
+        purchase_id: IapPurchaseId,
+        m: gp::ProductPurchaseModel,
+        p: Option<gi::InAppProductModel>,
+        platform_extras: PlatformExtras,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        let is_finalized_by_client =
+            Known(m.acknowledgement_state == gp::AcknowledgementState::Acknowledged);
+        let acknowledgement_deadline =
+            google_acknowledgement_deadline(&is_finalized_by_client, m.purchase_time_millis);
+        let purchase_source = match m.purchase_type {
+            Some(gp::PurchaseType::Test) => PurchaseSource::LicenseTester,
+            Some(gp::PurchaseType::Promo) => PurchaseSource::Promo,
+            Some(gp::PurchaseType::Rewarded) => PurchaseSource::Rewarded,
+            None => PurchaseSource::Production,
+        };
+        Ok(IapDetails {
+            canonical_id: purchase_id,
+            is_active: m.purchase_state == gp::PurchaseState::Purchased
+                && !config.is_after_evaluation_time(m.purchase_time_millis),
+            is_pending: m.purchase_state == gp::PurchaseState::Pending,
+            is_sandbox: purchase_source.is_test(),
+            purchase_source,
+            is_finalized_by_client,
+            purchase_time: m.purchase_time_millis,
+            original_purchase_time: Some(m.purchase_time_millis),
+            region_iso3166_alpha_2: m.region_code.clone(),
+            region_iso3166_alpha_3: rust_iso3166::from_alpha2(&m.region_code)
+                .ok_or_else(|| {
+                    GooglePlayDeveloperApiInvalidResponse::new(&format!(
+                        "invalid region code '{}'",
+                        m.region_code.clone()
+                    ))
+                })?
+                .alpha3
+                .to_string(),
+            price_info: p
+                .as_ref()
+                .map(|p| PriceInfo::from_google_in_app_product_model(p, &m.region_code))
+                .transpose()?
+                .map(|price| PriceInfo {
+                    quantity: m.quantity.map(|q| q as i64).unwrap_or(1),
+                    ..price
+                }),
+            acknowledgement_deadline,
+            account_id: m.obfuscated_external_account_id.clone(),
+            platform_extras,
+            type_specific_details: T::extract_details_from_google_product_purchase(&m)?,
+        })
+    }
+
+    fn from_google_subscription_purchase<T: TypedProductId<DetailsType = U>>(
+        purchase_id: IapPurchaseId,
+        m: gs::SubscriptionPurchaseV2Model,
+        price_info: Option<PriceInfo>,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        let is_finalized_by_client = match m.acknowledgement_state {
+            gs::AcknowledgementState::AcknowledgementStateAcknowledged => Known(true),
+            gs::AcknowledgementState::AcknowledgementStatePending => Known(false),
+            gs::AcknowledgementState::Unknown(_)
+            | gs::AcknowledgementState::AcknowledgementStateUnspecified => Unknown,
+        };
+        let purchase_time = m.start_time.ok_or_else(|| {
+            GooglePlayDeveloperApiInvalidResponse::new("subscription did not have a start time")
+        })?;
+        let acknowledgement_deadline =
+            google_acknowledgement_deadline(&is_finalized_by_client, purchase_time);
+        Ok(IapDetails {
+            canonical_id: purchase_id,
+            // NOTE: Certain states (ex. SubscriptionStateCanceled) may indicate
+            // the subscription is no longer being renewed, but it may still be
+            // active if it has not yet expired.
+            is_active: match m.subscription_state {
+                gs::SubscriptionState::SubscriptionStateActive
+                | gs::SubscriptionState::SubscriptionStateCanceled => true,
+                gs::SubscriptionState::SubscriptionStatePaused => config.active_policy.paused,
+                gs::SubscriptionState::SubscriptionStateOnHold => config.active_policy.account_hold,
+                gs::SubscriptionState::SubscriptionStateInGracePeriod => {
+                    config.active_policy.grace_period
+                }
+                // Expired subscriptions were active until their expiry, which
+                // is checked below.
+                gs::SubscriptionState::SubscriptionStateExpired => {
+                    current_evaluation_time().is_some()
+                }
+                _ => false,
+            } && m
+                .line_items
+                .iter()
+                .any(|li| !config.is_expired(li.expiry_time))
+                && !config.is_after_evaluation_time(purchase_time),
+            is_pending: m.subscription_state == gs::SubscriptionState::SubscriptionStatePending,
+            is_sandbox: m.test_purchase.is_some(),
+            purchase_source: match m.test_purchase {
+                Some(_) => PurchaseSource::LicenseTester,
+                None => PurchaseSource::Production,
+            },
+            is_finalized_by_client,
+            purchase_time,
+            // Purchases replacing earlier ones are resolved in 'get_details'.
+            original_purchase_time: m.linked_purchase_token.is_none().then_some(purchase_time),
+            region_iso3166_alpha_2: m.region_code.clone(),
+            region_iso3166_alpha_3: rust_iso3166::from_alpha2(&m.region_code)
+                .ok_or_else(|| {
+                    GooglePlayDeveloperApiInvalidResponse::new(&format!(
+                        "invalid region code '{}'",
+                        m.region_code.clone()
+                    ))
+                })?
+                .alpha3
+                .to_string(),
+            price_info,
+            acknowledgement_deadline,
+            account_id: m
+                .external_account_identifiers
+                .as_ref()
+                .and_then(|ids| ids.obfuscated_external_account_id.clone()),
+            platform_extras: match &m.subscribe_with_google_info {
+                Some(info) => PlatformExtras::SubscribeWithGoogle {
+                    profile: config.expose_subscribe_with_google_profile.then(|| {
+                        SubscribeWithGoogleProfile {
+                            profile_id: info.profile_id.clone(),
+                            profile_name: info.profile_name.clone(),
+                            email_address: info.email_address.clone(),
+                            given_name: info.given_name.clone(),
+                            family_name: info.family_name.clone(),
+                        }
+                    }),
+                },
+                None => PlatformExtras::None,
+            },
+            type_specific_details: T::extract_details_from_google_subscription_purchase(&m)?,
+        })
+    }
+}
+
+#[cfg(feature = "microsoft-store")]
+impl<U: IapTypeSpecificDetails> IapDetails<U> {
+    fn from_microsoft_collection_item<T: TypedProductId<DetailsType = U>>(
+        key: MicrosoftStoreIdKey,
+        m: mc::CollectionItemModel,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        // Durables cover both non-consumables and subscriptions.
+        match m.product_type {
+            mc::ProductType::UnmanagedConsumable => check_product_type::<T>(
+                T::product_type() == _ProductIdType::Consumable,
+                _ProductIdType::Consumable.name(),
+            )?,
+            mc::ProductType::Durable => {
+                check_product_type::<T>(T::product_type() != _ProductIdType::Consumable, "durable")?
+            }
+            mc::ProductType::Application | mc::ProductType::Unknown(_) => {}
+        }
+        Ok(IapDetails {
+            canonical_id: IapPurchaseId::MicrosoftStoreIdKey(key),
+            // NOTE: Items which do not expire (ex. durables) have an end date
+            // far in the future.
+            is_active: m.status == mc::ItemStatus::Active && !config.is_expired(m.end_date),
+            // The collections API does not distinguish sandbox purchases.
+            is_pending: false,
+            is_sandbox: false,
+            purchase_source: PurchaseSource::Production,
+            is_finalized_by_client: Unknown,
+            purchase_time: m.acquired_date,
+            original_purchase_time: None,
+            region_iso3166_alpha_2: m.purchased_country.clone().unwrap_or_default(),
+            region_iso3166_alpha_3: match &m.purchased_country {
+                Some(country) => rust_iso3166::from_alpha2(country)
+                    .ok_or_else(|| {
+                        MicrosoftStoreApiInvalidResponse::new(&format!(
+                            "invalid purchased country '{country}'"
+                        ))
+                    })?
+                    .alpha3
+                    .to_string(),
+                None => String::new(),
+            },
+            price_info: None,
+            acknowledgement_deadline: None,
+            account_id: None,
+            platform_extras: PlatformExtras::None,
+            type_specific_details: T::extract_details_from_microsoft_collection_item(&m)?,
+        })
+    }
+}
+
+#[cfg(feature = "steam")]
+impl<U: IapTypeSpecificDetails> IapDetails<U> {
+    fn from_steam_txn<T: TypedProductId<DetailsType = U>>(
+        m: st::QueryTxnModel,
+        sku: &str,
+        include_price_info: bool,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        let item = m
+            .items
+            .iter()
+            .find(|item| item.itemid.to_string() == sku)
+            .ok_or_else(PurchaseNotFound::new)?;
+        Ok(IapDetails {
+            canonical_id: IapPurchaseId::SteamOrderId(SteamOrderId::from(m.orderid)),
+            is_active: m.status == st::TxnStatus::Succeeded
+                && item
+                    .itemstatus
+                    .as_ref()
+                    .is_none_or(|status| *status == st::TxnStatus::Succeeded),
+            is_pending: false,
+            is_sandbox: config.steam_sandbox,
+            purchase_source: PurchaseSource::sandbox_if(config.steam_sandbox),
+            // Orders are approved by the user, then finalized by the server.
+            is_finalized_by_client: match m.status {
+                st::TxnStatus::Init | st::TxnStatus::Approved | st::TxnStatus::Failed => {
+                    Known(false)
+                }
+                st::TxnStatus::Unknown(_) => Unknown,
+                _ => Known(true),
+            },
+            purchase_time: m.time,
+            original_purchase_time: None,
+            region_iso3166_alpha_2: m.country.clone(),
+            region_iso3166_alpha_3: rust_iso3166::from_alpha2(&m.country)
+                .ok_or_else(|| {
+                    SteamMicroTxnApiInvalidResponse::new(&format!(
+                        "invalid country code '{}'",
+                        m.country
+                    ))
+                })?
+                .alpha3
+                .to_string(),
+            price_info: include_price_info.then(|| {
+                // Amounts are in cents, and cover all units of the item. If
+                // they can not be split evenly, the total is kept as a single
+                // unit so the total price stays exact.
+                let total_micros = item.amount * 10_000;
+                let quantity = match item.qty > 0 && total_micros % item.qty == 0 {
+                    true => item.qty,
+                    false => 1,
+                };
+                PriceInfo {
+                    price_micros: total_micros / quantity,
+                    currency_iso_4217: m.currency.clone(),
+                    quantity,
+                    offer: None,
+                }
+            }),
+            acknowledgement_deadline: None,
+            account_id: None,
+            platform_extras: PlatformExtras::None,
+            type_specific_details: T::extract_details_from_steam_txn_item(item)?,
+        })
+    }
+}
+
+#[cfg(feature = "paddle")]
+impl IapDetails<SubscriptionDetails> {
+    fn from_paddle_subscription(
+        m: &ps::SubscriptionModel,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        // Paused and canceled subscriptions no longer have a billing period,
+        // so they expired when they were paused / canceled.
+        let expiration_time = m
+            .current_billing_period
+            .as_ref()
+            .map(|period| period.ends_at)
+            .or(m.canceled_at)
+            .or(m.paused_at)
+            .ok_or_else(|| {
+                PaddleWebhookParseError::new("subscription did not have a billing period")
+            })?;
+        Ok(IapDetails {
+            canonical_id: IapPurchaseId::PaddleSubscriptionId(PaddleSubscriptionId::new_unchecked(
+                &m.id,
+            )),
+            // Past due subscriptions remain active while Paddle retries the
+            // payment (unless disabled by the policy), similar to a grace
+            // period.
+            is_active: match m.status {
+                ps::SubscriptionStatus::Active | ps::SubscriptionStatus::Trialing => true,
+                ps::SubscriptionStatus::PastDue => config.active_policy.past_due,
+                _ => false,
+            } && !config.is_expired(expiration_time),
+            is_pending: false,
+            is_sandbox: config.paddle_sandbox,
+            purchase_source: PurchaseSource::sandbox_if(config.paddle_sandbox),
+            // Web checkouts have nothing to finalize on the client.
+            is_finalized_by_client: Known(true),
+            purchase_time: m.started_at.unwrap_or(m.created_at),
+            original_purchase_time: None,
+            // The customer's address is not included in notifications.
+            region_iso3166_alpha_2: String::new(),
+            region_iso3166_alpha_3: String::new(),
+            price_info: None,
+            acknowledgement_deadline: None,
+            account_id: None,
+            platform_extras: PlatformExtras::None,
+            type_specific_details: SubscriptionDetails {
+                expiration_time,
+                // Paddle does not report why a subscription was canceled.
+                expiration_intent: None,
+                is_in_billing_retry: Known(m.status == ps::SubscriptionStatus::PastDue),
+                grace_period_expiration_time: None,
+            },
+        })
+    }
+}
+
+#[cfg(feature = "stripe")]
+impl IapDetails<SubscriptionDetails> {
+    fn from_stripe_subscription(
+        m: &ss::SubscriptionModel,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        let current_period_end = m.current_period_end.or(m
+            .items
+            .data
+            .iter()
+            .filter_map(|item| item.current_period_end)
+            .max());
+        // Subscriptions canceled immediately ended before the end of their
+        // last period.
+        let ended = matches!(
+            m.status,
+            ss::SubscriptionStatus::Canceled | ss::SubscriptionStatus::IncompleteExpired
+        );
+        let expiration_time = match ended {
+            true => m.ended_at.or(current_period_end),
+            false => current_period_end.or(m.ended_at),
+        }
+        .ok_or_else(|| {
+            StripeWebhookParseError::new("subscription did not have a current period")
+        })?;
+        Ok(IapDetails {
+            canonical_id: IapPurchaseId::StripeSubscriptionId(StripeSubscriptionId::new_unchecked(
+                &m.id,
+            )),
+            // Past due subscriptions remain active while Stripe retries the
+            // payment (unless disabled by the policy), similar to a grace
+            // period.
+            is_active: match m.status {
+                ss::SubscriptionStatus::Active | ss::SubscriptionStatus::Trialing => true,
+                ss::SubscriptionStatus::PastDue => config.active_policy.past_due,
+                _ => false,
+            } && !config.is_expired(expiration_time),
+            is_pending: false,
+            is_sandbox: !m.livemode,
+            purchase_source: PurchaseSource::sandbox_if(!m.livemode),
+            // Web checkouts have nothing to finalize on the client.
+            is_finalized_by_client: Known(true),
+            purchase_time: m.start_date,
+            original_purchase_time: None,
+            // The customer's address is not included in subscription events.
+            region_iso3166_alpha_2: String::new(),
+            region_iso3166_alpha_3: String::new(),
+            price_info: None,
+            acknowledgement_deadline: None,
+            account_id: None,
+            platform_extras: PlatformExtras::None,
+            type_specific_details: SubscriptionDetails {
+                expiration_time,
+                expiration_intent: match ended {
+                    true => m
+                        .cancellation_details
+                        .as_ref()
+                        .and_then(|details| details.reason.as_ref())
+                        .map(|reason| match reason {
+                            ss::CancellationReason::CancellationRequested => {
+                                ExpirationIntent::VoluntaryCancellation
+                            }
+                            ss::CancellationReason::PaymentFailed => ExpirationIntent::BillingError,
+                            ss::CancellationReason::PaymentDisputed
+                            | ss::CancellationReason::Unknown(_) => ExpirationIntent::Other,
+                        }),
+                    false => None,
+                },
+                is_in_billing_retry: Known(matches!(
+                    m.status,
+                    ss::SubscriptionStatus::PastDue | ss::SubscriptionStatus::Unpaid
+                )),
+                grace_period_expiration_time: None,
+            },
+        })
+    }
+
+    /// Invoices do not include the subscription itself, so the details are
+    /// derived from the paid period: 'purchase_time' is the start of the
+    /// period, rather than of the subscription.
+    fn from_stripe_invoice(
+        m: &si::InvoiceModel,
+        subscription_id: &str,
+        period: &si::PeriodModel,
+        config: &IapConfig,
+    ) -> Self {
+        IapDetails {
+            canonical_id: IapPurchaseId::StripeSubscriptionId(StripeSubscriptionId::new_unchecked(
+                subscription_id,
+            )),
+            is_active: !config.is_expired(period.end),
+            is_pending: false,
+            is_sandbox: !m.livemode,
+            purchase_source: PurchaseSource::sandbox_if(!m.livemode),
+            is_finalized_by_client: Known(true),
+            purchase_time: period.start,
+            original_purchase_time: None,
+            region_iso3166_alpha_2: String::new(),
+            region_iso3166_alpha_3: String::new(),
+            price_info: None,
+            acknowledgement_deadline: None,
+            account_id: None,
+            platform_extras: PlatformExtras::None,
+            type_specific_details: SubscriptionDetails {
+                expiration_time: period.end,
+                expiration_intent: None,
+                // The period was just paid for.
+                is_in_billing_retry: Known(false),
+                grace_period_expiration_time: None,
+            },
+        }
+    }
+}
+
+impl AppleExternalPurchaseToken {
+    fn from_apple_token(m: an::ExternalPurchaseToken) -> Result<Self, ServerError> {
+        Ok(Self {
+            external_purchase_id: AppleExternalPurchaseId::new(m.external_purchase_id).map_err(
+                |e| {
+                    InvalidAppleExternalPurchaseToken::with_debug(
+                        "invalid external purchase ID",
+                        &e,
+                    )
+                },
+            )?,
+            token_creation_time: m.token_creation_date,
+            app_apple_id: m.app_apple_id,
+            bundle_id: m.bundle_id,
+        })
+    }
+}
+
+impl AppleNotificationHistoryEntry {
+    fn from_apple_history_item(
+        signed_payload: String,
+        notification: an::ResponseBodyV2DecodedPayloadModel,
+        attempts: Vec<ah::SendAttemptItemModel>,
+    ) -> Self {
+        let mut send_attempts = attempts
+            .into_iter()
+            .map(|attempt| NotificationSendAttempt {
+                time: attempt.attempt_date,
+                result: attempt.send_attempt_result,
+            })
+            .collect::<Vec<_>>();
+        send_attempts.sort_by_key(|attempt| attempt.time);
+        Self {
+            notification_id: notification.notification_uuid,
+            notification_type: format!("{:?}", notification.notification_type),
+            notification_subtype: notification.subtype.map(|subtype| format!("{subtype:?}")),
+            signed_time: notification.signed_date,
+            send_attempts,
+            signed_payload,
+        }
+    }
+}
+
+impl SubscriptionBasePlan {
+    fn from_google_base_plan(
+        bp: gm::BasePlan,
+        offers: &[go::SubscriptionOfferModel],
+        now: DateTime<Utc>,
+    ) -> Self {
+        let billing_period = google_base_plan_period(&bp);
+        // Regions are reported as alpha-2 codes; unknown ones are skipped.
+        let alpha3 = |region_code: &str| {
+            rust_iso3166::from_alpha2(region_code).map(|country| country.alpha3.to_string())
+        };
+        let base_prices: HashMap<&str, PriceInfo> = bp
+            .regional_configs
+            .iter()
+            .filter_map(|c| {
+                Some((
+                    c.region_code.as_str(),
+                    PriceInfo::from_google_money(c.price.as_ref()?),
+                ))
+            })
+            .collect();
+        let tags: Vec<String> = bp.offer_tags.iter().map(|t| t.tag.clone()).collect();
+        let offers = offers
+            .iter()
+            .filter(|offer| offer.base_plan_id == bp.base_plan_id)
+            .map(|offer| SubscriptionOffer {
+                offer_id: offer.offer_id.clone(),
+                tags: tags
+                    .iter()
+                    .cloned()
+                    .chain(offer.offer_tags.iter().map(|t| t.tag.clone()))
+                    .collect(),
+                phases: offer
+                    .phases
+                    .iter()
+                    .map(|phase| {
+                        let duration = BillingPeriod::parse_iso8601(&phase.duration);
+                        PricingPhase {
+                            kind: match phase.regional_configs.iter().all(|c| c.free.is_some()) {
+                                true => PricingPhaseKind::FreeTrial,
+                                false => PricingPhaseKind::IntroductoryPrice,
+                            },
+                            duration,
+                            recurrence_count: phase.recurrence_count.max(0) as u32,
+                            regional_prices: phase
+                                .regional_configs
+                                .iter()
+                                .filter_map(|c| {
+                                    let price = google_offer_phase_price(
+                                        c,
+                                        duration,
+                                        base_prices.get(c.region_code.as_str())?,
+                                        billing_period,
+                                        now,
+                                    )?;
+                                    Some((alpha3(&c.region_code)?, price))
+                                })
+                                .collect(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            is_auto_renewing: bp.auto_renewing_base_plan_type.is_some(),
+            billing_period,
+            regional_prices: base_prices
+                .into_iter()
+                .filter_map(|(region_code, price)| Some((alpha3(region_code)?, price)))
+                .collect(),
+            tags,
+            offers,
+            base_plan_id: bp.base_plan_id,
+        }
+    }
+}
 
-    fn from_google_product_purchase<T: TypedProductId<DetailsType = U>>(
+impl AppleTransaction {
+    fn from_apple_transaction(m: at::JwsTransactionDecodedPayloadModel) -> Self {
+        Self {
+            // Older transactions do not report their price.
+            price_info: PriceInfo::from_apple_transaction(&m, None),
+            transaction_id: AppleTransactionId::new_unchecked(m.transaction_id),
+            original_transaction_id: AppleTransactionId::new_unchecked(m.original_transaction_id),
+            product_id: m.product_id,
+            product_type: match m.transaction_type {
+                at::TransactionType::AutoRenewableSubscription => {
+                    Some(AppleProductType::AutoRenewableSubscription)
+                }
+                at::TransactionType::NonRenewableSubscription => {
+                    Some(AppleProductType::NonRenewingSubscription)
+                }
+                at::TransactionType::Consumable => Some(AppleProductType::Consumable),
+                at::TransactionType::NonConsumable => Some(AppleProductType::NonConsumable),
+                at::TransactionType::Unknown(_) => None,
+            },
+            subscription_group_id: m.subscription_group_identifier,
+            purchase_time: m.purchase_date,
+            expiration_time: m.expires_date,
+            revocation_time: m.revocation_date,
+            quantity: m.quantity.map(|q| q as i64).unwrap_or(1),
+            is_family_shared: matches!(
+                m.in_app_ownership_type,
+                Some(at::InAppOwnershipType::FamilyShared)
+            ),
+            is_sandbox: m.environment == app_store_server_api::common::Environment::Sandbox,
+            account_id: m.app_account_token.filter(|token| !token.is_empty()),
+        }
+    }
+}
+
+impl From<&AppleTransactionHistoryFilters> for ahr::TransactionHistoryRequestModel {
+    fn from(filters: &AppleTransactionHistoryFilters) -> Self {
+        Self {
+            start_date: filters.start_time,
+            end_date: filters.end_time,
+            product_id: filters.product_ids.clone(),
+            product_type: filters
+                .product_types
+                .iter()
+                .map(|product_type| match product_type {
+                    AppleProductType::AutoRenewableSubscription => ahr::ProductType::AutoRenewable,
+                    AppleProductType::NonRenewingSubscription => ahr::ProductType::NonRenewable,
+                    AppleProductType::Consumable => ahr::ProductType::Consumable,
+                    AppleProductType::NonConsumable => ahr::ProductType::NonConsumable,
+                })
+                .collect(),
+            subscription_group_identifier: filters.subscription_group_ids.clone(),
+            in_app_ownership_type: filters
+                .family_shared
+                .map(|family_shared| match family_shared {
+                    true => ahr::InAppOwnershipType::FamilyShared,
+                    false => ahr::InAppOwnershipType::Purchased,
+                }),
+            revoked: filters.revoked,
+        }
+    }
+}
+
+impl AppPurchaseDetails {
+    fn from_apple_app_transaction(m: aa::AppTransactionModel) -> Self {
+        Self {
+            is_sandbox: m.receipt_type == app_store_server_api::common::Environment::Sandbox,
+            bundle_id: m.bundle_id,
+            app_apple_id: m.app_apple_id,
+            app_transaction_id: m.app_transaction_id,
+            application_version: m.application_version,
+            original_application_version: m.original_application_version,
+            original_purchase_time: m.original_purchase_date,
+            preorder_time: m.preorder_date,
+        }
+    }
+}
+
+impl RefundRiskProfile {
+    fn from_apple_transactions(
         purchase_id: IapPurchaseId,
-        m: gp::ProductPurchaseModel,
-        p: Option<gi::InAppProductModel>,
+        transactions: Vec<at::JwsTransactionDecodedPayloadModel>,
+    ) -> Self {
+        let quantity = |t: &at::JwsTransactionDecodedPayloadModel| t.quantity.unwrap_or(1) as i64;
+        let mut refunded = transactions
+            .iter()
+            .filter(|t| t.revocation_date.is_some() || t.revocation_reason.is_some())
+            .collect::<Vec<_>>();
+        refunded.sort_by_key(|t| t.revocation_date);
+        Self {
+            purchase_id,
+            transaction_count: transactions.len() as u32,
+            purchased_quantity: transactions.iter().map(quantity).sum(),
+            refund_count: refunded.len() as u32,
+            refunded_quantity: refunded.iter().map(|t| quantity(t)).sum(),
+            refund_reasons: refunded
+                .iter()
+                .map(|t| match t.revocation_reason {
+                    Some(at::RevocationReason::Issue) => RefundReason::AppIssue,
+                    Some(at::RevocationReason::Other) | None => RefundReason::Other,
+                })
+                .collect(),
+            last_refund_time: refunded.iter().filter_map(|t| t.revocation_date).max(),
+            consumption_request_reasons: Vec::new(),
+        }
+    }
+}
+
+impl From<&ExternalPurchaseReport> for ae::ExternalPurchaseReportModel {
+    fn from(report: &ExternalPurchaseReport) -> Self {
+        Self {
+            request_identifier: report.request_identifier.clone(),
+            line_items: report
+                .line_items
+                .iter()
+                .map(|item| ae::LineItem {
+                    external_purchase_id: item.external_purchase_id.to_string(),
+                    line_item_id: item.line_item_id.clone(),
+                    event_type: match item.event_type {
+                        ExternalPurchaseEventType::Purchase => ae::EventType::Purchase,
+                        ExternalPurchaseEventType::Refund => ae::EventType::Refund,
+                        ExternalPurchaseEventType::NoPurchase => ae::EventType::NoLineItem,
+                    },
+                    event_date: item.event_time,
+                    // Apple expects milliunits rather than micro-units.
+                    amount_taxes_included: item
+                        .price_info
+                        .as_ref()
+                        .map(|p| p.total_price_micros() / 1000),
+                    currency: item
+                        .price_info
+                        .as_ref()
+                        .map(|p| p.currency_iso_4217.clone()),
+                    storefront: item.storefront_iso3166_alpha_3.clone(),
+                    is_subscription: item.is_subscription,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&ConsumptionInfo> for ac::ConsumptionRequestModel {
+    fn from(info: &ConsumptionInfo) -> Self {
+        // Apple only accepts ranges for the amounts and durations.
+        let duration_bucket = |duration: Option<chrono::Duration>, bounds: [i64; 6]| {
+            duration.map_or(0, |d| {
+                let minutes = d.num_minutes();
+                1 + bounds.iter().take_while(|&&bound| minutes >= bound).count() as u8
+            })
+        };
+        let usd_bucket = |micros: Option<i64>| match micros {
+            None => 0,
+            Some(micros) if micros <= 0 => 1,
+            Some(micros) => {
+                2 + [50, 100, 500, 1000, 2000]
+                    .iter()
+                    .take_while(|&&bound| micros >= bound * 1_000_000)
+                    .count() as u8
+            }
+        };
+        const DAY: i64 = 24 * 60;
+        Self {
+            customer_consented: info.customer_consented,
+            consumption_status: match info.consumption_status {
+                None => 0,
+                Some(ConsumptionStatus::NotConsumed) => 1,
+                Some(ConsumptionStatus::PartiallyConsumed) => 2,
+                Some(ConsumptionStatus::FullyConsumed) => 3,
+            },
+            platform: match info.delivered_on_apple_platform {
+                None => 0,
+                Some(true) => 1,
+                Some(false) => 2,
+            },
+            sample_content_provided: info.sample_content_provided,
+            // Unlike the other fields, there is no undeclared value, so the
+            // purchase is reported as delivered unless stated otherwise.
+            delivery_status: match info.delivery_status {
+                None | Some(DeliveryStatus::Delivered) => 0,
+                Some(DeliveryStatus::NotDeliveredQualityIssue) => 1,
+                Some(DeliveryStatus::WrongItemDelivered) => 2,
+                Some(DeliveryStatus::NotDeliveredServerOutage) => 3,
+                Some(DeliveryStatus::NotDeliveredCurrencyChange) => 4,
+                Some(DeliveryStatus::NotDeliveredOther) => 5,
+            },
+            app_account_token: info.app_account_token.clone().unwrap_or_default(),
+            account_tenure: duration_bucket(
+                info.account_tenure,
+                [3 * DAY, 10 * DAY, 30 * DAY, 90 * DAY, 180 * DAY, 365 * DAY],
+            ),
+            play_time: duration_bucket(info.play_time, [5, 60, 6 * 60, DAY, 4 * DAY, 16 * DAY]),
+            lifetime_dollars_refunded: usd_bucket(info.lifetime_refunded_usd_micros),
+            lifetime_dollars_purchased: usd_bucket(info.lifetime_purchased_usd_micros),
+            user_status: match info.user_status {
+                None => 0,
+                Some(UserStatus::Active) => 1,
+                Some(UserStatus::Suspended) => 2,
+                Some(UserStatus::Terminated) => 3,
+                Some(UserStatus::LimitedAccess) => 4,
+            },
+            refund_preference: match info.refund_preference {
+                None => 0,
+                Some(RefundPreference::PreferGrant) => 1,
+                Some(RefundPreference::PreferDecline) => 2,
+                Some(RefundPreference::NoPreference) => 3,
+            },
+        }
+    }
+}
+
+impl ExternalPurchaseReportStatus {
+    fn from_apple_model(m: ae::ExternalPurchaseReportStatusModel) -> Self {
+        Self {
+            request_identifier: m.request_identifier,
+            status: match m.status {
+                ae::ReportStatus::Pending => ExternalPurchaseReportState::Pending,
+                ae::ReportStatus::Processed => ExternalPurchaseReportState::Processed,
+                ae::ReportStatus::Failed => ExternalPurchaseReportState::Failed,
+                ae::ReportStatus::Unknown(s) => ExternalPurchaseReportState::Unknown(s),
+            },
+            errors: m
+                .errors
+                .into_iter()
+                .map(|e| {
+                    format!(
+                        "line item {}: error {} ({})",
+                        e.line_item_id.as_deref().unwrap_or("unknown"),
+                        e.error_code.map_or("unknown".to_owned(), |c| c.to_string()),
+                        e.error_message.as_deref().unwrap_or("no message")
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ge::ExternalTransactionModel {
+    fn from_google_external_transaction(
+        t: &GoogleExternalTransaction,
     ) -> Result<Self, ServerError> {
-        Ok(IapDetails {
-            cannonical_id: purchase_id,
-            is_active: m.purchase_state == gp::PurchaseState::Purchased,
-            is_sandbox: m.purchase_type == Some(gp::PurchaseType::Test),
-            is_finalized_by_client: Known(
-                m.acknowledgement_state == gp::AcknowledgementState::Acknowledged,
+        let region_code = rust_iso3166::from_alpha3(&t.region_iso3166_alpha_3)
+            .ok_or_else(|| {
+                InvalidGoogleExternalTransaction::new(&format!(
+                    "unknown region '{}'",
+                    t.region_iso3166_alpha_3
+                ))
+            })?
+            .alpha2
+            .to_owned();
+        let (one_time_transaction, recurring_transaction) = match &t.kind {
+            GoogleExternalTransactionKind::OneTime {
+                external_transaction_token,
+            } => (
+                Some(ge::OneTimeExternalTransaction {
+                    external_transaction_token: Some(external_transaction_token.clone()),
+                }),
+                None,
             ),
-            purchase_time: m.purchase_time_millis,
-            region_iso3166_alpha_3: rust_iso3166::from_alpha2(&m.region_code)
-                .ok_or_else(|| {
-                    GooglePlayDeveloperApiInvalidResponse::new(&format!(
-                        "invalid region code '{}'",
-                        m.region_code.clone()
-                    ))
-                })?
-                .alpha3
-                .to_string(),
-            price_info: p
-                .as_ref()
-                .map(|p| PriceInfo::from_google_in_app_product_model(p, &m.region_code))
-                .transpose()?,
-            type_specific_details: T::extract_details_from_google_product_purchase(&m)?,
+            GoogleExternalTransactionKind::SubscriptionStart {
+                external_transaction_token,
+                is_prepaid,
+            } => (
+                None,
+                Some(ge::RecurringExternalTransaction {
+                    external_transaction_token: Some(external_transaction_token.clone()),
+                    initial_external_transaction_id: None,
+                    external_subscription: Some(ge::ExternalSubscription::new(*is_prepaid)),
+                }),
+            ),
+            GoogleExternalTransactionKind::SubscriptionRenewal {
+                initial_external_transaction_id,
+                is_prepaid,
+            } => (
+                None,
+                Some(ge::RecurringExternalTransaction {
+                    external_transaction_token: None,
+                    initial_external_transaction_id: Some(
+                        initial_external_transaction_id.to_string(),
+                    ),
+                    external_subscription: Some(ge::ExternalSubscription::new(*is_prepaid)),
+                }),
+            ),
+        };
+        Ok(Self {
+            original_pre_tax_amount: Some((&t.pre_tax_price).into()),
+            original_tax_amount: Some((&t.tax).into()),
+            transaction_time: Some(t.transaction_time),
+            user_tax_address: Some(ge::ExternalTransactionAddress { region_code }),
+            one_time_transaction,
+            recurring_transaction,
+            ..Default::default()
         })
     }
+}
 
-    fn from_google_subscription_purchase<T: TypedProductId<DetailsType = U>>(
-        purchase_id: IapPurchaseId,
-        m: gs::SubscriptionPurchaseV2Model,
-        p: Option<gi::InAppProductModel>,
-    ) -> Result<Self, ServerError> {
-        Ok(IapDetails {
-            cannonical_id: purchase_id,
-            // NOTE: Certain states (ex. SubscriptionStateCanceled) may indicate
-            // the subscription is no longer being renewed, but it may still be
-            // active if it has not yet expired.
-            is_active: (m.subscription_state == gs::SubscriptionState::SubscriptionStateActive
-                || m.subscription_state == gs::SubscriptionState::SubscriptionStatePaused
-                || m.subscription_state == gs::SubscriptionState::SubscriptionStateOnHold
-                || m.subscription_state == gs::SubscriptionState::SubscriptionStateCanceled
-                || m.subscription_state == gs::SubscriptionState::SubscriptionStateInGracePeriod)
-                && m.line_items
-                    .iter()
-                    .any(|li| li.expiry_time > chrono::Utc::now()),
-            is_sandbox: m.test_purchase.is_some(),
-            is_finalized_by_client: match m.acknowledgement_state {
-                gs::AcknowledgementState::AcknowledgementStateAcknowledged => Known(true),
-                gs::AcknowledgementState::AcknowledgementStatePending => Known(false),
-                gs::AcknowledgementState::Unknown(_)
-                | gs::AcknowledgementState::AcknowledgementStateUnspecified => Unknown,
-            },
-            purchase_time: m.start_time.ok_or_else(|| {
-                GooglePlayDeveloperApiInvalidResponse::new("subscription did not have a start time")
+impl ge::ExternalSubscription {
+    fn new(is_prepaid: bool) -> Self {
+        Self {
+            subscription_type: match is_prepaid {
+                true => ge::SubscriptionType::Prepaid,
+                false => ge::SubscriptionType::Recurring,
+            },
+        }
+    }
+}
+
+impl ge::RefundExternalTransactionRequest {
+    fn from_google_refund(
+        refund: &GoogleExternalTransactionRefund,
+        refund_time: DateTime<Utc>,
+    ) -> Self {
+        match refund {
+            GoogleExternalTransactionRefund::Full => Self {
+                refund_time,
+                full_refund: Some(serde_json::json!({})),
+                partial_refund: None,
+            },
+            GoogleExternalTransactionRefund::Partial {
+                refund_id,
+                pre_tax_price,
+            } => Self {
+                refund_time,
+                full_refund: None,
+                partial_refund: Some(ge::PartialRefund {
+                    refund_id: refund_id.clone(),
+                    refund_pre_tax_amount: pre_tax_price.into(),
+                }),
+            },
+        }
+    }
+}
+
+impl From<&PriceInfo> for ge::Price {
+    fn from(price: &PriceInfo) -> Self {
+        Self {
+            price_micros: price.total_price_micros().to_string(),
+            currency: price.currency_iso_4217.clone(),
+        }
+    }
+}
+
+impl GoogleExternalTransactionDetails {
+    fn from_google_model(m: ge::ExternalTransactionModel) -> Result<Self, ServerError> {
+        let parse_price = |p: Option<ge::Price>| -> Result<Option<PriceInfo>, ServerError> {
+            p.map(|p| {
+                Ok(PriceInfo {
+                    price_micros: p.price_micros.parse::<i64>().map_err(|e| {
+                        GooglePlayDeveloperApiInvalidResponse::with_debug(
+                            "price micros could not be parsed",
+                            &e,
+                        )
+                    })?,
+                    currency_iso_4217: p.currency,
+                    quantity: 1,
+                    offer: None,
+                })
+            })
+            .transpose()
+        };
+        Ok(Self {
+            external_transaction_id: GoogleExternalTransactionId::new_unchecked(
+                m.external_transaction_id.ok_or_else(|| {
+                    GooglePlayDeveloperApiInvalidResponse::new(
+                        "external transaction did not contain its ID",
+                    )
+                })?,
+            ),
+            state: match m.transaction_state {
+                Some(ge::TransactionState::TransactionReported) => {
+                    GoogleExternalTransactionState::Reported
+                }
+                Some(ge::TransactionState::TransactionCanceled) => {
+                    GoogleExternalTransactionState::Canceled
+                }
+                Some(ge::TransactionState::TransactionStateUnspecified) | None => {
+                    GoogleExternalTransactionState::Unknown(
+                        "TRANSACTION_STATE_UNSPECIFIED".to_owned(),
+                    )
+                }
+                Some(ge::TransactionState::Unknown(s)) => {
+                    GoogleExternalTransactionState::Unknown(s)
+                }
+            },
+            is_test: m.test_purchase.is_some(),
+            transaction_time: m.transaction_time.ok_or_else(|| {
+                GooglePlayDeveloperApiInvalidResponse::new(
+                    "external transaction did not contain its transaction time",
+                )
             })?,
-            region_iso3166_alpha_3: rust_iso3166::from_alpha2(&m.region_code)
-                .ok_or_else(|| {
+            create_time: m.create_time,
+            current_pre_tax_price: parse_price(m.current_pre_tax_amount)?,
+            current_tax: parse_price(m.current_tax_amount)?,
+        })
+    }
+}
+
+/// Purchase option and offer of the productsv2 purchase of the given
+/// product. 'PlatformExtras::None' if the purchase does not contain the
+/// product.
+fn google_product_purchase_extras(m: &gp2::ProductPurchaseV2Model, sku: &str) -> PlatformExtras {
+    let offer = m
+        .product_line_item
+        .iter()
+        .find(|li| li.product_id == sku)
+        .and_then(|li| li.product_offer_details.as_ref());
+    match offer {
+        Some(offer) => PlatformExtras::GooglePlay {
+            purchase_option_id: offer.purchase_option_id.clone(),
+            offer_id: offer.offer_id.clone(),
+            offer_tags: offer.offer_tags.clone(),
+        },
+        None => PlatformExtras::None,
+    }
+}
+
+/// The productsv2 purchase of the given product, in the form returned by
+/// purchases.products. None if the purchase does not contain the product, or
+/// a field is missing or unknown.
+///
+/// NOTE: productsv2 does not report whether a purchase was made with a promo
+/// code or as a reward, so purchases without an order (which those are) are
+/// left to purchases.products. The deprecated developer payload is not
+/// reported either.
+fn google_product_purchase_from_v2(
+    m: gp2::ProductPurchaseV2Model,
+    sku: &str,
+    token: &str,
+) -> Option<gp::ProductPurchaseModel> {
+    let line_item = m
+        .product_line_item
+        .into_iter()
+        .find(|li| li.product_id == sku)?;
+    let offer = line_item.product_offer_details?;
+    let order_id = m.order_id?;
+    let purchase_state = match m.purchase_state_context?.purchase_state {
+        gp2::PurchaseState::Purchased => gp::PurchaseState::Purchased,
+        gp2::PurchaseState::Cancelled => gp::PurchaseState::Canceled,
+        gp2::PurchaseState::Pending => gp::PurchaseState::Pending,
+        gp2::PurchaseState::PurchaseStateUnspecified | gp2::PurchaseState::Unknown(_) => {
+            return None
+        }
+    };
+    let consumption_state = match offer.consumption_state? {
+        gp2::ConsumptionState::ConsumptionStateYetToBeConsumed => {
+            gp::ConsumptionState::YetToBeConsumed
+        }
+        gp2::ConsumptionState::ConsumptionStateConsumed => gp::ConsumptionState::Consumed,
+        gp2::ConsumptionState::ConsumptionStateUnspecified | gp2::ConsumptionState::Unknown(_) => {
+            return None
+        }
+    };
+    let acknowledgement_state = match m.acknowledgement_state {
+        gp2::AcknowledgementState::AcknowledgementStatePending => {
+            gp::AcknowledgementState::YetToBeAcknowledged
+        }
+        gp2::AcknowledgementState::AcknowledgementStateAcknowledged => {
+            gp::AcknowledgementState::Acknowledged
+        }
+        gp2::AcknowledgementState::AcknowledgementStateUnspecified
+        | gp2::AcknowledgementState::Unknown(_) => return None,
+    };
+    Some(gp::ProductPurchaseModel {
+        kind: m.kind,
+        purchase_time_millis: m.purchase_completion_time?,
+        purchase_state,
+        consumption_state,
+        developer_payload: None,
+        order_id: Some(order_id),
+        purchase_type: m.test_purchase_context.map(|_| gp::PurchaseType::Test),
+        acknowledgement_state,
+        purchase_token: Some(token.to_string()),
+        product_id: Some(line_item.product_id),
+        quantity: offer.quantity,
+        obfuscated_external_account_id: m.obfuscated_external_account_id,
+        obfuscated_external_profile_id: m.obfuscated_external_profile_id,
+        region_code: m.region_code,
+        refundable_quantity: offer.refundable_quantity,
+    })
+}
+
+/// Google Play refunds purchases which are not acknowledged within three days:
+/// https://developer.android.com/google/play/billing/integrate#process
+fn google_acknowledgement_deadline(
+    is_finalized_by_client: &MaybeKnown<bool>,
+    purchase_time: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match is_finalized_by_client {
+        Known(false) => Some(purchase_time + chrono::Duration::days(3)),
+        Known(true) | Unknown => None,
+    }
+}
+
+impl ProductListing {
+    fn from_google_in_app_product_model(
+        mut p: gi::InAppProductModel,
+        locale: &str,
+    ) -> Result<Option<Self>, ServerError> {
+        let parse_period = |period: &Option<String>| {
+            period
+                .as_deref()
+                .map(|period| {
+                    BillingPeriod::parse_iso8601(period).ok_or_else(|| {
+                        GooglePlayDeveloperApiInvalidResponse::new(&format!(
+                            "period '{period}' could not be parsed"
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let billing_period = parse_period(&p.subscription_period)?;
+        let free_trial_period = parse_period(&p.trial_period)?;
+        let grace_period = parse_period(&p.grace_period)?;
+        let tax_settings = p
+            .managed_product_taxes_and_compliance_settings
+            .take()
+            .or(p.subscription_taxes_and_compliance_settings.take())
+            .map(TaxSettings::from_google_settings)
+            .transpose()?;
+        let Some(matched) = match_locale(locale, p.listings.keys().map(String::as_str))
+            .or_else(|| {
+                // Google Play shows the default language's listing to users
+                // whose language is not localized.
+                p.listings
+                    .contains_key(&p.default_language)
+                    .then_some(p.default_language.as_str())
+            })
+            .map(str::to_owned)
+        else {
+            return Ok(None);
+        };
+        Ok(p.listings.remove(&matched).map(|listing| Self {
+            locale: matched,
+            title: listing.title,
+            description: Some(listing.description),
+            benefits: listing.benefits,
+            billing_period,
+            free_trial_period,
+            grace_period,
+            tax_settings,
+        }))
+    }
+
+    #[cfg(feature = "app-store-connect")]
+    fn from_app_store_connect_localizations(
+        d: DocumentModel<LocalizationAttributes>,
+        subscription_period: Option<String>,
+        locale: &str,
+    ) -> Result<Option<Self>, ServerError> {
+        let billing_period = subscription_period
+            .map(|period| {
+                BillingPeriod::from_app_store_connect_period(&period).ok_or_else(|| {
+                    AppStoreConnectApiInvalidResponse::new(&format!(
+                        "unknown subscription period '{period}'"
+                    ))
+                })
+            })
+            .transpose()?;
+        let localizations = d
+            .data
+            .into_iter()
+            .filter_map(|resource| resource.attributes)
+            .collect::<Vec<_>>();
+        let Some(matched) = match_locale(locale, localizations.iter().map(|l| l.locale.as_str()))
+            .map(str::to_owned)
+        else {
+            return Ok(None);
+        };
+        Ok(localizations
+            .into_iter()
+            .find(|l| l.locale == matched)
+            .and_then(|localization| {
+                Some(Self {
+                    locale: localization.locale,
+                    title: localization.name?,
+                    description: localization.description,
+                    benefits: Vec::new(),
+                    billing_period,
+                    // Introductory offers (including free trials) and billing
+                    // grace periods are configured separately in App Store
+                    // Connect, per territory and per app respectively.
+                    free_trial_period: None,
+                    grace_period: None,
+                    tax_settings: None,
+                })
+            }))
+    }
+}
+
+impl TaxSettings {
+    fn from_google_settings(m: gi::TaxesAndComplianceSettings) -> Result<Self, ServerError> {
+        let regional = m
+            .tax_rate_info_by_region_code
+            .into_iter()
+            .map(|(region_code, info)| {
+                let region = rust_iso3166::from_alpha2(&region_code).ok_or_else(|| {
                     GooglePlayDeveloperApiInvalidResponse::new(&format!(
-                        "invalid region code '{}'",
-                        m.region_code.clone()
+                        "invalid region code '{region_code}'"
                     ))
-                })?
-                .alpha3
-                .to_string(),
-            price_info: p
-                .as_ref()
-                .map(|p| PriceInfo::from_google_in_app_product_model(p, &m.region_code))
-                .transpose()?,
-            type_specific_details: T::extract_details_from_google_subscription_purchase(&m)?,
+                })?;
+                Ok((
+                    region.alpha3.to_string(),
+                    RegionalTaxSettings {
+                        tax_tier: match info.tax_tier {
+                            None | Some(gi::TaxTier::TaxTierUnspecified) => None,
+                            Some(gi::TaxTier::TaxTierBooks1) => Some(TaxTier::Books1),
+                            Some(gi::TaxTier::TaxTierNews1) => Some(TaxTier::News1),
+                            Some(gi::TaxTier::TaxTierNews2) => Some(TaxTier::News2),
+                            Some(gi::TaxTier::TaxTierMusicOrAudio1) => Some(TaxTier::MusicOrAudio1),
+                            Some(gi::TaxTier::TaxTierLiveOrBroadcast1) => {
+                                Some(TaxTier::LiveOrBroadcast1)
+                            }
+                            Some(gi::TaxTier::Unknown(tier)) => Some(TaxTier::Unknown(tier)),
+                        },
+                        eligible_for_streaming_service_tax_rate: info
+                            .eligible_for_streaming_service_tax_rate,
+                        streaming_tax_type: match info.streaming_tax_type {
+                            None | Some(gi::StreamingTaxType::StreamingTaxTypeUnspecified) => None,
+                            Some(gi::StreamingTaxType::StreamingTaxTypeTelcoVideoRental) => {
+                                Some(StreamingTaxType::VideoRental)
+                            }
+                            Some(gi::StreamingTaxType::StreamingTaxTypeTelcoVideoSales) => {
+                                Some(StreamingTaxType::VideoSales)
+                            }
+                            Some(gi::StreamingTaxType::StreamingTaxTypeTelcoVideoMultiChannel) => {
+                                Some(StreamingTaxType::VideoMultiChannel)
+                            }
+                            Some(gi::StreamingTaxType::StreamingTaxTypeTelcoAudioRental) => {
+                                Some(StreamingTaxType::AudioRental)
+                            }
+                            Some(gi::StreamingTaxType::StreamingTaxTypeTelcoAudioSales) => {
+                                Some(StreamingTaxType::AudioSales)
+                            }
+                            Some(gi::StreamingTaxType::StreamingTaxTypeTelcoAudioMultiChannel) => {
+                                Some(StreamingTaxType::AudioMultiChannel)
+                            }
+                            Some(gi::StreamingTaxType::Unknown(tax_type)) => {
+                                Some(StreamingTaxType::Unknown(tax_type))
+                            }
+                        },
+                    },
+                ))
+            })
+            .collect::<Result<HashMap<_, _>, ServerError>>()?;
+        Ok(Self {
+            tax_category_code: m.product_tax_category_code,
+            is_tokenized_digital_asset: m.is_tokenized_digital_asset,
+            eea_withdrawal_right: match m.eea_withdrawal_right_type {
+                None | Some(gi::EeaWithdrawalRightType::WithdrawalRightTypeUnspecified) => None,
+                Some(gi::EeaWithdrawalRightType::WithdrawalRightDigitalContent) => {
+                    Some(EeaWithdrawalRight::DigitalContent)
+                }
+                Some(gi::EeaWithdrawalRightType::WithdrawalRightService) => {
+                    Some(EeaWithdrawalRight::Service)
+                }
+                Some(gi::EeaWithdrawalRightType::Unknown(right)) => {
+                    Some(EeaWithdrawalRight::Unknown(right))
+                }
+            },
+            regional,
+        })
+    }
+}
+
+#[cfg(feature = "app-store-connect")]
+impl BillingPeriod {
+    fn from_app_store_connect_period(period: &str) -> Option<Self> {
+        let (months, weeks) = match period {
+            "ONE_WEEK" => (0, 1),
+            "ONE_MONTH" => (1, 0),
+            "TWO_MONTHS" => (2, 0),
+            "THREE_MONTHS" => (3, 0),
+            "SIX_MONTHS" => (6, 0),
+            "ONE_YEAR" => {
+                return Some(Self {
+                    years: 1,
+                    ..Default::default()
+                })
+            }
+            _ => return None,
+        };
+        Some(Self {
+            months,
+            weeks,
+            ..Default::default()
+        })
+    }
+}
+
+/// Picks the available locale best matching the requested one: the same
+/// locale (ignoring case and separators, ex. 'en_us' for 'en-US'), or else
+/// one of the same language.
+fn match_locale<'a>(
+    requested: &str,
+    available: impl Iterator<Item = &'a str> + Clone,
+) -> Option<&'a str> {
+    let normalize = |locale: &str| locale.replace('_', "-").to_ascii_lowercase();
+    let language = |locale: &str| {
+        normalize(locale)
+            .split('-')
+            .next()
+            .unwrap_or_default()
+            .to_owned()
+    };
+    let requested_locale = normalize(requested);
+    let requested_language = language(requested);
+    available
+        .clone()
+        .find(|locale| normalize(locale) == requested_locale)
+        // Lowest of the candidates, so that the choice does not depend on the
+        // order of the listings.
+        .or_else(|| {
+            available
+                .filter(|locale| language(locale) == requested_language)
+                .min()
+        })
+}
+
+#[cfg(feature = "app-store-connect")]
+impl ScheduledPrice {
+    fn from_app_store_connect_in_app_purchase_prices(
+        d: DocumentModel<cp::InAppPurchasePriceAttributes>,
+    ) -> Result<Vec<Self>, ServerError> {
+        let lookup = AppStoreConnectLookup::new(&d.included);
+        d.data
+            .iter()
+            .map(|price| {
+                let attributes = price.attributes.as_ref();
+                Ok(Self {
+                    region_iso3166_alpha_3: lookup.region(&price.relationships.territory)?,
+                    price_info: lookup.price_info(
+                        &price.relationships.in_app_purchase_price_point,
+                        &price.relationships.territory,
+                    )?,
+                    start_date: attributes.and_then(|a| a.start_date),
+                    end_date: attributes.and_then(|a| a.end_date),
+                })
+            })
+            .collect()
+    }
+
+    fn from_app_store_connect_subscription_prices(
+        d: DocumentModel<cp::SubscriptionPriceAttributes>,
+    ) -> Result<Vec<Self>, ServerError> {
+        let lookup = AppStoreConnectLookup::new(&d.included);
+        let mut prices = d
+            .data
+            .iter()
+            .map(|price| {
+                Ok(Self {
+                    region_iso3166_alpha_3: lookup.region(&price.relationships.territory)?,
+                    price_info: lookup.price_info(
+                        &price.relationships.subscription_price_point,
+                        &price.relationships.territory,
+                    )?,
+                    start_date: price.attributes.as_ref().and_then(|a| a.start_date),
+                    end_date: None,
+                })
+            })
+            .collect::<Result<Vec<_>, ServerError>>()?;
+        // Subscription prices do not have an end date; each applies until the
+        // next price in the same territory.
+        prices.sort_by(|a, b| {
+            (&a.region_iso3166_alpha_3, a.start_date)
+                .cmp(&(&b.region_iso3166_alpha_3, b.start_date))
+        });
+        for i in 1..prices.len() {
+            if prices[i].region_iso3166_alpha_3 == prices[i - 1].region_iso3166_alpha_3 {
+                prices[i - 1].end_date = prices[i].start_date;
+            }
+        }
+        Ok(prices)
+    }
+}
+
+/// Resolves the price points and territories included in an App Store
+/// Connect price list.
+#[cfg(feature = "app-store-connect")]
+struct AppStoreConnectLookup<'a> {
+    price_points: HashMap<&'a str, &'a str>,
+    currencies: HashMap<&'a str, &'a str>,
+}
+
+#[cfg(feature = "app-store-connect")]
+impl<'a> AppStoreConnectLookup<'a> {
+    fn new(included: &'a [IncludedResourceModel]) -> Self {
+        let mut price_points = HashMap::new();
+        let mut currencies = HashMap::new();
+        for resource in included {
+            match resource {
+                IncludedResourceModel::InAppPurchasePricePoints { id, attributes }
+                | IncludedResourceModel::SubscriptionPricePoints { id, attributes } => {
+                    if let Some(customer_price) = &attributes.customer_price {
+                        price_points.insert(id.as_str(), customer_price.as_str());
+                    }
+                }
+                IncludedResourceModel::Territories { id, attributes } => {
+                    if let Some(currency) = &attributes.currency {
+                        currencies.insert(id.as_str(), currency.as_str());
+                    }
+                }
+                IncludedResourceModel::Subscriptions { .. } | IncludedResourceModel::Other => {}
+            }
+        }
+        Self {
+            price_points,
+            currencies,
+        }
+    }
+
+    fn region(&self, territory: &Option<RelationshipModel>) -> Result<String, ServerError> {
+        // Territory IDs are their ISO 3166-1 alpha-3 codes.
+        Ok(Self::related_id(territory, "territory")?.to_owned())
+    }
+
+    fn price_info(
+        &self,
+        price_point: &Option<RelationshipModel>,
+        territory: &Option<RelationshipModel>,
+    ) -> Result<PriceInfo, ServerError> {
+        let price_point_id = Self::related_id(price_point, "price point")?;
+        let territory_id = Self::related_id(territory, "territory")?;
+        let customer_price = self.price_points.get(price_point_id).ok_or_else(|| {
+            AppStoreConnectApiInvalidResponse::new(&format!(
+                "price point '{price_point_id}' was not included"
+            ))
+        })?;
+        let currency = self.currencies.get(territory_id).ok_or_else(|| {
+            AppStoreConnectApiInvalidResponse::new(&format!(
+                "territory '{territory_id}' was not included"
+            ))
+        })?;
+        Ok(PriceInfo {
+            price_micros: decimal_to_micros(customer_price).ok_or_else(|| {
+                AppStoreConnectApiInvalidResponse::new(&format!(
+                    "customer price '{customer_price}' could not be parsed"
+                ))
+            })?,
+            currency_iso_4217: (*currency).to_owned(),
+            quantity: 1,
+            offer: None,
         })
     }
+
+    fn related_id<'r>(
+        relationship: &'r Option<RelationshipModel>,
+        name: &str,
+    ) -> Result<&'r str, ServerError> {
+        relationship
+            .as_ref()
+            .and_then(|r| r.data.as_ref())
+            .map(|data| data.id.as_str())
+            .ok_or_else(|| {
+                AppStoreConnectApiInvalidResponse::new(&format!("price did not contain its {name}"))
+            })
+    }
+}
+
+/// Parses a decimal amount (ex. '9.99') into micros.
+#[cfg(feature = "app-store-connect")]
+fn decimal_to_micros(value: &str) -> Option<i64> {
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if fraction.len() > 6 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let whole = whole.parse::<i64>().ok()?;
+    let fraction = format!("{fraction:0<6}").parse::<i64>().ok()?;
+    whole.checked_mul(1_000_000)?.checked_add(fraction)
 }
 
 impl PriceInfo {
+    /// The price paid for the transaction, which for subscriptions is that of
+    /// the offer the period was bought with, if any. Older transactions do
+    /// not report their price, so it is only resolved for free trials, or
+    /// from the renewal info for pay-as-you-go offers which still apply.
+    /// None if it can not be resolved.
+    fn from_apple_transaction(
+        m: &at::JwsTransactionDecodedPayloadModel,
+        r: Option<&ar::JwsRenewalInfoDecodedPayloadModel>,
+    ) -> Option<Self> {
+        use app_store_server_api::common::OfferDiscountType;
+
+        let price = match (m.price, &m.offer_discount_type) {
+            (Some(price), _) => Some(price),
+            (None, Some(OfferDiscountType::FreeTrial)) => Some(0),
+            (None, Some(OfferDiscountType::PayAsYouGo)) => r
+                .filter(|r| {
+                    r.offer_type == m.offer_type
+                        && r.offer_identifier == m.offer_identifier
+                        && r.offer_discount_type == m.offer_discount_type
+                })
+                .and_then(|r| r.renewal_price),
+            (None, _) => None,
+        };
+        Some(Self {
+            // Apple reports prices in milliunits.
+            price_micros: price? * 1000,
+            currency_iso_4217: m
+                .currency
+                .clone()
+                .or_else(|| r.and_then(|r| r.currency.clone()))?, // Already in ISO 4217 format.
+            // Apple reports the price of a single unit.
+            quantity: m.quantity.map(|q| q as i64).unwrap_or(1),
+            offer: PriceOffer::from_apple(
+                &m.offer_type,
+                &m.offer_identifier,
+                &m.offer_discount_type,
+            ),
+        })
+    }
+
+    /// The price of the next renewal, including the offer it renews with, if
+    /// any.
+    fn from_apple_renewal_info(r: &ar::JwsRenewalInfoDecodedPayloadModel) -> Option<Self> {
+        Some(Self {
+            // Apple reports prices in milliunits.
+            price_micros: r.renewal_price? * 1000,
+            currency_iso_4217: r.currency.clone()?, // Already in ISO 4217 format.
+            quantity: 1,
+            offer: PriceOffer::from_apple(
+                &r.offer_type,
+                &r.offer_identifier,
+                &r.offer_discount_type,
+            ),
+        })
+    }
+
+    fn from_google_money(m: &gs::Money) -> Self {
+        Self {
+            price_micros: google_money_micros(m),
+            currency_iso_4217: m.currency_code.clone(),
+            quantity: 1,
+            offer: None,
+        }
+    }
+
     fn from_google_in_app_product_model(
         p: &gi::InAppProductModel,
         region_code: &str,
@@ -382,15 +4001,117 @@ impl PriceInfo {
                 )
             })?,
             currency_iso_4217: details.currency.clone(),
+            quantity: 1,
+            offer: None,
+        })
+    }
+}
+
+impl PriceOffer {
+    fn from_apple(
+        offer_type: &Option<app_store_server_api::common::OfferType>,
+        offer_identifier: &Option<String>,
+        offer_discount_type: &Option<app_store_server_api::common::OfferDiscountType>,
+    ) -> Option<Self> {
+        use app_store_server_api::common::{OfferDiscountType, OfferType};
+
+        Some(Self {
+            offer_type: match offer_type.as_ref()? {
+                OfferType::Introductory => PriceOfferType::Introductory,
+                OfferType::Promotional => PriceOfferType::Promotional,
+                OfferType::OfferCode => PriceOfferType::OfferCode,
+                OfferType::WinBack => PriceOfferType::WinBack,
+            },
+            offer_id: offer_identifier.clone(),
+            is_free_trial: matches!(offer_discount_type, Some(OfferDiscountType::FreeTrial)),
         })
     }
 }
 
+/// Converts Google's units and nanos into micros.
+fn google_money_micros(m: &gs::Money) -> i64 {
+    m.units * 1_000_000 + i64::from(m.nanos) / 1000
+}
+
+/// Billing period of a Google Play base plan.
+fn google_base_plan_period(base_plan: &gm::BasePlan) -> Option<BillingPeriod> {
+    let duration = match (
+        &base_plan.auto_renewing_base_plan_type,
+        &base_plan.prepaid_base_plan_type,
+    ) {
+        (Some(t), _) => &t.billing_period_duration,
+        (None, Some(t)) => &t.billing_period_duration,
+        (None, None) => return None,
+    };
+    BillingPeriod::parse_iso8601(duration)
+}
+
+/// Price of a phase of a Google Play offer in a region, given the base plan's
+/// price there. Discounts apply to the base price prorated over the phase,
+/// measured from 'reference'. None if it can not be determined.
+fn google_offer_phase_price(
+    config: &go::RegionalSubscriptionOfferPhaseConfig,
+    phase_duration: Option<BillingPeriod>,
+    base_price: &PriceInfo,
+    base_period: Option<BillingPeriod>,
+    reference: DateTime<Utc>,
+) -> Option<PriceInfo> {
+    let prorated_base_price = || -> Option<f64> {
+        let seconds = |p: BillingPeriod| Some((p.add_to(reference)? - reference).num_seconds());
+        let phase_seconds = seconds(phase_duration?)?;
+        let base_seconds = seconds(base_period?)?;
+        (base_seconds > 0)
+            .then(|| base_price.price_micros as f64 * phase_seconds as f64 / base_seconds as f64)
+    };
+    let price_micros = match (
+        &config.price,
+        config.relative_discount,
+        &config.absolute_discount,
+        &config.free,
+    ) {
+        (Some(price), _, _, _) => return Some(PriceInfo::from_google_money(price)),
+        (None, Some(discount), _, _) => (prorated_base_price()? * (1.0 - discount)).round() as i64,
+        (None, None, Some(discount), _) => {
+            prorated_base_price()?.round() as i64 - google_money_micros(discount)
+        }
+        (None, None, None, Some(_)) => 0,
+        (None, None, None, None) => return None,
+    };
+    Some(PriceInfo {
+        price_micros,
+        ..base_price.clone()
+    })
+}
+
+/// Fails with 'ProductTypeMismatch' if the product type reported by the
+/// platform is not compatible with the 'TypedProductId' used.
+fn check_product_type<T: TypedProductId>(
+    compatible: bool,
+    actual: &str,
+) -> Result<(), ServerError> {
+    match compatible {
+        true => Ok(()),
+        false => Err(ProductTypeMismatch::new(T::product_type().name(), actual)),
+    }
+}
+
+/// End of the App Store billing grace period, if the subscription is in one
+/// and the policy grants access meanwhile.
+fn apple_grace_period_expiration(
+    r: Option<&ar::JwsRenewalInfoDecodedPayloadModel>,
+    policy: &ActivePolicy,
+) -> Option<DateTime<Utc>> {
+    r.filter(|r| r.is_in_billing_retry_period && policy.grace_period)
+        .and_then(|r| r.grace_period_expires_date)
+}
+
 impl TypedProductId for IapNonConsumableId {
     type DetailsType = NonConsumableDetails;
 
     fn extract_details_from_apple_transaction(
         _m: &at::JwsTransactionDecodedPayloadModel,
+        _r: Option<&ar::JwsRenewalInfoDecodedPayloadModel>,
+        _policy: &ActivePolicy,
     ) -> Result<Self::DetailsType, ServerError> {
         Ok(NonConsumableDetails {})
     }
@@ -404,7 +4125,24 @@ impl TypedProductId for IapNonConsumableId {
     fn extract_details_from_google_subscription_purchase(
         _m: &gs::SubscriptionPurchaseV2Model,
     ) -> Result<Self::DetailsType, ServerError> {
-        unreachable!()
+        Err(ProductTypeMismatch::new(
+            _ProductIdType::NonConsumable.name(),
+            _ProductIdType::Subscription.name(),
+        ))
+    }
+
+    #[cfg(feature = "microsoft-store")]
+    fn extract_details_from_microsoft_collection_item(
+        _m: &mc::CollectionItemModel,
+    ) -> Result<Self::DetailsType, ServerError> {
+        Ok(NonConsumableDetails {})
+    }
+
+    #[cfg(feature = "steam")]
+    fn extract_details_from_steam_txn_item(
+        _m: &st::TxnItemModel,
+    ) -> Result<Self::DetailsType, ServerError> {
+        Ok(NonConsumableDetails {})
     }
 }
 
@@ -413,6 +4151,8 @@ impl TypedProductId for IapConsumableId {
 
     fn extract_details_from_apple_transaction(
         m: &at::JwsTransactionDecodedPayloadModel,
+        _r: Option<&ar::JwsRenewalInfoDecodedPayloadModel>,
+        _policy: &ActivePolicy,
     ) -> Result<Self::DetailsType, ServerError> {
         Ok(ConsumableDetails {
             is_consumed: Unknown,
@@ -432,7 +4172,34 @@ impl TypedProductId for IapConsumableId {
     fn extract_details_from_google_subscription_purchase(
         _m: &gs::SubscriptionPurchaseV2Model,
     ) -> Result<Self::DetailsType, ServerError> {
-        unreachable!()
+        Err(ProductTypeMismatch::new(
+            _ProductIdType::Consumable.name(),
+            _ProductIdType::Subscription.name(),
+        ))
+    }
+
+    #[cfg(feature = "microsoft-store")]
+    fn extract_details_from_microsoft_collection_item(
+        m: &mc::CollectionItemModel,
+    ) -> Result<Self::DetailsType, ServerError> {
+        // Consumed quantity is removed from the item, so whether the purchase
+        // was consumed can not be told apart from a smaller quantity.
+        Ok(ConsumableDetails {
+            is_consumed: Unknown,
+            quantity: m.quantity.unwrap_or(1),
+        })
+    }
+
+    #[cfg(feature = "steam")]
+    fn extract_details_from_steam_txn_item(
+        m: &st::TxnItemModel,
+    ) -> Result<Self::DetailsType, ServerError> {
+        // Steam has no notion of consumption; items are granted once the
+        // order is finalized.
+        Ok(ConsumableDetails {
+            is_consumed: Unknown,
+            quantity: m.qty,
+        })
     }
 }
 
@@ -441,6 +4208,8 @@ impl TypedProductId for IapSubscriptionId {
 
     fn extract_details_from_apple_transaction(
         m: &at::JwsTransactionDecodedPayloadModel,
+        r: Option<&ar::JwsRenewalInfoDecodedPayloadModel>,
+        policy: &ActivePolicy,
     ) -> Result<Self::DetailsType, ServerError> {
         Ok(SubscriptionDetails {
             expiration_time: m.expires_date.ok_or_else(|| {
@@ -448,13 +4217,36 @@ impl TypedProductId for IapSubscriptionId {
                     "subscription's transaction info did not contain expiration date",
                 )
             })?,
+            expiration_intent: r.and_then(|r| r.expiration_intent.as_ref()).map(|intent| {
+                match intent {
+                    ar::ExpirationIntent::VoluntaryCancellation => {
+                        ExpirationIntent::VoluntaryCancellation
+                    }
+                    ar::ExpirationIntent::BillingError => ExpirationIntent::BillingError,
+                    ar::ExpirationIntent::PriceIncreaseDecline => {
+                        ExpirationIntent::PriceIncreaseDeclined
+                    }
+                    ar::ExpirationIntent::ProductUnavailable => {
+                        ExpirationIntent::ProductUnavailable
+                    }
+                    ar::ExpirationIntent::Other => ExpirationIntent::Other,
+                }
+            }),
+            is_in_billing_retry: match r {
+                Some(r) => Known(r.is_in_billing_retry_period),
+                None => Unknown,
+            },
+            grace_period_expiration_time: apple_grace_period_expiration(r, policy),
         })
     }
 
     fn extract_details_from_google_product_purchase(
         _m: &gp::ProductPurchaseModel,
     ) -> Result<Self::DetailsType, ServerError> {
-        unreachable!()
+        Err(ProductTypeMismatch::new(
+            _ProductIdType::Subscription.name(),
+            _ProductIdType::Consumable.name(),
+        ))
     }
 
     fn extract_details_from_google_subscription_purchase(
@@ -471,14 +4263,59 @@ impl TypedProductId for IapSubscriptionId {
                     )
                 })?
                 .expiry_time,
+            expiration_intent: match (&m.subscription_state, &m.canceled_state_context) {
+                (gs::SubscriptionState::SubscriptionStateExpired, Some(csc)) => {
+                    Some(if csc.user_initiated_cancellation.is_some() {
+                        ExpirationIntent::VoluntaryCancellation
+                    } else if csc.system_initiated_cancellation.is_some() {
+                        ExpirationIntent::BillingError
+                    } else {
+                        ExpirationIntent::Other
+                    })
+                }
+                _ => None,
+            },
+            is_in_billing_retry: Known(matches!(
+                m.subscription_state,
+                gs::SubscriptionState::SubscriptionStateInGracePeriod
+                    | gs::SubscriptionState::SubscriptionStateOnHold
+            )),
+            grace_period_expiration_time: None,
+        })
+    }
+
+    #[cfg(feature = "microsoft-store")]
+    fn extract_details_from_microsoft_collection_item(
+        m: &mc::CollectionItemModel,
+    ) -> Result<Self::DetailsType, ServerError> {
+        // The collections API does not report why a subscription lapsed.
+        Ok(SubscriptionDetails {
+            expiration_time: m.end_date,
+            expiration_intent: None,
+            is_in_billing_retry: Unknown,
+            grace_period_expiration_time: None,
         })
     }
+
+    #[cfg(feature = "steam")]
+    fn extract_details_from_steam_txn_item(
+        _m: &st::TxnItemModel,
+    ) -> Result<Self::DetailsType, ServerError> {
+        Err(ProductTypeMismatch::new(
+            _ProductIdType::Subscription.name(),
+            "Steam item",
+        ))
+    }
 }
 
 impl NotificationDetails {
     fn from_apple_notification(
         notification: an::ResponseBodyV2DecodedPayloadModel,
         transaction_info: Option<at::JwsTransactionDecodedPayloadModel>,
+        renewal_info: Option<ar::JwsRenewalInfoDecodedPayloadModel>,
+        is_offer_conversion: MaybeKnown<bool>,
+        billing_periods: (Option<BillingPeriod>, Option<BillingPeriod>),
+        config: &IapConfig,
     ) -> Result<Self, ServerError> {
         let expected_data_missing_err = || {
             Err(AppStoreServerApiInvalidResponse::new(&format!(
@@ -486,26 +4323,54 @@ impl NotificationDetails {
                 notification.notification_type
             )))
         };
+        let renewal_price = renewal_info
+            .as_ref()
+            .and_then(PriceInfo::from_apple_renewal_info);
         Ok(
             match (&notification.notification_type, &notification.subtype) {
                 (an::NotificationType::Test, _) => NotificationDetails::Test,
 
-                (an::NotificationType::Subscribed, _) => {
+                (an::NotificationType::Subscribed, _)
+                | (
+                    an::NotificationType::DidRenew,
+                    Some(an::NotificationSubtype::BillingRecovery),
+                ) => {
                     let (Some(data), Some(transaction_info)) =
                         (notification.data, transaction_info)
                     else {
                         return expected_data_missing_err();
                     };
+                    // Family Sharing access is reported with either subtype.
+                    let start_kind = if notification.notification_type
+                        == an::NotificationType::DidRenew
+                    {
+                        SubscriptionStartKind::Recovered
+                    } else if matches!(
+                        transaction_info.in_app_ownership_type,
+                        Some(at::InAppOwnershipType::FamilyShared)
+                    ) {
+                        SubscriptionStartKind::FamilyShareGained
+                    } else if notification.subtype == Some(an::NotificationSubtype::Resubscribe) {
+                        SubscriptionStartKind::Resubscribe
+                    } else {
+                        SubscriptionStartKind::InitialBuy
+                    };
                     NotificationDetails::SubscriptionStarted {
                         application_id: data.bundle_id,
                         product_id: IapSubscriptionId(transaction_info.product_id.clone()),
                         purchase_id: IapPurchaseId::AppStoreTransactionId(
-                            transaction_info.original_transaction_id.clone(),
+                            AppleTransactionId::new_unchecked(
+                                transaction_info.original_transaction_id.clone(),
+                            ),
                         ),
                         details: IapDetails::from_apple_transaction::<IapSubscriptionId>(
                             transaction_info,
+                            renewal_info.as_ref(),
                             false,
+                            config,
                         )?,
+                        start_kind,
+                        renewal_price,
                     }
                 }
 
@@ -525,7 +4390,9 @@ impl NotificationDetails {
                         application_id: data.bundle_id,
                         product_id: IapSubscriptionId(transaction_info.product_id.clone()),
                         purchase_id: IapPurchaseId::AppStoreTransactionId(
-                            transaction_info.original_transaction_id.clone(),
+                            AppleTransactionId::new_unchecked(
+                                transaction_info.original_transaction_id.clone(),
+                            ),
                         ),
                         renewal_id: if notification.notification_type
                             == an::NotificationType::DidRenew
@@ -536,8 +4403,12 @@ impl NotificationDetails {
                         },
                         details: IapDetails::from_apple_transaction::<IapSubscriptionId>(
                             transaction_info,
+                            renewal_info.as_ref(),
                             false,
+                            config,
                         )?,
+                        is_offer_conversion,
+                        renewal_price,
                     }
                 }
 
@@ -553,11 +4424,15 @@ impl NotificationDetails {
                         application_id: data.bundle_id,
                         product_id: IapSubscriptionId(transaction_info.product_id.clone()),
                         purchase_id: IapPurchaseId::AppStoreTransactionId(
-                            transaction_info.original_transaction_id.clone(),
+                            AppleTransactionId::new_unchecked(
+                                transaction_info.original_transaction_id.clone(),
+                            ),
                         ),
                         details: IapDetails::from_apple_transaction::<IapSubscriptionId>(
                             transaction_info,
+                            renewal_info.as_ref(),
                             false,
+                            config,
                         )?,
                         reason: if notification.notification_type
                             == an::NotificationType::GracePeriodExpired
@@ -573,6 +4448,7 @@ impl NotificationDetails {
                         } else {
                             SubscriptionEndReason::Unknown
                         },
+                        renewal_price,
                     }
                 }
 
@@ -588,12 +4464,16 @@ impl NotificationDetails {
                                 application_id: data.bundle_id,
                                 product_id: IapNonConsumableId(transaction_info.product_id.clone()),
                                 purchase_id: IapPurchaseId::AppStoreTransactionId(
-                                    transaction_info.original_transaction_id.clone(),
+                                    AppleTransactionId::new_unchecked(
+                                        transaction_info.original_transaction_id.clone(),
+                                    ),
                                 ),
                                 reason: Some(format!("{:?}", transaction_info.revocation_reason)),
                                 details: IapDetails::from_apple_transaction::<IapNonConsumableId>(
                                     transaction_info,
+                                    renewal_info.as_ref(),
                                     false,
+                                    config,
                                 )?,
                                 is_refunded: notification.notification_type
                                     == an::NotificationType::Refund,
@@ -603,12 +4483,16 @@ impl NotificationDetails {
                             application_id: data.bundle_id,
                             product_id: IapConsumableId(transaction_info.product_id.clone()),
                             purchase_id: IapPurchaseId::AppStoreTransactionId(
-                                transaction_info.original_transaction_id.clone(),
+                                AppleTransactionId::new_unchecked(
+                                    transaction_info.original_transaction_id.clone(),
+                                ),
                             ),
                             reason: Some(format!("{:?}", transaction_info.revocation_reason)),
                             details: IapDetails::from_apple_transaction::<IapConsumableId>(
                                 transaction_info,
+                                renewal_info.as_ref(),
                                 false,
+                                config,
                             )?,
                             is_refunded: notification.notification_type
                                 == an::NotificationType::Refund,
@@ -617,20 +4501,134 @@ impl NotificationDetails {
                             application_id: data.bundle_id,
                             product_id: IapSubscriptionId(transaction_info.product_id.clone()),
                             purchase_id: IapPurchaseId::AppStoreTransactionId(
-                                transaction_info.original_transaction_id.clone(),
+                                AppleTransactionId::new_unchecked(
+                                    transaction_info.original_transaction_id.clone(),
+                                ),
                             ),
                             details: IapDetails::from_apple_transaction::<IapSubscriptionId>(
                                 transaction_info,
+                                renewal_info.as_ref(),
                                 false,
+                                config,
                             )?,
                             reason: SubscriptionEndReason::Voided {
                                 is_refunded: notification.notification_type
                                     == an::NotificationType::Refund,
                             },
+                            renewal_price,
                         },
                     }
                 }
 
+                (
+                    an::NotificationType::ExternalPurchaseToken,
+                    Some(an::NotificationSubtype::Unreported),
+                ) => {
+                    let Some(token) = notification.external_purchase_token else {
+                        return expected_data_missing_err();
+                    };
+                    NotificationDetails::ExternalPurchaseTokenUnreported {
+                        application_id: token.bundle_id,
+                        purchase_id: IapPurchaseId::AppleExternalPurchaseId(
+                            AppleExternalPurchaseId::new_unchecked(token.external_purchase_id),
+                        ),
+                        token_creation_time: token.token_creation_date,
+                    }
+                }
+
+                (an::NotificationType::ConsumptionRequest, _) => {
+                    let (Some(data), Some(transaction_info)) =
+                        (notification.data, transaction_info)
+                    else {
+                        return expected_data_missing_err();
+                    };
+                    NotificationDetails::ConsumptionRequested {
+                        application_id: data.bundle_id,
+                        product_sku: transaction_info.product_id,
+                        // Consumption information is sent for the transaction
+                        // itself, rather than the original transaction.
+                        purchase_id: IapPurchaseId::AppStoreTransactionId(
+                            AppleTransactionId::new_unchecked(transaction_info.transaction_id),
+                        ),
+                        account_id: transaction_info
+                            .app_account_token
+                            .filter(|token| !token.is_empty()),
+                        reason: data.consumption_request_reason.map(|reason| match reason {
+                            an::ConsumptionRequestReason::UnintendedPurchase => {
+                                ConsumptionRequestReason::UnintendedPurchase
+                            }
+                            an::ConsumptionRequestReason::FulfillmentIssue => {
+                                ConsumptionRequestReason::FulfillmentIssue
+                            }
+                            an::ConsumptionRequestReason::UnsatisfiedWithPurchase => {
+                                ConsumptionRequestReason::UnsatisfiedWithPurchase
+                            }
+                            an::ConsumptionRequestReason::Legal => ConsumptionRequestReason::Legal,
+                            an::ConsumptionRequestReason::Other => ConsumptionRequestReason::Other,
+                            an::ConsumptionRequestReason::Unknown(s) => {
+                                ConsumptionRequestReason::Unknown(s)
+                            }
+                        }),
+                        // Apple only considers information sent within 12
+                        // hours of the request.
+                        deadline: notification.signed_date + chrono::Duration::hours(12),
+                    }
+                }
+
+                // Upgrades take effect immediately, with a new transaction for
+                // the new product. Downgrades (and cross-grades to another
+                // billing period) take effect at the next renewal, which is
+                // for the product of the renewal info.
+                (
+                    an::NotificationType::DidChangeRenewalPref
+                    | an::NotificationType::OfferRedeemed,
+                    Some(an::NotificationSubtype::Upgrade | an::NotificationSubtype::Downgrade),
+                ) => {
+                    let (Some(data), Some(transaction_info)) =
+                        (notification.data, transaction_info)
+                    else {
+                        return expected_data_missing_err();
+                    };
+                    let (product_id, previous_product_id, takes_effect) =
+                        if notification.subtype == Some(an::NotificationSubtype::Upgrade) {
+                            (
+                                transaction_info.product_id.clone(),
+                                None,
+                                PlanChangeTiming::Immediately,
+                            )
+                        } else {
+                            let Some(renewal_info) = &renewal_info else {
+                                return expected_data_missing_err();
+                            };
+                            (
+                                renewal_info.auto_renew_product_id.clone(),
+                                Some(IapSubscriptionId(transaction_info.product_id.clone())),
+                                PlanChangeTiming::AtNextRenewal,
+                            )
+                        };
+                    let (previous_billing_period, billing_period) = billing_periods;
+                    NotificationDetails::SubscriptionPlanChanged {
+                        application_id: data.bundle_id,
+                        product_id: IapSubscriptionId(product_id),
+                        purchase_id: IapPurchaseId::AppStoreTransactionId(
+                            AppleTransactionId::new_unchecked(
+                                transaction_info.original_transaction_id.clone(),
+                            ),
+                        ),
+                        details: IapDetails::from_apple_transaction::<IapSubscriptionId>(
+                            transaction_info,
+                            renewal_info.as_ref(),
+                            false,
+                            config,
+                        )?,
+                        previous_product_id,
+                        previous_billing_period,
+                        billing_period,
+                        takes_effect,
+                        renewal_price,
+                    }
+                }
+
                 // Changes that do not affect validity or expiry.
                 (an::NotificationType::DidChangeRenewalPref, _)
                 | (an::NotificationType::DidChangeRenewalStatus, _)
@@ -640,17 +4638,77 @@ impl NotificationDetails {
                 | (an::NotificationType::RenewalExtension, _)
                 | (an::NotificationType::ExternalPurchaseToken, _)
                 | (an::NotificationType::OneTimeCharge, _)
-                | (an::NotificationType::ConsumptionRequest, _)
-                | (an::NotificationType::Unknown(_), _) => NotificationDetails::Other,
+                | (an::NotificationType::Unknown(_), _) => NotificationDetails::Other { raw: None },
             },
         )
     }
 
+    /// Google reports the pricing phase of the current period, but not of
+    /// previous ones. Order IDs of renewals carry a '..N' suffix counting from
+    /// 0, so a base price period funded by the first renewal order of an offer
+    /// purchase follows the offer's free trial or introductory price.
+    fn google_offer_conversion(m: &gs::SubscriptionPurchaseV2Model) -> MaybeKnown<bool> {
+        let Some(line_item) = m.line_items.iter().max_by_key(|li| li.expiry_time) else {
+            return Unknown;
+        };
+        let Some(phase) = &line_item.offer_phase else {
+            return Unknown;
+        };
+        if phase.base_price.is_none() {
+            return Known(false);
+        }
+        let has_offer = line_item
+            .offer_details
+            .as_ref()
+            .is_some_and(|od| od.offer_id.is_some());
+        if !has_offer {
+            Known(false)
+        } else if m.latest_order_id.ends_with("..0") {
+            Known(true)
+        } else {
+            // Later renewals may still follow a multi-period introductory
+            // price.
+            Unknown
+        }
+    }
+
+    /// Product and base plan of the latest line item of the subscription.
+    fn google_plan(m: &gs::SubscriptionPurchaseV2Model) -> Option<(String, Option<String>)> {
+        let line_item = m.line_items.last()?;
+        Some((
+            line_item.product_id.clone(),
+            line_item
+                .offer_details
+                .as_ref()
+                .and_then(|od| od.base_plan_id.clone()),
+        ))
+    }
+
+    /// Billing period of the Google Play base plan, if it can be looked up.
+    async fn google_billing_period<T: GooglePlayDeveloperApiDatasource>(
+        google_play_developer_api_datasource: &T,
+        package_name: &str,
+        product_id: &str,
+        base_plan_id: Option<&str>,
+    ) -> Option<BillingPeriod> {
+        let base_plan_id = base_plan_id?;
+        let subscription = google_play_developer_api_datasource
+            .get_subscription(package_name, product_id)
+            .await
+            .ok()?;
+        let base_plan = subscription
+            .base_plans
+            .into_iter()
+            .find(|bp| bp.base_plan_id == base_plan_id)?;
+        google_base_plan_period(&base_plan)
+    }
+
     async fn from_google_subscription_notification<T: GooglePlayDeveloperApiDatasource>(
         notification: gn::SubscriptionNotification,
         application_id: String,
         google_play_developer_api_datasource: &T,
-    ) -> Result<Self, ServerError> {
+        config: &IapConfig,
+    ) -> Result<Self, CalloutError> {
         let api_data = google_play_developer_api_datasource
             .get_subscription_purchase_v2(&application_id, &notification.purchase_token)
             .await?;
@@ -666,34 +4724,116 @@ impl NotificationDetails {
                 .product_id
                 .clone(),
         );
-        let purchase_id = IapPurchaseId::GooglePlayPurchaseToken(notification.purchase_token);
+        let purchase_id = IapPurchaseId::GooglePlayPurchaseToken(
+            GooglePurchaseToken::new_unchecked(notification.purchase_token),
+        );
         Ok(match notification.notification_type {
-            gn::SubscriptionNotificationType::SubscriptionPurchased => {
+            gn::SubscriptionNotificationType::SubscriptionPurchased
+            | gn::SubscriptionNotificationType::SubscriptionRecovered => {
+                // Purchases linked to a previous one are either
+                // resubscriptions, or switches to another product or base plan.
+                let previous = match &api_data.linked_purchase_token {
+                    Some(token)
+                        if notification.notification_type
+                            == gn::SubscriptionNotificationType::SubscriptionPurchased =>
+                    {
+                        match google_play_developer_api_datasource
+                            .get_subscription_purchase_v2(&application_id, token)
+                            .await
+                        {
+                            Ok(previous) => Some(previous),
+                            // Old purchases may no longer be available.
+                            Err(e) if !e.is_transient() => None,
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    _ => None,
+                };
+                let previous_plan = previous.as_ref().and_then(Self::google_plan);
+                let plan = Self::google_plan(&api_data);
+                if let (Some((previous_product, previous_base_plan)), Some((_, base_plan))) =
+                    (&previous_plan, &plan)
+                {
+                    if previous_plan != plan {
+                        let previous_billing_period = Self::google_billing_period(
+                            google_play_developer_api_datasource,
+                            &application_id,
+                            previous_product,
+                            previous_base_plan.as_deref(),
+                        )
+                        .await;
+                        let billing_period = Self::google_billing_period(
+                            google_play_developer_api_datasource,
+                            &application_id,
+                            &product_id.0,
+                            base_plan.as_deref(),
+                        )
+                        .await;
+                        let takes_effect = if api_data
+                            .line_items
+                            .iter()
+                            .any(|li| li.deferred_item_replacement.is_some())
+                        {
+                            PlanChangeTiming::AtNextRenewal
+                        } else {
+                            PlanChangeTiming::Immediately
+                        };
+                        return Ok(NotificationDetails::SubscriptionPlanChanged {
+                            application_id,
+                            previous_product_id: Some(IapSubscriptionId(previous_product.clone())),
+                            product_id,
+                            purchase_id: purchase_id.clone(),
+                            details: IapDetails::from_google_subscription_purchase::<
+                                IapSubscriptionId,
+                            >(
+                                purchase_id, api_data, None, config
+                            )?,
+                            previous_billing_period,
+                            billing_period,
+                            takes_effect,
+                            renewal_price: None,
+                        });
+                    }
+                }
+                let start_kind = if notification.notification_type
+                    == gn::SubscriptionNotificationType::SubscriptionRecovered
+                {
+                    SubscriptionStartKind::Recovered
+                } else if api_data.linked_purchase_token.is_some() {
+                    SubscriptionStartKind::Resubscribe
+                } else {
+                    SubscriptionStartKind::InitialBuy
+                };
                 NotificationDetails::SubscriptionStarted {
                     application_id,
                     product_id,
                     purchase_id: purchase_id.clone(),
+                    start_kind,
                     details: IapDetails::from_google_subscription_purchase::<IapSubscriptionId>(
                         purchase_id,
                         api_data,
                         None,
+                        config,
                     )?,
+                    renewal_price: None,
                 }
             }
 
             gn::SubscriptionNotificationType::SubscriptionRenewed
-            | gn::SubscriptionNotificationType::SubscriptionRecovered
             | gn::SubscriptionNotificationType::SubscriptionInGracePeriod
             | gn::SubscriptionNotificationType::SubscriptionDeferred => {
+                let is_renewal = notification.notification_type
+                    == gn::SubscriptionNotificationType::SubscriptionRenewed;
+                let is_offer_conversion = if is_renewal {
+                    Self::google_offer_conversion(&api_data)
+                } else {
+                    Known(false)
+                };
                 NotificationDetails::SubscriptionExpiryChanged {
                     application_id,
                     product_id,
                     purchase_id: purchase_id.clone(),
-                    renewal_id: if notification.notification_type
-                        == gn::SubscriptionNotificationType::SubscriptionRenewed
-                        || notification.notification_type
-                            == gn::SubscriptionNotificationType::SubscriptionRecovered
-                    {
+                    renewal_id: if is_renewal {
                         Some(api_data.latest_order_id.clone())
                     } else {
                         None
@@ -702,18 +4842,49 @@ impl NotificationDetails {
                         purchase_id,
                         api_data,
                         None,
+                        config,
                     )?,
+                    is_offer_conversion,
+                    renewal_price: None,
+                }
+            }
+
+            gn::SubscriptionNotificationType::SubscriptionPaused => {
+                NotificationDetails::SubscriptionPaused {
+                    application_id,
+                    product_id,
+                    purchase_id: purchase_id.clone(),
+                    auto_resume_time: api_data
+                        .paused_state_context
+                        .as_ref()
+                        .map(|psc| psc.auto_resume_time),
+                    details: IapDetails::from_google_subscription_purchase::<IapSubscriptionId>(
+                        purchase_id,
+                        api_data,
+                        None,
+                        config,
+                    )?,
+                    renewal_price: None,
                 }
             }
 
             gn::SubscriptionNotificationType::SubscriptionExpired
             | gn::SubscriptionNotificationType::SubscriptionRevoked
-            | gn::SubscriptionNotificationType::SubscriptionPaused
             | gn::SubscriptionNotificationType::SubscriptionOnHold => {
-                let reason = if notification.notification_type
-                    == gn::SubscriptionNotificationType::SubscriptionPaused
+                let reason = if api_data
+                    .canceled_state_context
+                    .as_ref()
+                    .map(|csc| csc.replacement_cancellation.is_some())
+                    .unwrap_or(false)
+                {
+                    SubscriptionEndReason::Replaced
+                } else if api_data
+                    .canceled_state_context
+                    .as_ref()
+                    .map(|csc| csc.developer_initiated_cancellation.is_some())
+                    .unwrap_or(false)
                 {
-                    SubscriptionEndReason::Paused
+                    SubscriptionEndReason::RevokedByDeveloper
                 } else if api_data
                     .canceled_state_context
                     .as_ref()
@@ -750,8 +4921,10 @@ impl NotificationDetails {
                         purchase_id,
                         api_data,
                         None,
+                        config,
                     )?,
                     reason,
+                    renewal_price: None,
                 }
             }
 
@@ -773,14 +4946,55 @@ impl NotificationDetails {
             //   an expiry event, we will be able to see cancellation reason at
             //   that point, so we don't need to capture it now.
             gn::SubscriptionNotificationType::SubscriptionRestarted
-            | gn::SubscriptionNotificationType::SubscriptionCanceled => NotificationDetails::Other,
+            | gn::SubscriptionNotificationType::SubscriptionCanceled => {
+                NotificationDetails::Other { raw: None }
+            }
 
             // Changes that do not affect validity or expiry.
             gn::SubscriptionNotificationType::SubscriptionPriceChangeConfirmed
             | gn::SubscriptionNotificationType::SubscriptionPauseScheduleChanged
             | gn::SubscriptionNotificationType::SubscriptionPendingPurchaseCanceled => {
-                NotificationDetails::Other
+                NotificationDetails::Other { raw: None }
+            }
+        })
+    }
+
+    async fn from_google_one_time_product_notification<T: GooglePlayDeveloperApiDatasource>(
+        notification: gn::OneTimeProductNotification,
+        application_id: String,
+        google_play_developer_api_datasource: &T,
+    ) -> Result<Self, CalloutError> {
+        let (state, account_id) = match notification.notification_type {
+            // Sent both for purchases which are paid immediately, and for
+            // pending purchases (once when made, and again once paid), so
+            // the purchase's current state needs to be fetched.
+            gn::OneTimeProductNotificationType::OneTimeProductPurchased => {
+                let m = google_play_developer_api_datasource
+                    .get_product_purchase(
+                        &application_id,
+                        &notification.sku,
+                        &notification.purchase_token,
+                    )
+                    .await?;
+                let state = match m.purchase_state {
+                    gp::PurchaseState::Purchased => OneTimePurchaseState::Completed,
+                    gp::PurchaseState::Pending => OneTimePurchaseState::Pending,
+                    gp::PurchaseState::Canceled => OneTimePurchaseState::Canceled,
+                };
+                (state, m.obfuscated_external_account_id)
             }
+            gn::OneTimeProductNotificationType::OneTimeProductCanceled => {
+                (OneTimePurchaseState::Canceled, None)
+            }
+        };
+        Ok(NotificationDetails::OneTimePurchaseStateChanged {
+            application_id,
+            product_sku: notification.sku,
+            purchase_id: IapPurchaseId::GooglePlayPurchaseToken(
+                GooglePurchaseToken::new_unchecked(notification.purchase_token),
+            ),
+            state,
+            account_id,
         })
     }
 
@@ -788,7 +5002,8 @@ impl NotificationDetails {
         notification: gn::VoidedPurchaseNotification,
         application_id: String,
         google_play_developer_api_datasource: &T,
-    ) -> Result<Self, ServerError> {
+        config: &IapConfig,
+    ) -> Result<Self, CalloutError> {
         Ok(match notification.product_type {
             gn::VoidedPurchaseProductType::ProductTypeOneTime => {
                 // Unfortunately, we don't have access to the product ID here,
@@ -797,7 +5012,7 @@ impl NotificationDetails {
                 NotificationDetails::UnknownOneTimePurchaseVoided {
                     application_id,
                     purchase_id: IapPurchaseId::GooglePlayPurchaseToken(
-                        notification.purchase_token,
+                        GooglePurchaseToken::new_unchecked(notification.purchase_token),
                     ),
                     is_refunded: notification.refund_type
                         == gn::VoidedPurchaseRefundType::RefundTypeFullRefund,
@@ -808,8 +5023,9 @@ impl NotificationDetails {
                 let m = google_play_developer_api_datasource
                     .get_subscription_purchase_v2(&application_id, &notification.purchase_token)
                     .await?;
-                let purchase_id =
-                    IapPurchaseId::GooglePlayPurchaseToken(notification.purchase_token);
+                let purchase_id = IapPurchaseId::GooglePlayPurchaseToken(
+                    GooglePurchaseToken::new_unchecked(notification.purchase_token),
+                );
                 NotificationDetails::SubscriptionEnded {
                     application_id,
                     product_id: IapSubscriptionId(
@@ -828,13 +5044,250 @@ impl NotificationDetails {
                         purchase_id,
                         m,
                         None,
+                        config,
                     )?,
                     reason: SubscriptionEndReason::Voided {
                         is_refunded: notification.refund_type
                             == gn::VoidedPurchaseRefundType::RefundTypeFullRefund,
                     },
+                    renewal_price: None,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "paddle")]
+impl NotificationDetails {
+    fn from_paddle_event(
+        event: pe::EventModel,
+        application_id: String,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        if let pe::EventType::Unknown(_) = event.event_type {
+            // Events about other entities (ex. transactions, customers).
+            return Ok(NotificationDetails::Other { raw: None });
+        }
+        let m: ps::SubscriptionModel = serde_json::from_value(event.data)
+            .map_err(|e| PaddleWebhookParseError::with_debug("failed to parse subscription", &e))?;
+        let product_id = IapSubscriptionId(
+            m.items
+                .first()
+                .ok_or_else(|| PaddleWebhookParseError::new("subscription did not have any items"))?
+                .price
+                .product_id
+                .clone(),
+        );
+        let purchase_id =
+            IapPurchaseId::PaddleSubscriptionId(PaddleSubscriptionId::new_unchecked(&m.id));
+        let details = IapDetails::from_paddle_subscription(&m, config)?;
+        Ok(match event.event_type {
+            pe::EventType::SubscriptionCreated | pe::EventType::SubscriptionImported => {
+                NotificationDetails::SubscriptionStarted {
+                    application_id,
+                    product_id,
+                    purchase_id,
+                    details,
+                    start_kind: SubscriptionStartKind::InitialBuy,
+                    renewal_price: None,
+                }
+            }
+
+            // Renewals are reported as updates (with the new billing period),
+            // and recovered payments as activations, so the renewing
+            // transaction is not known here.
+            pe::EventType::SubscriptionActivated
+            | pe::EventType::SubscriptionTrialing
+            | pe::EventType::SubscriptionUpdated
+            | pe::EventType::SubscriptionPastDue
+            | pe::EventType::SubscriptionResumed => {
+                // The first payment of a trialing subscription is made when it
+                // is activated, after the (unbilled) trial period.
+                let is_offer_conversion = event.event_type == pe::EventType::SubscriptionActivated
+                    && m.first_billed_at.is_some_and(|first_billed| {
+                        m.started_at.is_some_and(|started| started < first_billed)
+                            && m.current_billing_period
+                                .as_ref()
+                                .is_some_and(|period| period.starts_at == first_billed)
+                    });
+                NotificationDetails::SubscriptionExpiryChanged {
+                    application_id,
+                    product_id,
+                    purchase_id,
+                    renewal_id: None,
+                    details,
+                    is_offer_conversion: Known(is_offer_conversion),
+                    renewal_price: None,
+                }
+            }
+
+            pe::EventType::SubscriptionPaused => NotificationDetails::SubscriptionPaused {
+                application_id,
+                product_id,
+                purchase_id,
+                details,
+                // Paused subscriptions only have a scheduled change if they
+                // are set to resume.
+                auto_resume_time: m
+                    .scheduled_change
+                    .as_ref()
+                    .filter(|change| change.action == ps::ScheduledChangeAction::Resume)
+                    .map(|change| change.effective_at),
+                renewal_price: None,
+            },
+
+            // Includes subscriptions canceled after payment recovery failed,
+            // which Paddle does not distinguish in the notification.
+            pe::EventType::SubscriptionCanceled => NotificationDetails::SubscriptionEnded {
+                application_id,
+                product_id,
+                purchase_id,
+                details,
+                reason: SubscriptionEndReason::Cancelled { details: None },
+                renewal_price: None,
+            },
+
+            pe::EventType::Unknown(_) => unreachable!(),
+        })
+    }
+}
+
+#[cfg(feature = "stripe")]
+impl NotificationDetails {
+    fn from_stripe_event(
+        event: se::EventModel,
+        application_id: String,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        match event.event_type {
+            // Events about other objects (ex. charges, customers).
+            se::EventType::Unknown(_) => return Ok(NotificationDetails::Other { raw: None }),
+            se::EventType::InvoicePaid => {
+                return Self::from_stripe_invoice(event.data.object, application_id, config)
+            }
+            _ => {}
+        }
+        let m: ss::SubscriptionModel = serde_json::from_value(event.data.object)
+            .map_err(|e| StripeWebhookParseError::with_debug("failed to parse subscription", &e))?;
+        let product_id = IapSubscriptionId(
+            m.items
+                .data
+                .first()
+                .ok_or_else(|| StripeWebhookParseError::new("subscription did not have any items"))?
+                .price
+                .product
+                .clone(),
+        );
+        let purchase_id =
+            IapPurchaseId::StripeSubscriptionId(StripeSubscriptionId::new_unchecked(&m.id));
+        let details = IapDetails::from_stripe_subscription(&m, config)?;
+        Ok(match event.event_type {
+            se::EventType::SubscriptionCreated => NotificationDetails::SubscriptionStarted {
+                application_id,
+                product_id,
+                purchase_id,
+                details,
+                start_kind: SubscriptionStartKind::InitialBuy,
+                renewal_price: None,
+            },
+
+            // Renewals are also reported as updates (with the new period), but
+            // the renewing invoice is only known from 'invoice.paid'.
+            se::EventType::SubscriptionUpdated | se::EventType::SubscriptionResumed => {
+                let was_trialing = event
+                    .data
+                    .previous_attributes
+                    .as_ref()
+                    .and_then(|previous| previous.get("status"))
+                    .and_then(|status| status.as_str())
+                    == Some("trialing");
+                NotificationDetails::SubscriptionExpiryChanged {
+                    application_id,
+                    product_id,
+                    purchase_id,
+                    renewal_id: None,
+                    details,
+                    is_offer_conversion: Known(
+                        was_trialing && m.status == ss::SubscriptionStatus::Active,
+                    ),
+                    renewal_price: None,
+                }
+            }
+
+            // Stripe only pauses subscriptions whose trial ended without a
+            // payment method, so they do not resume on their own.
+            se::EventType::SubscriptionPaused => NotificationDetails::SubscriptionPaused {
+                application_id,
+                product_id,
+                purchase_id,
+                details,
+                auto_resume_time: None,
+                renewal_price: None,
+            },
+
+            se::EventType::SubscriptionDeleted => {
+                let cancellation = m.cancellation_details.as_ref();
+                let reason = match cancellation.and_then(|details| details.reason.as_ref()) {
+                    Some(ss::CancellationReason::CancellationRequested) => {
+                        SubscriptionEndReason::Cancelled {
+                            details: cancellation.and_then(|details| details.feedback.clone()),
+                        }
+                    }
+                    Some(ss::CancellationReason::PaymentFailed) => {
+                        SubscriptionEndReason::FailedToRenew
+                    }
+                    _ => SubscriptionEndReason::Unknown,
+                };
+                NotificationDetails::SubscriptionEnded {
+                    application_id,
+                    product_id,
+                    purchase_id,
+                    details,
+                    reason,
+                    renewal_price: None,
                 }
             }
+
+            se::EventType::InvoicePaid | se::EventType::Unknown(_) => unreachable!(),
+        })
+    }
+
+    /// Only renewal invoices are relevant: the first invoice is reported
+    /// through 'customer.subscription.created' / 'updated', and prorations do
+    /// not change the period.
+    fn from_stripe_invoice(
+        object: serde_json::Value,
+        application_id: String,
+        config: &IapConfig,
+    ) -> Result<Self, ServerError> {
+        let m: si::InvoiceModel = serde_json::from_value(object)
+            .map_err(|e| StripeWebhookParseError::with_debug("failed to parse invoice", &e))?;
+        let Some(subscription_id) = m.subscription_id() else {
+            return Ok(NotificationDetails::Other { raw: None });
+        };
+        if m.billing_reason != Some(si::BillingReason::SubscriptionCycle) {
+            return Ok(NotificationDetails::Other { raw: None });
+        }
+        let line = m
+            .lines
+            .data
+            .iter()
+            .filter(|line| line.product_id().is_some())
+            .max_by_key(|line| line.period.end)
+            .ok_or_else(|| {
+                StripeWebhookParseError::new("renewal invoice did not have any subscription lines")
+            })?;
+        Ok(NotificationDetails::SubscriptionExpiryChanged {
+            application_id,
+            product_id: IapSubscriptionId(line.product_id().unwrap_or_default().to_owned()),
+            purchase_id: IapPurchaseId::StripeSubscriptionId(StripeSubscriptionId::new_unchecked(
+                subscription_id,
+            )),
+            renewal_id: Some(m.id.clone()),
+            details: IapDetails::from_stripe_invoice(&m, subscription_id, &line.period, config),
+            // Trial conversions are reported by 'customer.subscription.updated'.
+            is_offer_conversion: Known(false),
+            renewal_price: None,
         })
     }
 }
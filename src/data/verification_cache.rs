@@ -0,0 +1,169 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::CacheStore,
+    domain::entities::{
+        iap_details::{IapDetails, IapTypeSpecificDetails},
+        iap_purchase_id::IapPurchaseId,
+    },
+};
+
+/// Short-lived cache of successful verification results, used to avoid
+/// repeating store API callouts when clients retry the same verification many
+/// times in a short period.
+///
+/// Entries are removed once their TTL passes, or when a notification is
+/// received for the same purchase.
+pub(crate) struct VerificationCache {
+    store: Arc<dyn CacheStore>,
+    ttl: Duration,
+}
+
+/// Value stored per purchase ID.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum CacheRecord {
+    /// Results for the purchase, keyed by 'entry_key'.
+    Results {
+        entries: HashMap<String, CacheEntry>,
+    },
+    /// The ID is not the canonical one (ex. a non-original Apple transaction
+    /// ID), and results are stored under the canonical ID instead. This way,
+    /// invalidating the canonical ID also invalidates results looked up
+    /// through other IDs.
+    Alias {
+        #[serde(alias = "cannonical_id")]
+        canonical_id: IapPurchaseId,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    inserted_at: DateTime<Utc>,
+    /// Always an 'IapDetails<T>', where T depends on the product type (which
+    /// is determined by the SKU).
+    details: serde_json::Value,
+}
+
+impl VerificationCache {
+    pub(crate) fn new(store: Arc<dyn CacheStore>, ttl: Duration) -> Self {
+        Self { store, ttl }
+    }
+
+    pub(crate) async fn get<T: IapTypeSpecificDetails>(
+        &self,
+        purchase_id: &IapPurchaseId,
+        sku: &str,
+        include_price_info: bool,
+    ) -> Option<IapDetails<T>> {
+        let record = match self.load(purchase_id).await? {
+            CacheRecord::Alias { canonical_id } => self.load(&canonical_id).await?,
+            record => record,
+        };
+        let CacheRecord::Results { entries } = record else {
+            return None;
+        };
+        entries
+            .get(&Self::entry_key(sku, include_price_info))
+            .filter(|entry| !self.is_expired(entry))
+            .and_then(|entry| serde_json::from_value(entry.details.clone()).ok())
+    }
+
+    pub(crate) async fn insert<T: IapTypeSpecificDetails>(
+        &self,
+        purchase_id: IapPurchaseId,
+        sku: &str,
+        include_price_info: bool,
+        details: &IapDetails<T>,
+    ) {
+        let Ok(details_value) = serde_json::to_value(details) else {
+            return;
+        };
+        let canonical_id = &details.canonical_id;
+        let mut entries = match self.load(canonical_id).await {
+            Some(CacheRecord::Results { entries }) => entries,
+            _ => HashMap::new(),
+        };
+        entries.retain(|_, entry| !self.is_expired(entry));
+        entries.insert(
+            Self::entry_key(sku, include_price_info),
+            CacheEntry {
+                inserted_at: Utc::now(),
+                details: details_value,
+            },
+        );
+        self.save(canonical_id, &CacheRecord::Results { entries })
+            .await;
+        if &purchase_id != canonical_id {
+            self.save(
+                &purchase_id,
+                &CacheRecord::Alias {
+                    canonical_id: canonical_id.clone(),
+                },
+            )
+            .await;
+        }
+    }
+
+    /// Remove all cached results for the given purchase.
+    pub(crate) async fn invalidate(&self, purchase_id: &IapPurchaseId) {
+        let _ = self.store.delete(&Self::record_key(purchase_id)).await;
+    }
+
+    async fn load(&self, purchase_id: &IapPurchaseId) -> Option<CacheRecord> {
+        let value = self
+            .store
+            .get(&Self::record_key(purchase_id))
+            .await
+            .ok()??;
+        serde_json::from_str(&value).ok()
+    }
+
+    async fn save(&self, purchase_id: &IapPurchaseId, record: &CacheRecord) {
+        if let Ok(value) = serde_json::to_string(record) {
+            let _ = self
+                .store
+                .set(&Self::record_key(purchase_id), &value, self.ttl)
+                .await;
+        }
+    }
+
+    fn is_expired(&self, entry: &CacheEntry) -> bool {
+        (Utc::now() - entry.inserted_at)
+            .to_std()
+            .map(|age| age >= self.ttl)
+            .unwrap_or(false)
+    }
+
+    fn record_key(purchase_id: &IapPurchaseId) -> String {
+        match purchase_id {
+            IapPurchaseId::AppStoreTransactionId(id) => format!("iap:verification:apple:{id}"),
+            IapPurchaseId::AppleExternalPurchaseId(id) => {
+                format!("iap:verification:apple-external:{id}")
+            }
+            IapPurchaseId::GooglePlayPurchaseToken(token) => {
+                format!("iap:verification:google:{token}")
+            }
+            IapPurchaseId::GoogleExternalTransactionId(id) => {
+                format!("iap:verification:google-external:{id}")
+            }
+            #[cfg(feature = "microsoft-store")]
+            IapPurchaseId::MicrosoftStoreIdKey(key) => {
+                format!("iap:verification:microsoft:{key}")
+            }
+            #[cfg(feature = "steam")]
+            IapPurchaseId::SteamOrderId(id) => format!("iap:verification:steam:{id}"),
+            #[cfg(feature = "paddle")]
+            IapPurchaseId::PaddleSubscriptionId(id) => format!("iap:verification:paddle:{id}"),
+            #[cfg(feature = "stripe")]
+            IapPurchaseId::StripeSubscriptionId(id) => format!("iap:verification:stripe:{id}"),
+        }
+    }
+
+    fn entry_key(sku: &str, include_price_info: bool) -> String {
+        format!("{sku}:{include_price_info}")
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    collections::HashSet,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use async_trait::async_trait;
+use fractic_server_error::ServerError;
+
+use crate::domain::entities::iap_purchase_id::IapPurchaseId;
+
+/// Purchases which verification must reject even if the platform reports
+/// them active, ex. for chargeback fraud handled outside the stores.
+/// Registered through 'IapUtilBuilder::deny_list'.
+///
+/// Both the verified purchase ID and the purchase's
+/// 'IapDetails::canonical_id' are checked, so App Store purchases can be
+/// denied by transaction ID (a single transaction) or original transaction
+/// ID (all transactions of the purchase).
+///
+/// Denied purchases fail 'IapUtil::verify_and_get_details' with
+/// 'PurchaseDenied', and are reported as inactive by
+/// 'IapUtil::get_details_allow_inactive'. Unlike cache failures, failures of
+/// the store fail the verification.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait DenyListStore: Send + Sync {
+    async fn is_denied(&self, purchase_id: &IapPurchaseId) -> Result<bool, ServerError>;
+}
+
+/// 'DenyListStore' local to the process, ex. loaded from configuration on
+/// startup.
+#[derive(Default)]
+pub struct InMemoryDenyList {
+    denied: Mutex<HashSet<IapPurchaseId>>,
+}
+
+impl InMemoryDenyList {
+    pub fn new(purchase_ids: impl IntoIterator<Item = IapPurchaseId>) -> Self {
+        Self {
+            denied: Mutex::new(purchase_ids.into_iter().collect()),
+        }
+    }
+
+    pub fn deny(&self, purchase_id: IapPurchaseId) {
+        self.denied().insert(purchase_id);
+    }
+
+    pub fn allow(&self, purchase_id: &IapPurchaseId) {
+        self.denied().remove(purchase_id);
+    }
+
+    /// A set insert or removal can not leave it inconsistent, so poisoning is
+    /// ignored (rather than failing open and allowing denied purchases).
+    fn denied(&self) -> MutexGuard<'_, HashSet<IapPurchaseId>> {
+        self.denied.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl DenyListStore for InMemoryDenyList {
+    async fn is_denied(&self, purchase_id: &IapPurchaseId) -> Result<bool, ServerError> {
+        Ok(self.denied().contains(purchase_id))
+    }
+}
@@ -0,0 +1,332 @@
+use serde::Deserialize;
+
+/// Failure response (non-2xx status) from one of the platform APIs, parsed
+/// into the platform's error code / reasons where possible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlatformApiError {
+    AppStore {
+        status: u16,
+        /// 'errorCode' from the response body, if present.
+        error_code: Option<AppStoreServerApiErrorCode>,
+        /// 'errorMessage' from the response body, if present.
+        error_message: Option<String>,
+    },
+    GooglePlay {
+        status: u16,
+        /// 'error.errors[].reason' from the response body (possibly empty).
+        reasons: Vec<GooglePlayDeveloperApiErrorReason>,
+    },
+    #[cfg(feature = "app-store-connect")]
+    AppStoreConnect {
+        status: u16,
+        /// 'errors[].code' from the response body (ex. "NOT_FOUND"), possibly
+        /// empty.
+        codes: Vec<String>,
+    },
+    #[cfg(feature = "microsoft-store")]
+    MicrosoftStore {
+        status: u16,
+        /// 'code' from the response body (ex. "Unauthorized"), if present.
+        code: Option<String>,
+    },
+    /// NOTE: Steam reports most failures with a 200 status code.
+    #[cfg(feature = "steam")]
+    Steam {
+        status: u16,
+        /// 'response.error.errorcode' from the response body, if present.
+        error_code: Option<String>,
+        /// 'response.error.errordesc' from the response body, if present.
+        error_description: Option<String>,
+    },
+}
+
+/// Error codes returned by the App Store Server API:
+/// https://developer.apple.com/documentation/appstoreserverapi/error_codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppStoreServerApiErrorCode {
+    InvalidAppIdentifier,
+    InvalidOriginalTransactionId,
+    InvalidTransactionId,
+    AccountNotFound,
+    AccountNotFoundRetryable,
+    AppNotFound,
+    AppNotFoundRetryable,
+    OriginalTransactionIdNotFound,
+    OriginalTransactionIdNotFoundRetryable,
+    TransactionIdNotFound,
+    RateLimitExceeded,
+    GeneralInternal,
+    GeneralInternalRetryable,
+    Other(u32),
+}
+
+/// Common 'reason' values in Google API error responses:
+/// https://cloud.google.com/apis/design/errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GooglePlayDeveloperApiErrorReason {
+    QuotaExceeded,
+    RateLimitExceeded,
+    UserRateLimitExceeded,
+    BackendError,
+    InternalError,
+    NotFound,
+    Invalid,
+    PermissionDenied,
+    Other(String),
+}
+
+impl PlatformApiError {
+    /// Parse the failure response of a Google Play Developer API callout.
+    pub fn from_google_play_response(status: u16, body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            error: ErrorStatus,
+        }
+        #[derive(Deserialize)]
+        struct ErrorStatus {
+            #[serde(default)]
+            errors: Vec<ErrorItem>,
+        }
+        #[derive(Deserialize)]
+        struct ErrorItem {
+            reason: String,
+        }
+        PlatformApiError::GooglePlay {
+            status,
+            reasons: serde_json::from_str::<ErrorBody>(body)
+                .map(|body| {
+                    body.error
+                        .errors
+                        .into_iter()
+                        .map(|item| GooglePlayDeveloperApiErrorReason::parse(&item.reason))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Parse the failure response of an App Store Connect API callout.
+    #[cfg(feature = "app-store-connect")]
+    pub fn from_app_store_connect_response(status: u16, body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            #[serde(default)]
+            errors: Vec<ErrorItem>,
+        }
+        #[derive(Deserialize)]
+        struct ErrorItem {
+            code: String,
+        }
+        PlatformApiError::AppStoreConnect {
+            status,
+            codes: serde_json::from_str::<ErrorBody>(body)
+                .map(|body| body.errors.into_iter().map(|item| item.code).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Parse the failure response of a Microsoft Store collections API
+    /// callout.
+    #[cfg(feature = "microsoft-store")]
+    pub fn from_microsoft_store_response(status: u16, body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            code: Option<String>,
+        }
+        PlatformApiError::MicrosoftStore {
+            status,
+            code: serde_json::from_str::<ErrorBody>(body)
+                .ok()
+                .and_then(|body| body.code),
+        }
+    }
+
+    /// Parse the failure response of a Steam ISteamMicroTxn callout.
+    #[cfg(feature = "steam")]
+    pub fn from_steam_response(status: u16, body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            response: ErrorResponse,
+        }
+        #[derive(Deserialize)]
+        struct ErrorResponse {
+            error: Option<ErrorDetails>,
+        }
+        #[derive(Deserialize)]
+        struct ErrorDetails {
+            errorcode: Option<serde_json::Value>,
+            errordesc: Option<String>,
+        }
+        let error = serde_json::from_str::<ErrorBody>(body)
+            .ok()
+            .and_then(|body| body.response.error);
+        PlatformApiError::Steam {
+            status,
+            error_code: error
+                .as_ref()
+                .and_then(|e| e.errorcode.as_ref())
+                .map(|code| match code {
+                    serde_json::Value::String(code) => code.clone(),
+                    other => other.to_string(),
+                }),
+            error_description: error.and_then(|e| e.errordesc),
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            PlatformApiError::AppStore { status, .. }
+            | PlatformApiError::GooglePlay { status, .. } => *status,
+            #[cfg(feature = "app-store-connect")]
+            PlatformApiError::AppStoreConnect { status, .. } => *status,
+            #[cfg(feature = "microsoft-store")]
+            PlatformApiError::MicrosoftStore { status, .. } => *status,
+            #[cfg(feature = "steam")]
+            PlatformApiError::Steam { status, .. } => *status,
+        }
+    }
+
+    /// Whether the same request may succeed if retried later (ex. quota
+    /// exhausted, or the platform is temporarily unavailable).
+    ///
+    /// Recognized error codes / reasons take precedence. Otherwise, 429 and
+    /// 5xx responses are considered transient. Rejected credentials (401) are
+    /// not, since the callout was already retried once with new credentials.
+    pub fn is_transient(&self) -> bool {
+        let status_is_transient = self.status() == 429 || self.status() >= 500;
+        match self {
+            PlatformApiError::AppStore {
+                error_code: Some(AppStoreServerApiErrorCode::Other(_)) | None,
+                ..
+            } => status_is_transient,
+            PlatformApiError::AppStore {
+                error_code: Some(error_code),
+                ..
+            } => error_code.is_transient(),
+            PlatformApiError::GooglePlay { reasons, .. } => {
+                let known = reasons
+                    .iter()
+                    .filter(|reason| !matches!(reason, GooglePlayDeveloperApiErrorReason::Other(_)))
+                    .collect::<Vec<_>>();
+                if known.is_empty() {
+                    status_is_transient
+                } else {
+                    known.iter().any(|reason| reason.is_transient())
+                }
+            }
+            #[cfg(feature = "app-store-connect")]
+            PlatformApiError::AppStoreConnect { .. } => status_is_transient,
+            #[cfg(feature = "microsoft-store")]
+            PlatformApiError::MicrosoftStore { .. } => status_is_transient,
+            #[cfg(feature = "steam")]
+            PlatformApiError::Steam { .. } => status_is_transient,
+        }
+    }
+}
+
+impl AppStoreServerApiErrorCode {
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            4000002 => AppStoreServerApiErrorCode::InvalidAppIdentifier,
+            4000005 => AppStoreServerApiErrorCode::InvalidOriginalTransactionId,
+            4000006 => AppStoreServerApiErrorCode::InvalidTransactionId,
+            4040001 => AppStoreServerApiErrorCode::AccountNotFound,
+            4040002 => AppStoreServerApiErrorCode::AccountNotFoundRetryable,
+            4040003 => AppStoreServerApiErrorCode::AppNotFound,
+            4040004 => AppStoreServerApiErrorCode::AppNotFoundRetryable,
+            4040005 => AppStoreServerApiErrorCode::OriginalTransactionIdNotFound,
+            4040006 => AppStoreServerApiErrorCode::OriginalTransactionIdNotFoundRetryable,
+            4040010 => AppStoreServerApiErrorCode::TransactionIdNotFound,
+            4290000 => AppStoreServerApiErrorCode::RateLimitExceeded,
+            5000000 => AppStoreServerApiErrorCode::GeneralInternal,
+            5000001 => AppStoreServerApiErrorCode::GeneralInternalRetryable,
+            other => AppStoreServerApiErrorCode::Other(other),
+        }
+    }
+
+    pub fn code(&self) -> u32 {
+        match self {
+            AppStoreServerApiErrorCode::InvalidAppIdentifier => 4000002,
+            AppStoreServerApiErrorCode::InvalidOriginalTransactionId => 4000005,
+            AppStoreServerApiErrorCode::InvalidTransactionId => 4000006,
+            AppStoreServerApiErrorCode::AccountNotFound => 4040001,
+            AppStoreServerApiErrorCode::AccountNotFoundRetryable => 4040002,
+            AppStoreServerApiErrorCode::AppNotFound => 4040003,
+            AppStoreServerApiErrorCode::AppNotFoundRetryable => 4040004,
+            AppStoreServerApiErrorCode::OriginalTransactionIdNotFound => 4040005,
+            AppStoreServerApiErrorCode::OriginalTransactionIdNotFoundRetryable => 4040006,
+            AppStoreServerApiErrorCode::TransactionIdNotFound => 4040010,
+            AppStoreServerApiErrorCode::RateLimitExceeded => 4290000,
+            AppStoreServerApiErrorCode::GeneralInternal => 5000000,
+            AppStoreServerApiErrorCode::GeneralInternalRetryable => 5000001,
+            AppStoreServerApiErrorCode::Other(code) => *code,
+        }
+    }
+
+    /// Whether the requested transaction does not exist (in the environment
+    /// the request was sent to).
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            AppStoreServerApiErrorCode::TransactionIdNotFound
+                | AppStoreServerApiErrorCode::OriginalTransactionIdNotFound
+        )
+    }
+
+    /// Apple marks which errors are worth retrying with the "Retryable"
+    /// variants. For unknown codes, this is based on the status code instead
+    /// (see 'PlatformApiError::is_transient').
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            AppStoreServerApiErrorCode::AccountNotFoundRetryable
+                | AppStoreServerApiErrorCode::AppNotFoundRetryable
+                | AppStoreServerApiErrorCode::OriginalTransactionIdNotFoundRetryable
+                | AppStoreServerApiErrorCode::RateLimitExceeded
+                | AppStoreServerApiErrorCode::GeneralInternalRetryable
+        )
+    }
+}
+
+impl GooglePlayDeveloperApiErrorReason {
+    pub fn parse(reason: &str) -> Self {
+        match reason {
+            "quotaExceeded" => GooglePlayDeveloperApiErrorReason::QuotaExceeded,
+            "rateLimitExceeded" => GooglePlayDeveloperApiErrorReason::RateLimitExceeded,
+            "userRateLimitExceeded" => GooglePlayDeveloperApiErrorReason::UserRateLimitExceeded,
+            "backendError" => GooglePlayDeveloperApiErrorReason::BackendError,
+            "internalError" => GooglePlayDeveloperApiErrorReason::InternalError,
+            "notFound" => GooglePlayDeveloperApiErrorReason::NotFound,
+            "invalid" => GooglePlayDeveloperApiErrorReason::Invalid,
+            "permissionDenied" => GooglePlayDeveloperApiErrorReason::PermissionDenied,
+            other => GooglePlayDeveloperApiErrorReason::Other(other.to_owned()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            GooglePlayDeveloperApiErrorReason::QuotaExceeded => "quotaExceeded",
+            GooglePlayDeveloperApiErrorReason::RateLimitExceeded => "rateLimitExceeded",
+            GooglePlayDeveloperApiErrorReason::UserRateLimitExceeded => "userRateLimitExceeded",
+            GooglePlayDeveloperApiErrorReason::BackendError => "backendError",
+            GooglePlayDeveloperApiErrorReason::InternalError => "internalError",
+            GooglePlayDeveloperApiErrorReason::NotFound => "notFound",
+            GooglePlayDeveloperApiErrorReason::Invalid => "invalid",
+            GooglePlayDeveloperApiErrorReason::PermissionDenied => "permissionDenied",
+            GooglePlayDeveloperApiErrorReason::Other(reason) => reason,
+        }
+    }
+
+    /// Unknown reasons are not considered transient (see
+    /// 'PlatformApiError::is_transient').
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            GooglePlayDeveloperApiErrorReason::QuotaExceeded
+                | GooglePlayDeveloperApiErrorReason::RateLimitExceeded
+                | GooglePlayDeveloperApiErrorReason::UserRateLimitExceeded
+                | GooglePlayDeveloperApiErrorReason::BackendError
+                | GooglePlayDeveloperApiErrorReason::InternalError
+        )
+    }
+}
@@ -0,0 +1,61 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Details of the customer's purchase (or download) of the app itself, from a
+/// verified App Store AppTransaction (see 'IapUtil::verify_apple_app_transaction').
+///
+/// Useful for apps which switched from paid to freemium: customers whose
+/// 'original_application_version' predates the switch bought the app, and
+/// should keep access to what they paid for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppPurchaseDetails {
+    pub is_sandbox: bool,
+    pub bundle_id: String,
+    /// The App Store identifier of the app (not available in sandbox).
+    pub app_apple_id: Option<u64>,
+    /// Identifies the app download transaction, which is the same across all
+    /// of the customer's devices.
+    pub app_transaction_id: Option<String>,
+    /// The app version the AppTransaction was issued to.
+    pub application_version: String,
+    /// The app version the customer originally purchased or downloaded. On
+    /// iOS, this is the build number ('CFBundleVersion'); on macOS, the
+    /// version string ('CFBundleShortVersionString').
+    pub original_application_version: String,
+    pub original_purchase_time: DateTime<Utc>,
+    /// Set if the customer pre-ordered the app, and it has not been released
+    /// yet.
+    pub preorder_time: Option<DateTime<Utc>>,
+}
+
+impl AppPurchaseDetails {
+    /// Whether 'original_application_version' is older than 'version' (ex.
+    /// the first free version of the app). Versions are compared component
+    /// by component (ex. "1.10" is newer than "1.9"), with missing components
+    /// treated as 0; non-numeric components are compared as strings.
+    pub fn originally_purchased_before(&self, version: &str) -> bool {
+        compare_versions(&self.original_application_version, version) == Ordering::Less
+    }
+}
+
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = a.trim().split('.');
+    let mut b = b.trim().split('.');
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (a, b) => {
+                let (a, b) = (a.unwrap_or("0"), b.unwrap_or("0"));
+                match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.cmp(b),
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::capture::CapturedPayload;
+
+use super::{
+    iap_details::{IapDetails, IapTypeSpecificDetails},
+    iap_purchase_id::IapPurchaseId,
+};
+
+/// Record of a purchase verification (see
+/// 'IapUtil::verify_and_get_details_audited'), describing why access was
+/// granted or denied, for audit storage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct IapAuditRecord<T: IapTypeSpecificDetails> {
+    pub purchase_id: IapPurchaseId,
+    pub product_sku: String,
+    pub verified_at: DateTime<Utc>,
+    /// Whether the verification succeeded (ie. access should be granted).
+    pub granted: bool,
+    /// The checks the decision was based on, in the order they were made.
+    pub checks: Vec<AuditCheck>,
+    /// The platform responses the checks were based on (API responses and
+    /// decoded JWS payloads), with signatures and tokens redacted.
+    pub responses: Vec<CapturedPayload>,
+    /// The verified purchase details, if the purchase could be retrieved
+    /// (even if access was denied, ex. because it is no longer active).
+    pub details: Option<IapDetails<T>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditCheck {
+    /// Identifies the check (ex. "platform_verification", "is_active").
+    pub name: String,
+    pub outcome: AuditCheckOutcome,
+    /// Human-readable explanation of the outcome (ex. the error message if
+    /// the check failed).
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditCheckOutcome {
+    Passed,
+    /// Passed, but requires attention (ex. a purchase that still needs to be
+    /// acknowledged). Does not affect whether access is granted.
+    Warning,
+    Failed,
+    /// Not performed, because an earlier check failed.
+    Skipped,
+}
+
+impl AuditCheck {
+    pub(crate) fn new(
+        name: &str,
+        outcome: AuditCheckOutcome,
+        detail: impl Into<Option<String>>,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            outcome,
+            detail: detail.into(),
+        }
+    }
+}
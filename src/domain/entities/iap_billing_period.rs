@@ -0,0 +1,94 @@
+use std::fmt;
+
+use chrono::{DateTime, Days, Months, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Calendar period of a subscription's billing cycle, free trial or grace
+/// period (ex. one month). Serialized as an ISO 8601 duration (ex. 'P1M').
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BillingPeriod {
+    pub years: u32,
+    pub months: u32,
+    pub weeks: u32,
+    pub days: u32,
+}
+
+impl BillingPeriod {
+    /// Parses an ISO 8601 duration of whole years, months, weeks and days (ex.
+    /// 'P1M', 'P1Y', 'P7D'), as used by Google Play. Durations with a time
+    /// component (ex. 'PT1H') are not billing periods, so are rejected.
+    pub fn parse_iso8601(value: &str) -> Option<Self> {
+        let mut rest = value.strip_prefix('P')?;
+        if rest.is_empty() {
+            return None;
+        }
+        let mut period = Self::default();
+        while !rest.is_empty() {
+            let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+            let amount = rest[..digits].parse::<u32>().ok()?;
+            let unit = match rest[digits..].chars().next()? {
+                'Y' => &mut period.years,
+                'M' => &mut period.months,
+                'W' => &mut period.weeks,
+                'D' => &mut period.days,
+                _ => return None,
+            };
+            *unit = amount;
+            rest = &rest[digits + 1..];
+        }
+        Some(period)
+    }
+
+    /// Formats the period as an ISO 8601 duration (ex. 'P1M').
+    pub fn to_iso8601(&self) -> String {
+        let mut value = "P".to_owned();
+        for (amount, unit) in [
+            (self.years, 'Y'),
+            (self.months, 'M'),
+            (self.weeks, 'W'),
+            (self.days, 'D'),
+        ] {
+            if amount > 0 {
+                value.push_str(&format!("{amount}{unit}"));
+            }
+        }
+        if value.len() == 1 {
+            value.push_str("0D");
+        }
+        value
+    }
+
+    /// The time one period after the given time. Months are added by
+    /// calendar (clamped to the end of shorter months, ex. January 31st + 1
+    /// month is February 28th/29th), matching how the stores schedule
+    /// renewals.
+    ///
+    /// Returns None if the result is out of range.
+    pub fn add_to(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        time.checked_add_months(Months::new(
+            self.years.checked_mul(12)?.checked_add(self.months)?,
+        ))?
+        .checked_add_days(Days::new(u64::from(self.weeks) * 7 + u64::from(self.days)))
+    }
+}
+
+impl fmt::Display for BillingPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_iso8601())
+    }
+}
+
+impl Serialize for BillingPeriod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_iso8601())
+    }
+}
+
+impl<'de> Deserialize<'de> for BillingPeriod {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Self::parse_iso8601(&value).ok_or_else(|| {
+            serde::de::Error::custom(format!("invalid ISO 8601 billing period '{value}'"))
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Result of consuming a purchase (see 'IapUtil::consume').
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsumeResult {
+    /// The purchase was consumed by this call (or is consumed automatically
+    /// by the platform, ex. on the App Store).
+    Consumed,
+    /// The platform reports the purchase was already consumed, so nothing
+    /// was done.
+    AlreadyConsumed,
+}
+
+/// Why the customer requested a refund, as reported in App Store consumption
+/// requests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsumptionRequestReason {
+    /// The customer didn't intend to make the purchase.
+    UnintendedPurchase,
+    /// The customer had issues with receiving or using the purchase.
+    FulfillmentIssue,
+    /// The customer wasn't satisfied with the purchase.
+    UnsatisfiedWithPurchase,
+    Legal,
+    Other,
+    Unknown(String),
+}
+
+/// Information about a purchase and its customer, sent to Apple in response
+/// to a consumption request (see 'IapUtil::send_apple_consumption_info'), to
+/// help Apple decide on the refund request.
+///
+/// Fields which are not known can be left as 'None' (ie. undeclared).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsumptionInfo {
+    /// Whether the customer consented to sharing their data with Apple. Apple
+    /// rejects the information if not set.
+    pub customer_consented: bool,
+    pub consumption_status: Option<ConsumptionStatus>,
+    pub delivery_status: Option<DeliveryStatus>,
+    /// Whether the purchase was delivered on an Apple platform.
+    pub delivered_on_apple_platform: Option<bool>,
+    /// Whether a free sample or trial of the content was provided, or its
+    /// functionality described, before the purchase.
+    pub sample_content_provided: bool,
+    /// The UUID the app set as 'appAccountToken' at purchase time, if any.
+    pub app_account_token: Option<String>,
+    /// Age of the customer's account.
+    pub account_tenure: Option<Duration>,
+    /// Time the customer spent using the app.
+    pub play_time: Option<Duration>,
+    /// Total of the customer's in-app purchases (across platforms), in USD
+    /// micros.
+    pub lifetime_purchased_usd_micros: Option<i64>,
+    /// Total refunded to the customer (across platforms), in USD micros.
+    pub lifetime_refunded_usd_micros: Option<i64>,
+    pub user_status: Option<UserStatus>,
+    pub refund_preference: Option<RefundPreference>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsumptionStatus {
+    NotConsumed,
+    PartiallyConsumed,
+    FullyConsumed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    /// The purchase was delivered and is working properly.
+    Delivered,
+    /// Not delivered, due to a quality issue.
+    NotDeliveredQualityIssue,
+    /// The wrong item was delivered.
+    WrongItemDelivered,
+    /// Not delivered, due to a server outage.
+    NotDeliveredServerOutage,
+    /// Not delivered, due to an in-game currency change.
+    NotDeliveredCurrencyChange,
+    NotDeliveredOther,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UserStatus {
+    Active,
+    Suspended,
+    Terminated,
+    LimitedAccess,
+}
+
+/// The developer's preference for the outcome of the refund request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefundPreference {
+    PreferGrant,
+    PreferDecline,
+    NoPreference,
+}
@@ -1,61 +1,316 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use super::iap_purchase_id::IapPurchaseId;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MaybeKnown<T> {
     Known(T),
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PriceInfo {
-    /// The price in micro-units, where 1,000,000 micro-units equal one unit of
-    /// the currency.
+    /// The price of a single unit in micro-units, where 1,000,000 micro-units
+    /// equal one unit of the currency.
     pub price_micros: i64,
     /// 3-letter ISO 4217 currency code.
     pub currency_iso_4217: String,
+    /// Number of units bought at 'price_micros' each (ex. App Store
+    /// consumables bought with a quantity). Always 1 for subscriptions and
+    /// product listings. See 'total_price_micros'.
+    #[serde(default = "default_quantity")]
+    pub quantity: i64,
+    /// The offer the price is discounted by, if any, in which case
+    /// 'price_micros' is the price paid under the offer rather than the base
+    /// price.
+    #[serde(default)]
+    pub offer: Option<PriceOffer>,
+}
+
+/// Subscription offer (ex. an introductory price or free trial) applying to a
+/// 'PriceInfo'.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceOffer {
+    pub offer_type: PriceOfferType,
+    /// ID of the offer: the offer identifier or offer code for the App Store
+    /// (not set for introductory offers), or the offer ID for Google Play.
+    pub offer_id: Option<String>,
+    /// Whether the offer is a free trial (in which case 'price_micros' is 0).
+    pub is_free_trial: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PriceOfferType {
+    Introductory,
+    Promotional,
+    OfferCode,
+    WinBack,
+    /// Google Play offers, whose eligibility is configured per offer rather
+    /// than by type.
+    GooglePlay,
 }
 
-#[derive(Debug, Clone)]
+impl PriceInfo {
+    /// The price of all units purchased, ex. for revenue reporting.
+    pub fn total_price_micros(&self) -> i64 {
+        self.price_micros.saturating_mul(self.quantity)
+    }
+}
+
+fn default_quantity() -> i64 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct IapDetails<T: IapTypeSpecificDetails> {
-    pub cannonical_id: IapPurchaseId,
+    /// Stable ID of the purchase, to key records by: the original
+    /// transaction ID for the App Store, and the purchase token for Google
+    /// Play. See also 'IapUtil::canonical_purchase_id'.
+    #[serde(alias = "cannonical_id")]
+    pub canonical_id: IapPurchaseId,
     pub is_active: bool,
+    /// Whether the purchase is awaiting payment (ex. Google Play purchases
+    /// paid in cash at a store, or subscriptions bought with such a payment
+    /// method). Pending purchases are not active, and should only be
+    /// fulfilled once they complete; see
+    /// 'NotificationDetails::OneTimePurchaseStateChanged'.
+    #[serde(default)]
+    pub is_pending: bool,
     pub is_sandbox: bool,
+    /// Finer-grained than 'is_sandbox', ex. to exclude license testers or
+    /// promo code redemptions from analytics.
+    #[serde(default)]
+    pub purchase_source: PurchaseSource,
     pub is_finalized_by_client: MaybeKnown<bool>,
     pub purchase_time: DateTime<Utc>,
+    /// When the customer first bought the product, as opposed to
+    /// 'purchase_time' (the current transaction or purchase), ex. for
+    /// tenure-based features like loyalty pricing. For the App Store, the
+    /// purchase date of the original transaction (unchanged by renewals and
+    /// restores). For Google Play subscriptions, the start time of the
+    /// earliest purchase in the chain of purchases this one replaced (ex.
+    /// before upgrades, or resubscribing), as far as still known to Google;
+    /// resolving the chain requires one callout per earlier purchase, so it
+    /// is not populated in notifications for purchases which replaced others.
+    /// For Google Play one-time purchases, same as 'purchase_time'. None if
+    /// not known.
+    #[serde(default)]
+    pub original_purchase_time: Option<DateTime<Utc>>,
+    /// The 2-letter ISO 3166-1 region code, as reported by the platform (for
+    /// the App Store, converted from the alpha-3 storefront). See
+    /// 'crate::region' to convert other codes. Empty if not known.
+    #[serde(default)]
+    pub region_iso3166_alpha_2: String,
     pub region_iso3166_alpha_3: String,
     pub price_info: Option<PriceInfo>,
+    /// For Google Play purchases which are not yet acknowledged, the time by
+    /// which they must be (purchase time + 3 days), after which Google
+    /// automatically refunds them. See also 'ErrorObserver::on_warning'.
+    #[serde(default)]
+    pub acknowledgement_deadline: Option<DateTime<Utc>>,
+    /// Identifier of the customer's account in the app, as attached to the
+    /// purchase by the app (Apple's 'appAccountToken', Google Play's
+    /// obfuscated account ID), ex. to attribute notifications to a user
+    /// without looking up the purchase. Not available for other platforms.
+    #[serde(default)]
+    pub account_id: Option<String>,
+    #[serde(default)]
+    pub platform_extras: PlatformExtras,
 
     pub type_specific_details: T,
 }
 
-pub trait IapTypeSpecificDetails: Send + Sync {}
+/// Where the purchase came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PurchaseSource {
+    /// A regular purchase, paid by the customer.
+    #[default]
+    Production,
+    /// A purchase made in the platform's sandbox / test environment (Apple
+    /// sandbox, Steam sandbox, Paddle sandbox, Stripe test mode).
+    Sandbox,
+    /// A Google Play purchase made by a license tester account, which is not
+    /// charged.
+    LicenseTester,
+    /// A Google Play purchase made by redeeming a promo code.
+    Promo,
+    /// A Google Play purchase granted for watching a video ad instead of
+    /// paying.
+    Rewarded,
+}
+
+impl PurchaseSource {
+    /// Whether the purchase was made for testing ('Sandbox' or
+    /// 'LicenseTester'). Same as 'IapDetails::is_sandbox'.
+    pub fn is_test(&self) -> bool {
+        matches!(
+            self,
+            PurchaseSource::Sandbox | PurchaseSource::LicenseTester
+        )
+    }
+
+    pub(crate) fn sandbox_if(is_sandbox: bool) -> Self {
+        match is_sandbox {
+            true => PurchaseSource::Sandbox,
+            false => PurchaseSource::Production,
+        }
+    }
+}
+
+/// Platform-specific information not covered by the generic fields.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum PlatformExtras {
+    #[default]
+    None,
+    Apple {
+        /// Three-letter code of the country or region of the App Store
+        /// storefront (the source of 'region_iso3166_alpha_3').
+        storefront: String,
+        /// Apple-defined identifier of the App Store storefront, which is
+        /// more precise than the country or region (ex. for tax purposes).
+        storefront_id: Option<String>,
+    },
+    /// Only reported for one-time products, if looked up through Google's
+    /// productsv2 API.
+    GooglePlay {
+        /// ID of the product's purchase option that was bought (ex. a
+        /// buy or rent option).
+        purchase_option_id: Option<String>,
+        /// ID of the offer applied to the purchase, if any.
+        offer_id: Option<String>,
+        /// Tags of the offer, including those inherited from the purchase
+        /// option.
+        offer_tags: Vec<String>,
+    },
+    /// Google Play subscriptions bought through Subscribe with Google.
+    SubscribeWithGoogle {
+        /// The customer's Google profile at the time of purchase. Only
+        /// reported if enabled with
+        /// 'IapUtilBuilder::expose_subscribe_with_google_profile'.
+        profile: Option<SubscribeWithGoogleProfile>,
+    },
+}
+
+/// Google profile of a Subscribe with Google subscriber. The values are
+/// redacted from 'Debug' output, so that they do not end up in logs.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscribeWithGoogleProfile {
+    pub profile_id: Option<String>,
+    pub profile_name: Option<String>,
+    pub email_address: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
+
+impl fmt::Debug for SubscribeWithGoogleProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |value: &Option<String>| value.as_ref().map(|_| "[REDACTED]");
+        f.debug_struct("SubscribeWithGoogleProfile")
+            .field("profile_id", &redacted(&self.profile_id))
+            .field("profile_name", &redacted(&self.profile_name))
+            .field("email_address", &redacted(&self.email_address))
+            .field("given_name", &redacted(&self.given_name))
+            .field("family_name", &redacted(&self.family_name))
+            .finish()
+    }
+}
+
+pub trait IapTypeSpecificDetails:
+    Clone + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+}
 impl IapTypeSpecificDetails for NonConsumableDetails {}
 impl IapTypeSpecificDetails for ConsumableDetails {}
 impl IapTypeSpecificDetails for SubscriptionDetails {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NonConsumableDetails {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsumableDetails {
     pub is_consumed: MaybeKnown<bool>,
+    /// Number of units purchased. If price info was requested, the same as
+    /// 'PriceInfo::quantity'.
     pub quantity: i64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubscriptionDetails {
     pub expiration_time: DateTime<Utc>,
+    /// The reason the subscription lapsed. Only populated for subscriptions
+    /// which have expired, and only if the platform reported a reason.
+    pub expiration_intent: Option<ExpirationIntent>,
+    /// Whether the platform is retrying a failed renewal payment (Apple's
+    /// billing retry period, Google's grace period / account hold, or a past
+    /// due web subscription). Independent of 'is_active', since access may or
+    /// may not be retained meanwhile; useful to prompt the user to update
+    /// their payment method.
+    ///
+    /// For the App Store, this is only known if the renewal info was
+    /// retrieved (ie. in notifications, or for expired subscriptions).
+    pub is_in_billing_retry: MaybeKnown<bool>,
+    /// For App Store subscriptions in a billing grace period, when the grace
+    /// period ends. Only set if 'ActivePolicy::grace_period' grants access
+    /// during grace periods, and the renewal info was retrieved (see
+    /// 'is_in_billing_retry').
+    #[serde(default)]
+    pub grace_period_expiration_time: Option<DateTime<Utc>>,
+}
+
+impl SubscriptionDetails {
+    /// When the customer's access ends: 'expiration_time', extended to the
+    /// end of the grace period if the subscription is in one (see
+    /// 'grace_period_expiration_time'). Other platforms already extend
+    /// 'expiration_time' over their grace periods.
+    pub fn access_expiry(&self) -> DateTime<Utc> {
+        self.grace_period_expiration_time
+            .map_or(self.expiration_time, |grace| {
+                grace.max(self.expiration_time)
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExpirationIntent {
+    /// The customer cancelled the subscription.
+    VoluntaryCancellation,
+    /// The renewal payment failed (ex. payment method no longer valid).
+    BillingError,
+    /// The customer did not consent to a price increase.
+    PriceIncreaseDeclined,
+    /// The product was not available for purchase at the time of renewal.
+    ProductUnavailable,
+    Other,
 }
 
 pub trait IapGenericDetails {
     fn is_active(&self) -> bool;
+    fn is_pending(&self) -> bool;
     fn is_sandbox(&self) -> bool;
+    fn purchase_source(&self) -> PurchaseSource;
     fn is_finalized_by_client(&self) -> MaybeKnown<bool>;
     fn purchase_time(&self) -> DateTime<Utc>;
+    fn original_purchase_time(&self) -> Option<DateTime<Utc>>;
+    fn region_iso3166_alpha_2(&self) -> &str;
     fn region_iso3166_alpha_3(&self) -> &str;
     fn price_info(&self) -> Option<&PriceInfo>;
+    fn acknowledgement_deadline(&self) -> Option<DateTime<Utc>>;
+    fn account_id(&self) -> Option<&str>;
+    fn platform_extras(&self) -> &PlatformExtras;
+}
+
+impl<T: IapTypeSpecificDetails> IapDetails<T> {
+    #[deprecated(
+        since = "0.2.0",
+        note = "misspelled; use the 'canonical_id' field instead"
+    )]
+    pub fn cannonical_id(&self) -> &IapPurchaseId {
+        &self.canonical_id
+    }
 }
 
 impl<T: IapTypeSpecificDetails> IapGenericDetails for IapDetails<T> {
@@ -63,10 +318,18 @@ impl<T: IapTypeSpecificDetails> IapGenericDetails for IapDetails<T> {
         self.is_active
     }
 
+    fn is_pending(&self) -> bool {
+        self.is_pending
+    }
+
     fn is_sandbox(&self) -> bool {
         self.is_sandbox
     }
 
+    fn purchase_source(&self) -> PurchaseSource {
+        self.purchase_source
+    }
+
     fn is_finalized_by_client(&self) -> MaybeKnown<bool> {
         self.is_finalized_by_client.clone()
     }
@@ -75,6 +338,14 @@ impl<T: IapTypeSpecificDetails> IapGenericDetails for IapDetails<T> {
         self.purchase_time
     }
 
+    fn original_purchase_time(&self) -> Option<DateTime<Utc>> {
+        self.original_purchase_time
+    }
+
+    fn region_iso3166_alpha_2(&self) -> &str {
+        &self.region_iso3166_alpha_2
+    }
+
     fn region_iso3166_alpha_3(&self) -> &str {
         &self.region_iso3166_alpha_3
     }
@@ -82,4 +353,16 @@ impl<T: IapTypeSpecificDetails> IapGenericDetails for IapDetails<T> {
     fn price_info(&self) -> Option<&PriceInfo> {
         self.price_info.as_ref()
     }
+
+    fn acknowledgement_deadline(&self) -> Option<DateTime<Utc>> {
+        self.acknowledgement_deadline
+    }
+
+    fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    fn platform_extras(&self) -> &PlatformExtras {
+        &self.platform_extras
+    }
 }
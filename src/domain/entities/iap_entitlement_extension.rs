@@ -0,0 +1,40 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use super::iap_external_purchase::random_request_identifier;
+
+/// Extension of a subscription's current period, without charging the
+/// customer, ex. to compensate for a service outage (see
+/// 'IapUtil::extend_entitlement').
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntitlementExtension {
+    /// How long to extend the subscription by. The App Store only supports
+    /// whole days, between 1 and 90.
+    pub duration: Duration,
+    /// Reported to Apple (not used by Google Play).
+    pub reason: ExtensionReason,
+    /// Unique identifier (UUID) of the extension, sent to Apple to track the
+    /// request.
+    pub request_identifier: String,
+}
+
+impl EntitlementExtension {
+    /// Creates an extension by the given number of days, with a randomly
+    /// generated request identifier.
+    pub fn days(days: i64, reason: ExtensionReason) -> Self {
+        Self {
+            duration: Duration::days(days),
+            reason,
+            request_identifier: random_request_identifier(),
+        }
+    }
+}
+
+/// Why a subscription was extended, as reported to Apple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExtensionReason {
+    Undeclared,
+    CustomerSatisfaction,
+    ServiceIssue,
+    Other,
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    iap_details::PriceInfo,
+    iap_purchase_id::{AppleExternalPurchaseId, GoogleExternalTransactionId, IapPurchaseId},
+};
+
+/// Decoded Apple external purchase token, as returned by StoreKit on the
+/// device when the customer is sent to an alternative payment option (see
+/// 'IapUtil::decode_apple_external_purchase_token').
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppleExternalPurchaseToken {
+    /// Identifies the token in reports sent to Apple.
+    pub external_purchase_id: AppleExternalPurchaseId,
+    pub token_creation_time: DateTime<Utc>,
+    /// Not set for tokens created in the sandbox environment.
+    pub app_apple_id: Option<u64>,
+    pub bundle_id: String,
+}
+
+impl AppleExternalPurchaseToken {
+    pub fn purchase_id(&self) -> IapPurchaseId {
+        IapPurchaseId::AppleExternalPurchaseId(self.external_purchase_id.clone())
+    }
+}
+
+/// Report of transactions made through alternative payment options, sent to
+/// Apple with 'IapUtil::report_apple_external_purchases'.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalPurchaseReport {
+    /// Unique identifier (UUID) of the report, used to look up its processing
+    /// status. Resending a report with the same identifier replaces it.
+    pub request_identifier: String,
+    pub line_items: Vec<ExternalPurchaseLineItem>,
+}
+
+impl ExternalPurchaseReport {
+    /// Creates a report with a randomly generated request identifier.
+    pub fn new(line_items: Vec<ExternalPurchaseLineItem>) -> Self {
+        Self {
+            request_identifier: random_request_identifier(),
+            line_items,
+        }
+    }
+}
+
+/// Random (version 4) UUID, to identify requests sent to Apple.
+pub(crate) fn random_request_identifier() -> String {
+    let bytes = rand::random::<[u8; 16]>();
+    let hex = bytes
+        .iter()
+        .enumerate()
+        .map(|(i, b)| match i {
+            // Version 4, RFC 4122 variant.
+            6 => format!("{:02x}", (b & 0x0f) | 0x40),
+            8 => format!("{:02x}", (b & 0x3f) | 0x80),
+            _ => format!("{b:02x}"),
+        })
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalPurchaseLineItem {
+    /// The token the transaction was made under.
+    pub external_purchase_id: AppleExternalPurchaseId,
+    /// Unique identifier of the transaction in the developer's system.
+    pub line_item_id: String,
+    pub event_type: ExternalPurchaseEventType,
+    pub event_time: DateTime<Utc>,
+    /// Amount charged (or refunded), including taxes. Not set for
+    /// 'ExternalPurchaseEventType::NoPurchase'.
+    pub price_info: Option<PriceInfo>,
+    /// Storefront of the customer, as a 3-letter ISO 3166-1 code.
+    pub storefront_iso3166_alpha_3: String,
+    pub is_subscription: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExternalPurchaseEventType {
+    Purchase,
+    Refund,
+    /// The token was issued, but the customer did not make a purchase. Tokens
+    /// must still be reported, so that Apple does not consider them missing.
+    NoPurchase,
+}
+
+/// Processing status of a report, as returned by
+/// 'IapUtil::get_apple_external_purchase_report'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalPurchaseReportStatus {
+    pub request_identifier: String,
+    pub status: ExternalPurchaseReportState,
+    /// Errors reported by Apple for individual line items.
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExternalPurchaseReportState {
+    Pending,
+    Processed,
+    Failed,
+    Unknown(String),
+}
+
+/// Transaction made through Google Play's alternative billing programs (user
+/// choice billing), reported to Google with
+/// 'IapUtil::report_google_external_transaction'.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoogleExternalTransaction {
+    /// Developer-chosen ID, unique across all transactions of the app.
+    pub external_transaction_id: GoogleExternalTransactionId,
+    pub kind: GoogleExternalTransactionKind,
+    pub transaction_time: DateTime<Utc>,
+    pub pre_tax_price: PriceInfo,
+    pub tax: PriceInfo,
+    /// Country of the customer's tax address, as a 3-letter ISO 3166-1 code.
+    pub region_iso3166_alpha_3: String,
+}
+
+impl GoogleExternalTransaction {
+    pub fn purchase_id(&self) -> IapPurchaseId {
+        IapPurchaseId::GoogleExternalTransactionId(self.external_transaction_id.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GoogleExternalTransactionKind {
+    OneTime {
+        /// Token returned by the Play Billing Library on the device when the
+        /// alternative billing flow was launched.
+        external_transaction_token: String,
+    },
+    /// First payment of a subscription.
+    SubscriptionStart {
+        external_transaction_token: String,
+        /// Prepaid subscriptions are paid up front, rather than charged every
+        /// billing cycle.
+        is_prepaid: bool,
+    },
+    /// Subsequent payment of a subscription, referring to the transaction of
+    /// its first payment.
+    SubscriptionRenewal {
+        initial_external_transaction_id: GoogleExternalTransactionId,
+        is_prepaid: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GoogleExternalTransactionRefund {
+    Full,
+    Partial {
+        /// Unique among the refunds of the transaction.
+        refund_id: String,
+        pre_tax_price: PriceInfo,
+    },
+}
+
+/// A reported transaction, as known to Google.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoogleExternalTransactionDetails {
+    pub external_transaction_id: GoogleExternalTransactionId,
+    pub state: GoogleExternalTransactionState,
+    /// Google does not charge for test transactions.
+    pub is_test: bool,
+    pub transaction_time: DateTime<Utc>,
+    /// When the transaction was reported to Google.
+    pub create_time: Option<DateTime<Utc>>,
+    /// Amounts after any refunds.
+    pub current_pre_tax_price: Option<PriceInfo>,
+    pub current_tax: Option<PriceInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GoogleExternalTransactionState {
+    Reported,
+    /// Fully refunded.
+    Canceled,
+    Unknown(String),
+}
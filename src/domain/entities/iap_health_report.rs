@@ -0,0 +1,140 @@
+use fractic_server_error::ServerError;
+
+use crate::errors::CredentialValidationFailed;
+
+/// Result of 'IapUtil::health_check', per platform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IapHealthReport {
+    pub apple: PlatformHealth,
+    pub google: PlatformHealth,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlatformHealth {
+    Healthy,
+    /// The credentials could not be used to authenticate with the platform.
+    /// Either the credentials are invalid, or the platform could not be
+    /// reached; 'details' describes the failure.
+    Unhealthy {
+        details: String,
+    },
+}
+
+impl IapHealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.apple.is_healthy() && self.google.is_healthy()
+    }
+}
+
+impl PlatformHealth {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, PlatformHealth::Healthy)
+    }
+}
+
+impl From<Result<(), ServerError>> for PlatformHealth {
+    fn from(result: Result<(), ServerError>) -> Self {
+        match result {
+            Ok(()) => PlatformHealth::Healthy,
+            Err(e) => PlatformHealth::Unhealthy {
+                details: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Result of 'IapUtil::validate_credentials': the outcome of each check, in
+/// the order they ran.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CredentialReport {
+    pub checks: Vec<CredentialCheck>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CredentialCheck {
+    pub kind: CredentialCheckKind,
+    pub outcome: CheckOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialCheckKind {
+    /// The App Store Server API key is a valid ES256 private key (PEM).
+    AppleKeyParse,
+    /// Apple accepts a JWT signed with the key (authenticated callout).
+    AppleJwtAccepted,
+    /// The Google Play service account key is valid JSON, with a valid RSA
+    /// private key.
+    GoogleKeyParse,
+    /// Google mints an access token for the service account.
+    GoogleTokenMint,
+    /// The service account may access the app's purchases through the
+    /// androidpublisher API (authenticated callout).
+    GooglePublisherPermission,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckOutcome {
+    Passed,
+    Failed {
+        details: String,
+    },
+    /// Not run, since a check it depends on failed.
+    Skipped,
+}
+
+impl CredentialReport {
+    pub fn is_valid(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.outcome == CheckOutcome::Passed)
+    }
+
+    /// Checks which did not pass (failed or skipped).
+    pub fn failures(&self) -> impl Iterator<Item = &CredentialCheck> {
+        self.checks
+            .iter()
+            .filter(|check| check.outcome != CheckOutcome::Passed)
+    }
+
+    /// Fails with 'CredentialValidationFailed' listing the failed checks, if
+    /// any, ex. to abort a deployment.
+    pub fn into_result(self) -> Result<(), ServerError> {
+        if self.is_valid() {
+            return Ok(());
+        }
+        let details = self
+            .failures()
+            .map(|check| match &check.outcome {
+                CheckOutcome::Failed { details } => format!("{:?} failed ({details})", check.kind),
+                _ => format!("{:?} skipped", check.kind),
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        Err(CredentialValidationFailed::new(&details))
+    }
+}
+
+impl CredentialCheck {
+    pub(crate) fn new(kind: CredentialCheckKind, result: Result<(), ServerError>) -> Self {
+        Self {
+            kind,
+            outcome: match result {
+                Ok(()) => CheckOutcome::Passed,
+                Err(e) => CheckOutcome::Failed {
+                    details: e.to_string(),
+                },
+            },
+        }
+    }
+
+    pub(crate) fn skipped(kind: CredentialCheckKind) -> Self {
+        Self {
+            kind,
+            outcome: CheckOutcome::Skipped,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.outcome == CheckOutcome::Passed
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A notification the App Store sent (or tried to send) for the app, as
+/// reported by its notification history (see
+/// 'IapUtil::get_apple_notification_history').
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppleNotificationHistoryEntry {
+    /// Same as 'IapUpdateNotification::notification_id' of the parsed
+    /// notification.
+    pub notification_id: String,
+    /// Apple's notification type and subtype (ex. "DidRenew").
+    pub notification_type: String,
+    pub notification_subtype: Option<String>,
+    pub signed_time: DateTime<Utc>,
+    /// Apple's attempts to deliver the notification, oldest first.
+    pub send_attempts: Vec<NotificationSendAttempt>,
+    /// The notification's signed payload, as it would have been delivered.
+    pub signed_payload: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationSendAttempt {
+    pub time: DateTime<Utc>,
+    /// Apple's result of the attempt (ex. "SUCCESS", "TIMED_OUT").
+    pub result: String,
+}
+
+impl AppleNotificationHistoryEntry {
+    /// Whether any attempt to deliver the notification succeeded.
+    pub fn was_delivered(&self) -> bool {
+        self.send_attempts
+            .iter()
+            .any(|attempt| attempt.result == "SUCCESS")
+    }
+
+    /// The notification's POST body, as Apple delivers it, ex. to replay a
+    /// missed notification through 'IapUtil::parse_apple_notification' or
+    /// 'WebhookHandler::handle_apple'.
+    pub fn notification_body(&self) -> String {
+        serde_json::json!({ "signedPayload": self.signed_payload }).to_string()
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::iap_details::PriceInfo;
+
+/// Price of a product in one region, for the dates it applies, as scheduled
+/// in App Store Connect (see 'IapUtil::get_apple_price_schedule').
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledPrice {
+    /// 3-letter ISO 3166-1 code of the App Store storefront (as in
+    /// 'IapDetails::region_iso3166_alpha_3').
+    pub region_iso3166_alpha_3: String,
+    /// The price charged to customers, including taxes where applicable.
+    pub price_info: PriceInfo,
+    /// First day the price applies. Not set if it applied from the start.
+    pub start_date: Option<NaiveDate>,
+    /// First day the price no longer applies. Not set if no later price is
+    /// scheduled.
+    pub end_date: Option<NaiveDate>,
+}
+
+impl ScheduledPrice {
+    /// Whether the price applies on the given day.
+    pub fn applies_on(&self, date: NaiveDate) -> bool {
+        self.start_date.is_none_or(|start| start <= date)
+            && self.end_date.is_none_or(|end| date < end)
+    }
+}
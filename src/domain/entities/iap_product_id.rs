@@ -1,10 +1,12 @@
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IapNonConsumableId(pub String);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IapConsumableId(pub String);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IapSubscriptionId(pub String);
 
 // Internal type sugar:
@@ -18,13 +20,23 @@ pub(crate) mod private {
         fn sku(&self) -> &str;
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, PartialEq, Eq)]
     pub enum _ProductIdType {
         Subscription,
         Consumable,
         NonConsumable,
     }
 
+    impl _ProductIdType {
+        pub(crate) fn name(&self) -> &'static str {
+            match self {
+                _ProductIdType::Subscription => "subscription",
+                _ProductIdType::Consumable => "consumable",
+                _ProductIdType::NonConsumable => "non-consumable",
+            }
+        }
+    }
+
     impl IapProductId for IapSubscriptionId {
         fn product_type() -> _ProductIdType {
             _ProductIdType::Subscription
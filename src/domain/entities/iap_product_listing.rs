@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::iap_billing_period::BillingPeriod;
+
+/// Localized store listing of a product, as returned by
+/// 'IapUtil::get_product_listing'.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductListing {
+    /// BCP-47 locale of the listing (ex. 'en-US'). May differ from the
+    /// requested locale if the product is not localized for it, but is for
+    /// another region of the same language (or, for Google Play, falls back
+    /// to the product's default language).
+    pub locale: String,
+    /// Display name of the product.
+    pub title: String,
+    pub description: Option<String>,
+    /// Localized entitlement benefits. Only set for Google Play subscriptions.
+    pub benefits: Vec<String>,
+    /// Billing cycle of the subscription. Not set for one-time products.
+    pub billing_period: Option<BillingPeriod>,
+    /// Free trial configured on the product, if any. Only available for
+    /// Google Play products.
+    pub free_trial_period: Option<BillingPeriod>,
+    /// Grace period given to subscribers whose renewal payment is declined,
+    /// if any. Only available for Google Play products.
+    pub grace_period: Option<BillingPeriod>,
+    /// Tax and legal compliance settings of the product, ex. to apply the
+    /// right tax treatment on invoices. Only available for Google Play
+    /// products (the App Store Connect API does not report the tax category
+    /// of products or price points).
+    pub tax_settings: Option<TaxSettings>,
+}
+
+/// Tax and legal compliance settings of a product, as configured in the
+/// store (see 'ProductListing::tax_settings').
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxSettings {
+    /// Google Play product tax category code, which determines the
+    /// transaction tax rates applied to the product. Not set if the default
+    /// category applies.
+    pub tax_category_code: Option<String>,
+    /// Whether the product represents a tokenized digital asset.
+    pub is_tokenized_digital_asset: bool,
+    /// Classification of the product for withdrawal rights under consumer
+    /// laws of the European Economic Area, if declared.
+    pub eea_withdrawal_right: Option<EeaWithdrawalRight>,
+    /// Tax treatment in specific regions, keyed by 3-letter ISO 3166-1 code
+    /// (as in 'IapDetails::region_iso3166_alpha_3'). Regions not listed use
+    /// the standard treatment.
+    pub regional: HashMap<String, RegionalTaxSettings>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EeaWithdrawalRight {
+    /// The product is digital content.
+    DigitalContent,
+    /// The product is a service.
+    Service,
+    Unknown(String),
+}
+
+/// Tax treatment of a product in one region.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionalTaxSettings {
+    /// Reduced tax rate tier the product is eligible for, if any.
+    pub tax_tier: Option<TaxTier>,
+    /// Whether the product is a streaming product, subject to US state and
+    /// local sales tax rates for streaming services.
+    pub eligible_for_streaming_service_tax_rate: bool,
+    /// US communications or amusement tax category of the product, if any.
+    pub streaming_tax_type: Option<StreamingTaxType>,
+}
+
+/// Reduced tax rate tiers, ex. for digital news, books, or audiobooks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaxTier {
+    Books1,
+    News1,
+    News2,
+    MusicOrAudio1,
+    LiveOrBroadcast1,
+    Unknown(String),
+}
+
+/// US telecommunications tax categories of streaming products.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StreamingTaxType {
+    VideoRental,
+    VideoSales,
+    VideoMultiChannel,
+    AudioRental,
+    AudioSales,
+    AudioMultiChannel,
+    Unknown(String),
+}
@@ -1,14 +1,402 @@
-#[derive(Debug, Clone)]
+use std::fmt;
+
+use fractic_server_error::ServerError;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::InvalidPurchaseId;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum IapPurchaseId {
     /// The transaction ID from the Apple App Store.
     ///
     /// In the case of subscriptions, this should always be the 'original'
     /// transaction ID, not the transaction ID of the latest renewal.
-    AppStoreTransactionId(String),
+    AppStoreTransactionId(AppleTransactionId),
+
+    /// External purchase ID from an Apple external purchase token, for
+    /// purchases made through alternative payment options (External Purchase
+    /// / StoreKit External Link entitlements).
+    ///
+    /// These purchases have no App Store transaction, so they can not be
+    /// verified; instead, they are reported to Apple (see
+    /// 'IapUtil::report_apple_external_purchases').
+    AppleExternalPurchaseId(AppleExternalPurchaseId),
 
     /// Purchase token received on the device when purchasing an in-app-purchase
     /// with the Google Play Store.
     ///
     /// In the case of subscriptions, this ID does not change accross renewals.
-    GooglePlayPurchaseToken(String),
+    GooglePlayPurchaseToken(GooglePurchaseToken),
+
+    /// Developer-chosen ID of a transaction made through Google Play's
+    /// alternative billing programs (user choice billing), and reported to
+    /// Google with 'IapUtil::report_google_external_transaction'.
+    ///
+    /// These purchases are not processed by Google Play, so they can not be
+    /// verified.
+    GoogleExternalTransactionId(GoogleExternalTransactionId),
+
+    /// Microsoft Store ID key (for the collections API) of the user who made
+    /// the purchase, generated by the client.
+    ///
+    /// Since the key identifies the user rather than a single purchase, the
+    /// purchase is looked up by the key together with the product ID.
+    #[cfg(feature = "microsoft-store")]
+    MicrosoftStoreIdKey(MicrosoftStoreIdKey),
+
+    /// Order ID of a Steam microtransaction (chosen by the developer when the
+    /// transaction was initiated).
+    #[cfg(feature = "steam")]
+    SteamOrderId(SteamOrderId),
+
+    /// ID of a Paddle Billing subscription, for subscriptions sold on the
+    /// web.
+    ///
+    /// Paddle subscriptions are only tracked through webhook notifications
+    /// (see 'IapUtil::parse_paddle_notification'), so can not be verified
+    /// directly.
+    #[cfg(feature = "paddle")]
+    PaddleSubscriptionId(PaddleSubscriptionId),
+
+    /// ID of a Stripe subscription, for subscriptions sold on the web.
+    ///
+    /// Stripe subscriptions are only tracked through webhook events (see
+    /// 'IapUtil::parse_stripe_notification'), so can not be verified
+    /// directly.
+    #[cfg(feature = "stripe")]
+    StripeSubscriptionId(StripeSubscriptionId),
+}
+
+/// Result of 'IapUtil::canonical_purchase_id'.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanonicalPurchaseId {
+    /// Same as 'IapDetails::canonical_id' of the verified purchase.
+    pub canonical_id: IapPurchaseId,
+    /// For Google Play subscriptions, the purchase tokens of the earlier
+    /// purchases this one replaced (ex. upgrades, or resubscribing after a
+    /// cancellation), most recent first. Only includes purchases still known
+    /// to Google. Records keyed by these should be moved to 'canonical_id'.
+    pub linked_ids: Vec<IapPurchaseId>,
+}
+
+/// Transaction identifier issued by the Apple App Store.
+///
+/// Apple transaction IDs are always numeric strings, so values which are
+/// obviously malformed (ex. empty, or a Google purchase token passed by
+/// mistake) are rejected on construction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AppleTransactionId(String);
+
+impl AppleTransactionId {
+    pub fn new(id: impl Into<String>) -> Result<Self, ServerError> {
+        let id = id.into();
+        if id.is_empty() {
+            return Err(InvalidPurchaseId::new("Apple transaction ID is empty"));
+        }
+        if !id.chars().all(|c| c.is_ascii_digit()) {
+            return Err(InvalidPurchaseId::new(
+                "Apple transaction ID must only contain digits",
+            ));
+        }
+        Ok(Self(id))
+    }
+
+    /// Skips validation, for IDs received directly from Apple.
+    pub(crate) fn new_unchecked(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for AppleTransactionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// External purchase ID issued by Apple, as found in external purchase tokens
+/// (see 'IapUtil::decode_apple_external_purchase_token').
+///
+/// IDs are UUIDs, so values containing anything other than hexadecimal digits
+/// and hyphens are rejected on construction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AppleExternalPurchaseId(String);
+
+impl AppleExternalPurchaseId {
+    pub fn new(id: impl Into<String>) -> Result<Self, ServerError> {
+        let id = id.into();
+        if id.is_empty() {
+            return Err(InvalidPurchaseId::new(
+                "Apple external purchase ID is empty",
+            ));
+        }
+        if !id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Err(InvalidPurchaseId::new(
+                "Apple external purchase ID must be a UUID",
+            ));
+        }
+        Ok(Self(id))
+    }
+
+    /// Skips validation, for IDs received directly from Apple.
+    pub(crate) fn new_unchecked(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for AppleExternalPurchaseId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Purchase token issued by the Google Play Store.
+///
+/// Tokens are opaque, but always URL-safe (they are embedded directly in the
+/// Google Play Developer API request path), so values containing whitespace or
+/// URL delimiters are rejected on construction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GooglePurchaseToken(String);
+
+impl GooglePurchaseToken {
+    pub fn new(token: impl Into<String>) -> Result<Self, ServerError> {
+        let token = token.into();
+        if token.is_empty() {
+            return Err(InvalidPurchaseId::new("Google purchase token is empty"));
+        }
+        if !token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        {
+            return Err(InvalidPurchaseId::new(
+                "Google purchase token contains invalid characters",
+            ));
+        }
+        Ok(Self(token))
+    }
+
+    /// Skips validation, for tokens received directly from Google.
+    pub(crate) fn new_unchecked(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for GooglePurchaseToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Developer-chosen ID of a Google Play external transaction.
+///
+/// Google requires IDs to be 1-63 characters, containing only alphanumerics,
+/// underscores and hyphens, so other values are rejected on construction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GoogleExternalTransactionId(String);
+
+impl GoogleExternalTransactionId {
+    pub fn new(id: impl Into<String>) -> Result<Self, ServerError> {
+        let id = id.into();
+        if id.is_empty() || id.len() > 63 {
+            return Err(InvalidPurchaseId::new(
+                "Google external transaction ID must be 1-63 characters",
+            ));
+        }
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        {
+            return Err(InvalidPurchaseId::new(
+                "Google external transaction ID contains invalid characters",
+            ));
+        }
+        Ok(Self(id))
+    }
+
+    /// Skips validation, for IDs received directly from Google.
+    pub(crate) fn new_unchecked(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for GoogleExternalTransactionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Microsoft Store ID key, generated on the client with
+/// 'StoreContext.GetCustomerCollectionsIdAsync':
+/// https://learn.microsoft.com/en-us/windows/uwp/monetize/view-and-grant-products-from-a-service#step-4
+///
+/// Keys are JWTs, so values which are obviously malformed (ex. empty, or
+/// containing characters outside of the base64url alphabet) are rejected on
+/// construction.
+#[cfg(feature = "microsoft-store")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MicrosoftStoreIdKey(String);
+
+#[cfg(feature = "microsoft-store")]
+impl MicrosoftStoreIdKey {
+    pub fn new(key: impl Into<String>) -> Result<Self, ServerError> {
+        let key = key.into();
+        if key.is_empty() {
+            return Err(InvalidPurchaseId::new("Microsoft Store ID key is empty"));
+        }
+        if !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        {
+            return Err(InvalidPurchaseId::new(
+                "Microsoft Store ID key contains invalid characters",
+            ));
+        }
+        Ok(Self(key))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "microsoft-store")]
+impl fmt::Display for MicrosoftStoreIdKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Order ID of a Steam microtransaction. Order IDs are unsigned 64-bit
+/// integers, so values which do not parse as such are rejected on
+/// construction.
+#[cfg(feature = "steam")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SteamOrderId(u64);
+
+#[cfg(feature = "steam")]
+impl SteamOrderId {
+    pub fn new(id: impl AsRef<str>) -> Result<Self, ServerError> {
+        let id = id.as_ref();
+        if id.is_empty() {
+            return Err(InvalidPurchaseId::new("Steam order ID is empty"));
+        }
+        id.parse().map(Self).map_err(|_| {
+            InvalidPurchaseId::new("Steam order ID must be an unsigned 64-bit integer")
+        })
+    }
+
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(feature = "steam")]
+impl From<u64> for SteamOrderId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+#[cfg(feature = "steam")]
+impl fmt::Display for SteamOrderId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// ID of a Paddle Billing subscription. IDs are prefixed with 'sub_', followed
+/// by alphanumeric characters, so values which do not match (ex. a customer or
+/// transaction ID passed by mistake) are rejected on construction.
+#[cfg(feature = "paddle")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PaddleSubscriptionId(String);
+
+#[cfg(feature = "paddle")]
+impl PaddleSubscriptionId {
+    pub fn new(id: impl Into<String>) -> Result<Self, ServerError> {
+        let id = id.into();
+        let Some(suffix) = id.strip_prefix("sub_") else {
+            return Err(InvalidPurchaseId::new(
+                "Paddle subscription ID must start with 'sub_'",
+            ));
+        };
+        if suffix.is_empty() || !suffix.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(InvalidPurchaseId::new(
+                "Paddle subscription ID contains invalid characters",
+            ));
+        }
+        Ok(Self(id))
+    }
+
+    /// Skips validation, for IDs received directly from Paddle.
+    pub(crate) fn new_unchecked(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "paddle")]
+impl fmt::Display for PaddleSubscriptionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// ID of a Stripe subscription. IDs are prefixed with 'sub_', followed by
+/// alphanumeric characters, so values which do not match (ex. a customer or
+/// invoice ID passed by mistake) are rejected on construction.
+#[cfg(feature = "stripe")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StripeSubscriptionId(String);
+
+#[cfg(feature = "stripe")]
+impl StripeSubscriptionId {
+    pub fn new(id: impl Into<String>) -> Result<Self, ServerError> {
+        let id = id.into();
+        let Some(suffix) = id.strip_prefix("sub_") else {
+            return Err(InvalidPurchaseId::new(
+                "Stripe subscription ID must start with 'sub_'",
+            ));
+        };
+        if suffix.is_empty() || !suffix.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(InvalidPurchaseId::new(
+                "Stripe subscription ID contains invalid characters",
+            ));
+        }
+        Ok(Self(id))
+    }
+
+    /// Skips validation, for IDs received directly from Stripe.
+    pub(crate) fn new_unchecked(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "stripe")]
+impl fmt::Display for StripeSubscriptionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    iap_consumption::ConsumptionRequestReason, iap_purchase_id::IapPurchaseId,
+    iap_update_notification::NotificationDetails,
+};
+
+/// Refund-relevant signals about a customer, aggregated from their App Store
+/// transaction history (see 'IapUtil::get_apple_refund_risk_profile'), for
+/// abuse-prevention heuristics.
+///
+/// Apple's transaction history covers all of the customer's transactions in
+/// the app, so the profile describes the customer rather than the single
+/// purchase it was looked up by.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefundRiskProfile {
+    /// The purchase the profile was looked up by.
+    pub purchase_id: IapPurchaseId,
+    /// Number of transactions (purchases and renewals) in the history.
+    pub transaction_count: u32,
+    /// Total quantity purchased, across all transactions (transactions of
+    /// non-consumables and subscriptions count as one).
+    pub purchased_quantity: i64,
+    /// Number of transactions refunded or revoked by Apple.
+    pub refund_count: u32,
+    /// Total quantity of the refunded transactions.
+    pub refunded_quantity: i64,
+    /// The reason of each refunded transaction, oldest first.
+    pub refund_reasons: Vec<RefundReason>,
+    pub last_refund_time: Option<DateTime<Utc>>,
+    /// Reasons given in consumption requests (ie. refund requests) for the
+    /// customer's purchases. Apple does not keep these in the transaction
+    /// history, so they are only included once recorded with
+    /// 'record_notification'.
+    #[serde(default)]
+    pub consumption_request_reasons: Vec<ConsumptionRequestReason>,
+}
+
+/// Why Apple refunded (or revoked) a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefundReason {
+    /// Refunded due to an actual or perceived issue within the app.
+    AppIssue,
+    /// Refunded for other reasons (ex. an accidental purchase), or revoked
+    /// from Family Sharing.
+    Other,
+}
+
+impl RefundRiskProfile {
+    /// Fraction of the customer's transactions which were refunded (0 if
+    /// there are none).
+    pub fn refund_rate(&self) -> f64 {
+        match self.transaction_count {
+            0 => 0.0,
+            count => self.refund_count as f64 / count as f64,
+        }
+    }
+
+    /// Record the signals carried by a notification for one of the
+    /// customer's purchases. Currently, this only records the reason of
+    /// consumption requests; refunds are already reflected in the transaction
+    /// history.
+    pub fn record_notification(&mut self, details: &NotificationDetails) {
+        if let NotificationDetails::ConsumptionRequested {
+            reason: Some(reason),
+            ..
+        } = details
+        {
+            self.consumption_request_reasons.push(reason.clone());
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{iap_billing_period::BillingPeriod, iap_details::PriceInfo};
+
+/// A base plan of a Google Play subscription, with its offers, as configured
+/// in the Play Console (see 'IapUtil::get_google_subscription_plans'), ex. to
+/// render offer terms on a paywall.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionBasePlan {
+    pub base_plan_id: String,
+    /// Whether the plan renews automatically, as opposed to prepaid plans
+    /// which the customer must top up.
+    pub is_auto_renewing: bool,
+    /// The period each payment of the recurring price covers.
+    pub billing_period: Option<BillingPeriod>,
+    /// The recurring price, by 3-letter ISO 3166-1 region code (as in
+    /// 'IapDetails::region_iso3166_alpha_3'). Regions the plan is not
+    /// available in are omitted.
+    pub regional_prices: HashMap<String, PriceInfo>,
+    pub tags: Vec<String>,
+    /// The plan's active offers.
+    pub offers: Vec<SubscriptionOffer>,
+}
+
+/// An offer of a 'SubscriptionBasePlan', whose phases apply before the base
+/// plan's recurring price.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionOffer {
+    pub offer_id: String,
+    /// Tags of the offer, including those inherited from the base plan.
+    pub tags: Vec<String>,
+    /// The phases the customer goes through, in order.
+    pub phases: Vec<PricingPhase>,
+}
+
+/// A phase of a 'SubscriptionOffer' (ex. a free trial, or an introductory
+/// price).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PricingPhase {
+    pub kind: PricingPhaseKind,
+    /// How long each recurrence of the phase lasts.
+    pub duration: Option<BillingPeriod>,
+    /// How many times the phase repeats (ex. 3 monthly payments at the
+    /// introductory price).
+    pub recurrence_count: u32,
+    /// The price of each recurrence, by 3-letter ISO 3166-1 region code.
+    /// Discounts relative to the base plan's price are resolved against its
+    /// price in the region, prorated over the phase's duration (so may differ
+    /// slightly from the price Google rounds to). Free phases have a price of
+    /// 0.
+    pub regional_prices: HashMap<String, PriceInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PricingPhaseKind {
+    /// The phase is free in all regions.
+    FreeTrial,
+    /// The phase is discounted.
+    IntroductoryPrice,
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    iap_details::{IapDetails, PriceInfo, SubscriptionDetails},
+    iap_product_id::IapSubscriptionId,
+};
+
+/// The customer's App Store subscriptions in one subscription group (see
+/// 'IapUtil::get_subscription_statuses').
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppleSubscriptionGroupStatus {
+    pub subscription_group_id: String,
+    /// The latest state of each of the customer's subscriptions in the group
+    /// (usually one, unless some were bought through Family Sharing).
+    pub subscriptions: Vec<AppleSubscriptionStatus>,
+}
+
+/// The latest state of an App Store subscription, from its latest
+/// transaction and renewal info.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppleSubscriptionStatus {
+    pub status: AppleSubscriptionState,
+    /// The product of the latest transaction.
+    pub product_id: IapSubscriptionId,
+    /// The details of the latest transaction. Unlike when verifying the
+    /// subscription, 'SubscriptionDetails::is_in_billing_retry' is always
+    /// known.
+    pub details: IapDetails<SubscriptionDetails>,
+    /// Whether the subscription renews at the end of the current period.
+    pub will_auto_renew: bool,
+    /// The product the subscription renews to, which differs from
+    /// 'product_id' if the customer downgraded (or crossgraded) to take
+    /// effect at the next renewal.
+    pub auto_renew_product_id: IapSubscriptionId,
+    /// The price of the next renewal, including any offer it renews with.
+    pub renewal_price: Option<PriceInfo>,
+    /// When the grace period ends, if the subscription is in one.
+    pub grace_period_expiration_time: Option<DateTime<Utc>>,
+}
+
+/// Apple's status of an auto-renewable subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppleSubscriptionState {
+    Active,
+    Expired,
+    /// Renewal failed, and Apple is retrying the payment; the customer no
+    /// longer has access.
+    BillingRetry,
+    /// Renewal failed, and Apple is retrying the payment; the customer keeps
+    /// access until the grace period ends.
+    BillingGracePeriod,
+    /// Apple refunded or revoked the subscription.
+    Revoked,
+}
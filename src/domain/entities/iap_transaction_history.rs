@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{iap_details::PriceInfo, iap_purchase_id::AppleTransactionId};
+
+/// A transaction from a customer's App Store transaction history (see
+/// 'IapUtil::get_apple_transaction_history'): a purchase, renewal or restore
+/// of any of the app's products.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppleTransaction {
+    pub transaction_id: AppleTransactionId,
+    /// Same for all transactions of a purchase (ex. renewals of a
+    /// subscription); see 'IapDetails::canonical_id'.
+    pub original_transaction_id: AppleTransactionId,
+    pub product_id: String,
+    /// None for product types not known to this library.
+    pub product_type: Option<AppleProductType>,
+    pub subscription_group_id: Option<String>,
+    pub purchase_time: DateTime<Utc>,
+    /// For subscriptions, when the period bought by the transaction ends.
+    pub expiration_time: Option<DateTime<Utc>>,
+    /// Set if Apple refunded or revoked the transaction.
+    pub revocation_time: Option<DateTime<Utc>>,
+    pub quantity: i64,
+    /// Whether the customer has access through Family Sharing, rather than
+    /// having bought the product themselves.
+    pub is_family_shared: bool,
+    pub is_sandbox: bool,
+    /// The 'appAccountToken' the app attached to the purchase, if any (see
+    /// 'IapDetails::account_id').
+    pub account_id: Option<String>,
+    /// None for older transactions, which do not report their price.
+    pub price_info: Option<PriceInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AppleProductType {
+    AutoRenewableSubscription,
+    NonRenewingSubscription,
+    Consumable,
+    NonConsumable,
+}
+
+/// Which transactions 'IapUtil::get_apple_transaction_history' returns. By
+/// default, all of the customer's transactions are included.
+///
+/// Apple omits consumables which were finished by the app from the history,
+/// unless they were refunded or revoked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppleTransactionHistoryFilters {
+    /// Only include transactions purchased at or after this time.
+    pub start_time: Option<DateTime<Utc>>,
+    /// Only include transactions purchased before this time.
+    pub end_time: Option<DateTime<Utc>>,
+    /// Only include transactions of these products (all if empty).
+    pub product_ids: Vec<String>,
+    /// Only include transactions of these product types (all if empty).
+    pub product_types: Vec<AppleProductType>,
+    /// Only include transactions of subscriptions in these subscription
+    /// groups (all if empty).
+    pub subscription_group_ids: Vec<String>,
+    /// Only include transactions shared through Family Sharing (true), or
+    /// bought by the customer (false).
+    pub family_shared: Option<bool>,
+    /// Only include revoked (true), or non-revoked (false) transactions.
+    pub revoked: Option<bool>,
+}
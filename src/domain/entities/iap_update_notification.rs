@@ -1,19 +1,28 @@
 use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::InvalidNotificationEnvelope;
 
 use super::{
-    iap_details::{ConsumableDetails, IapDetails, NonConsumableDetails, SubscriptionDetails},
+    iap_billing_period::BillingPeriod,
+    iap_consumption::ConsumptionRequestReason,
+    iap_details::{
+        ConsumableDetails, IapDetails, MaybeKnown, NonConsumableDetails, PriceInfo,
+        SubscriptionDetails,
+    },
     iap_product_id::{IapConsumableId, IapNonConsumableId, IapSubscriptionId},
     iap_purchase_id::IapPurchaseId,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IapUpdateNotification {
     pub notification_id: String,
     pub time: DateTime<Utc>,
     pub details: NotificationDetails,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NotificationDetails {
     Test,
     ConsumableVoided {
@@ -38,11 +47,35 @@ pub enum NotificationDetails {
         is_refunded: bool,
         reason: Option<String>,
     },
+    /// A Google Play one-time purchase was made, or a pending one (awaiting
+    /// payment, ex. in cash at a store) completed or was canceled. Purchases
+    /// should be fulfilled when their state is 'Completed'; verify them
+    /// first (see 'IapUtil::verify_purchase') to know whether they are
+    /// consumables or non-consumables.
+    OneTimePurchaseStateChanged {
+        application_id: String,
+        /// Product ID (SKU) of the purchase.
+        product_sku: String,
+        purchase_id: IapPurchaseId,
+        state: OneTimePurchaseState,
+        /// See 'IapDetails::account_id'. Not available for canceled
+        /// purchases.
+        account_id: Option<String>,
+    },
     SubscriptionStarted {
         application_id: String,
         product_id: IapSubscriptionId,
         purchase_id: IapPurchaseId,
         details: IapDetails<SubscriptionDetails>,
+        /// How the customer came to (again) have the subscription, ex. to
+        /// treat resubscribers differently from new subscribers.
+        #[serde(default)]
+        start_kind: SubscriptionStartKind,
+        /// Price the subscription renews at in the next billing period, if
+        /// reported by the store (currently only the App Store, from the
+        /// renewal info included in the notification). Useful to track cohorts
+        /// affected by a price increase.
+        renewal_price: Option<PriceInfo>,
     },
     SubscriptionEnded {
         application_id: String,
@@ -50,6 +83,49 @@ pub enum NotificationDetails {
         purchase_id: IapPurchaseId,
         details: IapDetails<SubscriptionDetails>,
         reason: SubscriptionEndReason,
+        /// See 'SubscriptionStarted::renewal_price'.
+        renewal_price: Option<PriceInfo>,
+    },
+    /// The subscription was paused, and is not renewed until it resumes (at
+    /// which point a 'SubscriptionStarted' notification is sent, with
+    /// 'SubscriptionStartKind::Recovered'). Unlike
+    /// 'SubscriptionEnded', the subscription is expected to resume, so access
+    /// should be suspended rather than revoked.
+    SubscriptionPaused {
+        application_id: String,
+        product_id: IapSubscriptionId,
+        purchase_id: IapPurchaseId,
+        details: IapDetails<SubscriptionDetails>,
+        /// When the subscription automatically resumes, if scheduled.
+        auto_resume_time: Option<DateTime<Utc>>,
+        /// See 'SubscriptionStarted::renewal_price'.
+        renewal_price: Option<PriceInfo>,
+    },
+    /// The customer switched the subscription to another product (App Store
+    /// upgrades, downgrades and cross-grades within the subscription group),
+    /// or to another Google Play base plan.
+    ///
+    /// For Google Play, the switch creates a new purchase, which is reported
+    /// with this notification instead of 'SubscriptionStarted' (the previous
+    /// purchase ends with 'SubscriptionEndReason::Replaced').
+    SubscriptionPlanChanged {
+        application_id: String,
+        /// The product the subscription is on after the change.
+        product_id: IapSubscriptionId,
+        purchase_id: IapPurchaseId,
+        details: IapDetails<SubscriptionDetails>,
+        /// The product before the change. Not reported for App Store
+        /// upgrades (which include cross-grades to the same billing period).
+        previous_product_id: Option<IapSubscriptionId>,
+        /// Billing periods before and after the change, if known, ex. to
+        /// detect cross-grades between monthly and yearly plans. Looked up in
+        /// the store's catalog; for the App Store, only if App Store Connect
+        /// credentials are configured.
+        previous_billing_period: Option<BillingPeriod>,
+        billing_period: Option<BillingPeriod>,
+        takes_effect: PlanChangeTiming,
+        /// See 'SubscriptionStarted::renewal_price'.
+        renewal_price: Option<PriceInfo>,
     },
     /// Any events that change the expiry of a subscription. This is most
     /// commonly renewal, but also includes things like grace periods.
@@ -62,16 +138,252 @@ pub enum NotificationDetails {
         /// differ from the type of identifier used for 'purchase_id').
         renewal_id: Option<String>,
         details: IapDetails<SubscriptionDetails>,
+        /// Whether this renewal converted the subscription from a free trial
+        /// or introductory offer to a paid period, for conversion tracking.
+        /// Unknown if the platform does not report the previous period's
+        /// offer (ex. after multi-period introductory prices).
+        is_offer_conversion: MaybeKnown<bool>,
+        /// See 'SubscriptionStarted::renewal_price'.
+        renewal_price: Option<PriceInfo>,
+    },
+    /// Apple created an external purchase token (the customer was sent to an
+    /// alternative payment option), but it has not been reported yet (see
+    /// 'IapUtil::report_apple_external_purchases').
+    ExternalPurchaseTokenUnreported {
+        application_id: String,
+        purchase_id: IapPurchaseId,
+        token_creation_time: DateTime<Utc>,
+    },
+    /// The customer requested a refund of an App Store purchase, and Apple
+    /// requests information about its consumption (see
+    /// 'IapUtil::send_apple_consumption_info', or 'consumption' for a helper
+    /// responding automatically).
+    ConsumptionRequested {
+        application_id: String,
+        /// Product ID (SKU) of the purchase. Consumption requests are sent
+        /// for consumables, non-consumables and subscriptions alike.
+        product_sku: String,
+        purchase_id: IapPurchaseId,
+        /// See 'IapDetails::account_id'.
+        account_id: Option<String>,
+        reason: Option<ConsumptionRequestReason>,
+        /// Information sent after this time is not considered by Apple.
+        deadline: DateTime<Utc>,
     },
-    Other,
+    Other {
+        /// For Google notifications of a category not recognized by this
+        /// library, the unrecognized top-level fields of the payload. For
+        /// notifications read with 'IapUpdateNotification::from_envelope',
+        /// the details of a notification type not known to this version of
+        /// the library.
+        raw: Option<serde_json::Value>,
+    },
+}
+
+/// Version of the envelope format written by
+/// 'IapUpdateNotification::to_envelope'. Only bumped for incompatible
+/// changes; new notification types and fields are added without a bump.
+pub const NOTIFICATION_ENVELOPE_VERSION: u32 = 1;
+
+/// Wire format of 'IapUpdateNotification::to_envelope'.
+#[derive(Serialize, Deserialize)]
+struct NotificationEnvelope {
+    schema_version: u32,
+    notification_id: String,
+    time: DateTime<Utc>,
+    details: serde_json::Value,
 }
 
-#[derive(Debug, Clone)]
+impl IapUpdateNotification {
+    /// Serialize the notification into a versioned JSON envelope, ex. to
+    /// queue it for processing by another service. Read it back with
+    /// 'from_envelope', which also accepts envelopes written by newer
+    /// versions of the library (see 'NOTIFICATION_ENVELOPE_VERSION').
+    pub fn to_envelope(&self) -> Result<String, ServerError> {
+        let envelope = NotificationEnvelope {
+            schema_version: NOTIFICATION_ENVELOPE_VERSION,
+            notification_id: self.notification_id.clone(),
+            time: self.time,
+            details: serde_json::to_value(&self.details).map_err(|e| {
+                InvalidNotificationEnvelope::with_debug("failed to serialize details", &e)
+            })?,
+        };
+        serde_json::to_string(&envelope).map_err(|e| {
+            InvalidNotificationEnvelope::with_debug("failed to serialize envelope", &e)
+        })
+    }
+
+    /// Parse an envelope written by 'to_envelope'.
+    ///
+    /// Details which this version of the library can not parse (ex. a
+    /// notification type added in a newer version) are returned as
+    /// 'NotificationDetails::Other', with the details' JSON as 'raw'.
+    /// Envelopes with a newer (incompatible) schema version are rejected.
+    pub fn from_envelope(envelope: &str) -> Result<Self, ServerError> {
+        let envelope: NotificationEnvelope = serde_json::from_str(envelope)
+            .map_err(|e| InvalidNotificationEnvelope::with_debug("failed to parse envelope", &e))?;
+        if envelope.schema_version > NOTIFICATION_ENVELOPE_VERSION {
+            return Err(InvalidNotificationEnvelope::new(&format!(
+                "unsupported schema version {} (latest supported: {})",
+                envelope.schema_version, NOTIFICATION_ENVELOPE_VERSION
+            )));
+        }
+        let details = serde_json::from_value(envelope.details.clone()).unwrap_or(
+            NotificationDetails::Other {
+                raw: Some(envelope.details),
+            },
+        );
+        Ok(Self {
+            notification_id: envelope.notification_id,
+            time: envelope.time,
+            details,
+        })
+    }
+}
+
+impl NotificationDetails {
+    /// The purchase the notification relates to, if any.
+    pub fn purchase_id(&self) -> Option<&IapPurchaseId> {
+        match self {
+            NotificationDetails::ConsumableVoided { purchase_id, .. }
+            | NotificationDetails::NonConsumableVoided { purchase_id, .. }
+            | NotificationDetails::UnknownOneTimePurchaseVoided { purchase_id, .. }
+            | NotificationDetails::OneTimePurchaseStateChanged { purchase_id, .. }
+            | NotificationDetails::SubscriptionStarted { purchase_id, .. }
+            | NotificationDetails::SubscriptionEnded { purchase_id, .. }
+            | NotificationDetails::SubscriptionPaused { purchase_id, .. }
+            | NotificationDetails::SubscriptionPlanChanged { purchase_id, .. }
+            | NotificationDetails::SubscriptionExpiryChanged { purchase_id, .. }
+            | NotificationDetails::ExternalPurchaseTokenUnreported { purchase_id, .. }
+            | NotificationDetails::ConsumptionRequested { purchase_id, .. } => Some(purchase_id),
+            NotificationDetails::Test | NotificationDetails::Other { .. } => None,
+        }
+    }
+
+    /// Identifier of the customer's account in the app, if the app attached
+    /// one to the purchase (see 'IapDetails::account_id'), ex. to route the
+    /// notification to the user without looking up the purchase.
+    pub fn account_id(&self) -> Option<&str> {
+        match self {
+            NotificationDetails::ConsumableVoided { details, .. } => details.account_id.as_deref(),
+            NotificationDetails::NonConsumableVoided { details, .. } => {
+                details.account_id.as_deref()
+            }
+            NotificationDetails::SubscriptionStarted { details, .. }
+            | NotificationDetails::SubscriptionEnded { details, .. }
+            | NotificationDetails::SubscriptionPaused { details, .. }
+            | NotificationDetails::SubscriptionPlanChanged { details, .. }
+            | NotificationDetails::SubscriptionExpiryChanged { details, .. } => {
+                details.account_id.as_deref()
+            }
+            NotificationDetails::OneTimePurchaseStateChanged { account_id, .. }
+            | NotificationDetails::ConsumptionRequested { account_id, .. } => account_id.as_deref(),
+            NotificationDetails::Test
+            | NotificationDetails::UnknownOneTimePurchaseVoided { .. }
+            | NotificationDetails::ExternalPurchaseTokenUnreported { .. }
+            | NotificationDetails::Other { .. } => None,
+        }
+    }
+
+    /// Name of the notification type (the variant, ex.
+    /// "SubscriptionStarted"), ex. to aggregate notifications by type.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            NotificationDetails::Test => "Test",
+            NotificationDetails::ConsumableVoided { .. } => "ConsumableVoided",
+            NotificationDetails::NonConsumableVoided { .. } => "NonConsumableVoided",
+            NotificationDetails::UnknownOneTimePurchaseVoided { .. } => {
+                "UnknownOneTimePurchaseVoided"
+            }
+            NotificationDetails::OneTimePurchaseStateChanged { .. } => {
+                "OneTimePurchaseStateChanged"
+            }
+            NotificationDetails::SubscriptionStarted { .. } => "SubscriptionStarted",
+            NotificationDetails::SubscriptionEnded { .. } => "SubscriptionEnded",
+            NotificationDetails::SubscriptionPaused { .. } => "SubscriptionPaused",
+            NotificationDetails::SubscriptionPlanChanged { .. } => "SubscriptionPlanChanged",
+            NotificationDetails::SubscriptionExpiryChanged { .. } => "SubscriptionExpiryChanged",
+            NotificationDetails::ExternalPurchaseTokenUnreported { .. } => {
+                "ExternalPurchaseTokenUnreported"
+            }
+            NotificationDetails::ConsumptionRequested { .. } => "ConsumptionRequested",
+            NotificationDetails::Other { .. } => "Other",
+        }
+    }
+
+    /// For 'SubscriptionPlanChanged', whether the change affects the billing
+    /// period (ex. a cross-grade from a monthly to a yearly plan). Unknown if
+    /// either billing period is not known.
+    pub fn is_billing_period_change(&self) -> MaybeKnown<bool> {
+        match self {
+            NotificationDetails::SubscriptionPlanChanged {
+                previous_billing_period: Some(previous),
+                billing_period: Some(current),
+                ..
+            } => MaybeKnown::Known(previous != current),
+            NotificationDetails::SubscriptionPlanChanged { .. } => MaybeKnown::Unknown,
+            _ => MaybeKnown::Known(false),
+        }
+    }
+}
+
+/// State of a one-time purchase, see
+/// 'NotificationDetails::OneTimePurchaseStateChanged'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OneTimePurchaseState {
+    /// The purchase was made, but is awaiting payment. It should not be
+    /// fulfilled yet.
+    Pending,
+    /// The purchase was paid (either immediately, or after being pending),
+    /// and should be fulfilled.
+    Completed,
+    /// The pending purchase was canceled before it was paid (by the customer,
+    /// or because the payment deadline passed).
+    Canceled,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubscriptionStartKind {
+    /// The customer subscribed for the first time.
+    #[default]
+    InitialBuy,
+    /// The customer subscribed again after a previous subscription (in the
+    /// same subscription group) ended.
+    Resubscribe,
+    /// The customer gained access through App Store Family Sharing.
+    FamilyShareGained,
+    /// A subscription which ended after failing to renew (Apple's billing
+    /// retry, Google's account hold) was renewed after all, or a paused
+    /// Google Play subscription resumed. Also counts as a renewal.
+    Recovered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlanChangeTiming {
+    /// The new plan applies right away, starting a new billing period.
+    Immediately,
+    /// The current billing period finishes on the previous plan, and the
+    /// subscription renews on the new one.
+    AtNextRenewal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SubscriptionEndReason {
-    Paused,
-    Cancelled { details: Option<String> },
+    Cancelled {
+        details: Option<String>,
+    },
     FailedToRenew,
-    Voided { is_refunded: bool },
+    Voided {
+        is_refunded: bool,
+    },
     DeclinedPriceIncrease,
+    /// The subscription was replaced by a new one (ex. an upgrade or
+    /// downgrade), so access should carry over to the new subscription rather
+    /// than be revoked. The new subscription is reported with its own
+    /// 'SubscriptionStarted' (or 'SubscriptionPlanChanged') notification.
+    Replaced,
+    /// The subscription was cancelled by the developer (ex. through the
+    /// platform's API or console), rather than by the user or the platform.
+    RevokedByDeveloper,
     Unknown,
 }
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use super::iap_details::{IapDetails, IapTypeSpecificDetails};
+
+/// Result of verifying a purchase with 'IapUtil::verify_purchase', which
+/// (unlike 'IapUtil::verify_and_get_details') tells purchases awaiting payment
+/// apart from ones which are no longer active.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum VerificationResult<T: IapTypeSpecificDetails> {
+    /// The purchase is active, and should be fulfilled.
+    Active(IapDetails<T>),
+    /// The purchase was made, but payment has not completed yet (ex. Google
+    /// Play purchases paid in cash at a store). It should not be fulfilled
+    /// yet; once payment completes or is canceled, a
+    /// 'NotificationDetails::OneTimePurchaseStateChanged' notification is
+    /// sent (for subscriptions, 'SubscriptionStarted' or 'SubscriptionEnded').
+    Pending(IapDetails<T>),
+    /// The purchase exists, but is not active (ex. voided, canceled, or
+    /// expired).
+    Inactive(IapDetails<T>),
+}
+
+impl<T: IapTypeSpecificDetails> VerificationResult<T> {
+    pub(crate) fn from_details(details: IapDetails<T>) -> Self {
+        if details.is_active {
+            VerificationResult::Active(details)
+        } else if details.is_pending {
+            VerificationResult::Pending(details)
+        } else {
+            VerificationResult::Inactive(details)
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, VerificationResult::Active(_))
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self, VerificationResult::Pending(_))
+    }
+
+    pub fn details(&self) -> &IapDetails<T> {
+        match self {
+            VerificationResult::Active(details)
+            | VerificationResult::Pending(details)
+            | VerificationResult::Inactive(details) => details,
+        }
+    }
+
+    pub fn into_details(self) -> IapDetails<T> {
+        match self {
+            VerificationResult::Active(details)
+            | VerificationResult::Pending(details)
+            | VerificationResult::Inactive(details) => details,
+        }
+    }
+}
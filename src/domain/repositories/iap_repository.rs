@@ -1,20 +1,31 @@
 use async_trait::async_trait;
 use fractic_server_error::ServerError;
 
+#[cfg(feature = "microsoft-store")]
+use crate::data::models::microsoft_store_collections_api::collections_query_response_model::CollectionItemModel;
+#[cfg(feature = "steam")]
+use crate::data::models::steam_micro_txn_api::query_txn_response_model::TxnItemModel;
 use crate::{
+    config::ActivePolicy,
     data::models::{
-        app_store_server_api::jws_transaction_decoded_payload_model::JwsTransactionDecodedPayloadModel,
+        app_store_server_api::{
+            jws_renewal_info_decoded_payload_model::JwsRenewalInfoDecodedPayloadModel,
+            jws_transaction_decoded_payload_model::JwsTransactionDecodedPayloadModel,
+        },
         google_play_developer_api::{
             product_purchase_model::ProductPurchaseModel,
             subscription_purchase_v2_model::SubscriptionPurchaseV2Model,
         },
     },
     domain::entities::{
+        iap_consumption::ConsumeResult,
         iap_details::{IapDetails, IapTypeSpecificDetails},
+        iap_health_report::IapHealthReport,
         iap_product_id::{private::IapProductId, IapConsumableId},
         iap_purchase_id::IapPurchaseId,
         iap_update_notification::IapUpdateNotification,
     },
+    error_observer::IapError,
 };
 
 pub trait TypedProductId: IapProductId {
@@ -22,6 +33,8 @@ pub trait TypedProductId: IapProductId {
 
     fn extract_details_from_apple_transaction(
         m: &JwsTransactionDecodedPayloadModel,
+        r: Option<&JwsRenewalInfoDecodedPayloadModel>,
+        policy: &ActivePolicy,
     ) -> Result<Self::DetailsType, ServerError>;
 
     fn extract_details_from_google_product_purchase(
@@ -31,33 +44,63 @@ pub trait TypedProductId: IapProductId {
     fn extract_details_from_google_subscription_purchase(
         m: &SubscriptionPurchaseV2Model,
     ) -> Result<Self::DetailsType, ServerError>;
+
+    #[cfg(feature = "microsoft-store")]
+    fn extract_details_from_microsoft_collection_item(
+        m: &CollectionItemModel,
+    ) -> Result<Self::DetailsType, ServerError>;
+
+    #[cfg(feature = "steam")]
+    fn extract_details_from_steam_txn_item(
+        m: &TxnItemModel,
+    ) -> Result<Self::DetailsType, ServerError>;
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub trait IapRepository: Send + Sync {
     async fn verify_and_get_details<T: TypedProductId>(
         &self,
         product_id: T,
         purchase_id: IapPurchaseId,
         include_price_info: bool,
-    ) -> Result<IapDetails<T::DetailsType>, ServerError>;
+    ) -> Result<IapDetails<T::DetailsType>, IapError>;
+
+    async fn get_details_allow_inactive<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+    ) -> Result<IapDetails<T::DetailsType>, IapError>;
 
     async fn consume(
         &self,
         product_id: IapConsumableId,
         purchase_id: IapPurchaseId,
-    ) -> Result<(), ServerError>;
+    ) -> Result<ConsumeResult, IapError>;
 
-    async fn parse_apple_notification(
-        &self,
-        body: &str,
-    ) -> Result<IapUpdateNotification, ServerError>;
+    async fn parse_apple_notification(&self, body: &str)
+        -> Result<IapUpdateNotification, IapError>;
 
     async fn parse_google_notification(
         &self,
         authorization_header: &str,
         body: &str,
-    ) -> Result<IapUpdateNotification, ServerError>;
+    ) -> Result<IapUpdateNotification, IapError>;
+
+    async fn parse_google_developer_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError>;
+
+    async fn verify_apple_notification_signature(&self, body: &str) -> Result<(), IapError>;
+
+    async fn verify_google_notification_auth(
+        &self,
+        authorization_header: &str,
+    ) -> Result<(), IapError>;
+
+    async fn request_apple_test_notification(&self, sandbox: bool) -> Result<String, IapError>;
 
-    async fn request_apple_test_notification(&self, sandbox: bool) -> Result<String, ServerError>;
+    async fn health_check(&self) -> IapHealthReport;
 }
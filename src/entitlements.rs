@@ -0,0 +1,94 @@
+use std::any::Any;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+
+use crate::domain::{
+    entities::{
+        iap_details::{IapDetails, SubscriptionDetails},
+        iap_product_id::private::_ProductIdType,
+        iap_purchase_id::IapPurchaseId,
+    },
+    repositories::iap_repository::TypedProductId,
+};
+
+/// Latest known state of a purchase, as recorded in an 'EntitlementStore'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entitlement {
+    pub purchase_id: IapPurchaseId,
+    pub product_id: String,
+    pub kind: EntitlementKind,
+    pub is_active: bool,
+    pub is_sandbox: bool,
+    /// Only set for subscriptions.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// When this state was observed. Stores use this to discard stale updates
+    /// (ex. from notifications delivered out of order).
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntitlementKind {
+    NonConsumable,
+    Consumable,
+    Subscription,
+}
+
+impl Entitlement {
+    /// Snapshot of purchase details returned by 'IapUtil', observed now.
+    pub fn from_details<T: TypedProductId>(
+        product_id: &T,
+        details: &IapDetails<T::DetailsType>,
+    ) -> Self {
+        let kind = match T::product_type() {
+            _ProductIdType::NonConsumable => EntitlementKind::NonConsumable,
+            _ProductIdType::Consumable => EntitlementKind::Consumable,
+            _ProductIdType::Subscription => EntitlementKind::Subscription,
+        };
+        let expires_at = (&details.type_specific_details as &dyn Any)
+            .downcast_ref::<SubscriptionDetails>()
+            .map(|subscription| subscription.expiration_time);
+        Self {
+            purchase_id: details.canonical_id.clone(),
+            product_id: product_id.sku().to_owned(),
+            kind,
+            is_active: details.is_active,
+            is_sandbox: details.is_sandbox,
+            expires_at,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+impl EntitlementKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntitlementKind::NonConsumable => "NON_CONSUMABLE",
+            EntitlementKind::Consumable => "CONSUMABLE",
+            EntitlementKind::Subscription => "SUBSCRIPTION",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "NON_CONSUMABLE" => Some(EntitlementKind::NonConsumable),
+            "CONSUMABLE" => Some(EntitlementKind::Consumable),
+            "SUBSCRIPTION" => Some(EntitlementKind::Subscription),
+            _ => None,
+        }
+    }
+}
+
+/// Persists the latest known state of purchases, so that application code can
+/// check entitlements without calling out to the stores, and keep them up to
+/// date from notifications.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait EntitlementStore: Send + Sync {
+    async fn get(&self, purchase_id: &IapPurchaseId) -> Result<Option<Entitlement>, ServerError>;
+
+    /// Record the state of a purchase. Implementations must ignore updates
+    /// which are older than the recorded state (by 'updated_at').
+    async fn put(&self, entitlement: &Entitlement) -> Result<(), ServerError>;
+}
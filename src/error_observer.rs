@@ -0,0 +1,254 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use crate::{
+    correlation::current_correlation_id,
+    domain::entities::{iap_api_error::PlatformApiError, iap_purchase_id::IapPurchaseId},
+    secrets::SecretString,
+};
+
+/// Hook notified whenever an 'IapUtil' operation fails, with structured
+/// context about the failure (ex. to route alerts by platform, endpoint or
+/// failure class, without parsing error messages).
+///
+/// Observers are registered through 'IapUtilBuilder::error_observer', and are
+/// called in the order they were registered, before the error is returned.
+///
+/// NOTE: Purchases which are verified but not active ('NotActive') are an
+/// expected outcome, and are not reported.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ErrorObserver: Send + Sync {
+    async fn on_error(&self, context: &IapErrorContext, error: &ServerError);
+
+    /// Called when an operation succeeds, but with a condition which needs
+    /// attention. Ignored by default.
+    async fn on_warning(&self, _context: &IapErrorContext, _warning: &IapWarning) {}
+}
+
+/// Condition reported to 'ErrorObserver::on_warning'.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IapWarning {
+    /// A verified Google Play purchase has not been acknowledged, and will be
+    /// refunded by Google if it is not acknowledged by the deadline (see
+    /// 'IapDetails::acknowledgement_deadline').
+    AcknowledgementPending { deadline: DateTime<Utc> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IapPlatform {
+    AppStore,
+    GooglePlay,
+    #[cfg(feature = "microsoft-store")]
+    MicrosoftStore,
+    #[cfg(feature = "steam")]
+    Steam,
+    #[cfg(feature = "paddle")]
+    Paddle,
+    #[cfg(feature = "stripe")]
+    Stripe,
+}
+
+/// Error returned by 'IapUtil' operations. Failures of operations on the
+/// platforms (ex. a failed callout) keep their structured context, so that
+/// callers can handle them by failure class without parsing error messages.
+///
+/// Converts into 'ServerError' (ex. with '?'), dropping the context.
+#[derive(Debug)]
+pub struct IapError {
+    error: ServerError,
+    context: Option<Box<IapErrorContext>>,
+}
+
+impl IapError {
+    pub(crate) fn new(error: ServerError, context: IapErrorContext) -> Self {
+        Self {
+            error,
+            context: Some(Box::new(context)),
+        }
+    }
+
+    /// The underlying error.
+    pub fn error(&self) -> &ServerError {
+        &self.error
+    }
+
+    pub fn into_error(self) -> ServerError {
+        self.error
+    }
+
+    /// Context of the failure, as passed to the error observers. None for
+    /// failures which are not reported to them (ex. 'NotActive', or a denied
+    /// purchase).
+    pub fn context(&self) -> Option<&IapErrorContext> {
+        self.context.as_deref()
+    }
+
+    /// See 'IapErrorContext::platform'.
+    pub fn platform(&self) -> Option<IapPlatform> {
+        self.context().and_then(IapErrorContext::platform)
+    }
+
+    /// See 'IapErrorContext::status'.
+    pub fn status(&self) -> Option<u16> {
+        self.context().and_then(IapErrorContext::status)
+    }
+
+    /// See 'IapErrorContext::is_transient'. False if there is no context.
+    pub fn is_transient(&self) -> bool {
+        self.context().is_some_and(IapErrorContext::is_transient)
+    }
+}
+
+impl fmt::Display for IapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for IapError {}
+
+impl From<ServerError> for IapError {
+    fn from(error: ServerError) -> Self {
+        Self {
+            error,
+            context: None,
+        }
+    }
+}
+
+impl From<IapError> for ServerError {
+    fn from(error: IapError) -> Self {
+        error.error
+    }
+}
+
+/// Structured context of a failed operation (or of a warning), as seen by an
+/// 'ErrorObserver' (and returned with 'IapError::context').
+#[derive(Debug, Clone)]
+pub struct IapErrorContext {
+    operation: &'static str,
+    platform: Option<IapPlatform>,
+    endpoint: Option<String>,
+    api_error: Option<PlatformApiError>,
+    purchase_id_hash: Option<String>,
+    transient: bool,
+    correlation_id: Option<String>,
+}
+
+impl IapErrorContext {
+    pub(crate) fn new(
+        operation: &'static str,
+        platform: Option<IapPlatform>,
+        endpoint: Option<String>,
+        api_error: Option<PlatformApiError>,
+        purchase_id: Option<&IapPurchaseId>,
+        purchase_id_hash_key: Option<&SecretString>,
+        transient: bool,
+    ) -> Self {
+        let platform = platform.or(match purchase_id {
+            Some(IapPurchaseId::AppStoreTransactionId(_))
+            | Some(IapPurchaseId::AppleExternalPurchaseId(_)) => Some(IapPlatform::AppStore),
+            Some(IapPurchaseId::GooglePlayPurchaseToken(_))
+            | Some(IapPurchaseId::GoogleExternalTransactionId(_)) => Some(IapPlatform::GooglePlay),
+            #[cfg(feature = "microsoft-store")]
+            Some(IapPurchaseId::MicrosoftStoreIdKey(_)) => Some(IapPlatform::MicrosoftStore),
+            #[cfg(feature = "steam")]
+            Some(IapPurchaseId::SteamOrderId(_)) => Some(IapPlatform::Steam),
+            #[cfg(feature = "paddle")]
+            Some(IapPurchaseId::PaddleSubscriptionId(_)) => Some(IapPlatform::Paddle),
+            #[cfg(feature = "stripe")]
+            Some(IapPurchaseId::StripeSubscriptionId(_)) => Some(IapPlatform::Stripe),
+            None => None,
+        });
+        Self {
+            operation,
+            platform,
+            endpoint,
+            api_error,
+            purchase_id_hash: purchase_id
+                .zip(purchase_id_hash_key)
+                .and_then(|(purchase_id, key)| hash_purchase_id(purchase_id, key)),
+            transient,
+            correlation_id: current_correlation_id(),
+        }
+    }
+
+    /// The 'IapUtil' operation which failed (ex. "verify_and_get_details").
+    pub fn operation(&self) -> &str {
+        self.operation
+    }
+
+    /// The platform concerned, if known.
+    pub fn platform(&self) -> Option<IapPlatform> {
+        self.platform
+    }
+
+    /// Name of the platform API function whose callout failed (ex.
+    /// "GetTransactionInfo"), if the failure came from a callout.
+    pub fn endpoint(&self) -> Option<&str> {
+        self.endpoint.as_deref()
+    }
+
+    /// HTTP status code of the failed callout, if a response was received.
+    pub fn status(&self) -> Option<u16> {
+        self.api_error.as_ref().map(PlatformApiError::status)
+    }
+
+    /// The platform's parsed error response, if the callout failed with a
+    /// non-2xx response.
+    pub fn api_error(&self) -> Option<&PlatformApiError> {
+        self.api_error.as_ref()
+    }
+
+    /// Hex-encoded HMAC-SHA256 of the purchase ID (transaction ID or purchase
+    /// token), keyed with 'IapUtilBuilder::purchase_id_hash_key', if the
+    /// operation concerned a specific purchase and a key is configured. Allows
+    /// correlating failures for the same purchase without logging the ID
+    /// itself.
+    pub fn purchase_id_hash(&self) -> Option<&str> {
+        self.purchase_id_hash.as_deref()
+    }
+
+    /// Whether the operation may succeed if retried later (ex. the platform
+    /// was temporarily unavailable).
+    pub fn is_transient(&self) -> bool {
+        self.transient
+    }
+
+    /// See 'correlation::with_correlation_id'.
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+}
+
+fn hash_purchase_id(purchase_id: &IapPurchaseId, key: &SecretString) -> Option<String> {
+    let raw = match purchase_id {
+        IapPurchaseId::AppStoreTransactionId(id) => id.to_string(),
+        IapPurchaseId::AppleExternalPurchaseId(id) => id.to_string(),
+        IapPurchaseId::GooglePlayPurchaseToken(token) => token.to_string(),
+        IapPurchaseId::GoogleExternalTransactionId(id) => id.to_string(),
+        #[cfg(feature = "microsoft-store")]
+        IapPurchaseId::MicrosoftStoreIdKey(key) => key.to_string(),
+        #[cfg(feature = "steam")]
+        IapPurchaseId::SteamOrderId(id) => id.to_string(),
+        #[cfg(feature = "paddle")]
+        IapPurchaseId::PaddleSubscriptionId(id) => id.to_string(),
+        #[cfg(feature = "stripe")]
+        IapPurchaseId::StripeSubscriptionId(id) => id.to_string(),
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.expose_secret().as_bytes()).ok()?;
+    mac.update(raw.as_bytes());
+    Some(
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect(),
+    )
+}
@@ -5,6 +5,106 @@ define_sensitive_error!(
     NotActive,
     "In-app-purchase exists, but is not currently valid / active."
 );
+define_sensitive_error!(PurchaseNotFound, "In-app-purchase does not exist.");
+define_sensitive_error!(
+    PurchaseDenied,
+    "In-app-purchase is on the deny list, and can not be granted."
+);
+define_sensitive_error!(
+    InvalidPurchaseId,
+    "Invalid purchase ID: {details}.",
+    { details: &str }
+);
+define_sensitive_error!(
+    ProductTypeMismatch,
+    "Purchase was verified as a {expected} product, but the platform reports a {actual} product.",
+    { expected: &str, actual: &str }
+);
+define_sensitive_error!(
+    PurchaseNotConsumable,
+    "In-app-purchase cannot be consumed, since it is {state}.",
+    { state: &str }
+);
+define_sensitive_error!(
+    InsufficientQuantity,
+    "Not enough unconsumed units in purchase: {details}.",
+    { details: &str }
+);
+define_sensitive_error!(
+    InvalidConsumeQuantity,
+    "Invalid quantity to consume: {details}.",
+    { details: &str }
+);
+define_sensitive_error!(
+    InvalidEntitlementExtension,
+    "Invalid entitlement extension: {details}.",
+    { details: &str }
+);
+
+define_internal_error!(
+    ProductListingNotAvailable,
+    "Product listings are not available for {platform} products.",
+    { platform: &str }
+);
+define_internal_error!(
+    EntitlementExtensionNotAvailable,
+    "Entitlements can only be extended for App Store and Google Play subscriptions."
+);
+define_internal_error!(
+    CredentialValidationFailed,
+    "Credential validation failed: {details}.",
+    { details: &str }
+);
+define_internal_error!(
+    HttpClientConfigInvalid,
+    "Invalid HTTP client configuration: {details}.",
+    { details: &str }
+);
+
+// Configuration.
+define_internal_error!(
+    ConfigValueMissing,
+    "IAP configuration value '{field}' is empty.",
+    { field: &str }
+);
+define_internal_error!(
+    AppleApiKeyMalformed,
+    "App Store Server API key ('APPLE_API_KEY') is malformed: {details}.",
+    { details: &str }
+);
+define_internal_error!(
+    AppleKeyIdMalformed,
+    "App Store Server API key ID ('APPLE_KEY_ID') is malformed: {details}.",
+    { details: &str }
+);
+define_internal_error!(
+    AppleIssuerIdMalformed,
+    "App Store Server API issuer ID ('APPLE_ISSUER_ID') is malformed: {details}.",
+    { details: &str }
+);
+define_internal_error!(
+    GoogleApiKeyMalformed,
+    "Google Play Developer API key ('GOOGLE_API_KEY') is malformed: {details}.",
+    { details: &str }
+);
+define_internal_error!(
+    EnvVarMissing,
+    "Environment variable '{name}' is not set.",
+    { name: &str }
+);
+define_internal_error!(
+    EnvVarInvalid,
+    "Environment variable '{name}' is invalid: {details}.",
+    { name: &str, details: &str }
+);
+
+// Secret loading.
+#[cfg(any(feature = "aws-secrets", feature = "gcp-secrets"))]
+define_internal_error!(
+    SecretLoadError,
+    "Failed to load IAP credentials from secret '{secret_id}': {details}.",
+    { secret_id: &str, details: &str }
+);
 
 // Google Play Developer API.
 define_internal_error!(
@@ -22,6 +122,81 @@ define_internal_error!(
     "Invalid response from Google Play Developer API: {details}.",
     { details: &str }
 );
+define_internal_error!(
+    InvalidGoogleExternalTransaction,
+    "Invalid Google Play external transaction: {details}.",
+    { details: &str }
+);
+
+// Microsoft Store collections API.
+#[cfg(feature = "microsoft-store")]
+define_internal_error!(
+    MicrosoftStoreCredentialsInvalid,
+    "Invalid Microsoft Store credentials: {details}.",
+    { details: &str }
+);
+#[cfg(feature = "microsoft-store")]
+define_internal_error!(
+    MicrosoftStoreApiError,
+    "Error calling Microsoft Store collections API '{function_name}': {details}.",
+    { function_name: &str, details: &str }
+);
+#[cfg(feature = "microsoft-store")]
+define_internal_error!(
+    MicrosoftStoreApiInvalidResponse,
+    "Invalid response from Microsoft Store collections API: {details}.",
+    { details: &str }
+);
+#[cfg(feature = "microsoft-store")]
+define_internal_error!(
+    MicrosoftStoreNotConfigured,
+    "Microsoft Store purchase could not be verified, since no Microsoft Store credentials are configured."
+);
+
+// Steam ISteamMicroTxn Web API.
+#[cfg(feature = "steam")]
+define_internal_error!(
+    SteamMicroTxnApiError,
+    "Error calling Steam ISteamMicroTxn API '{function_name}': {details}.",
+    { function_name: &str, details: &str }
+);
+#[cfg(feature = "steam")]
+define_internal_error!(
+    SteamMicroTxnApiInvalidResponse,
+    "Invalid response from Steam ISteamMicroTxn API: {details}.",
+    { details: &str }
+);
+#[cfg(feature = "steam")]
+define_internal_error!(
+    SteamNotConfigured,
+    "Steam order could not be verified, since no Steam credentials are configured."
+);
+
+// Paddle Billing webhooks.
+#[cfg(feature = "paddle")]
+define_internal_error!(
+    PaddleWebhookParseError,
+    "Error parsing Paddle webhook notification: {details}.",
+    { details: &str }
+);
+#[cfg(feature = "paddle")]
+define_internal_error!(
+    PaddleNotConfigured,
+    "Paddle notification could not be verified, since no Paddle webhook secret is configured."
+);
+
+// Stripe webhooks.
+#[cfg(feature = "stripe")]
+define_internal_error!(
+    StripeWebhookParseError,
+    "Error parsing Stripe webhook event: {details}.",
+    { details: &str }
+);
+#[cfg(feature = "stripe")]
+define_internal_error!(
+    StripeNotConfigured,
+    "Stripe event could not be verified, since no Stripe webhook secret is configured."
+);
 
 // Google Cloud RTDN Notifications.
 define_internal_error!(
@@ -47,6 +222,51 @@ define_internal_error!(
     { details: &str }
 );
 
+// Apple app transactions.
+define_sensitive_error!(
+    InvalidAppleAppTransaction,
+    "Invalid Apple app transaction: {details}.",
+    { details: &str }
+);
+
+// Apple external purchases.
+define_sensitive_error!(
+    InvalidAppleExternalPurchaseToken,
+    "Invalid Apple external purchase token: {details}.",
+    { details: &str }
+);
+
+// App Store Connect API.
+#[cfg(feature = "app-store-connect")]
+define_internal_error!(
+    AppStoreConnectApiKeyInvalid,
+    "Invalid App Store Connect API key: {details}.",
+    { details: &str }
+);
+#[cfg(feature = "app-store-connect")]
+define_internal_error!(
+    AppStoreConnectApiError,
+    "Error calling App Store Connect API '{function_name}': {details}.",
+    { function_name: &str, details: &str }
+);
+#[cfg(feature = "app-store-connect")]
+define_internal_error!(
+    AppStoreConnectApiInvalidResponse,
+    "Invalid response from App Store Connect API: {details}.",
+    { details: &str }
+);
+#[cfg(feature = "app-store-connect")]
+define_internal_error!(
+    AppStoreConnectNotConfigured,
+    "App Store product could not be looked up, since no App Store Connect credentials are configured."
+);
+#[cfg(feature = "app-store-connect")]
+define_sensitive_error!(
+    AppStoreProductNotFound,
+    "Product '{product_id}' does not exist in App Store Connect.",
+    { product_id: &str }
+);
+
 // App Store Server Notifications.
 define_internal_error!(
     AppStoreServerNotificationParseError,
@@ -64,8 +284,94 @@ define_sensitive_error!(
     "Unable to verify the message was signed by Apple (invalid component: {invalid_component}).",
     { invalid_component: &str }
 );
+#[cfg(feature = "paddle")]
+define_sensitive_error!(
+    InvalidPaddleSignature,
+    "Unable to verify the message was signed by Paddle (invalid component: {invalid_component}).",
+    { invalid_component: &str }
+);
+#[cfg(feature = "stripe")]
+define_sensitive_error!(
+    InvalidStripeSignature,
+    "Unable to verify the message was signed by Stripe (invalid component: {invalid_component}).",
+    { invalid_component: &str }
+);
+#[cfg(feature = "native")]
+define_internal_error!(
+    GoogleJwksInvalid,
+    "Invalid Google JWK set: {details}.",
+    { details: &str }
+);
+define_internal_error!(
+    SignatureVerifierMissing,
+    "No signature verifier configured (required when the 'native' feature is disabled)."
+);
 define_sensitive_error!(
     InvalidJws,
     "Unable to decode JWS payload: {details}.",
     { details: &str }
 );
+
+// Webhooks.
+define_internal_error!(
+    InvalidNotificationEnvelope,
+    "Invalid notification envelope: {details}.",
+    { details: &str }
+);
+define_internal_error!(
+    NotificationAuditUnavailable,
+    "Missed notifications can only be audited if a dedupe store is configured."
+);
+define_internal_error!(
+    NotificationInFlight,
+    "Notification '{notification_id}' is already being processed.",
+    { notification_id: &str }
+);
+#[cfg(any(feature = "dynamodb", feature = "postgres", feature = "redis"))]
+define_internal_error!(
+    DedupeStoreError,
+    "Dedupe store error for notification '{notification_id}': {details}.",
+    { notification_id: &str, details: &str }
+);
+#[cfg(feature = "s3")]
+define_internal_error!(
+    ReportFlushError,
+    "Failed to flush processing report of window starting {window_start}: {details}.",
+    { window_start: &str, details: &str }
+);
+#[cfg(feature = "s3")]
+define_internal_error!(
+    ArchiveError,
+    "Failed to archive notification '{notification_id}': {details}.",
+    { notification_id: &str, details: &str }
+);
+
+// Caching.
+#[cfg(feature = "redis")]
+define_internal_error!(
+    CacheStoreError,
+    "Cache store error: {details}.",
+    { details: &str }
+);
+
+// Entitlements.
+#[cfg(feature = "postgres")]
+define_internal_error!(
+    EntitlementStoreError,
+    "Entitlement store error: {details}.",
+    { details: &str }
+);
+
+// Test utilities.
+#[cfg(feature = "test-util")]
+define_internal_error!(
+    MockResponseNotScripted,
+    "Mock response not scripted: {details}.",
+    { details: &str }
+);
+#[cfg(feature = "test-util")]
+define_internal_error!(
+    TestFixtureError,
+    "Failed to build test fixture: {details}.",
+    { details: &str }
+);
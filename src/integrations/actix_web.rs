@@ -0,0 +1,215 @@
+use std::{future::Future, pin::Pin};
+
+use actix_web::{
+    body::BoxBody,
+    dev::Payload,
+    http::{header::AUTHORIZATION, StatusCode},
+    web, FromRequest, HttpRequest, HttpResponse, Responder,
+};
+
+#[cfg(feature = "paddle")]
+use crate::webhook::PADDLE_SIGNATURE_HEADER;
+#[cfg(feature = "stripe")]
+use crate::webhook::STRIPE_SIGNATURE_HEADER;
+use crate::{
+    error_observer::IapPlatform,
+    webhook::{WebhookHandler, WebhookOutcome, WebhookResponse},
+};
+
+/// Registers ready-made webhook endpoints:
+///
+/// - POST '/webhooks/apple': App Store Server Notifications (V2).
+/// - POST '/webhooks/google': Google Cloud Pub/Sub push subscription for Play
+///   RTDN notifications (with authentication enabled).
+/// - POST '/webhooks/paddle': Paddle Billing notification destination (with
+///   the 'paddle' feature).
+/// - POST '/webhooks/stripe': Stripe webhook endpoint (with the 'stripe'
+///   feature).
+///
+/// Usage:
+/// 'App::new().configure(fractic_iap::integrations::actix_web::configure(handler.clone()))',
+/// where 'handler' is a 'web::Data<WebhookHandler>' created once, outside the
+/// app factory.
+pub fn configure(handler: web::Data<WebhookHandler>) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        cfg.app_data(handler)
+            .route("/webhooks/apple", web::post().to(apple_webhook))
+            .route("/webhooks/google", web::post().to(google_webhook));
+        #[cfg(feature = "paddle")]
+        cfg.route("/webhooks/paddle", web::post().to(paddle_webhook));
+        #[cfg(feature = "stripe")]
+        cfg.route("/webhooks/stripe", web::post().to(stripe_webhook));
+    }
+}
+
+/// Handler for App Store Server Notifications, for use in custom routes.
+pub async fn apple_webhook(
+    handler: web::Data<WebhookHandler>,
+    request: AppleWebhookRequest,
+) -> WebhookResponse {
+    handler
+        .handle_apple(&request.body)
+        .await
+        .response(IapPlatform::AppStore)
+}
+
+/// Handler for Google Cloud Pub/Sub push requests, for use in custom routes.
+pub async fn google_webhook(
+    handler: web::Data<WebhookHandler>,
+    request: GoogleWebhookRequest,
+) -> WebhookResponse {
+    handler
+        .handle_google(request.authorization_header.as_deref(), &request.body)
+        .await
+        .response(IapPlatform::GooglePlay)
+}
+
+/// Handler for Paddle Billing notifications, for use in custom routes.
+#[cfg(feature = "paddle")]
+pub async fn paddle_webhook(
+    handler: web::Data<WebhookHandler>,
+    request: PaddleWebhookRequest,
+) -> WebhookResponse {
+    handler
+        .handle_paddle(request.signature_header.as_deref(), &request.body)
+        .await
+        .response(IapPlatform::Paddle)
+}
+
+/// Handler for Stripe webhook events, for use in custom routes.
+#[cfg(feature = "stripe")]
+pub async fn stripe_webhook(
+    handler: web::Data<WebhookHandler>,
+    request: StripeWebhookRequest,
+) -> WebhookResponse {
+    handler
+        .handle_stripe(request.signature_header.as_deref(), &request.body)
+        .await
+        .response(IapPlatform::Stripe)
+}
+
+/// Extractor for an App Store Server Notification request. The body contains
+/// the signed payload, which is verified by 'WebhookHandler::handle_apple'.
+pub struct AppleWebhookRequest {
+    pub body: String,
+}
+
+impl FromRequest for AppleWebhookRequest {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let body = String::from_request(req, payload);
+        Box::pin(async move { Ok(Self { body: body.await? }) })
+    }
+}
+
+/// Extractor for a Google Cloud Pub/Sub push request. The body contains the
+/// Pub/Sub envelope, and the 'Authorization' header the OIDC token signed by
+/// Google, both of which are verified by 'WebhookHandler::handle_google'.
+pub struct GoogleWebhookRequest {
+    pub authorization_header: Option<String>,
+    pub body: String,
+}
+
+impl FromRequest for GoogleWebhookRequest {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let authorization_header = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let body = String::from_request(req, payload);
+        Box::pin(async move {
+            Ok(Self {
+                authorization_header,
+                body: body.await?,
+            })
+        })
+    }
+}
+
+/// Extractor for a Paddle Billing notification request. The body is signed
+/// with the notification destination's secret, and the signature sent in the
+/// 'Paddle-Signature' header, both of which are verified by
+/// 'WebhookHandler::handle_paddle'.
+#[cfg(feature = "paddle")]
+pub struct PaddleWebhookRequest {
+    pub signature_header: Option<String>,
+    pub body: String,
+}
+
+#[cfg(feature = "paddle")]
+impl FromRequest for PaddleWebhookRequest {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let signature_header = req
+            .headers()
+            .get(PADDLE_SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let body = String::from_request(req, payload);
+        Box::pin(async move {
+            Ok(Self {
+                signature_header,
+                body: body.await?,
+            })
+        })
+    }
+}
+
+/// Extractor for a Stripe webhook event request. The body is signed with the
+/// endpoint's signing secret, and the signature sent in the
+/// 'Stripe-Signature' header, both of which are verified by
+/// 'WebhookHandler::handle_stripe'.
+#[cfg(feature = "stripe")]
+pub struct StripeWebhookRequest {
+    pub signature_header: Option<String>,
+    pub body: String,
+}
+
+#[cfg(feature = "stripe")]
+impl FromRequest for StripeWebhookRequest {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let signature_header = req
+            .headers()
+            .get(STRIPE_SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let body = String::from_request(req, payload);
+        Box::pin(async move {
+            Ok(Self {
+                signature_header,
+                body: body.await?,
+            })
+        })
+    }
+}
+
+impl Responder for WebhookResponse {
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        HttpResponse::new(
+            StatusCode::from_u16(self.status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        )
+    }
+}
+
+impl Responder for WebhookOutcome {
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        HttpResponse::new(
+            StatusCode::from_u16(self.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        )
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+
+#[cfg(feature = "paddle")]
+use crate::webhook::PADDLE_SIGNATURE_HEADER;
+#[cfg(feature = "stripe")]
+use crate::webhook::STRIPE_SIGNATURE_HEADER;
+use crate::{
+    error_observer::IapPlatform,
+    webhook::{WebhookHandler, WebhookOutcome, WebhookResponse},
+};
+
+/// Router with ready-made webhook endpoints:
+///
+/// - POST '/webhooks/apple': App Store Server Notifications (V2).
+/// - POST '/webhooks/google': Google Cloud Pub/Sub push subscription for Play
+///   RTDN notifications (with authentication enabled).
+/// - POST '/webhooks/paddle': Paddle Billing notification destination (with
+///   the 'paddle' feature).
+/// - POST '/webhooks/stripe': Stripe webhook endpoint (with the 'stripe'
+///   feature).
+///
+/// Can be nested or merged into an existing router, ex.
+/// 'app.merge(fractic_iap::integrations::axum::router(handler))'.
+pub fn router<S>(handler: WebhookHandler) -> Router<S> {
+    let router = Router::new()
+        .route("/webhooks/apple", post(apple_webhook))
+        .route("/webhooks/google", post(google_webhook));
+    #[cfg(feature = "paddle")]
+    let router = router.route("/webhooks/paddle", post(paddle_webhook));
+    #[cfg(feature = "stripe")]
+    let router = router.route("/webhooks/stripe", post(stripe_webhook));
+    router.with_state(Arc::new(handler))
+}
+
+/// Handler for App Store Server Notifications, for use in custom routes.
+pub async fn apple_webhook(State(handler): State<Arc<WebhookHandler>>, body: String) -> Response {
+    handler
+        .handle_apple(&body)
+        .await
+        .response(IapPlatform::AppStore)
+        .into_response()
+}
+
+/// Handler for Google Cloud Pub/Sub push requests, for use in custom routes.
+pub async fn google_webhook(
+    State(handler): State<Arc<WebhookHandler>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let authorization_header = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    handler
+        .handle_google(authorization_header, &body)
+        .await
+        .response(IapPlatform::GooglePlay)
+        .into_response()
+}
+
+/// Handler for Paddle Billing notifications, for use in custom routes.
+#[cfg(feature = "paddle")]
+pub async fn paddle_webhook(
+    State(handler): State<Arc<WebhookHandler>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let signature_header = headers
+        .get(PADDLE_SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok());
+    handler
+        .handle_paddle(signature_header, &body)
+        .await
+        .response(IapPlatform::Paddle)
+        .into_response()
+}
+
+/// Handler for Stripe webhook events, for use in custom routes.
+#[cfg(feature = "stripe")]
+pub async fn stripe_webhook(
+    State(handler): State<Arc<WebhookHandler>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let signature_header = headers
+        .get(STRIPE_SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok());
+    handler
+        .handle_stripe(signature_header, &body)
+        .await
+        .response(IapPlatform::Stripe)
+        .into_response()
+}
+
+impl IntoResponse for WebhookResponse {
+    fn into_response(self) -> Response {
+        StatusCode::from_u16(self.status_code)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+            .into_response()
+    }
+}
+
+impl IntoResponse for WebhookOutcome {
+    fn into_response(self) -> Response {
+        StatusCode::from_u16(self.status_code())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+            .into_response()
+    }
+}
@@ -0,0 +1,216 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::{
+    operation::put_item::PutItemError,
+    types::{AttributeValue, ReturnValuesOnConditionCheckFailure},
+    Client,
+};
+use fractic_server_error::ServerError;
+
+use crate::{
+    errors::DedupeStoreError,
+    webhook::{DedupeState, NotificationDedupeStore},
+};
+
+const STATUS_IN_FLIGHT: &str = "IN_FLIGHT";
+const STATUS_COMPLETED: &str = "COMPLETED";
+
+/// 'NotificationDedupeStore' backed by a DynamoDB table, so that redeliveries
+/// are detected across instances (ex. multiple Lambda invocations).
+///
+/// The table needs a string partition key (named 'notification_id' by
+/// default), and should have TTL enabled on the 'expires_at' attribute so
+/// that old records are cleaned up. Records are claimed with conditional
+/// writes, so concurrent deliveries of the same notification are only
+/// processed once.
+///
+/// Handled notifications are remembered for 24 hours by default (see
+/// 'dedupe_window'). Claims of notifications which are still being processed
+/// expire after 5 minutes (see 'in_flight_timeout'), so that a crashed
+/// instance does not block redeliveries indefinitely.
+///
+/// Usage:
+///
+/// ```ignore
+/// let store = DynamoDbDedupeStore::from_env("iap-notifications").await;
+/// let handler = WebhookHandler::new(iap_util, callback).dedupe_store(store);
+/// ```
+pub struct DynamoDbDedupeStore {
+    client: Client,
+    table_name: String,
+    partition_key: String,
+    dedupe_window: Duration,
+    in_flight_timeout: Duration,
+}
+
+impl DynamoDbDedupeStore {
+    pub fn new(client: Client, table_name: impl Into<String>) -> Self {
+        Self {
+            client,
+            table_name: table_name.into(),
+            partition_key: "notification_id".to_owned(),
+            dedupe_window: Duration::from_secs(24 * 60 * 60),
+            in_flight_timeout: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// Uses a client built from the default AWS configuration chain
+    /// (environment, profile, or instance / Lambda role).
+    pub async fn from_env(table_name: impl Into<String>) -> Self {
+        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .load()
+            .await;
+        Self::new(Client::new(&config), table_name)
+    }
+
+    /// Name of the table's partition key (default: 'notification_id').
+    pub fn partition_key(mut self, partition_key: impl Into<String>) -> Self {
+        self.partition_key = partition_key.into();
+        self
+    }
+
+    /// How long handled notifications are remembered (default: 24 hours).
+    pub fn dedupe_window(mut self, window: Duration) -> Self {
+        self.dedupe_window = window;
+        self
+    }
+
+    /// How long a notification may be processed before its claim expires,
+    /// and redeliveries are processed again (default: 5 minutes).
+    pub fn in_flight_timeout(mut self, timeout: Duration) -> Self {
+        self.in_flight_timeout = timeout;
+        self
+    }
+
+    fn expires_at(duration: Duration) -> AttributeValue {
+        let expires_at = chrono::Utc::now().timestamp() + duration.as_secs() as i64;
+        AttributeValue::N(expires_at.to_string())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl NotificationDedupeStore for DynamoDbDedupeStore {
+    async fn begin(&self, notification_id: &str) -> Result<DedupeState, ServerError> {
+        // Claim the notification, unless there is an unexpired record for it.
+        // Expired records are checked explicitly, since DynamoDB's TTL
+        // deletion can lag behind by several hours.
+        let result = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item(
+                &self.partition_key,
+                AttributeValue::S(notification_id.to_owned()),
+            )
+            .item("status", AttributeValue::S(STATUS_IN_FLIGHT.to_owned()))
+            .item("expires_at", Self::expires_at(self.in_flight_timeout))
+            .condition_expression("attribute_not_exists(#pk) OR #expires_at < :now")
+            .expression_attribute_names("#pk", &self.partition_key)
+            .expression_attribute_names("#expires_at", "expires_at")
+            .expression_attribute_values(":now", Self::expires_at(Duration::ZERO))
+            .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld)
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(DedupeState::New),
+            Err(e) => match e.as_service_error() {
+                Some(PutItemError::ConditionalCheckFailedException(existing)) => {
+                    let completed = existing
+                        .item()
+                        .and_then(|item| item.get("status"))
+                        .and_then(|status| status.as_s().ok())
+                        .is_some_and(|status| status == STATUS_COMPLETED);
+                    Ok(match completed {
+                        true => DedupeState::Completed,
+                        false => DedupeState::InFlight,
+                    })
+                }
+                _ => Err(DedupeStoreError::with_debug(
+                    notification_id,
+                    "failed to claim notification",
+                    &e,
+                )),
+            },
+        }
+    }
+
+    async fn finish(&self, notification_id: &str, success: bool) -> Result<(), ServerError> {
+        if success {
+            self.client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(
+                    &self.partition_key,
+                    AttributeValue::S(notification_id.to_owned()),
+                )
+                .item("status", AttributeValue::S(STATUS_COMPLETED.to_owned()))
+                .item("expires_at", Self::expires_at(self.dedupe_window))
+                .send()
+                .await
+                .map_err(|e| {
+                    DedupeStoreError::with_debug(
+                        notification_id,
+                        "failed to mark notification as completed",
+                        &e,
+                    )
+                })?;
+        } else {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(
+                    &self.partition_key,
+                    AttributeValue::S(notification_id.to_owned()),
+                )
+                .send()
+                .await
+                .map_err(|e| {
+                    DedupeStoreError::with_debug(
+                        notification_id,
+                        "failed to release notification",
+                        &e,
+                    )
+                })?;
+        }
+        Ok(())
+    }
+    async fn state(&self, notification_id: &str) -> Result<Option<DedupeState>, ServerError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                &self.partition_key,
+                AttributeValue::S(notification_id.to_owned()),
+            )
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|e| {
+                DedupeStoreError::with_debug(notification_id, "failed to read notification", &e)
+            })?;
+        let Some(item) = output.item() else {
+            return Ok(None);
+        };
+        // Expired records may not have been deleted yet (see 'begin').
+        let now = chrono::Utc::now().timestamp();
+        let expired = item
+            .get("expires_at")
+            .and_then(|expires_at| expires_at.as_n().ok())
+            .and_then(|expires_at| expires_at.parse::<i64>().ok())
+            .is_some_and(|expires_at| expires_at < now);
+        if expired {
+            return Ok(None);
+        }
+        let completed = item
+            .get("status")
+            .and_then(|status| status.as_s().ok())
+            .is_some_and(|status| status == STATUS_COMPLETED);
+        Ok(Some(match completed {
+            true => DedupeState::Completed,
+            false => DedupeState::InFlight,
+        }))
+    }
+}
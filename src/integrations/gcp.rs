@@ -0,0 +1,99 @@
+use fractic_server_error::ServerError;
+use serde_json::{json, Value};
+
+use crate::{
+    domain::entities::iap_update_notification::IapUpdateNotification,
+    error_observer::IapError,
+    errors::{GoogleCloudRtdnNotificationParseError, InvalidGoogleSignature},
+    util::IapUtil,
+};
+
+/// A Pub/Sub push request received through Cloud Functions or Cloud Run,
+/// normalized to the standard push format expected by
+/// 'IapUtil::parse_google_notification'.
+#[derive(Debug, Clone)]
+pub struct GcpPushRequest {
+    pub authorization_header: String,
+    pub body: String,
+}
+
+/// Normalize a Pub/Sub push request received by a Cloud Functions / Cloud Run
+/// handler. Supports:
+///
+/// - Plain Pub/Sub push requests ('{"message": ..., "subscription": ...}'),
+///   as received by Cloud Run and HTTP-triggered functions.
+/// - CloudEvents in structured mode, where the push request is nested in the
+///   'data' field (Eventarc / 2nd gen functions).
+/// - Legacy background function events, where the message fields are at the
+///   top level (1st gen functions).
+///
+/// The OIDC token is read from the 'Authorization' header (matched
+/// case-insensitively).
+///
+/// NOTE: 'X-Serverless-Authorization' is not supported, since Cloud Run
+/// removes the signature from its tokens before passing them on, so they can
+/// not be verified.
+pub fn normalize_push_request<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    body: &str,
+) -> Result<GcpPushRequest, ServerError> {
+    let authorization_header = headers
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+        .map(|(_, value)| value.to_owned())
+        .ok_or(InvalidGoogleSignature::new("missing authorization header"))?;
+
+    let envelope: Value = serde_json::from_str(body).map_err(|e| {
+        GoogleCloudRtdnNotificationParseError::with_debug("failed to parse push request", &e)
+    })?;
+    let normalized = if envelope.get("message").is_some() {
+        envelope
+    } else if let Some(data) = envelope
+        .get("data")
+        .filter(|data| data.get("message").is_some())
+    {
+        data.clone()
+    } else if let Some(data) = envelope.get("data").and_then(Value::as_str) {
+        let message_id = envelope
+            .get("messageId")
+            .or(envelope.get("message_id"))
+            .or(envelope.pointer("/context/eventId"))
+            .cloned()
+            .unwrap_or(json!(""));
+        let subscription = envelope
+            .pointer("/context/resource/name")
+            .or(envelope.pointer("/context/resource"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        json!({
+            "message": {
+                "attributes": envelope.get("attributes").cloned().unwrap_or(json!({})),
+                "data": data,
+                "messageId": message_id,
+            },
+            "subscription": subscription,
+        })
+    } else {
+        return Err(GoogleCloudRtdnNotificationParseError::new(
+            "push request is not in a recognized Pub/Sub or CloudEvents format",
+        ));
+    };
+
+    Ok(GcpPushRequest {
+        authorization_header,
+        body: normalized.to_string(),
+    })
+}
+
+/// Normalize a push request received by a Cloud Functions / Cloud Run
+/// handler (see 'normalize_push_request'), then verify and parse it.
+pub async fn parse_push_request<'a>(
+    iap_util: &IapUtil,
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    body: &str,
+) -> Result<IapUpdateNotification, IapError> {
+    let request = normalize_push_request(headers, body)?;
+    iap_util
+        .parse_google_notification(&request.authorization_header, &request.body)
+        .await
+}
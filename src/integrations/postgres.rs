@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+use sqlx::{PgPool, Row as _};
+
+use crate::{
+    domain::entities::iap_purchase_id::IapPurchaseId,
+    entitlements::{Entitlement, EntitlementKind, EntitlementStore},
+    errors::{DedupeStoreError, EntitlementStoreError},
+    webhook::{DedupeState, NotificationDedupeStore},
+};
+
+/// Creates the tables used by 'PostgresStore' (if they do not exist yet).
+pub const MIGRATION_SQL: &str =
+    include_str!("../../migrations/postgres/0001_create_iap_tables.sql");
+
+const PLATFORM_APPLE: &str = "APPLE";
+const PLATFORM_APPLE_EXTERNAL: &str = "APPLE_EXTERNAL";
+const PLATFORM_GOOGLE: &str = "GOOGLE";
+const PLATFORM_GOOGLE_EXTERNAL: &str = "GOOGLE_EXTERNAL";
+#[cfg(feature = "microsoft-store")]
+const PLATFORM_MICROSOFT: &str = "MICROSOFT";
+#[cfg(feature = "steam")]
+const PLATFORM_STEAM: &str = "STEAM";
+#[cfg(feature = "paddle")]
+const PLATFORM_PADDLE: &str = "PADDLE";
+#[cfg(feature = "stripe")]
+const PLATFORM_STRIPE: &str = "STRIPE";
+
+/// 'NotificationDedupeStore' and 'EntitlementStore' backed by Postgres
+/// (through sqlx), for conventional backends.
+///
+/// The tables are created by 'MIGRATION_SQL', which can be applied with
+/// 'migrate' or copied into the application's own migrations.
+///
+/// As with 'integrations::dynamodb::DynamoDbDedupeStore', handled
+/// notifications are remembered for 24 hours by default (see
+/// 'dedupe_window'), and claims of notifications which are still being
+/// processed expire after 5 minutes (see 'in_flight_timeout'). Postgres has no
+/// TTL, so expired rows should be removed periodically with
+/// 'delete_expired_notifications'.
+///
+/// Usage:
+///
+/// ```ignore
+/// let store = PostgresStore::new(pool);
+/// store.migrate().await?;
+/// let handler = WebhookHandler::new(iap_util, callback).dedupe_store(store.clone());
+/// ```
+#[derive(Clone)]
+pub struct PostgresStore {
+    pool: PgPool,
+    dedupe_window: Duration,
+    in_flight_timeout: Duration,
+}
+
+impl PostgresStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            dedupe_window: Duration::from_secs(24 * 60 * 60),
+            in_flight_timeout: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// How long handled notifications are remembered (default: 24 hours).
+    pub fn dedupe_window(mut self, window: Duration) -> Self {
+        self.dedupe_window = window;
+        self
+    }
+
+    /// How long a notification may be processed before its claim expires,
+    /// and redeliveries are processed again (default: 5 minutes).
+    pub fn in_flight_timeout(mut self, timeout: Duration) -> Self {
+        self.in_flight_timeout = timeout;
+        self
+    }
+
+    /// Apply 'MIGRATION_SQL'.
+    pub async fn migrate(&self) -> Result<(), ServerError> {
+        sqlx::raw_sql(MIGRATION_SQL)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EntitlementStoreError::with_debug("failed to apply migration", &e))?;
+        Ok(())
+    }
+
+    /// Remove dedupe records which have expired. Returns the number of
+    /// records removed.
+    pub async fn delete_expired_notifications(&self) -> Result<u64, ServerError> {
+        sqlx::query("DELETE FROM iap_notification_dedupe WHERE expires_at < now()")
+            .execute(&self.pool)
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(|e| DedupeStoreError::with_debug("*", "failed to delete expired records", &e))
+    }
+
+    fn expires_at(duration: Duration) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX)
+    }
+
+    fn purchase_key(purchase_id: &IapPurchaseId) -> (&'static str, String) {
+        match purchase_id {
+            IapPurchaseId::AppStoreTransactionId(id) => (PLATFORM_APPLE, id.to_string()),
+            IapPurchaseId::AppleExternalPurchaseId(id) => (PLATFORM_APPLE_EXTERNAL, id.to_string()),
+            IapPurchaseId::GooglePlayPurchaseToken(token) => (PLATFORM_GOOGLE, token.to_string()),
+            IapPurchaseId::GoogleExternalTransactionId(id) => {
+                (PLATFORM_GOOGLE_EXTERNAL, id.to_string())
+            }
+            #[cfg(feature = "microsoft-store")]
+            IapPurchaseId::MicrosoftStoreIdKey(key) => (PLATFORM_MICROSOFT, key.to_string()),
+            #[cfg(feature = "steam")]
+            IapPurchaseId::SteamOrderId(id) => (PLATFORM_STEAM, id.to_string()),
+            #[cfg(feature = "paddle")]
+            IapPurchaseId::PaddleSubscriptionId(id) => (PLATFORM_PADDLE, id.to_string()),
+            #[cfg(feature = "stripe")]
+            IapPurchaseId::StripeSubscriptionId(id) => (PLATFORM_STRIPE, id.to_string()),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl NotificationDedupeStore for PostgresStore {
+    async fn begin(&self, notification_id: &str) -> Result<DedupeState, ServerError> {
+        // Claim the notification, unless there is an unexpired record for it.
+        let claimed = sqlx::query(
+            "INSERT INTO iap_notification_dedupe (notification_id, completed, expires_at) \
+             VALUES ($1, FALSE, $2) \
+             ON CONFLICT (notification_id) DO UPDATE \
+             SET completed = FALSE, expires_at = EXCLUDED.expires_at \
+             WHERE iap_notification_dedupe.expires_at < now() \
+             RETURNING notification_id",
+        )
+        .bind(notification_id)
+        .bind(Self::expires_at(self.in_flight_timeout))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            DedupeStoreError::with_debug(notification_id, "failed to claim notification", &e)
+        })?;
+        if claimed.is_some() {
+            return Ok(DedupeState::New);
+        }
+
+        let completed =
+            sqlx::query("SELECT completed FROM iap_notification_dedupe WHERE notification_id = $1")
+                .bind(notification_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| {
+                    DedupeStoreError::with_debug(notification_id, "failed to read notification", &e)
+                })?
+                .map(|row| row.try_get::<bool, _>("completed"))
+                .transpose()
+                .map_err(|e| {
+                    DedupeStoreError::with_debug(notification_id, "failed to read notification", &e)
+                })?;
+        // If the record was released in the meantime, report the
+        // notification as in-flight, so that it is redelivered.
+        Ok(match completed {
+            Some(true) => DedupeState::Completed,
+            _ => DedupeState::InFlight,
+        })
+    }
+
+    async fn finish(&self, notification_id: &str, success: bool) -> Result<(), ServerError> {
+        let result = if success {
+            sqlx::query(
+                "INSERT INTO iap_notification_dedupe (notification_id, completed, expires_at) \
+                 VALUES ($1, TRUE, $2) \
+                 ON CONFLICT (notification_id) DO UPDATE \
+                 SET completed = TRUE, expires_at = EXCLUDED.expires_at",
+            )
+            .bind(notification_id)
+            .bind(Self::expires_at(self.dedupe_window))
+            .execute(&self.pool)
+            .await
+        } else {
+            sqlx::query("DELETE FROM iap_notification_dedupe WHERE notification_id = $1")
+                .bind(notification_id)
+                .execute(&self.pool)
+                .await
+        };
+        result.map(|_| ()).map_err(|e| {
+            DedupeStoreError::with_debug(notification_id, "failed to record outcome", &e)
+        })
+    }
+    async fn state(&self, notification_id: &str) -> Result<Option<DedupeState>, ServerError> {
+        let completed = sqlx::query(
+            "SELECT completed FROM iap_notification_dedupe \
+             WHERE notification_id = $1 AND expires_at >= now()",
+        )
+        .bind(notification_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            DedupeStoreError::with_debug(notification_id, "failed to read notification", &e)
+        })?
+        .map(|row| row.try_get::<bool, _>("completed"))
+        .transpose()
+        .map_err(|e| {
+            DedupeStoreError::with_debug(notification_id, "failed to read notification", &e)
+        })?;
+        Ok(completed.map(|completed| match completed {
+            true => DedupeState::Completed,
+            false => DedupeState::InFlight,
+        }))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl EntitlementStore for PostgresStore {
+    async fn get(&self, purchase_id: &IapPurchaseId) -> Result<Option<Entitlement>, ServerError> {
+        let (platform, id) = Self::purchase_key(purchase_id);
+        let Some(row) = sqlx::query(
+            "SELECT product_id, kind, is_active, is_sandbox, expires_at, updated_at \
+             FROM iap_entitlements WHERE platform = $1 AND purchase_id = $2",
+        )
+        .bind(platform)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| EntitlementStoreError::with_debug("failed to read entitlement", &e))?
+        else {
+            return Ok(None);
+        };
+        let parse_error =
+            |e: sqlx::Error| EntitlementStoreError::with_debug("failed to parse entitlement", &e);
+        let kind: String = row.try_get("kind").map_err(parse_error)?;
+        Ok(Some(Entitlement {
+            purchase_id: purchase_id.clone(),
+            product_id: row.try_get("product_id").map_err(parse_error)?,
+            kind: EntitlementKind::parse(&kind).ok_or_else(|| {
+                EntitlementStoreError::with_debug("unknown entitlement kind", &kind)
+            })?,
+            is_active: row.try_get("is_active").map_err(parse_error)?,
+            is_sandbox: row.try_get("is_sandbox").map_err(parse_error)?,
+            expires_at: row.try_get("expires_at").map_err(parse_error)?,
+            updated_at: row.try_get("updated_at").map_err(parse_error)?,
+        }))
+    }
+
+    async fn put(&self, entitlement: &Entitlement) -> Result<(), ServerError> {
+        let (platform, id) = Self::purchase_key(&entitlement.purchase_id);
+        sqlx::query(
+            "INSERT INTO iap_entitlements \
+             (platform, purchase_id, product_id, kind, is_active, is_sandbox, expires_at, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+             ON CONFLICT (platform, purchase_id) DO UPDATE SET \
+             product_id = EXCLUDED.product_id, kind = EXCLUDED.kind, \
+             is_active = EXCLUDED.is_active, is_sandbox = EXCLUDED.is_sandbox, \
+             expires_at = EXCLUDED.expires_at, updated_at = EXCLUDED.updated_at \
+             WHERE iap_entitlements.updated_at <= EXCLUDED.updated_at",
+        )
+        .bind(platform)
+        .bind(id)
+        .bind(&entitlement.product_id)
+        .bind(entitlement.kind.as_str())
+        .bind(entitlement.is_active)
+        .bind(entitlement.is_sandbox)
+        .bind(entitlement.expires_at)
+        .bind(entitlement.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EntitlementStoreError::with_debug("failed to write entitlement", &e))?;
+        Ok(())
+    }
+}
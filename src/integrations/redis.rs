@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use fractic_server_error::ServerError;
+use redis::aio::ConnectionManager;
+
+use crate::{
+    cache::CacheStore,
+    errors::{CacheStoreError, DedupeStoreError},
+    webhook::{DedupeState, NotificationDedupeStore},
+};
+
+const STATUS_IN_FLIGHT: &str = "IN_FLIGHT";
+const STATUS_COMPLETED: &str = "COMPLETED";
+
+/// 'CacheStore' and 'NotificationDedupeStore' backed by Redis, so that
+/// multiple instances share verification results, product metadata, and
+/// handled notification IDs.
+///
+/// As with the other dedupe stores, handled notifications are remembered for
+/// 24 hours by default (see 'dedupe_window'), and claims of notifications
+/// which are still being processed expire after 5 minutes (see
+/// 'in_flight_timeout'). All records are written with an expiry, so no
+/// cleanup is required.
+///
+/// Usage:
+///
+/// ```ignore
+/// let store = RedisStore::from_url("redis://cache.internal:6379").await?;
+/// let iap_util = IapUtilBuilder::new(...)
+///     .verification_cache_ttl(Duration::from_secs(30))
+///     .cache_store(store.clone())
+///     .build()
+///     .await?;
+/// let handler = WebhookHandler::new(iap_util, callback).dedupe_store(store);
+/// ```
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: ConnectionManager,
+    dedupe_window: Duration,
+    in_flight_timeout: Duration,
+}
+
+impl RedisStore {
+    pub fn new(connection: ConnectionManager) -> Self {
+        Self {
+            connection,
+            dedupe_window: Duration::from_secs(24 * 60 * 60),
+            in_flight_timeout: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// Connect to the Redis server at the given URL (ex.
+    /// "redis://127.0.0.1:6379"). The connection is re-established
+    /// automatically if it drops.
+    pub async fn from_url(url: &str) -> Result<Self, ServerError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| CacheStoreError::with_debug("invalid Redis URL", &e))?;
+        let connection = client
+            .get_connection_manager()
+            .await
+            .map_err(|e| CacheStoreError::with_debug("failed to connect to Redis", &e))?;
+        Ok(Self::new(connection))
+    }
+
+    /// How long handled notifications are remembered (default: 24 hours).
+    pub fn dedupe_window(mut self, window: Duration) -> Self {
+        self.dedupe_window = window;
+        self
+    }
+
+    /// How long a notification may be processed before its claim expires,
+    /// and redeliveries are processed again (default: 5 minutes).
+    pub fn in_flight_timeout(mut self, timeout: Duration) -> Self {
+        self.in_flight_timeout = timeout;
+        self
+    }
+
+    fn notification_key(notification_id: &str) -> String {
+        format!("iap:notification:{notification_id}")
+    }
+
+    /// Redis rejects non-positive expiry times.
+    fn expiry_millis(duration: Duration) -> u64 {
+        (duration.as_millis() as u64).max(1)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl CacheStore for RedisStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, ServerError> {
+        redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| CacheStoreError::with_debug("GET failed", &e))
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), ServerError> {
+        redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("PX")
+            .arg(Self::expiry_millis(ttl))
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| CacheStoreError::with_debug("SET failed", &e))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), ServerError> {
+        redis::cmd("DEL")
+            .arg(key)
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| CacheStoreError::with_debug("DEL failed", &e))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl NotificationDedupeStore for RedisStore {
+    async fn begin(&self, notification_id: &str) -> Result<DedupeState, ServerError> {
+        let key = Self::notification_key(notification_id);
+        let mut connection = self.connection.clone();
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(STATUS_IN_FLIGHT)
+            .arg("NX")
+            .arg("PX")
+            .arg(Self::expiry_millis(self.in_flight_timeout))
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| {
+                DedupeStoreError::with_debug(notification_id, "failed to claim notification", &e)
+            })?;
+        if claimed.is_some() {
+            return Ok(DedupeState::New);
+        }
+
+        let status: Option<String> = redis::cmd("GET")
+            .arg(&key)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| {
+                DedupeStoreError::with_debug(notification_id, "failed to read notification", &e)
+            })?;
+        // If the claim was released in the meantime, report the notification
+        // as in-flight, so that it is redelivered.
+        Ok(match status.as_deref() {
+            Some(STATUS_COMPLETED) => DedupeState::Completed,
+            _ => DedupeState::InFlight,
+        })
+    }
+
+    async fn finish(&self, notification_id: &str, success: bool) -> Result<(), ServerError> {
+        let key = Self::notification_key(notification_id);
+        let command = if success {
+            let mut command = redis::cmd("SET");
+            command
+                .arg(&key)
+                .arg(STATUS_COMPLETED)
+                .arg("PX")
+                .arg(Self::expiry_millis(self.dedupe_window));
+            command
+        } else {
+            let mut command = redis::cmd("DEL");
+            command.arg(&key);
+            command
+        };
+        command
+            .query_async::<()>(&mut self.connection.clone())
+            .await
+            .map_err(|e| {
+                DedupeStoreError::with_debug(notification_id, "failed to record outcome", &e)
+            })
+    }
+    async fn state(&self, notification_id: &str) -> Result<Option<DedupeState>, ServerError> {
+        let status: Option<String> = redis::cmd("GET")
+            .arg(Self::notification_key(notification_id))
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| {
+                DedupeStoreError::with_debug(notification_id, "failed to read notification", &e)
+            })?;
+        Ok(status.map(|status| match status.as_str() {
+            STATUS_COMPLETED => DedupeState::Completed,
+            _ => DedupeState::InFlight,
+        }))
+    }
+}
@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use aws_sdk_s3::{primitives::ByteStream, Client};
+use fractic_server_error::ServerError;
+use serde_json::json;
+
+use crate::{
+    archive::{ArchiveSink, ArchivedNotification},
+    error_observer::IapPlatform,
+    errors::{ArchiveError, ReportFlushError},
+    reporting::{ProcessingReport, ReportSink},
+};
+
+/// 'ArchiveSink' storing each notification as a JSON object in an S3 bucket.
+///
+/// Objects are written to
+/// "<prefix><platform>/<yyyy>/<mm>/<dd>/<notification_id>.json" (by the date
+/// the notification was received), and contain the notification ID,
+/// platform, receive time, original body, and decoded payloads. Redeliveries
+/// of notifications which were archived, but whose callback failed, overwrite
+/// the same object. Retention (ex. S3 Object Lock, lifecycle rules) is left to
+/// the bucket's configuration.
+///
+/// Also a 'ReportSink', storing processing reports in the same bucket, at
+/// "<prefix>reports/<yyyy>/<mm>/<dd>/<window_start>.json".
+///
+/// Usage:
+///
+/// ```ignore
+/// let sink = S3ArchiveSink::from_env("iap-notification-archive").await;
+/// let handler = WebhookHandler::new(iap_util, callback).archive_sink(sink);
+/// ```
+#[derive(Clone)]
+pub struct S3ArchiveSink {
+    client: Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3ArchiveSink {
+    pub fn new(client: Client, bucket: impl Into<String>) -> Self {
+        Self {
+            client,
+            bucket: bucket.into(),
+            prefix: String::new(),
+        }
+    }
+
+    /// Uses a client built from the default AWS configuration chain
+    /// (environment, profile, or instance / Lambda role).
+    pub async fn from_env(bucket: impl Into<String>) -> Self {
+        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .load()
+            .await;
+        Self::new(Client::new(&config), bucket)
+    }
+
+    /// Prefix of the object keys (ex. "notifications/"; default: none).
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, notification: &ArchivedNotification) -> String {
+        format!(
+            "{}{}/{}/{}.json",
+            self.prefix,
+            platform_name(notification.platform),
+            notification.received_at.format("%Y/%m/%d"),
+            notification.notification_id
+        )
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ArchiveSink for S3ArchiveSink {
+    async fn archive(&self, notification: &ArchivedNotification) -> Result<(), ServerError> {
+        let object = json!({
+            "notification_id": notification.notification_id,
+            "platform": platform_name(notification.platform),
+            "received_at": notification.received_at,
+            "body": notification.body,
+            "decoded_payloads": notification.decoded_payloads,
+        });
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.key(notification))
+            .content_type("application/json")
+            .body(ByteStream::from(object.to_string().into_bytes()))
+            .send()
+            .await
+            .map_err(|e| {
+                ArchiveError::with_debug(&notification.notification_id, "failed to put object", &e)
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ReportSink for S3ArchiveSink {
+    async fn flush(&self, report: &ProcessingReport) -> Result<(), ServerError> {
+        let window_start = report.window_start.to_rfc3339();
+        let object = serde_json::to_vec(report).map_err(|e| {
+            ReportFlushError::with_debug(&window_start, "failed to serialize report", &e)
+        })?;
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(format!(
+                "{}reports/{}/{}.json",
+                self.prefix,
+                report.window_start.format("%Y/%m/%d"),
+                window_start
+            ))
+            .content_type("application/json")
+            .body(ByteStream::from(object))
+            .send()
+            .await
+            .map_err(|e| ReportFlushError::with_debug(&window_start, "failed to put object", &e))?;
+        Ok(())
+    }
+}
+
+fn platform_name(platform: IapPlatform) -> &'static str {
+    match platform {
+        IapPlatform::AppStore => "app-store",
+        IapPlatform::GooglePlay => "google-play",
+        #[cfg(feature = "microsoft-store")]
+        IapPlatform::MicrosoftStore => "microsoft-store",
+        #[cfg(feature = "steam")]
+        IapPlatform::Steam => "steam",
+        #[cfg(feature = "paddle")]
+        IapPlatform::Paddle => "paddle",
+        #[cfg(feature = "stripe")]
+        IapPlatform::Stripe => "stripe",
+    }
+}
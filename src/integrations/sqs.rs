@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use futures::{stream, StreamExt as _};
+use serde::{Deserialize, Serialize};
+
+use crate::webhook::{WebhookHandler, WebhookOutcome};
+
+/// SQS event delivered to an AWS Lambda function (only the fields used by
+/// 'handle_batch'). Can be deserialized directly from the Lambda payload.
+///
+/// Each record's body should be the raw body of a store notification request,
+/// forwarded unchanged (ex. by an API Gateway SQS integration):
+///
+/// - App Store Server Notifications are detected by their 'signedPayload'
+///   field.
+/// - Anything else is handled as a Google Cloud Pub/Sub push request, which
+///   requires the request's authorization header to be forwarded as the
+///   'Authorization' message attribute.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SqsEvent {
+    #[serde(rename = "Records")]
+    pub records: Vec<SqsRecord>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqsRecord {
+    pub message_id: String,
+    pub body: String,
+    #[serde(default)]
+    pub message_attributes: HashMap<String, SqsMessageAttribute>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqsMessageAttribute {
+    pub string_value: Option<String>,
+}
+
+/// Outcome of each record in a batch, in the same order as the records.
+#[derive(Debug)]
+pub struct SqsBatchOutcome {
+    pub records: Vec<(String, WebhookOutcome)>,
+}
+
+/// Partial batch failure response ('ReportBatchItemFailures'), to return from
+/// the Lambda function.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqsBatchResponse {
+    pub batch_item_failures: Vec<SqsBatchItemFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqsBatchItemFailure {
+    pub item_identifier: String,
+}
+
+impl SqsBatchOutcome {
+    /// Only retryable failures are reported, so that SQS redelivers them (and
+    /// eventually moves them to the dead-letter queue, if configured).
+    /// Permanent failures can never succeed, so they are removed from the
+    /// queue along with successful records; inspect 'records' to log them.
+    pub fn response(&self) -> SqsBatchResponse {
+        SqsBatchResponse {
+            batch_item_failures: self
+                .records
+                .iter()
+                .filter(|(_, outcome)| matches!(outcome, WebhookOutcome::RetryableFailure(_)))
+                .map(|(message_id, _)| SqsBatchItemFailure {
+                    item_identifier: message_id.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Verify and handle each record of the batch through the given handler,
+/// processing at most 'max_concurrency' records at a time.
+///
+/// Usage (with the 'lambda_runtime' crate):
+///
+/// ```ignore
+/// lambda_runtime::run(service_fn(|event: LambdaEvent<SqsEvent>| async {
+///     let outcome = sqs::handle_batch(&handler, event.payload, 10).await;
+///     Ok::<_, Error>(outcome.response())
+/// }))
+/// .await
+/// ```
+pub async fn handle_batch(
+    handler: &WebhookHandler,
+    event: SqsEvent,
+    max_concurrency: usize,
+) -> SqsBatchOutcome {
+    let records = stream::iter(event.records)
+        .map(|record| async move {
+            let outcome = handle_record(handler, &record).await;
+            (record.message_id, outcome)
+        })
+        .buffered(max_concurrency.max(1))
+        .collect()
+        .await;
+    SqsBatchOutcome { records }
+}
+
+async fn handle_record(handler: &WebhookHandler, record: &SqsRecord) -> WebhookOutcome {
+    let is_apple = serde_json::from_str::<serde_json::Value>(&record.body)
+        .map(|body| body.get("signedPayload").is_some())
+        .unwrap_or(false);
+    if is_apple {
+        handler.handle_apple(&record.body).await
+    } else {
+        let authorization_header = record
+            .message_attributes
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+            .and_then(|(_, attribute)| attribute.string_value.as_deref());
+        handler
+            .handle_google(authorization_header, &record.body)
+            .await
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+/// Hook into the callouts made to the platform APIs (App Store Server API,
+/// Google Play Developer API), ex. for audit logging or to add headers
+/// required by an egress proxy.
+///
+/// Interceptors are registered through 'IapUtilBuilder::interceptor', and are
+/// called in the order they were registered. Both methods default to no-ops.
+///
+/// NOTE: Requests made internally by the underlying auth libraries (fetching
+/// Google OAuth tokens and signing keys) are not intercepted.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait CalloutInterceptor: Send + Sync {
+    /// Called once before a callout is sent (retries reuse the same request).
+    /// Headers added here are sent with the request.
+    async fn on_request(&self, _request: &mut CalloutRequest) {}
+
+    /// Called after each attempt, with either the response status or the
+    /// error which prevented a response from being received.
+    async fn on_response(&self, _response: &CalloutResponse) {}
+}
+
+/// An outgoing callout, as seen by a 'CalloutInterceptor'.
+#[derive(Debug, Clone)]
+pub struct CalloutRequest {
+    /// Name of the platform API function being called (ex.
+    /// "GetTransactionInfo").
+    pub function_name: String,
+    pub method: String,
+    pub url: String,
+    /// Request headers. Credentials (ex. the 'Authorization' header) are
+    /// redacted.
+    pub headers: Vec<(String, String)>,
+    /// See 'correlation::with_correlation_id'.
+    pub correlation_id: Option<String>,
+    added_headers: Vec<(String, String)>,
+}
+
+impl CalloutRequest {
+    pub(crate) fn new(
+        function_name: &str,
+        method: String,
+        url: String,
+        headers: Vec<(String, String)>,
+        correlation_id: Option<String>,
+    ) -> Self {
+        Self {
+            function_name: function_name.to_owned(),
+            method,
+            url,
+            headers,
+            correlation_id,
+            added_headers: Vec::new(),
+        }
+    }
+
+    /// Add a header to the outgoing request. If the header is already set, it
+    /// is replaced.
+    pub fn insert_header(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let (name, value) = (name.into(), value.into());
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+        self.headers.push((name.clone(), value.clone()));
+        self.added_headers.push((name, value));
+    }
+
+    pub(crate) fn added_headers(&self) -> &[(String, String)] {
+        &self.added_headers
+    }
+}
+
+/// The outcome of a single callout attempt, as seen by a 'CalloutInterceptor'.
+#[derive(Debug, Clone)]
+pub struct CalloutResponse {
+    pub function_name: String,
+    pub method: String,
+    pub url: String,
+    /// 1 for the first attempt, incremented for each retry.
+    pub attempt: u32,
+    /// HTTP status code, if a response was received.
+    pub status: Option<u16>,
+    /// Description of the error, if no response was received.
+    pub error: Option<String>,
+    pub elapsed: Duration,
+    /// See 'correlation::with_correlation_id'.
+    pub correlation_id: Option<String>,
+}
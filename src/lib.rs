@@ -1,17 +1,47 @@
 pub(crate) mod data {
     pub(crate) mod datasources {
+        #[cfg(feature = "app-store-connect")]
+        pub(crate) mod app_store_connect_api_datasource;
         pub(crate) mod app_store_server_api_datasource;
         pub(crate) mod app_store_server_notification_datasource;
+        pub(crate) mod cached_credential;
+        pub(crate) mod callout_error;
         pub(crate) mod google_cloud_rtdn_notification_datasource;
         pub(crate) mod google_play_developer_api_datasource;
+        pub(crate) mod http_client;
+        #[cfg(feature = "microsoft-store")]
+        pub(crate) mod microsoft_store_collections_api_datasource;
+        #[cfg(feature = "native")]
+        pub(crate) mod native_signature_verifier;
+        #[cfg(feature = "paddle")]
+        pub(crate) mod paddle_webhook_datasource;
+        #[cfg(feature = "steam")]
+        pub(crate) mod steam_micro_txn_api_datasource;
+        #[cfg(feature = "stripe")]
+        pub(crate) mod stripe_webhook_datasource;
         mod utils;
     }
     pub(crate) mod models {
+        #[cfg(feature = "app-store-connect")]
+        pub(crate) mod app_store_connect_api {
+            pub(crate) mod document_model;
+            pub(crate) mod localization_model;
+            pub(crate) mod price_model;
+            pub(crate) mod product_model;
+        }
         pub(crate) mod app_store_server_api {
+            pub(crate) mod app_transaction_model;
             pub(crate) mod common;
+            pub(crate) mod consumption_request_model;
+            pub(crate) mod error_response_model;
+            pub(crate) mod extend_renewal_date_model;
+            pub(crate) mod external_purchase_report_model;
+            pub(crate) mod history_response_model;
             pub(crate) mod jws_renewal_info_decoded_payload_model;
             pub(crate) mod jws_transaction_decoded_payload_model;
+            pub(crate) mod notification_history_model;
             pub(crate) mod send_test_notification_response;
+            pub(crate) mod status_response_model;
             pub(crate) mod transaction_info_response_model;
         }
         pub(crate) mod app_store_server_notifications {
@@ -23,29 +53,118 @@ pub(crate) mod data {
             pub(crate) mod pub_sub_model;
         }
         pub(crate) mod google_play_developer_api {
+            pub(crate) mod external_transaction_model;
             pub(crate) mod in_app_product_model;
             pub(crate) mod product_purchase_model;
+            pub(crate) mod product_purchase_v2_model;
+            pub(crate) mod subscription_deferral_model;
+            pub(crate) mod subscription_model;
+            pub(crate) mod subscription_offer_model;
             pub(crate) mod subscription_purchase_v2_model;
         }
+        #[cfg(feature = "microsoft-store")]
+        pub(crate) mod microsoft_store_collections_api {
+            pub(crate) mod collections_query_response_model;
+        }
+        #[cfg(feature = "paddle")]
+        pub(crate) mod paddle_billing_webhooks {
+            pub(crate) mod event_model;
+            pub(crate) mod subscription_model;
+        }
+        #[cfg(feature = "steam")]
+        pub(crate) mod steam_micro_txn_api {
+            pub(crate) mod query_txn_response_model;
+        }
+        #[cfg(feature = "stripe")]
+        pub(crate) mod stripe_webhooks {
+            pub(crate) mod event_model;
+            pub(crate) mod invoice_model;
+            pub(crate) mod subscription_model;
+        }
     }
     pub(crate) mod repositories {
         pub(crate) mod iap_repository_impl;
     }
+    pub(crate) mod verification_cache;
 }
 
 pub mod domain {
     pub mod entities {
+        pub mod iap_api_error;
+        pub mod iap_app_purchase;
+        pub mod iap_audit;
+        pub mod iap_billing_period;
+        pub mod iap_consumption;
         pub mod iap_details;
+        pub mod iap_entitlement_extension;
+        pub mod iap_external_purchase;
+        pub mod iap_health_report;
+        pub mod iap_notification_history;
+        pub mod iap_price_schedule;
         pub mod iap_product_id;
+        pub mod iap_product_listing;
         pub mod iap_purchase_id;
+        pub mod iap_refund_risk;
+        pub mod iap_subscription_plan;
+        pub mod iap_subscription_status;
+        pub mod iap_transaction_history;
         pub mod iap_update_notification;
+        pub mod iap_verification;
     }
     pub mod repositories {
         pub mod iap_repository;
     }
 }
 
+pub mod archive;
+pub mod budget;
+pub mod cache;
+pub mod capture;
+pub mod config;
+mod config_validation;
 pub mod constants;
+pub mod consumption;
+pub mod correlation;
+#[cfg(feature = "unverified-jws")]
+pub mod dangerous;
+pub mod deny_list;
+pub mod entitlements;
+pub mod error_observer;
 pub mod errors;
+pub mod integrations {
+    #[cfg(feature = "actix-web")]
+    pub mod actix_web;
+    #[cfg(feature = "axum")]
+    pub mod axum;
+    #[cfg(feature = "dynamodb")]
+    pub mod dynamodb;
+    pub mod gcp;
+    #[cfg(feature = "postgres")]
+    pub mod postgres;
+    #[cfg(feature = "redis")]
+    pub mod redis;
+    #[cfg(feature = "s3")]
+    pub mod s3;
+    #[cfg(feature = "sqs")]
+    pub mod sqs;
+}
+pub mod interceptor;
+pub mod pagination;
+pub mod partial_consumption;
+pub mod region;
+pub mod reporting;
+mod scoped;
 pub mod secrets;
+pub mod subscription_groups;
+#[cfg(feature = "test-util")]
+pub mod test_util {
+    pub mod mock_iap_repository;
+    pub mod notification_fixtures;
+    #[cfg(feature = "store-simulator")]
+    pub mod store_simulator;
+    #[cfg(feature = "native")]
+    pub mod test_signature_verifier;
+}
 pub mod util;
+pub mod verifier;
+pub mod webhook;
@@ -0,0 +1,51 @@
+use std::future::Future;
+
+use fractic_server_error::ServerError;
+use futures::{stream, Stream, TryStreamExt as _};
+
+/// One page of results from a paginated platform API.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Token to request the next page with, or 'None' if this is the last
+    /// page.
+    pub next_token: Option<String>,
+}
+
+/// Stream of all items from a paginated platform API, following pagination
+/// tokens transparently. Pages are only requested as the stream is consumed,
+/// and the stream ends after the first error.
+///
+/// 'fetch_page' is called with 'None' for the first page, and with the
+/// previous page's 'next_token' afterwards.
+///
+/// List APIs are exposed as such streams, so callers do not need their own
+/// token loops:
+///
+/// ```ignore
+/// let mut items = std::pin::pin!(paginate(|token| fetch_list_page(token)));
+/// while let Some(item) = items.try_next().await? {
+///     ...
+/// }
+/// ```
+pub fn paginate<T, F, Fut>(fetch_page: F) -> impl Stream<Item = Result<T, ServerError>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<Page<T>, ServerError>>,
+{
+    // State: the fetch function, and the token for the next page (outer
+    // 'None' once the last page was fetched).
+    stream::try_unfold(
+        (fetch_page, Some(None)),
+        |(mut fetch_page, token): (F, Option<Option<String>>)| async move {
+            let Some(token) = token else {
+                return Ok::<_, ServerError>(None);
+            };
+            let page = fetch_page(token).await?;
+            let next = page.next_token.map(Some);
+            Ok(Some((page.items, (fetch_page, next))))
+        },
+    )
+    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+    .try_flatten()
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use async_trait::async_trait;
+use fractic_server_error::ServerError;
+
+use crate::{
+    domain::entities::{
+        iap_details::MaybeKnown, iap_product_id::IapConsumableId, iap_purchase_id::IapPurchaseId,
+    },
+    error_observer::IapError,
+    errors::{InsufficientQuantity, InvalidConsumeQuantity},
+    util::IapUtil,
+};
+
+/// Records how many units of multi-quantity consumable purchases have been
+/// granted, for 'PartialConsumer'.
+///
+/// The default store keeps quantities in memory. Implement this trait to
+/// persist them (and share them across instances); otherwise units granted
+/// before a restart can be granted again.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ConsumedQuantityStore: Send + Sync {
+    /// Number of units of the purchase consumed so far (0 if unknown).
+    async fn get(&self, purchase_id: &IapPurchaseId) -> Result<i64, ServerError>;
+
+    /// Atomically add 'quantity' to the consumed units of the purchase, and
+    /// return the new total. If the total would exceed 'limit', nothing is
+    /// recorded, and None is returned.
+    async fn add(
+        &self,
+        purchase_id: &IapPurchaseId,
+        quantity: i64,
+        limit: i64,
+    ) -> Result<Option<i64>, ServerError>;
+}
+
+/// Units of a purchase, after a 'PartialConsumer::consume_quantity'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsumedQuantity {
+    /// Units bought with the purchase.
+    pub purchased: i64,
+    /// Units consumed so far, including the ones just consumed.
+    pub consumed: i64,
+}
+
+impl ConsumedQuantity {
+    pub fn remaining(&self) -> i64 {
+        self.purchased - self.consumed
+    }
+}
+
+/// Consumes multi-quantity consumable purchases (ex. 5 units bought at once)
+/// a few units at a time, keeping track of the units granted so far in a
+/// 'ConsumedQuantityStore'.
+///
+/// The platforms only consume purchases as a whole, so the purchase is only
+/// consumed (see 'IapUtil::consume') once all of its units are.
+pub struct PartialConsumer {
+    iap_util: IapUtil,
+    store: Arc<dyn ConsumedQuantityStore>,
+}
+
+impl PartialConsumer {
+    /// Consumed quantities are kept in memory by default (see 'store').
+    pub fn new(iap_util: IapUtil) -> Self {
+        Self {
+            iap_util,
+            store: Arc::new(InMemoryConsumedQuantityStore::default()),
+        }
+    }
+
+    /// Record consumed quantities in the given store instead of in memory.
+    pub fn store(mut self, store: impl ConsumedQuantityStore + 'static) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Verify the purchase, and consume 'quantity' of its units. Fails with
+    /// 'InsufficientQuantity' (without consuming anything) if fewer units
+    /// remain.
+    ///
+    /// NOTE: If consuming the purchase on the platform fails after its last
+    /// units were recorded, the error is returned, but the units stay
+    /// recorded; retry with 'IapUtil::consume'.
+    pub async fn consume_quantity(
+        &self,
+        product_id: IapConsumableId,
+        purchase_id: IapPurchaseId,
+        quantity: i64,
+    ) -> Result<ConsumedQuantity, IapError> {
+        if quantity <= 0 {
+            return Err(InvalidConsumeQuantity::new(&format!(
+                "quantity must be positive, got {quantity}"
+            ))
+            .into());
+        }
+        let details = self
+            .iap_util
+            .verify_and_get_details(product_id.clone(), purchase_id.clone(), false)
+            .await?;
+        let purchased = details.type_specific_details.quantity;
+        let key = &details.canonical_id;
+        // Purchases consumed on the platform have no units left, regardless
+        // of what was recorded.
+        if details.type_specific_details.is_consumed == MaybeKnown::Known(true) {
+            return Err(InsufficientQuantity::new(&format!(
+                "requested {quantity}, but the purchase was already consumed"
+            ))
+            .into());
+        }
+        let Some(consumed) = self.store.add(key, quantity, purchased).await? else {
+            let remaining = purchased - self.store.get(key).await?;
+            return Err(InsufficientQuantity::new(&format!(
+                "requested {quantity}, but only {remaining} remain"
+            ))
+            .into());
+        };
+        if consumed == purchased {
+            self.iap_util.consume(product_id, purchase_id).await?;
+        }
+        Ok(ConsumedQuantity {
+            purchased,
+            consumed,
+        })
+    }
+
+    /// Units of the purchase consumed so far, as recorded in the store.
+    /// Quantities are recorded by the purchase's 'IapDetails::canonical_id',
+    /// so 'purchase_id' is resolved to it first (see
+    /// 'IapUtil::canonical_purchase_id').
+    pub async fn consumed_quantity(
+        &self,
+        product_id: IapConsumableId,
+        purchase_id: IapPurchaseId,
+    ) -> Result<i64, IapError> {
+        let canonical = self
+            .iap_util
+            .canonical_purchase_id(product_id, purchase_id)
+            .await?;
+        Ok(self.store.get(&canonical.canonical_id).await?)
+    }
+}
+
+/// Default 'ConsumedQuantityStore', local to the process.
+#[derive(Default)]
+struct InMemoryConsumedQuantityStore {
+    consumed: Mutex<HashMap<IapPurchaseId, i64>>,
+}
+
+impl InMemoryConsumedQuantityStore {
+    /// Totals are only updated after the limit check, so they stay consistent
+    /// even if a holder of the lock panicked, and poisoning is ignored.
+    fn consumed(&self) -> MutexGuard<'_, HashMap<IapPurchaseId, i64>> {
+        self.consumed.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ConsumedQuantityStore for InMemoryConsumedQuantityStore {
+    async fn get(&self, purchase_id: &IapPurchaseId) -> Result<i64, ServerError> {
+        Ok(self.consumed().get(purchase_id).copied().unwrap_or(0))
+    }
+
+    async fn add(
+        &self,
+        purchase_id: &IapPurchaseId,
+        quantity: i64,
+        limit: i64,
+    ) -> Result<Option<i64>, ServerError> {
+        let mut consumed = self.consumed();
+        let total = consumed.entry(purchase_id.clone()).or_insert(0);
+        if *total + quantity > limit {
+            return Ok(None);
+        }
+        *total += quantity;
+        Ok(Some(*total))
+    }
+}
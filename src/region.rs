@@ -0,0 +1,11 @@
+/// The alpha-3 code (ex. "USA") of an alpha-2 region code (ex. "US"), or None
+/// if the code is not known. Case-insensitive.
+pub fn alpha2_to_alpha3(alpha_2: &str) -> Option<&'static str> {
+    rust_iso3166::from_alpha2(&alpha_2.to_ascii_uppercase()).map(|country| country.alpha3)
+}
+
+/// The alpha-2 code (ex. "US") of an alpha-3 region code (ex. "USA"), or None
+/// if the code is not known. Case-insensitive.
+pub fn alpha3_to_alpha2(alpha_3: &str) -> Option<&'static str> {
+    rust_iso3166::from_alpha3(&alpha_3.to_ascii_uppercase()).map(|country| country.alpha2)
+}
@@ -0,0 +1,186 @@
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+use serde::{Deserialize, Serialize};
+
+use crate::webhook::WebhookOutcome;
+
+/// Receives the 'ProcessingReport' of each elapsed window from a
+/// 'ProcessingReporter', ex. to publish it as metrics or archive it for ops
+/// dashboards.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ReportSink: Send + Sync {
+    async fn flush(&self, report: &ProcessingReport) -> Result<(), ServerError>;
+}
+
+/// Summary of the notifications handled in a time window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessingReport {
+    pub window_start: DateTime<Utc>,
+    /// When the report was flushed (or, for snapshots, taken).
+    pub window_end: DateTime<Utc>,
+    /// Counts per notification type ('NotificationDetails::type_name').
+    pub by_type: BTreeMap<String, ProcessingCounts>,
+    /// Requests which failed before the notification could be parsed (ex.
+    /// invalid signatures, or failed callouts while parsing), so whose type
+    /// is not known.
+    pub unparsed: ProcessingCounts,
+}
+
+/// Outcomes of the notifications counted in a 'ProcessingReport' (see
+/// 'WebhookOutcome').
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessingCounts {
+    pub processed: u64,
+    pub duplicates: u64,
+    pub permanent_failures: u64,
+    pub retryable_failures: u64,
+}
+
+impl ProcessingCounts {
+    pub fn total(&self) -> u64 {
+        self.processed + self.duplicates + self.permanent_failures + self.retryable_failures
+    }
+
+    fn record(&mut self, outcome: &WebhookOutcome) {
+        match outcome {
+            WebhookOutcome::Processed => self.processed += 1,
+            WebhookOutcome::Duplicate => self.duplicates += 1,
+            WebhookOutcome::PermanentFailure(_) => self.permanent_failures += 1,
+            WebhookOutcome::RetryableFailure(_) => self.retryable_failures += 1,
+        }
+    }
+
+    fn add(&mut self, other: &ProcessingCounts) {
+        self.processed += other.processed;
+        self.duplicates += other.duplicates;
+        self.permanent_failures += other.permanent_failures;
+        self.retryable_failures += other.retryable_failures;
+    }
+}
+
+impl ProcessingReport {
+    fn new(window_start: DateTime<Utc>) -> Self {
+        Self {
+            window_start,
+            window_end: window_start,
+            by_type: BTreeMap::new(),
+            unparsed: ProcessingCounts::default(),
+        }
+    }
+
+    /// Counts across all notification types, including unparsed requests.
+    pub fn totals(&self) -> ProcessingCounts {
+        let mut totals = self.unparsed;
+        for counts in self.by_type.values() {
+            totals.add(counts);
+        }
+        totals
+    }
+
+    /// Record the outcome of a webhook request, for a notification of the
+    /// given type (None if it could not be parsed).
+    pub fn record(&mut self, notification_type: Option<&str>, outcome: &WebhookOutcome) {
+        match notification_type {
+            Some(notification_type) => self
+                .by_type
+                .entry(notification_type.to_owned())
+                .or_default()
+                .record(outcome),
+            None => self.unparsed.record(outcome),
+        }
+    }
+
+    /// Add the counts of an earlier report (ex. one which could not be
+    /// flushed), extending the window to cover it.
+    fn merge(&mut self, earlier: ProcessingReport) {
+        self.window_start = self.window_start.min(earlier.window_start);
+        for (notification_type, counts) in &earlier.by_type {
+            self.by_type
+                .entry(notification_type.clone())
+                .or_default()
+                .add(counts);
+        }
+        self.unparsed.add(&earlier.unparsed);
+    }
+}
+
+/// Accumulates the outcomes of webhook requests into a 'ProcessingReport',
+/// and passes it to a 'ReportSink' once per window. Registered through
+/// 'WebhookHandler::processing_reporter', but can also be filled by callers
+/// handling notifications themselves (see 'record').
+///
+/// Windows are flushed lazily, by the first request recorded after the
+/// window elapsed (or by calling 'flush', ex. on shutdown or from a
+/// scheduled job). If the sink fails, the counts are kept and included in
+/// the next report.
+///
+/// Counts are kept in memory, per instance.
+pub struct ProcessingReporter {
+    window: Duration,
+    sink: Box<dyn ReportSink>,
+    current: Mutex<ProcessingReport>,
+}
+
+impl ProcessingReporter {
+    pub fn new(window: Duration, sink: impl ReportSink + 'static) -> Self {
+        Self {
+            window,
+            sink: Box::new(sink),
+            current: Mutex::new(ProcessingReport::new(Utc::now())),
+        }
+    }
+
+    /// Record the outcome of a webhook request (see
+    /// 'ProcessingReport::record'), flushing the report if the window
+    /// elapsed.
+    pub async fn record(
+        &self,
+        notification_type: Option<&str>,
+        outcome: &WebhookOutcome,
+    ) -> Result<(), ServerError> {
+        let elapsed = {
+            let Ok(mut current) = self.current.lock() else {
+                return Ok(());
+            };
+            current.record(notification_type, outcome);
+            let window = chrono::Duration::from_std(self.window).unwrap_or(chrono::Duration::MAX);
+            Utc::now() - current.window_start >= window
+        };
+        match elapsed {
+            true => self.flush().await,
+            false => Ok(()),
+        }
+    }
+
+    /// The counts of the current window so far, without flushing them.
+    pub fn snapshot(&self) -> Option<ProcessingReport> {
+        let current = self.current.lock().ok()?;
+        Some(ProcessingReport {
+            window_end: Utc::now(),
+            ..current.clone()
+        })
+    }
+
+    /// Pass the current report to the sink, and start a new window.
+    pub async fn flush(&self) -> Result<(), ServerError> {
+        let now = Utc::now();
+        let mut report = {
+            let Ok(mut current) = self.current.lock() else {
+                return Ok(());
+            };
+            std::mem::replace(&mut *current, ProcessingReport::new(now))
+        };
+        report.window_end = now;
+        let result = self.sink.flush(&report).await;
+        if result.is_err() {
+            if let Ok(mut current) = self.current.lock() {
+                current.merge(report);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    thread::LocalKey,
+};
+
+/// Value stored per task: set for the duration of a future through 'scope',
+/// and read with 'current' by code running within it.
+pub(crate) type ScopedKey<T> = LocalKey<RefCell<Option<T>>>;
+
+pub(crate) async fn scope<T: 'static, F: Future>(
+    key: &'static ScopedKey<T>,
+    value: T,
+    future: F,
+) -> F::Output {
+    Scoped {
+        key,
+        value: Some(value),
+        future: Box::pin(future),
+    }
+    .await
+}
+
+pub(crate) fn current<T: Clone + 'static>(key: &'static ScopedKey<T>) -> Option<T> {
+    key.with(|current| current.borrow().clone())
+}
+
+/// Sets the value for the duration of each poll of the inner future,
+/// restoring the previous one afterwards (so scopes can be nested).
+struct Scoped<T: 'static, F> {
+    key: &'static ScopedKey<T>,
+    value: Option<T>,
+    future: Pin<Box<F>>,
+}
+
+impl<T: 'static, F: Future> Future for Scoped<T, F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let previous = this.key.with(|current| current.replace(this.value.take()));
+        let result = this.future.as_mut().poll(cx);
+        this.value = this.key.with(|current| current.replace(previous));
+        result
+    }
+}
+
+// 'Scoped' never pins 'value', so it is 'Unpin' regardless of 'T'.
+impl<T: 'static, F> Unpin for Scoped<T, F> {}
@@ -1,4 +1,17 @@
+use std::fmt;
+
+#[cfg(feature = "gcp-secrets")]
+use base64::{prelude::BASE64_STANDARD, Engine as _};
 use fractic_env_config::{define_secret_key, define_secrets_config, SecretsConfigEnum};
+#[cfg(any(feature = "aws-secrets", feature = "gcp-secrets"))]
+use fractic_server_error::ServerError;
+use serde::{Deserialize, Deserializer};
+use zeroize::Zeroizing;
+
+#[cfg(feature = "gcp-secrets")]
+use crate::constants::{GCP_METADATA_TOKEN_URL, GCP_SECRET_MANAGER_URL};
+#[cfg(any(feature = "aws-secrets", feature = "gcp-secrets"))]
+use crate::errors::SecretLoadError;
 
 define_secret_key!(GOOGLE_API_KEY);
 define_secret_key!(APPLE_API_KEY);
@@ -12,3 +25,211 @@ define_secrets_config!(
     AppleKeyId => APPLE_KEY_ID,
     AppleIssuerId => APPLE_ISSUER_ID,
 );
+
+/// A credential (private key, service account JSON, or a token derived from
+/// them). The contents are zeroized when dropped, and never printed through
+/// 'Debug', so they cannot leak into logs or error payloads by accident.
+#[derive(Clone)]
+pub(crate) struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub(crate) fn new(value: impl Into<String>) -> Self {
+        Self(Zeroizing::new(value.into()))
+    }
+
+    pub(crate) fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Describes a JSON parsing error without quoting the input, which serde does
+/// for some error kinds (ex. "invalid type: string \"...\""), for errors
+/// raised while parsing credentials.
+pub(crate) fn redacted_json_error(e: &serde_json::Error) -> String {
+    format!(
+        "{:?} error at line {}, column {}",
+        e.classify(),
+        e.line(),
+        e.column()
+    )
+}
+
+/// The four credentials required by 'IapUtil', loaded from a secret manager.
+///
+/// The secret must be a JSON object with the keys "GOOGLE_API_KEY",
+/// "APPLE_API_KEY", "APPLE_KEY_ID", and "APPLE_ISSUER_ID" (the same layout
+/// used with 'IapSecretsConfig'). Other keys are ignored.
+#[cfg(any(feature = "aws-secrets", feature = "gcp-secrets"))]
+pub(crate) struct IapCredentials {
+    pub(crate) google_api_key: SecretString,
+    pub(crate) apple_api_key: SecretString,
+    pub(crate) apple_key_id: String,
+    pub(crate) apple_issuer_id: String,
+}
+
+#[cfg(any(feature = "aws-secrets", feature = "gcp-secrets"))]
+impl IapCredentials {
+    fn from_json(secret_id: &str, json: &str) -> Result<Self, ServerError> {
+        // Parsed into 'SecretString's, so that the keys are zeroized when
+        // dropped. Copies made before (ex. the HTTP client's response buffer)
+        // or while parsing (ex. when un-escaping newlines) are not.
+        #[derive(Deserialize)]
+        #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+        struct SecretValues {
+            google_api_key: Option<SecretString>,
+            apple_api_key: Option<SecretString>,
+            apple_key_id: Option<String>,
+            apple_issuer_id: Option<String>,
+        }
+
+        let values: SecretValues = serde_json::from_str(json).map_err(|e| {
+            SecretLoadError::with_debug(
+                secret_id,
+                "secret is not a JSON object of strings",
+                &redacted_json_error(&e),
+            )
+        })?;
+        let missing = |key: &str| SecretLoadError::new(secret_id, &format!("missing key '{key}'"));
+        Ok(Self {
+            google_api_key: values
+                .google_api_key
+                .ok_or_else(|| missing("GOOGLE_API_KEY"))?,
+            apple_api_key: values
+                .apple_api_key
+                .ok_or_else(|| missing("APPLE_API_KEY"))?,
+            apple_key_id: values.apple_key_id.ok_or_else(|| missing("APPLE_KEY_ID"))?,
+            apple_issuer_id: values
+                .apple_issuer_id
+                .ok_or_else(|| missing("APPLE_ISSUER_ID"))?,
+        })
+    }
+
+    /// Fetch from AWS Secrets Manager, using the default AWS credential chain
+    /// (environment, profile, or instance / task role).
+    ///
+    /// If 'secret_id' is a full ARN, the secret is fetched from the ARN's
+    /// region. Otherwise, the default region is used.
+    #[cfg(feature = "aws-secrets")]
+    pub(crate) async fn from_aws_secrets_manager(secret_id: &str) -> Result<Self, ServerError> {
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+        if let Some(region) = secret_id
+            .strip_prefix("arn:")
+            .and_then(|arn| arn.split(':').nth(2))
+            .filter(|region| !region.is_empty())
+        {
+            loader = loader.region(aws_config::Region::new(region.to_owned()));
+        }
+        let client = aws_sdk_secretsmanager::Client::new(&loader.load().await);
+        let mut output = client
+            .get_secret_value()
+            .secret_id(secret_id)
+            .send()
+            .await
+            .map_err(|e| SecretLoadError::with_debug(secret_id, "GetSecretValue failed", &e))?;
+        let json = output
+            .secret_string
+            .take()
+            .map(Zeroizing::new)
+            .ok_or_else(|| SecretLoadError::new(secret_id, "secret has no string value"))?;
+        Self::from_json(secret_id, &json)
+    }
+
+    /// Fetch from GCP Secret Manager, authenticating as the runtime's service
+    /// account through the metadata server (available on Cloud Run, Cloud
+    /// Functions, GKE, and Compute Engine).
+    ///
+    /// 'name' is the secret's resource name, ex.
+    /// "projects/my-project/secrets/iap-credentials". If no version is given
+    /// ("/versions/..."), the latest version is used.
+    ///
+    /// 'client' should be built from the 'IapUtil' configuration, so that the
+    /// configured proxy and root certificates apply.
+    #[cfg(feature = "gcp-secrets")]
+    pub(crate) async fn from_gcp_secret_manager(
+        name: &str,
+        client: &reqwest::Client,
+    ) -> Result<Self, ServerError> {
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            access_token: SecretString,
+        }
+        #[derive(Deserialize)]
+        struct AccessSecretVersionResponse {
+            payload: SecretPayload,
+        }
+        #[derive(Deserialize)]
+        struct SecretPayload {
+            data: SecretString,
+        }
+
+        let version_name = if name.contains("/versions/") {
+            name.to_owned()
+        } else {
+            format!("{name}/versions/latest")
+        };
+        let token = client
+            .get(GCP_METADATA_TOKEN_URL)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| {
+                SecretLoadError::with_debug(name, "metadata server token request failed", &e)
+            })?;
+        let token = parse_json_response::<TokenResponse>(
+            name,
+            token,
+            "metadata server token could not be parsed",
+        )
+        .await?
+        .access_token;
+        let response = client
+            .get(format!("{GCP_SECRET_MANAGER_URL}/{version_name}:access"))
+            .bearer_auth(token.expose_secret())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| SecretLoadError::with_debug(name, "AccessSecretVersion failed", &e))?;
+        let response = parse_json_response::<AccessSecretVersionResponse>(
+            name,
+            response,
+            "AccessSecretVersion response invalid",
+        )
+        .await?;
+        let data = BASE64_STANDARD
+            .decode(response.payload.data.expose_secret())
+            .map(Zeroizing::new)
+            .map_err(|_| SecretLoadError::new(name, "secret payload is not base64"))?;
+        let json = std::str::from_utf8(&data)
+            .map_err(|_| SecretLoadError::new(name, "secret payload is not UTF-8 text"))?;
+        Self::from_json(name, json)
+    }
+}
+
+/// Parses a JSON response body without quoting it in the error (see
+/// 'redacted_json_error').
+#[cfg(feature = "gcp-secrets")]
+async fn parse_json_response<T: serde::de::DeserializeOwned>(
+    name: &str,
+    response: reqwest::Response,
+    message: &str,
+) -> Result<T, ServerError> {
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| SecretLoadError::with_debug(name, message, &e))?;
+    serde_json::from_slice(&body)
+        .map_err(|e| SecretLoadError::with_debug(name, message, &redacted_json_error(&e)))
+}
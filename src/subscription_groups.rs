@@ -0,0 +1,108 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::domain::entities::{
+    iap_product_id::IapSubscriptionId, iap_update_notification::NotificationDetails,
+};
+
+/// How a subscription changed from one product to another, by their rank
+/// within the subscription group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanChangeKind {
+    /// To a higher tier.
+    Upgrade,
+    /// To a lower tier.
+    Downgrade,
+    /// To another product of the same tier (ex. from monthly to yearly), or
+    /// to another Google Play base plan of the same product.
+    Crossgrade,
+}
+
+/// Tiers of subscription products per subscription group, mirroring the
+/// subscription levels configured in App Store Connect, to classify plan
+/// changes (see 'NotificationDetails::SubscriptionPlanChanged', which only
+/// reports the product IDs).
+///
+/// ```ignore
+/// let groups = SubscriptionGroups::new().group(
+///     "premium",
+///     [
+///         vec!["pro_monthly", "pro_yearly"],
+///         vec!["basic_monthly", "basic_yearly"],
+///     ],
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionGroups {
+    /// Group and rank (0 being the highest tier) of each product ID.
+    ranks: HashMap<String, (String, usize)>,
+}
+
+impl SubscriptionGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the tiers of a subscription group, highest first (like the
+    /// App Store's subscription levels, where level 1 is the highest). Each
+    /// tier lists the product IDs of that rank (ex. the monthly and yearly
+    /// product of the same service level).
+    ///
+    /// Products registered again (in this or another group) take the last
+    /// registered rank.
+    pub fn group<G, T, P>(mut self, group: G, tiers: impl IntoIterator<Item = T>) -> Self
+    where
+        G: Into<String>,
+        T: IntoIterator<Item = P>,
+        P: Into<String>,
+    {
+        let group = group.into();
+        for (rank, tier) in tiers.into_iter().enumerate() {
+            for product_id in tier {
+                self.ranks.insert(product_id.into(), (group.clone(), rank));
+            }
+        }
+        self
+    }
+
+    /// Name of the group the product was registered in, if any.
+    pub fn group_of(&self, product_id: &IapSubscriptionId) -> Option<&str> {
+        self.ranks
+            .get(&product_id.0)
+            .map(|(group, _)| group.as_str())
+    }
+
+    /// Classify a change from one product to another. Returns None if either
+    /// product is not registered, or they are not in the same group.
+    pub fn classify(
+        &self,
+        from: &IapSubscriptionId,
+        to: &IapSubscriptionId,
+    ) -> Option<PlanChangeKind> {
+        let (from_group, from_rank) = self.ranks.get(&from.0)?;
+        let (to_group, to_rank) = self.ranks.get(&to.0)?;
+        if from_group != to_group {
+            return None;
+        }
+        Some(match to_rank.cmp(from_rank) {
+            Ordering::Less => PlanChangeKind::Upgrade,
+            Ordering::Greater => PlanChangeKind::Downgrade,
+            Ordering::Equal => PlanChangeKind::Crossgrade,
+        })
+    }
+
+    /// Classify the change reported by a 'SubscriptionPlanChanged'
+    /// notification. Returns None for other notifications, if the previous
+    /// product was not reported (App Store upgrades, which Apple only reports
+    /// as either an upgrade or a cross-grade to the same billing period), or
+    /// if the products are not registered in the same group.
+    pub fn classify_notification(&self, details: &NotificationDetails) -> Option<PlanChangeKind> {
+        match details {
+            NotificationDetails::SubscriptionPlanChanged {
+                product_id,
+                previous_product_id: Some(previous_product_id),
+                ..
+            } => self.classify(previous_product_id, product_id),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use async_trait::async_trait;
+use fractic_server_error::ServerError;
+
+use crate::{
+    domain::{
+        entities::{
+            iap_consumption::ConsumeResult,
+            iap_details::{IapDetails, IapTypeSpecificDetails},
+            iap_health_report::{IapHealthReport, PlatformHealth},
+            iap_product_id::IapConsumableId,
+            iap_purchase_id::IapPurchaseId,
+            iap_update_notification::IapUpdateNotification,
+        },
+        repositories::iap_repository::{IapRepository, TypedProductId},
+    },
+    error_observer::IapError,
+    errors::{MockResponseNotScripted, NotActive},
+};
+
+type ErrorFactory = Arc<dyn Fn() -> ServerError + Send + Sync>;
+
+enum ScriptedDetails {
+    /// Always an 'IapDetails<T>', where T depends on the product type.
+    Details(Box<dyn Any + Send + Sync>),
+    Error(ErrorFactory),
+}
+
+/// Scriptable in-memory 'IapRepository', for unit testing application code
+/// without store credentials.
+///
+/// Application code should depend on 'IapRepository' (implemented by both
+/// 'IapUtil' and this mock) rather than on 'IapUtil' directly. Calls which
+/// were not scripted fail with 'MockResponseNotScripted'.
+///
+/// The platform datasources are internal, so are not faked individually; to
+/// exercise 'IapUtil' itself against scripted platform responses, use
+/// 'test_util::store_simulator' (with the 'store-simulator' feature).
+#[derive(Default)]
+pub struct MockIapRepository {
+    details: Mutex<HashMap<IapPurchaseId, ScriptedDetails>>,
+    notifications: Mutex<HashMap<String, IapUpdateNotification>>,
+    test_notification_token: Mutex<Option<String>>,
+    health_report: Mutex<Option<IapHealthReport>>,
+    consumed: Mutex<Vec<(IapConsumableId, IapPurchaseId)>>,
+}
+
+/// Scripted state stays consistent even if a test panicked while holding a
+/// lock, so poisoning is ignored.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl MockIapRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the given details when the purchase is queried. As with the real
+    /// implementation, 'verify_and_get_details' fails with 'NotActive' if
+    /// 'is_active' is false.
+    pub fn with_details<T: IapTypeSpecificDetails>(
+        self,
+        purchase_id: IapPurchaseId,
+        details: IapDetails<T>,
+    ) -> Self {
+        lock(&self.details).insert(purchase_id, ScriptedDetails::Details(Box::new(details)));
+        self
+    }
+
+    /// Fail with the given error when the purchase is queried.
+    pub fn with_error(
+        self,
+        purchase_id: IapPurchaseId,
+        error: impl Fn() -> ServerError + Send + Sync + 'static,
+    ) -> Self {
+        lock(&self.details).insert(purchase_id, ScriptedDetails::Error(Arc::new(error)));
+        self
+    }
+
+    /// Return the given notification when a notification with exactly this
+    /// body is parsed (for either platform).
+    pub fn with_notification(
+        self,
+        body: impl Into<String>,
+        notification: IapUpdateNotification,
+    ) -> Self {
+        lock(&self.notifications).insert(body.into(), notification);
+        self
+    }
+
+    pub fn with_test_notification_token(self, token: impl Into<String>) -> Self {
+        *lock(&self.test_notification_token) = Some(token.into());
+        self
+    }
+
+    /// Return the given report from 'health_check' (healthy on both platforms
+    /// if not set).
+    pub fn with_health_report(self, report: IapHealthReport) -> Self {
+        *lock(&self.health_report) = Some(report);
+        self
+    }
+
+    /// Purchases passed to 'consume' so far, in call order.
+    pub fn consumed(&self) -> Vec<(IapConsumableId, IapPurchaseId)> {
+        lock(&self.consumed).clone()
+    }
+
+    fn scripted_details<T: IapTypeSpecificDetails>(
+        &self,
+        purchase_id: &IapPurchaseId,
+    ) -> Result<IapDetails<T>, ServerError> {
+        match lock(&self.details).get(purchase_id) {
+            Some(ScriptedDetails::Details(details)) => details
+                .downcast_ref::<IapDetails<T>>()
+                .cloned()
+                .ok_or_else(|| {
+                    MockResponseNotScripted::new(&format!(
+                        "details scripted for {purchase_id:?} are for a different product type"
+                    ))
+                }),
+            Some(ScriptedDetails::Error(error)) => Err(error()),
+            None => Err(MockResponseNotScripted::new(&format!(
+                "no details scripted for {purchase_id:?}"
+            ))),
+        }
+    }
+
+    fn scripted_notification(&self, body: &str) -> Result<IapUpdateNotification, ServerError> {
+        lock(&self.notifications)
+            .get(body)
+            .cloned()
+            .ok_or_else(|| MockResponseNotScripted::new("no notification scripted for body"))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl IapRepository for MockIapRepository {
+    async fn verify_and_get_details<T: TypedProductId>(
+        &self,
+        _product_id: T,
+        purchase_id: IapPurchaseId,
+        _include_price_info: bool,
+    ) -> Result<IapDetails<T::DetailsType>, IapError> {
+        let details = self.scripted_details(&purchase_id)?;
+        if !details.is_active {
+            return Err(NotActive::new().into());
+        }
+        Ok(details)
+    }
+
+    async fn get_details_allow_inactive<T: TypedProductId>(
+        &self,
+        _product_id: T,
+        purchase_id: IapPurchaseId,
+        _include_price_info: bool,
+    ) -> Result<IapDetails<T::DetailsType>, IapError> {
+        Ok(self.scripted_details(&purchase_id)?)
+    }
+
+    async fn consume(
+        &self,
+        product_id: IapConsumableId,
+        purchase_id: IapPurchaseId,
+    ) -> Result<ConsumeResult, IapError> {
+        lock(&self.consumed).push((product_id, purchase_id));
+        Ok(ConsumeResult::Consumed)
+    }
+
+    async fn parse_apple_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        Ok(self.scripted_notification(body)?)
+    }
+
+    async fn parse_google_notification(
+        &self,
+        _authorization_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        Ok(self.scripted_notification(body)?)
+    }
+
+    async fn parse_google_developer_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        Ok(self.scripted_notification(body)?)
+    }
+
+    /// Succeeds if a notification was scripted for the body.
+    async fn verify_apple_notification_signature(&self, body: &str) -> Result<(), IapError> {
+        self.scripted_notification(body)?;
+        Ok(())
+    }
+
+    /// Accepts any header (as with 'parse_google_notification', only the body
+    /// is matched against scripted notifications).
+    async fn verify_google_notification_auth(
+        &self,
+        _authorization_header: &str,
+    ) -> Result<(), IapError> {
+        Ok(())
+    }
+
+    async fn request_apple_test_notification(&self, _sandbox: bool) -> Result<String, IapError> {
+        lock(&self.test_notification_token).clone().ok_or_else(|| {
+            MockResponseNotScripted::new("no test notification token scripted").into()
+        })
+    }
+
+    async fn health_check(&self) -> IapHealthReport {
+        lock(&self.health_report)
+            .clone()
+            .unwrap_or(IapHealthReport {
+                apple: PlatformHealth::Healthy,
+                google: PlatformHealth::Healthy,
+            })
+    }
+}
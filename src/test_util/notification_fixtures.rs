@@ -0,0 +1,129 @@
+use base64::{prelude::BASE64_STANDARD, Engine as _};
+use fractic_server_error::ServerError;
+use serde_json::{json, Value};
+
+use crate::errors::TestFixtureError;
+
+/// Signs App Store Server Notification payloads the same way Apple does (ES256
+/// JWS with an x5c certificate chain), using test keys.
+///
+/// To be accepted, the chain's root must be trusted by the signature verifier
+/// (see 'TestSignatureVerifier::trust_apple_root_der').
+pub struct AppleNotificationSigner {
+    encoding_key: jsonwebtoken::EncodingKey,
+    x5c_chain: Vec<String>,
+}
+
+impl AppleNotificationSigner {
+    /// 'leaf_private_key_pem': PKCS#8 PEM-encoded P-256 private key of the
+    /// leaf certificate.
+    ///
+    /// 'x5c_chain_der': DER-encoded certificates, leaf first (Apple sends the
+    /// leaf, intermediate, and root).
+    pub fn new(
+        leaf_private_key_pem: &[u8],
+        x5c_chain_der: &[Vec<u8>],
+    ) -> Result<Self, ServerError> {
+        Ok(Self {
+            encoding_key: jsonwebtoken::EncodingKey::from_ec_pem(leaf_private_key_pem)
+                .map_err(|e| TestFixtureError::with_debug("invalid leaf private key", &e))?,
+            x5c_chain: x5c_chain_der
+                .iter()
+                .map(|der| BASE64_STANDARD.encode(der))
+                .collect(),
+        })
+    }
+
+    /// Sign an arbitrary payload (ex. a JWSTransactionDecodedPayload) as a JWS.
+    pub fn sign(&self, payload: &Value) -> Result<String, ServerError> {
+        let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::ES256);
+        header.x5c = Some(self.x5c_chain.clone());
+        jsonwebtoken::encode(&header, payload, &self.encoding_key)
+            .map_err(|e| TestFixtureError::with_debug("failed to sign JWS", &e))
+    }
+
+    /// Build the POST body of a notification, as received by the webhook.
+    ///
+    /// 'payload' is the decoded notification payload
+    /// (responseBodyV2DecodedPayload). If given, the transaction and renewal
+    /// info are signed and inserted as 'data.signedTransactionInfo' and
+    /// 'data.signedRenewalInfo'.
+    pub fn notification_body(
+        &self,
+        mut payload: Value,
+        transaction_info: Option<&Value>,
+        renewal_info: Option<&Value>,
+    ) -> Result<String, ServerError> {
+        for (field, info) in [
+            ("signedTransactionInfo", transaction_info),
+            ("signedRenewalInfo", renewal_info),
+        ] {
+            if let Some(info) = info {
+                let signed = self.sign(info)?;
+                match payload.get_mut("data").and_then(Value::as_object_mut) {
+                    Some(data) => {
+                        data.insert(field.to_owned(), Value::String(signed));
+                    }
+                    None => return Err(TestFixtureError::new(
+                        "payload must have a 'data' object to attach transaction / renewal info",
+                    )),
+                }
+            }
+        }
+        Ok(json!({ "signedPayload": self.sign(&payload)? }).to_string())
+    }
+}
+
+/// Builds Google Cloud Pub/Sub push requests carrying RTDN notifications, with
+/// an OIDC token signed (RS256) by a test key.
+///
+/// To be accepted, the corresponding public key must be trusted by the
+/// signature verifier (see 'TestSignatureVerifier::trust_google_key_pem').
+pub struct GooglePushSigner {
+    encoding_key: jsonwebtoken::EncodingKey,
+    key_id: String,
+}
+
+impl GooglePushSigner {
+    /// 'private_key_pem': PEM-encoded RSA private key. 'key_id' is sent as the
+    /// token's 'kid'.
+    pub fn new(private_key_pem: &[u8], key_id: impl Into<String>) -> Result<Self, ServerError> {
+        Ok(Self {
+            encoding_key: jsonwebtoken::EncodingKey::from_rsa_pem(private_key_pem)
+                .map_err(|e| TestFixtureError::with_debug("invalid RSA private key", &e))?,
+            key_id: key_id.into(),
+        })
+    }
+
+    /// 'Authorization' header value for a push request, valid for one hour.
+    pub fn authorization_header(&self, aud: &str) -> Result<String, ServerError> {
+        let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+        header.kid = Some(self.key_id.clone());
+        let now = chrono::Utc::now().timestamp();
+        let claims = json!({
+            "iss": "https://accounts.google.com",
+            "aud": aud,
+            "iat": now,
+            "exp": now + 3600,
+            "email": "test-push@example.iam.gserviceaccount.com",
+            "email_verified": true,
+        });
+        let token = jsonwebtoken::encode(&header, &claims, &self.encoding_key)
+            .map_err(|e| TestFixtureError::with_debug("failed to sign OIDC token", &e))?;
+        Ok(format!("Bearer {token}"))
+    }
+
+    /// POST body of a push request, wrapping the given DeveloperNotification
+    /// JSON in a Pub/Sub envelope.
+    pub fn push_body(&self, developer_notification: &Value, message_id: &str) -> String {
+        json!({
+            "message": {
+                "attributes": {},
+                "data": BASE64_STANDARD.encode(developer_notification.to_string()),
+                "messageId": message_id,
+            },
+            "subscription": "projects/test/subscriptions/test",
+        })
+        .to_string()
+    }
+}
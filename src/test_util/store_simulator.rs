@@ -0,0 +1,874 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use fractic_server_error::ServerError;
+use openssl::{
+    asn1::Asn1Time,
+    bn::BigNum,
+    ec::{EcGroup, EcKey},
+    error::ErrorStack,
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    x509::{
+        extension::{BasicConstraints, KeyUsage},
+        X509Builder, X509NameBuilder, X509,
+    },
+};
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    Method, StatusCode,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    data::datasources::http_client::HttpTransport,
+    errors::TestFixtureError,
+    test_util::{
+        notification_fixtures::AppleNotificationSigner,
+        test_signature_verifier::TestSignatureVerifier,
+    },
+    util::IapUtilBuilder,
+};
+
+const APPLE_PRODUCTION_HOST: &str = "api.storekit.itunes.apple.com";
+const APPLE_SANDBOX_HOST: &str = "api.storekit-sandbox.itunes.apple.com";
+const GOOGLE_API_HOST: &str = "androidpublisher.googleapis.com";
+const GOOGLE_TOKEN_HOST: &str = "oauth2.googleapis.com";
+
+/// Lifecycle state of a simulated subscription, relative to the time it was
+/// registered with the simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionScenario {
+    /// Renewed a day ago, and set to renew again in 29 days.
+    Active,
+    /// Expired a day ago, after the user turned off auto-renewal.
+    Expired,
+    /// Renewal failed a day ago due to a billing issue, and the store is
+    /// retrying within its grace period (6 days for Apple, 3 for Google).
+    GracePeriod,
+    /// Renewed a day ago, then refunded (and revoked) an hour ago.
+    Refunded,
+}
+
+/// State of a simulated one-time purchase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PurchaseScenario {
+    /// Purchased a day ago.
+    Purchased,
+    /// Purchased a day ago, then refunded an hour ago.
+    Refunded,
+}
+
+enum AppleRecord {
+    Purchase {
+        product_id: String,
+        transaction_type: &'static str,
+        scenario: PurchaseScenario,
+        at: DateTime<Utc>,
+    },
+    Subscription {
+        product_id: String,
+        scenario: SubscriptionScenario,
+        at: DateTime<Utc>,
+    },
+}
+
+struct GooglePurchaseRecord {
+    product_id: String,
+    scenario: PurchaseScenario,
+    consumed: bool,
+    at: DateTime<Utc>,
+}
+
+struct GoogleSubscriptionRecord {
+    product_id: String,
+    scenario: SubscriptionScenario,
+    at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct State {
+    apple: HashMap<String, AppleRecord>,
+    google_purchases: HashMap<String, GooglePurchaseRecord>,
+    google_subscriptions: HashMap<String, GoogleSubscriptionRecord>,
+    consumed: Vec<(String, String)>,
+    pending_failures: VecDeque<StatusCode>,
+}
+
+struct Inner {
+    application_id: String,
+    apple_signer: AppleNotificationSigner,
+    apple_root_der: Vec<u8>,
+    apple_api_key: String,
+    google_api_key: String,
+    state: Mutex<State>,
+}
+
+/// In-process emulation of the subset of the App Store Server API and Google
+/// Play Developer API used by 'IapUtil' (transaction info, subscription
+/// statuses, test notifications, purchases.products get / consume,
+/// purchases.productsv2 get, purchases.subscriptionsv2 get, inappproducts get,
+/// and the Google OAuth
+/// token endpoint), for integration tests which should not call the real
+/// stores.
+///
+/// Purchases are registered with one of the predefined scenarios, and can be
+/// re-registered at any point to simulate a change in state. 'IapUtil'
+/// instances built from 'iap_util_builder' send their callouts to the
+/// simulator instead of the network, and trust the simulator's signing keys.
+///
+/// All purchases are made from the US storefront, at 0.99 USD, in the
+/// production environment.
+#[derive(Clone)]
+pub struct StoreSimulator {
+    inner: Arc<Inner>,
+}
+
+impl StoreSimulator {
+    /// Generates fresh signing keys and API credentials for the given
+    /// application (Apple bundle ID / Google package name).
+    pub fn new(application_id: impl Into<String>) -> Result<Self, ServerError> {
+        let keys = SimulatorKeys::generate()
+            .map_err(|e| TestFixtureError::with_debug("failed to generate simulator keys", &e))?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                application_id: application_id.into(),
+                apple_signer: AppleNotificationSigner::new(
+                    &keys.apple_leaf_key_pem,
+                    &[keys.apple_leaf_der, keys.apple_root_der.clone()],
+                )?,
+                apple_root_der: keys.apple_root_der,
+                apple_api_key: keys.apple_api_key,
+                google_api_key: keys.google_api_key,
+                state: Default::default(),
+            }),
+        })
+    }
+
+    /// Builder for an 'IapUtil' backed by this simulator. Further options
+    /// (ex. a retry policy) can be set on the returned builder as usual.
+    pub fn iap_util_builder(&self, expected_aud: impl Into<String>) -> IapUtilBuilder {
+        IapUtilBuilder::from_values(
+            self.inner.application_id.clone(),
+            expected_aud,
+            &self.inner.apple_api_key,
+            "SIMULATOR0",
+            "00000000-0000-0000-0000-000000000000",
+            &self.inner.google_api_key,
+        )
+        .signature_verifier(
+            TestSignatureVerifier::new().trust_apple_root_der(self.inner.apple_root_der.clone()),
+        )
+        .transport(Arc::new(self.clone()))
+    }
+
+    /// Signer using the same certificate chain as the simulated App Store, to
+    /// build App Store Server Notifications accepted by 'iap_util_builder'
+    /// instances.
+    pub fn apple_notification_signer(&self) -> &AppleNotificationSigner {
+        &self.inner.apple_signer
+    }
+
+    /// Register (or replace) an App Store auto-renewable subscription.
+    pub fn apple_subscription(
+        &self,
+        transaction_id: impl Into<String>,
+        product_id: impl Into<String>,
+        scenario: SubscriptionScenario,
+    ) {
+        self.state().apple.insert(
+            transaction_id.into(),
+            AppleRecord::Subscription {
+                product_id: product_id.into(),
+                scenario,
+                at: Utc::now(),
+            },
+        );
+    }
+
+    /// Register (or replace) an App Store consumable purchase.
+    pub fn apple_consumable(
+        &self,
+        transaction_id: impl Into<String>,
+        product_id: impl Into<String>,
+        scenario: PurchaseScenario,
+    ) {
+        self.insert_apple_purchase(transaction_id, product_id, "Consumable", scenario);
+    }
+
+    /// Register (or replace) an App Store non-consumable purchase.
+    pub fn apple_non_consumable(
+        &self,
+        transaction_id: impl Into<String>,
+        product_id: impl Into<String>,
+        scenario: PurchaseScenario,
+    ) {
+        self.insert_apple_purchase(transaction_id, product_id, "Non-Consumable", scenario);
+    }
+
+    /// Register (or replace) a Google Play subscription.
+    pub fn google_subscription(
+        &self,
+        purchase_token: impl Into<String>,
+        product_id: impl Into<String>,
+        scenario: SubscriptionScenario,
+    ) {
+        self.state().google_subscriptions.insert(
+            purchase_token.into(),
+            GoogleSubscriptionRecord {
+                product_id: product_id.into(),
+                scenario,
+                at: Utc::now(),
+            },
+        );
+    }
+
+    /// Register (or replace) a Google Play one-time purchase (consumable or
+    /// non-consumable; Google does not distinguish between the two).
+    pub fn google_purchase(
+        &self,
+        purchase_token: impl Into<String>,
+        product_id: impl Into<String>,
+        scenario: PurchaseScenario,
+    ) {
+        self.state().google_purchases.insert(
+            purchase_token.into(),
+            GooglePurchaseRecord {
+                product_id: product_id.into(),
+                scenario,
+                consumed: false,
+                at: Utc::now(),
+            },
+        );
+    }
+
+    /// Answer the next 'count' callouts (to either store) with the given
+    /// status code, ex. to exercise retry policies.
+    pub fn fail_next(&self, count: usize, status: u16) {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        self.state()
+            .pending_failures
+            .extend(std::iter::repeat_n(status, count));
+    }
+
+    /// Google Play purchases consumed so far, as (product ID, purchase token).
+    pub fn consumed(&self) -> Vec<(String, String)> {
+        self.state().consumed.clone()
+    }
+
+    /// Simulated state stays consistent even if a test panicked while
+    /// holding the lock, so poisoning is ignored.
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.inner
+            .state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn insert_apple_purchase(
+        &self,
+        transaction_id: impl Into<String>,
+        product_id: impl Into<String>,
+        transaction_type: &'static str,
+        scenario: PurchaseScenario,
+    ) {
+        self.state().apple.insert(
+            transaction_id.into(),
+            AppleRecord::Purchase {
+                product_id: product_id.into(),
+                transaction_type,
+                scenario,
+                at: Utc::now(),
+            },
+        );
+    }
+
+    fn respond(&self, request: &reqwest::Request) -> (StatusCode, Value) {
+        let mut guard = self.state();
+        let state = &mut *guard;
+        let url = request.url();
+        let host = url.host_str().unwrap_or_default();
+        if let Some(status) = state.pending_failures.pop_front() {
+            return match host {
+                APPLE_PRODUCTION_HOST | APPLE_SANDBOX_HOST => {
+                    apple_error(status, apple_error_code(status), "Simulated failure.")
+                }
+                _ => google_error(status, "Simulated failure."),
+            };
+        }
+
+        let segments = url
+            .path_segments()
+            .map(|segments| segments.collect::<Vec<_>>())
+            .unwrap_or_default();
+        let method = request.method();
+
+        if host == GOOGLE_TOKEN_HOST {
+            return match (method, segments.as_slice()) {
+                (&Method::POST, ["token"]) => (
+                    StatusCode::OK,
+                    json!({
+                        "access_token": "simulated-access-token",
+                        "expires_in": 3600,
+                        "token_type": "Bearer",
+                    }),
+                ),
+                _ => google_error(StatusCode::NOT_FOUND, "Not found."),
+            };
+        }
+        if request.headers().get(AUTHORIZATION).is_none() {
+            return google_error(StatusCode::UNAUTHORIZED, "Missing credentials.");
+        }
+
+        match (host, method, segments.as_slice()) {
+            (
+                APPLE_PRODUCTION_HOST | APPLE_SANDBOX_HOST,
+                &Method::POST,
+                ["inApps", "v1", "notifications", "test"],
+            ) => (
+                StatusCode::OK,
+                json!({ "testNotificationToken": format!("{:032x}", rand::random::<u128>()) }),
+            ),
+            // Simulated purchases are all in the production environment.
+            (
+                APPLE_PRODUCTION_HOST,
+                &Method::GET,
+                ["inApps", "v1", "transactions", transaction_id],
+            ) => match state.apple.get(*transaction_id) {
+                Some(record) => self
+                    .sign(&self.apple_transaction_info(transaction_id, record))
+                    .map(|jws| (StatusCode::OK, json!({ "signedTransactionInfo": jws })))
+                    .unwrap_or_else(|e| {
+                        apple_error(StatusCode::INTERNAL_SERVER_ERROR, 5000000, &e)
+                    }),
+                None => apple_error(StatusCode::NOT_FOUND, 4040010, "Transaction id not found."),
+            },
+            (
+                APPLE_PRODUCTION_HOST,
+                &Method::GET,
+                ["inApps", "v1", "subscriptions", transaction_id],
+            ) => match state.apple.get(*transaction_id) {
+                Some(record @ AppleRecord::Subscription { .. }) => self
+                    .apple_status_response(transaction_id, record)
+                    .map(|response| (StatusCode::OK, response))
+                    .unwrap_or_else(|e| {
+                        apple_error(StatusCode::INTERNAL_SERVER_ERROR, 5000000, &e)
+                    }),
+                _ => apple_error(StatusCode::NOT_FOUND, 4040010, "Transaction id not found."),
+            },
+            (
+                APPLE_SANDBOX_HOST,
+                &Method::GET,
+                ["inApps", "v1", "transactions" | "subscriptions", _],
+            ) => apple_error(StatusCode::NOT_FOUND, 4040010, "Transaction id not found."),
+            (
+                GOOGLE_API_HOST,
+                _,
+                ["androidpublisher", "v3", "applications", package_name, rest @ ..],
+            ) => {
+                if *package_name != self.inner.application_id {
+                    return google_error(
+                        StatusCode::NOT_FOUND,
+                        "No application was found for the given package name.",
+                    );
+                }
+                match (method, rest) {
+                    (&Method::GET, ["purchases", "products", product_id, "tokens", token]) => {
+                        match state.google_purchases.get(*token) {
+                            Some(record) if record.product_id == *product_id => {
+                                (StatusCode::OK, self.google_product_purchase(token, record))
+                            }
+                            _ => google_error(
+                                StatusCode::NOT_FOUND,
+                                "The purchase token was not found.",
+                            ),
+                        }
+                    }
+                    (&Method::GET, ["purchases", "productsv2", "tokens", token]) => {
+                        match state.google_purchases.get(*token) {
+                            Some(record) => {
+                                (StatusCode::OK, self.google_product_purchase_v2(record))
+                            }
+                            None => google_error(
+                                StatusCode::NOT_FOUND,
+                                "The purchase token was not found.",
+                            ),
+                        }
+                    }
+                    (&Method::POST, ["purchases", "products", product_id, "tokens", token])
+                        if token.ends_with(":consume") =>
+                    {
+                        let token = token.trim_end_matches(":consume");
+                        match state.google_purchases.get_mut(token) {
+                            Some(record) if record.product_id == *product_id => {
+                                if record.consumed || record.scenario != PurchaseScenario::Purchased
+                                {
+                                    return google_error(
+                                        StatusCode::BAD_REQUEST,
+                                        "The product purchase is not owned by the user.",
+                                    );
+                                }
+                                record.consumed = true;
+                                state
+                                    .consumed
+                                    .push((product_id.to_string(), token.to_owned()));
+                                (StatusCode::OK, json!({}))
+                            }
+                            _ => google_error(
+                                StatusCode::NOT_FOUND,
+                                "The purchase token was not found.",
+                            ),
+                        }
+                    }
+                    (&Method::GET, ["purchases", "subscriptionsv2", "tokens", token]) => {
+                        match state.google_subscriptions.get(*token) {
+                            Some(record) => {
+                                (StatusCode::OK, self.google_subscription_purchase(record))
+                            }
+                            None => google_error(
+                                StatusCode::NOT_FOUND,
+                                "The purchase token was not found.",
+                            ),
+                        }
+                    }
+                    (&Method::GET, ["inappproducts", sku]) => {
+                        if state
+                            .google_purchases
+                            .values()
+                            .any(|record| record.product_id == *sku)
+                        {
+                            (StatusCode::OK, self.google_in_app_product(sku))
+                        } else {
+                            google_error(StatusCode::NOT_FOUND, "The item was not found.")
+                        }
+                    }
+                    _ => google_error(StatusCode::NOT_FOUND, "Not found."),
+                }
+            }
+            _ => google_error(StatusCode::NOT_FOUND, "Not found."),
+        }
+    }
+
+    fn sign(&self, payload: &Value) -> Result<String, String> {
+        self.inner
+            .apple_signer
+            .sign(payload)
+            .map_err(|e| e.to_string())
+    }
+
+    /// JWSTransactionDecodedPayload:
+    /// https://developer.apple.com/documentation/appstoreserverapi/jwstransactiondecodedpayload
+    fn apple_transaction_info(&self, transaction_id: &str, record: &AppleRecord) -> Value {
+        let (product_id, transaction_type, at) = match record {
+            AppleRecord::Purchase {
+                product_id,
+                transaction_type,
+                at,
+                ..
+            } => (product_id, *transaction_type, *at),
+            AppleRecord::Subscription { product_id, at, .. } => {
+                (product_id, "Auto-Renewable Subscription", *at)
+            }
+        };
+        let mut info = json!({
+            "bundleId": self.inner.application_id,
+            "currency": "USD",
+            "environment": "Production",
+            "inAppOwnershipType": "PURCHASED",
+            "originalTransactionId": transaction_id,
+            "price": 990,
+            "productId": product_id,
+            "quantity": 1,
+            "signedDate": Utc::now().timestamp_millis(),
+            "storefront": "USA",
+            "storefrontId": "143441",
+            "transactionId": transaction_id,
+            "transactionReason": "PURCHASE",
+            "type": transaction_type,
+        });
+        match record {
+            AppleRecord::Purchase { scenario, .. } => {
+                info["purchaseDate"] = json!(millis(at - Duration::days(1)));
+                info["originalPurchaseDate"] = info["purchaseDate"].clone();
+                if *scenario == PurchaseScenario::Refunded {
+                    info["revocationDate"] = json!(millis(at - Duration::hours(1)));
+                    info["revocationReason"] = json!(0);
+                }
+            }
+            AppleRecord::Subscription { scenario, .. } => {
+                let (purchase_date, expires_date) = subscription_period(*scenario, at);
+                info["purchaseDate"] = json!(millis(purchase_date));
+                info["originalPurchaseDate"] = json!(millis(at - Duration::days(90)));
+                info["expiresDate"] = json!(millis(expires_date));
+                info["subscriptionGroupIdentifier"] = json!("simulator");
+                info["webOrderLineItemId"] = json!(transaction_id);
+                if *scenario == SubscriptionScenario::Refunded {
+                    info["revocationDate"] = json!(millis(at - Duration::hours(1)));
+                    info["revocationReason"] = json!(0);
+                }
+            }
+        }
+        info
+    }
+
+    /// JWSRenewalInfoDecodedPayload:
+    /// https://developer.apple.com/documentation/appstoreserverapi/jwsrenewalinfodecodedpayload
+    fn apple_renewal_info(
+        &self,
+        transaction_id: &str,
+        product_id: &str,
+        scenario: SubscriptionScenario,
+        at: DateTime<Utc>,
+    ) -> Value {
+        let (_, expires_date) = subscription_period(scenario, at);
+        let mut info = json!({
+            "autoRenewProductId": product_id,
+            "autoRenewStatus": 1,
+            "environment": "Production",
+            "originalTransactionId": transaction_id,
+            "productId": product_id,
+            "recentSubscriptionStartDate": millis(at - Duration::days(90)),
+            "renewalDate": millis(expires_date),
+            "signedDate": Utc::now().timestamp_millis(),
+        });
+        match scenario {
+            SubscriptionScenario::Active => {}
+            SubscriptionScenario::Expired => {
+                info["autoRenewStatus"] = json!(0);
+                info["expirationIntent"] = json!(1);
+            }
+            SubscriptionScenario::GracePeriod => {
+                info["expirationIntent"] = json!(2);
+                info["isInBillingRetryPeriod"] = json!(true);
+                info["gracePeriodExpiresDate"] = json!(millis(expires_date + Duration::days(6)));
+            }
+            SubscriptionScenario::Refunded => {
+                info["autoRenewStatus"] = json!(0);
+            }
+        }
+        info
+    }
+
+    /// StatusResponse:
+    /// https://developer.apple.com/documentation/appstoreserverapi/statusresponse
+    fn apple_status_response(
+        &self,
+        transaction_id: &str,
+        record: &AppleRecord,
+    ) -> Result<Value, String> {
+        let AppleRecord::Subscription {
+            product_id,
+            scenario,
+            at,
+        } = record
+        else {
+            return Err("not a subscription".to_owned());
+        };
+        let status = match scenario {
+            SubscriptionScenario::Active => 1,
+            SubscriptionScenario::Expired => 2,
+            SubscriptionScenario::GracePeriod => 4,
+            SubscriptionScenario::Refunded => 5,
+        };
+        Ok(json!({
+            "bundleId": self.inner.application_id,
+            "data": [{
+                "subscriptionGroupIdentifier": "simulator",
+                "lastTransactions": [{
+                    "status": status,
+                    "originalTransactionId": transaction_id,
+                    "signedTransactionInfo": self.sign(&self.apple_transaction_info(transaction_id, record))?,
+                    "signedRenewalInfo": self.sign(&self.apple_renewal_info(transaction_id, product_id, *scenario, *at))?,
+                }],
+            }],
+            "environment": "Production",
+        }))
+    }
+
+    /// ProductPurchase:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.products
+    fn google_product_purchase(&self, token: &str, record: &GooglePurchaseRecord) -> Value {
+        json!({
+            "kind": "androidpublisher#productPurchase",
+            "purchaseTimeMillis": millis(record.at - Duration::days(1)).to_string(),
+            "purchaseState": match record.scenario {
+                PurchaseScenario::Purchased => 0,
+                PurchaseScenario::Refunded => 1,
+            },
+            "consumptionState": u8::from(record.consumed),
+            "orderId": "GPA.0000-0000-0000-00000",
+            "acknowledgementState": 1,
+            "purchaseToken": token,
+            "productId": record.product_id,
+            "quantity": 1,
+            "regionCode": "US",
+        })
+    }
+
+    /// ProductPurchaseV2:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.productsv2
+    fn google_product_purchase_v2(&self, record: &GooglePurchaseRecord) -> Value {
+        json!({
+            "kind": "androidpublisher#productPurchaseV2",
+            "productLineItem": [{
+                "productId": record.product_id,
+                "productOfferDetails": {
+                    "purchaseOptionId": "buy",
+                    "quantity": 1,
+                    "refundableQuantity": match record.scenario {
+                        PurchaseScenario::Purchased => 1,
+                        PurchaseScenario::Refunded => 0,
+                    },
+                    "consumptionState": match record.consumed {
+                        true => "CONSUMPTION_STATE_CONSUMED",
+                        false => "CONSUMPTION_STATE_YET_TO_BE_CONSUMED",
+                    },
+                },
+            }],
+            "purchaseStateContext": {
+                "purchaseState": match record.scenario {
+                    PurchaseScenario::Purchased => "PURCHASED",
+                    PurchaseScenario::Refunded => "CANCELLED",
+                },
+            },
+            "orderId": "GPA.0000-0000-0000-00000",
+            "regionCode": "US",
+            "purchaseCompletionTime": (record.at - Duration::days(1)).to_rfc3339(),
+            "acknowledgementState": "ACKNOWLEDGEMENT_STATE_ACKNOWLEDGED",
+        })
+    }
+
+    /// SubscriptionPurchaseV2:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/purchases.subscriptionsv2
+    fn google_subscription_purchase(&self, record: &GoogleSubscriptionRecord) -> Value {
+        let at = record.at;
+        let (state, expiry_time, auto_renew_enabled, canceled_state_context) = match record.scenario
+        {
+            SubscriptionScenario::Active => (
+                "SUBSCRIPTION_STATE_ACTIVE",
+                at + Duration::days(29),
+                true,
+                None,
+            ),
+            SubscriptionScenario::Expired => (
+                "SUBSCRIPTION_STATE_EXPIRED",
+                at - Duration::days(1),
+                false,
+                Some(json!({ "userInitiatedCancellation": {} })),
+            ),
+            // During the grace period, Google extends the expiry time to
+            // the end of the grace period.
+            SubscriptionScenario::GracePeriod => (
+                "SUBSCRIPTION_STATE_IN_GRACE_PERIOD",
+                at + Duration::days(2),
+                true,
+                None,
+            ),
+            SubscriptionScenario::Refunded => (
+                "SUBSCRIPTION_STATE_EXPIRED",
+                at - Duration::hours(1),
+                false,
+                Some(json!({ "developerInitiatedCancellation": {} })),
+            ),
+        };
+        let mut purchase = json!({
+            "kind": "androidpublisher#subscriptionPurchaseV2",
+            "regionCode": "US",
+            "lineItems": [{
+                "productId": record.product_id,
+                "expiryTime": rfc3339(expiry_time),
+                "autoRenewingPlan": { "autoRenewEnabled": auto_renew_enabled },
+            }],
+            "startTime": rfc3339(at - Duration::days(90)),
+            "subscriptionState": state,
+            "latestOrderId": "GPA.0000-0000-0000-00000..2",
+            "acknowledgementState": "ACKNOWLEDGEMENT_STATE_ACKNOWLEDGED",
+        });
+        if let Some(canceled_state_context) = canceled_state_context {
+            purchase["canceledStateContext"] = canceled_state_context;
+        }
+        purchase
+    }
+
+    /// InAppProduct:
+    /// https://developers.google.com/android-publisher/api-ref/rest/v3/inappproducts
+    fn google_in_app_product(&self, sku: &str) -> Value {
+        let price = json!({ "priceMicros": "990000", "currency": "USD" });
+        json!({
+            "packageName": self.inner.application_id,
+            "sku": sku,
+            "status": "active",
+            "purchaseType": "managedUser",
+            "defaultPrice": price,
+            "prices": { "US": price },
+            "listings": { "en-US": { "title": sku, "description": sku } },
+            "defaultLanguage": "en-US",
+        })
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl HttpTransport for StoreSimulator {
+    async fn execute(
+        &self,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let (status, body) = self.respond(&request);
+        let mut response = http::Response::new(body.to_string());
+        *response.status_mut() = status;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        Ok(response.into())
+    }
+}
+
+/// Start and end of the current period of a subscription.
+fn subscription_period(
+    scenario: SubscriptionScenario,
+    at: DateTime<Utc>,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    match scenario {
+        SubscriptionScenario::Active | SubscriptionScenario::Refunded => {
+            (at - Duration::days(1), at + Duration::days(29))
+        }
+        SubscriptionScenario::Expired | SubscriptionScenario::GracePeriod => {
+            (at - Duration::days(31), at - Duration::days(1))
+        }
+    }
+}
+
+fn millis(time: DateTime<Utc>) -> i64 {
+    time.timestamp_millis()
+}
+
+fn rfc3339(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn apple_error(status: StatusCode, error_code: u32, message: &str) -> (StatusCode, Value) {
+    (
+        status,
+        json!({ "errorCode": error_code, "errorMessage": message }),
+    )
+}
+
+/// Generic App Store Server API error code for a simulated failure status.
+fn apple_error_code(status: StatusCode) -> u32 {
+    match status {
+        StatusCode::UNAUTHORIZED => 4010000,
+        StatusCode::NOT_FOUND => 4040010,
+        StatusCode::TOO_MANY_REQUESTS => 4290000,
+        status if status.is_server_error() => 5000001,
+        _ => 4000000,
+    }
+}
+
+fn google_error(status: StatusCode, message: &str) -> (StatusCode, Value) {
+    let reason = match status {
+        StatusCode::BAD_REQUEST => "invalid",
+        StatusCode::UNAUTHORIZED => "authError",
+        StatusCode::FORBIDDEN => "permissionDenied",
+        StatusCode::NOT_FOUND => "notFound",
+        StatusCode::TOO_MANY_REQUESTS => "rateLimitExceeded",
+        _ => "backendError",
+    };
+    (
+        status,
+        json!({
+            "error": {
+                "code": status.as_u16(),
+                "message": message,
+                "errors": [{ "domain": "global", "reason": reason, "message": message }],
+            }
+        }),
+    )
+}
+
+/// Keys generated for each simulator instance.
+struct SimulatorKeys {
+    apple_root_der: Vec<u8>,
+    apple_leaf_der: Vec<u8>,
+    apple_leaf_key_pem: Vec<u8>,
+    apple_api_key: String,
+    google_api_key: String,
+}
+
+impl SimulatorKeys {
+    fn generate() -> Result<Self, ErrorStack> {
+        let root_key = generate_ec_key()?;
+        let root = build_certificate("Store Simulator Root CA", &root_key, None)?;
+        let leaf_key = generate_ec_key()?;
+        let leaf = build_certificate(
+            "Store Simulator Signing",
+            &leaf_key,
+            Some((&root, &root_key)),
+        )?;
+        let google_key = PKey::from_rsa(Rsa::generate(2048)?)?;
+        Ok(Self {
+            apple_root_der: root.to_der()?,
+            apple_leaf_der: leaf.to_der()?,
+            apple_leaf_key_pem: leaf_key.private_key_to_pem_pkcs8()?,
+            apple_api_key: String::from_utf8_lossy(&generate_ec_key()?.private_key_to_pem_pkcs8()?)
+                .into_owned(),
+            google_api_key: json!({
+                "type": "service_account",
+                "project_id": "store-simulator",
+                "private_key_id": "simulator",
+                "private_key": String::from_utf8_lossy(&google_key.private_key_to_pem_pkcs8()?),
+                "client_email": "simulator@store-simulator.iam.gserviceaccount.com",
+                "client_id": "0",
+                "auth_uri": "https://accounts.google.com/o/oauth2/auth",
+                "token_uri": format!("https://{GOOGLE_TOKEN_HOST}/token"),
+            })
+            .to_string(),
+        })
+    }
+}
+
+fn generate_ec_key() -> Result<PKey<Private>, ErrorStack> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    PKey::from_ec_key(EcKey::generate(&group)?)
+}
+
+/// Self-signed CA certificate if no issuer is given, otherwise an end-entity
+/// certificate signed by the issuer.
+fn build_certificate(
+    common_name: &str,
+    key: &PKey<Private>,
+    issuer: Option<(&X509, &PKey<Private>)>,
+) -> Result<X509, ErrorStack> {
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, common_name)?;
+    let name = name.build();
+
+    let mut builder = X509Builder::new()?;
+    builder.set_version(2)?;
+    let serial_number = BigNum::from_u32(rand::random::<u32>() | 1)?.to_asn1_integer()?;
+    builder.set_serial_number(&serial_number)?;
+    builder.set_subject_name(&name)?;
+    builder.set_pubkey(key)?;
+    let (not_before, not_after) = (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(365)?);
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    match issuer {
+        Some((issuer_cert, issuer_key)) => {
+            builder.set_issuer_name(issuer_cert.subject_name())?;
+            builder.sign(issuer_key, MessageDigest::sha256())?;
+        }
+        None => {
+            builder.set_issuer_name(&name)?;
+            builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+            builder.append_extension(KeyUsage::new().critical().key_cert_sign().build()?)?;
+            builder.sign(key, MessageDigest::sha256())?;
+        }
+    }
+    Ok(builder.build())
+}
@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use fractic_server_error::{CriticalError, ServerError};
+use openssl::{
+    stack::Stack,
+    x509::{store::X509StoreBuilder, X509StoreContext, X509},
+};
+
+use crate::{
+    errors::{InvalidAppleSignature, InvalidGoogleSignature, TestFixtureError},
+    verifier::SignatureVerifier,
+};
+
+/// 'SignatureVerifier' trusting test keys instead of Apple's and Google's, so
+/// that payloads built with 'AppleNotificationSigner' and 'GooglePushSigner'
+/// pass the full verification path.
+///
+/// Install with 'IapUtilBuilder::signature_verifier'.
+#[derive(Default)]
+pub struct TestSignatureVerifier {
+    apple_roots_der: Vec<Vec<u8>>,
+    google_keys: Vec<(String, jsonwebtoken::DecodingKey)>,
+}
+
+impl TestSignatureVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust the given (DER-encoded) root certificate for Apple JWS chains.
+    pub fn trust_apple_root_der(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.apple_roots_der.push(der.into());
+        self
+    }
+
+    /// Trust the given (PEM-encoded) RSA public key for Google OIDC tokens
+    /// with the given 'kid'.
+    pub fn trust_google_key_pem(
+        mut self,
+        key_id: impl Into<String>,
+        public_key_pem: &[u8],
+    ) -> Result<Self, ServerError> {
+        let key = jsonwebtoken::DecodingKey::from_rsa_pem(public_key_pem)
+            .map_err(|e| TestFixtureError::with_debug("invalid RSA public key", &e))?;
+        self.google_keys.push((key_id.into(), key));
+        Ok(self)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl SignatureVerifier for TestSignatureVerifier {
+    async fn verify_apple_certificate_chain(
+        &self,
+        x5c_chain: &[Vec<u8>],
+    ) -> Result<Vec<u8>, ServerError> {
+        let mut store_builder = X509StoreBuilder::new()
+            .map_err(|e| CriticalError::with_debug("failed to create X509 store", &e))?;
+        for der in &self.apple_roots_der {
+            X509::from_der(der)
+                .and_then(|cert| store_builder.add_cert(cert))
+                .map_err(|e| TestFixtureError::with_debug("invalid trusted root", &e))?;
+        }
+        let store = store_builder.build();
+
+        let mut certs = x5c_chain.iter().map(|der| {
+            X509::from_der(der)
+                .map_err(|e| InvalidAppleSignature::with_debug("failed to decode x5c certs", &e))
+        });
+        let leaf_cert = certs
+            .next()
+            .ok_or(InvalidAppleSignature::new("empty x5c chain"))??;
+        let mut chain = Stack::new()
+            .map_err(|e| CriticalError::with_debug("failed to create X509 stack", &e))?;
+        for cert in certs {
+            chain
+                .push(cert?)
+                .map_err(|e| CriticalError::with_debug("failed to push cert to X509 stack", &e))?;
+        }
+        let valid = X509StoreContext::new()
+            .and_then(|mut cxt| cxt.init(&store, &leaf_cert, &chain, |cxt| cxt.verify_cert()))
+            .map_err(|e| InvalidAppleSignature::with_debug("failed to validate x5c chain", &e))?;
+        if !valid {
+            return Err(InvalidAppleSignature::new("invalid x5c chain"));
+        }
+        leaf_cert
+            .public_key()
+            .and_then(|key| key.public_key_to_pem())
+            .map_err(|e| InvalidAppleSignature::with_debug("couldn't get leaf public key", &e))
+    }
+
+    async fn verify_google_token(&self, token: &str) -> Result<Vec<String>, ServerError> {
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|e| InvalidGoogleSignature::with_debug("token header", &e))?;
+        let (_, key) = self
+            .google_keys
+            .iter()
+            .find(|(key_id, _)| Some(key_id) == header.kid.as_ref())
+            .ok_or(InvalidGoogleSignature::new("kid"))?;
+        let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
+        validation.validate_aud = false;
+        let claims = jsonwebtoken::decode::<serde_json::Value>(token, key, &validation)
+            .map_err(|e| InvalidGoogleSignature::with_debug("token", &e))?
+            .claims;
+        Ok(match claims.get("aud") {
+            Some(serde_json::Value::String(aud)) => vec![aud.clone()],
+            Some(serde_json::Value::Array(auds)) => auds
+                .iter()
+                .filter_map(|aud| aud.as_str().map(str::to_owned))
+                .collect(),
+            _ => Vec::new(),
+        })
+    }
+}
@@ -1,7 +1,28 @@
+use std::{ops::Range, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use fractic_env_config::SecretValues;
 use fractic_server_error::ServerError;
 
+#[cfg(feature = "app-store-connect")]
+use crate::config::AppStoreConnectCredentials;
+#[cfg(feature = "microsoft-store")]
+use crate::config::MicrosoftStoreCredentials;
+#[cfg(feature = "native")]
+use crate::config::{ProxyConfig, RootCertificate, TlsBackend};
+#[cfg(feature = "store-simulator")]
+use crate::data::datasources::http_client::HttpTransport;
+#[cfg(feature = "app-store-connect")]
+use crate::domain::entities::iap_price_schedule::ScheduledPrice;
+#[cfg(any(feature = "aws-secrets", feature = "gcp-secrets"))]
+use crate::secrets::IapCredentials;
 use crate::{
+    budget::CalloutClass,
+    cache::CacheStore,
+    capture::PayloadCapture,
+    config::{ActivePolicy, Environment, IapConfig, RetryPolicy},
+    config_validation::validate_config,
     data::{
         datasources::{
             app_store_server_api_datasource::AppStoreServerApiDatasourceImpl,
@@ -11,22 +32,62 @@ use crate::{
         },
         repositories::iap_repository_impl::IapRepositoryImpl,
     },
+    deny_list::DenyListStore,
     domain::{
         entities::{
-            iap_details::IapDetails, iap_product_id::IapConsumableId,
-            iap_purchase_id::IapPurchaseId, iap_update_notification::IapUpdateNotification,
+            iap_app_purchase::AppPurchaseDetails,
+            iap_audit::IapAuditRecord,
+            iap_consumption::{ConsumeResult, ConsumptionInfo},
+            iap_details::IapDetails,
+            iap_entitlement_extension::EntitlementExtension,
+            iap_external_purchase::{
+                AppleExternalPurchaseToken, ExternalPurchaseReport, ExternalPurchaseReportStatus,
+                GoogleExternalTransaction, GoogleExternalTransactionDetails,
+                GoogleExternalTransactionRefund,
+            },
+            iap_health_report::{CredentialReport, IapHealthReport},
+            iap_notification_history::AppleNotificationHistoryEntry,
+            iap_product_id::{IapConsumableId, IapSubscriptionId},
+            iap_product_listing::ProductListing,
+            iap_purchase_id::{
+                AppleTransactionId, CanonicalPurchaseId, GoogleExternalTransactionId, IapPurchaseId,
+            },
+            iap_refund_risk::RefundRiskProfile,
+            iap_subscription_plan::SubscriptionBasePlan,
+            iap_subscription_status::AppleSubscriptionGroupStatus,
+            iap_transaction_history::{AppleTransaction, AppleTransactionHistoryFilters},
+            iap_update_notification::IapUpdateNotification,
+            iap_verification::VerificationResult,
         },
         repositories::iap_repository::{IapRepository, TypedProductId},
     },
-    secrets::IapSecretsConfig,
+    error_observer::{ErrorObserver, IapError, IapPlatform},
+    errors::{EnvVarInvalid, EnvVarMissing},
+    interceptor::CalloutInterceptor,
+    secrets::{IapSecretsConfig, SecretString},
+    verifier::SignatureVerifier,
+};
+#[cfg(feature = "steam")]
+use crate::{config::SteamCredentials, domain::entities::iap_purchase_id::SteamOrderId};
+#[cfg(feature = "gcp-secrets")]
+use crate::{
+    config_validation::validate_application_id, data::datasources::http_client::HttpClient,
 };
 
+/// Verifies purchases and parses notifications for both platforms.
+///
+/// Cheap to clone: clones share the same HTTP client, caches and credentials,
+/// so an instance can be passed to each task (or stored in framework state)
+/// directly.
+#[derive(Clone)]
 pub struct IapUtil {
-    iap_repository: IapRepositoryImpl<
-        AppStoreServerApiDatasourceImpl,
-        AppStoreServerNotificationDatasourceImpl,
-        GooglePlayDeveloperApiDatasourceImpl,
-        GoogleCloudRtdnNotificationDatasourceImpl,
+    iap_repository: Arc<
+        IapRepositoryImpl<
+            AppStoreServerApiDatasourceImpl,
+            AppStoreServerNotificationDatasourceImpl,
+            GooglePlayDeveloperApiDatasourceImpl,
+            GoogleCloudRtdnNotificationDatasourceImpl,
+        >,
     >,
 }
 
@@ -36,31 +97,125 @@ impl IapUtil {
     ///
     /// If 'include_price_info' is true, the price and currency information will
     /// also be populated. For Google Play purchases, this requires an
-    /// additional callout.
+    /// additional callout. For App Store purchases whose transaction does not
+    /// carry a price, the price is looked up through the App Store Connect API
+    /// if configured (see 'IapUtilBuilder::app_store_connect_credentials').
+    /// For subscriptions, the price is what the customer pays for the current
+    /// period, including any offer (see 'PriceInfo::offer'); for Google Play,
+    /// it is None if this can not be determined (ex. during proration
+    /// periods).
     ///
     /// This callout will fail if the purchase does not exist, or if it is not
-    /// in an active state (ex. voided or subscription cancelled).
+    /// in an active state (ex. voided or subscription cancelled). If the
+    /// platform reports a different product type than 'product_id' (ex. a
+    /// subscription verified as a consumable), it fails with
+    /// 'ProductTypeMismatch'.
     pub async fn verify_and_get_details<T: TypedProductId>(
         &self,
         product_id: T,
         purchase_id: IapPurchaseId,
         include_price_info: bool,
-    ) -> Result<IapDetails<T::DetailsType>, ServerError> {
+    ) -> Result<IapDetails<T::DetailsType>, IapError> {
         self.iap_repository
             .verify_and_get_details(product_id, purchase_id, include_price_info)
             .await
     }
 
+    /// Same as 'verify_and_get_details', but also returns a serializable
+    /// record of the verification for audit storage: the checks the decision
+    /// was based on (which passed, failed or were skipped), and the platform
+    /// responses they were based on, with signatures and tokens redacted (as
+    /// for 'PayloadCapture').
+    ///
+    /// The record is returned whether or not the verification succeeded. The
+    /// verification cache is bypassed, so the recorded responses always come
+    /// from the platform.
+    pub async fn verify_and_get_details_audited<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+    ) -> (
+        Result<IapDetails<T::DetailsType>, IapError>,
+        IapAuditRecord<T::DetailsType>,
+    ) {
+        self.iap_repository
+            .verify_and_get_details_audited(product_id, purchase_id, include_price_info)
+            .await
+    }
+
+    /// Same as 'verify_and_get_details', but does not fail if the purchase is
+    /// no longer active (ex. voided or subscription expired). Check
+    /// 'is_active' on the returned details instead.
+    ///
+    /// For expired subscriptions, 'expiration_intent' is also populated with
+    /// the reason the subscription lapsed (if known). For App Store
+    /// subscriptions, this requires an additional callout.
+    pub async fn get_details_allow_inactive<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+    ) -> Result<IapDetails<T::DetailsType>, IapError> {
+        self.iap_repository
+            .get_details_allow_inactive(product_id, purchase_id, include_price_info)
+            .await
+    }
+
+    /// Same as 'get_details_allow_inactive', but classifies the purchase as
+    /// active, pending or inactive. Purchases which are awaiting payment (ex.
+    /// Google Play purchases paid in cash) are 'VerificationResult::Pending'
+    /// instead of failing with 'NotActive', so that they can be fulfilled
+    /// once their payment completes (see
+    /// 'NotificationDetails::OneTimePurchaseStateChanged').
+    pub async fn verify_purchase<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+    ) -> Result<VerificationResult<T::DetailsType>, IapError> {
+        self.iap_repository
+            .get_details_allow_inactive(product_id, purchase_id, include_price_info)
+            .await
+            .map(VerificationResult::from_details)
+    }
+
+    /// The ID the purchase is recorded by ('IapDetails::canonical_id'),
+    /// without verifying it, ex. to compute storage keys for purchase IDs
+    /// received from the client. For the App Store, this is the original
+    /// transaction ID (requires a callout); for Google Play, the purchase
+    /// token itself.
+    ///
+    /// For Google Play subscriptions, the earlier purchases the purchase
+    /// replaced are also resolved (one callout each), see
+    /// 'CanonicalPurchaseId::linked_ids'. Only the type of the product ID is
+    /// used, to tell subscriptions apart.
+    pub async fn canonical_purchase_id<T: TypedProductId>(
+        &self,
+        _product_id: T,
+        purchase_id: IapPurchaseId,
+    ) -> Result<CanonicalPurchaseId, IapError> {
+        self.iap_repository
+            .canonical_purchase_id::<T>(purchase_id)
+            .await
+    }
+
     /// Mark a consumable product as consumed.
     ///
-    /// Currently, this only has an effect on Google Play purchases. Apple
-    /// already assumes consumable products are consumed upon purchase, and
-    /// there is no API endpoint to consume them manually.
+    /// Currently, this only has an effect on Google Play (and Microsoft
+    /// Store) purchases. Apple already assumes consumable products are
+    /// consumed upon purchase, and there is no API endpoint to consume them
+    /// manually.
+    ///
+    /// Google Play purchases are looked up first: if already consumed,
+    /// nothing is done and 'ConsumeResult::AlreadyConsumed' is returned, so
+    /// that retries are safe. Canceled or pending purchases fail with
+    /// 'PurchaseNotConsumable'.
     pub async fn consume(
         &self,
         product_id: IapConsumableId,
         purchase_id: IapPurchaseId,
-    ) -> Result<(), ServerError> {
+    ) -> Result<ConsumeResult, IapError> {
         self.iap_repository.consume(product_id, purchase_id).await
     }
 
@@ -72,7 +227,7 @@ impl IapUtil {
     pub async fn parse_apple_notification(
         &self,
         body: &str,
-    ) -> Result<IapUpdateNotification, ServerError> {
+    ) -> Result<IapUpdateNotification, IapError> {
         self.iap_repository.parse_apple_notification(body).await
     }
 
@@ -85,12 +240,55 @@ impl IapUtil {
         &self,
         authorization_header: &str,
         body: &str,
-    ) -> Result<IapUpdateNotification, ServerError> {
+    ) -> Result<IapUpdateNotification, IapError> {
         self.iap_repository
             .parse_google_notification(authorization_header, body)
             .await
     }
 
+    /// Parse a Google RTDN developer notification which was already unwrapped
+    /// from its Pub/Sub message (ex. received through a pull subscription, or
+    /// forwarded by another service), into a generic update notification.
+    ///
+    /// NOTE: Unlike 'parse_google_notification', this can not verify the
+    /// notification's authenticity, so only use it for messages received
+    /// through a trusted channel. Since the Pub/Sub message ID is not
+    /// available, 'notification_id' is derived from the notification's
+    /// contents.
+    pub async fn parse_google_developer_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        self.iap_repository
+            .parse_google_developer_notification(body)
+            .await
+    }
+
+    /// Only check that an App Store Server Notification body is signed by
+    /// Apple (for this application), without parsing its contents or calling
+    /// the App Store Server API.
+    ///
+    /// Intended for gateways which authenticate notifications and forward
+    /// them (ex. to a queue) for processing with 'parse_apple_notification'
+    /// elsewhere.
+    pub async fn verify_apple_notification_signature(&self, body: &str) -> Result<(), IapError> {
+        self.iap_repository
+            .verify_apple_notification_signature(body)
+            .await
+    }
+
+    /// Only check the 'Authorization' header of a Google Cloud Pub/Sub push
+    /// request, without parsing the body or calling the Google Play Developer
+    /// API. See 'verify_apple_notification_signature'.
+    pub async fn verify_google_notification_auth(
+        &self,
+        authorization_header: &str,
+    ) -> Result<(), IapError> {
+        self.iap_repository
+            .verify_google_notification_auth(authorization_header)
+            .await
+    }
+
     /// Request a server-to-server notification of type 'TEST' from Apple.
     ///
     /// Currently, the only way to request test notifications from Apple is
@@ -98,14 +296,452 @@ impl IapUtil {
     /// notifications in the console.
     ///
     /// Returns the test notification token returned by Apple.
-    pub async fn request_apple_test_notification(
+    pub async fn request_apple_test_notification(&self, sandbox: bool) -> Result<String, IapError> {
+        self.iap_repository
+            .request_apple_test_notification(sandbox)
+            .await
+    }
+
+    /// Get the App Store Server Notifications Apple sent (or tried to send)
+    /// in the given period, with Apple's delivery attempts for each. Apple
+    /// keeps the history for 180 days.
+    ///
+    /// NOTE: This pages through the whole period, so it may take several
+    /// callouts for busy apps.
+    pub async fn get_apple_notification_history(
+        &self,
+        range: Range<DateTime<Utc>>,
+        sandbox: bool,
+    ) -> Result<Vec<AppleNotificationHistoryEntry>, IapError> {
+        self.iap_repository
+            .get_apple_notification_history(range, sandbox)
+            .await
+    }
+
+    /// Check that the configured credentials are accepted by both platforms:
+    /// builds a fresh App Store Server API JWT and uses it for an
+    /// authenticated callout, and mints a fresh Google Play Developer API
+    /// access token.
+    ///
+    /// Intended for readiness probes, so that invalid or revoked keys are
+    /// noticed at startup rather than on the first real purchase.
+    pub async fn health_check(&self) -> IapHealthReport {
+        self.iap_repository.health_check().await
+    }
+
+    /// Validate the configured credentials step by step (Apple key parse, JWT
+    /// accepted by Apple; Google key parse, access token mint, access to the
+    /// app's purchases), and report the outcome of each check. Unlike
+    /// 'health_check', failures point at the specific misconfiguration.
+    ///
+    /// Intended for deploy-time checks, ex. failing the deployment with
+    /// 'CredentialReport::into_result'.
+    pub async fn validate_credentials(&self) -> CredentialReport {
+        self.iap_repository.validate_credentials().await
+    }
+
+    /// Prepare the platform API credentials now, if they have not been yet
+    /// (see 'IapUtilBuilder::lazy_credentials'). Fails if either platform's
+    /// credentials are invalid.
+    pub async fn warm_up(&self) -> Result<(), IapError> {
+        self.iap_repository.warm_up().await
+    }
+
+    /// Decode an external purchase token, as returned by StoreKit on the device
+    /// when the customer is sent to an alternative payment option (External
+    /// Purchase / StoreKit External Link entitlements), and check that it was
+    /// issued for this app.
+    ///
+    /// NOTE: Tokens are not signed, so they only identify the external
+    /// purchase; they do not prove that it took place. Track the transaction
+    /// under 'token.purchase_id()', and report it to Apple with
+    /// 'report_apple_external_purchases'.
+    pub fn decode_apple_external_purchase_token(
+        &self,
+        token: &str,
+    ) -> Result<AppleExternalPurchaseToken, IapError> {
+        self.iap_repository
+            .decode_apple_external_purchase_token(token)
+    }
+
+    /// Verify that an App Store AppTransaction (proof that the customer
+    /// purchased or downloaded the app itself, obtained by the app through
+    /// StoreKit) is signed by Apple for this app, and return its details.
+    ///
+    /// 'signed_app_transaction' is the AppTransaction's JWS representation.
+    /// It is verified locally (like the transactions returned by the App Store
+    /// Server API), so no callout is made.
+    pub async fn verify_apple_app_transaction(
+        &self,
+        signed_app_transaction: &str,
+    ) -> Result<AppPurchaseDetails, IapError> {
+        self.iap_repository
+            .verify_apple_app_transaction(signed_app_transaction)
+            .await
+    }
+
+    /// Extend the current period of an App Store or Google Play subscription
+    /// without charging the customer (ex. as compensation for an outage),
+    /// and return its new expiry time. Uses Apple's subscription renewal
+    /// date extension, or Google's subscription deferral.
+    ///
+    /// NOTE: Apple limits extensions to 90 days each, and two per
+    /// subscription per year. Google defers the subscription's latest line
+    /// item, and fails if its expiry changed concurrently (ex. renewed).
+    pub async fn extend_entitlement(
+        &self,
+        purchase_id: IapPurchaseId,
+        extension: &EntitlementExtension,
+    ) -> Result<DateTime<Utc>, IapError> {
+        self.iap_repository
+            .extend_entitlement(purchase_id, extension)
+            .await
+    }
+
+    /// Respond to a consumption request (see
+    /// 'NotificationDetails::ConsumptionRequested'), with information Apple
+    /// uses to decide on the customer's refund request. Must be sent before
+    /// the request's deadline; 'consumption::ConsumptionResponder' handles
+    /// this automatically.
+    pub async fn send_apple_consumption_info(
+        &self,
+        transaction_id: AppleTransactionId,
+        info: &ConsumptionInfo,
+    ) -> Result<(), IapError> {
+        self.iap_repository
+            .send_apple_consumption_info(transaction_id, info)
+            .await
+    }
+
+    /// Aggregate refund-relevant signals (refund counts, quantities and
+    /// reasons) from the App Store transaction history of the customer who
+    /// made the given transaction (any of their transactions, including
+    /// original transactions, can be used).
+    ///
+    /// Consumption request reasons are not part of the history; record them
+    /// from notifications with 'RefundRiskProfile::record_notification'.
+    ///
+    /// NOTE: This pages through the customer's full history, so it may take
+    /// several callouts for long-standing subscribers.
+    pub async fn get_apple_refund_risk_profile(
+        &self,
+        transaction_id: AppleTransactionId,
+    ) -> Result<RefundRiskProfile, IapError> {
+        self.iap_repository
+            .get_apple_refund_risk_profile(transaction_id)
+            .await
+    }
+
+    /// Look up the latest state of all of the customer's App Store
+    /// subscriptions (in all subscription groups), by the original
+    /// transaction ID of any of them. Unlike 'get_details_allow_inactive',
+    /// this tells billing retry and grace periods apart, and reports pending
+    /// downgrades (see 'AppleSubscriptionStatus::auto_renew_product_id').
+    pub async fn get_subscription_statuses(
         &self,
+        original_transaction_id: AppleTransactionId,
+    ) -> Result<Vec<AppleSubscriptionGroupStatus>, IapError> {
+        self.iap_repository
+            .get_subscription_statuses(original_transaction_id)
+            .await
+    }
+
+    /// List the App Store transactions of the customer who made the given
+    /// transaction (any of their transactions, including original
+    /// transactions, can be used), oldest first, ex. to reconstruct their
+    /// purchases when re-linking their account. Transactions of all of the
+    /// app's products are included, unless limited by 'filters'.
+    ///
+    /// NOTE: This pages through the customer's full history, so it may take
+    /// several callouts for long-standing subscribers.
+    pub async fn get_apple_transaction_history(
+        &self,
+        transaction_id: AppleTransactionId,
+        filters: AppleTransactionHistoryFilters,
+    ) -> Result<Vec<AppleTransaction>, IapError> {
+        self.iap_repository
+            .get_apple_transaction_history(transaction_id, filters)
+            .await
+    }
+
+    /// Send a report of external purchases (and tokens which did not lead to
+    /// a purchase) to Apple, as required by the External Purchase entitlements.
+    ///
+    /// Set 'sandbox' for tokens created in the sandbox environment (ie. with
+    /// no 'app_apple_id').
+    pub async fn report_apple_external_purchases(
+        &self,
+        report: &ExternalPurchaseReport,
         sandbox: bool,
-    ) -> Result<String, ServerError> {
+    ) -> Result<(), IapError> {
+        self.iap_repository
+            .report_apple_external_purchases(report, sandbox)
+            .await
+    }
+
+    /// Check whether Apple has processed a report previously sent with
+    /// 'report_apple_external_purchases'.
+    pub async fn get_apple_external_purchase_report(
+        &self,
+        request_identifier: &str,
+        sandbox: bool,
+    ) -> Result<ExternalPurchaseReportStatus, IapError> {
+        self.iap_repository
+            .get_apple_external_purchase_report(request_identifier, sandbox)
+            .await
+    }
+
+    /// Look up the prices scheduled for an App Store product in App Store
+    /// Connect, including past and future prices. Requires
+    /// 'IapUtilBuilder::app_store_connect_credentials'.
+    ///
+    /// If 'region_iso3166_alpha_3' is set, only prices in that storefront are
+    /// returned; otherwise, prices in all storefronts.
+    #[cfg(feature = "app-store-connect")]
+    pub async fn get_apple_price_schedule<T: TypedProductId>(
+        &self,
+        product_id: T,
+        region_iso3166_alpha_3: Option<&str>,
+    ) -> Result<Vec<ScheduledPrice>, IapError> {
+        self.iap_repository
+            .get_apple_price_schedule(product_id, region_iso3166_alpha_3)
+            .await
+    }
+
+    /// Look up the active base plans of a Google Play subscription, with the
+    /// pricing phases of their active offers (ex. free trials and
+    /// introductory prices) in each region, through the monetization API.
+    pub async fn get_google_subscription_plans(
+        &self,
+        product_id: IapSubscriptionId,
+    ) -> Result<Vec<SubscriptionBasePlan>, IapError> {
+        self.iap_repository
+            .get_google_subscription_plans(product_id)
+            .await
+    }
+
+    /// Look up the localized store listing (title and description) of a
+    /// product on the given platform, for showing the product's name in the
+    /// customer's language (ex. on receipts).
+    ///
+    /// locale:
+    ///   BCP-47 locale (ex. 'en-US'). If the product is not localized for it,
+    ///   the listing of another region of the same language is returned
+    ///   instead (ex. 'en-GB'); for Google Play, then the listing in the
+    ///   product's default language.
+    ///
+    /// Returns None if the product has no listing for the locale. Available
+    /// for Google Play products (managed through the 'inappproducts' API), and
+    /// for App Store products if App Store Connect is configured (see
+    /// 'IapUtilBuilder::app_store_connect_credentials').
+    pub async fn get_product_listing<T: TypedProductId>(
+        &self,
+        product_id: T,
+        platform: IapPlatform,
+        locale: &str,
+    ) -> Result<Option<ProductListing>, IapError> {
+        self.iap_repository
+            .get_product_listing(product_id, platform, locale)
+            .await
+    }
+
+    /// Report a transaction made through Google Play's alternative billing
+    /// programs (user choice billing) to Google, as required within 24 hours
+    /// of the transaction. Subscriptions must be reported on each payment.
+    ///
+    /// NOTE: Google does not send real-time developer notifications for these
+    /// transactions (when the user picks Google Play billing instead, the
+    /// purchase is a regular one), so their lifecycle must be tracked in the
+    /// alternative billing system; refunds must also be reported, with
+    /// 'refund_google_external_transaction'.
+    pub async fn report_google_external_transaction(
+        &self,
+        transaction: &GoogleExternalTransaction,
+    ) -> Result<GoogleExternalTransactionDetails, IapError> {
+        self.iap_repository
+            .report_google_external_transaction(transaction)
+            .await
+    }
+
+    /// Look up a transaction previously reported with
+    /// 'report_google_external_transaction'.
+    pub async fn get_google_external_transaction(
+        &self,
+        external_transaction_id: GoogleExternalTransactionId,
+    ) -> Result<GoogleExternalTransactionDetails, IapError> {
+        self.iap_repository
+            .get_google_external_transaction(external_transaction_id)
+            .await
+    }
+
+    /// Report a full or partial refund of a transaction previously reported
+    /// with 'report_google_external_transaction'.
+    pub async fn refund_google_external_transaction(
+        &self,
+        external_transaction_id: GoogleExternalTransactionId,
+        refund: &GoogleExternalTransactionRefund,
+        refund_time: DateTime<Utc>,
+    ) -> Result<GoogleExternalTransactionDetails, IapError> {
+        self.iap_repository
+            .refund_google_external_transaction(external_transaction_id, refund, refund_time)
+            .await
+    }
+
+    /// Finalize a Steam order which the user has approved (in the Steam
+    /// overlay), charging the user. The order's details only report it as
+    /// active once it has been finalized.
+    ///
+    /// Finalizing an order which was already finalized fails.
+    #[cfg(feature = "steam")]
+    pub async fn finalize_steam_order(&self, order_id: SteamOrderId) -> Result<(), IapError> {
+        self.iap_repository.finalize_steam_order(order_id).await
+    }
+
+    /// Verify the notification authenticity (signed with the webhook secret,
+    /// see 'IapUtilBuilder::paddle_webhook_secret'), and parse a Paddle
+    /// Billing webhook notification into a generic update notification, so
+    /// that subscriptions sold on the web can be handled the same way as
+    /// in-app subscriptions.
+    ///
+    /// Subscription lifecycle events are mapped to the corresponding
+    /// subscription notifications, and all other events to
+    /// 'NotificationDetails::Other'. The subscription's product ID is the
+    /// Paddle product ID (ex. 'pro_01gsz4t5hdjse780zja8vvr7jg'), and
+    /// 'application_id' is this instance's application ID.
+    ///
+    /// NOTE: Unlike the store notifications, this does not call out to any
+    /// API, since Paddle includes the full subscription in the notification.
+    #[cfg(feature = "paddle")]
+    pub async fn parse_paddle_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        self.iap_repository
+            .parse_paddle_notification(signature_header, body)
+            .await
+    }
+
+    /// Verify the event authenticity (signed with the webhook secret, see
+    /// 'IapUtilBuilder::stripe_webhook_secret'), and parse a Stripe webhook
+    /// event into a generic update notification, so that subscriptions sold
+    /// on the web can be handled the same way as in-app subscriptions.
+    ///
+    /// 'customer.subscription.*' events are mapped to the corresponding
+    /// subscription notifications, paid renewal invoices ('invoice.paid') to
+    /// 'SubscriptionExpiryChanged' with the invoice ID as 'renewal_id', and
+    /// all other events to 'NotificationDetails::Other'. The subscription's
+    /// product ID is the Stripe product ID (ex. 'prod_NWjs8kKbJWmuuc'), and
+    /// 'application_id' is this instance's application ID.
+    ///
+    /// NOTE: Unlike the store notifications, this does not call out to any
+    /// API, since Stripe includes the full object in the event.
+    #[cfg(feature = "stripe")]
+    pub async fn parse_stripe_notification(
+        &self,
+        signature_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        self.iap_repository
+            .parse_stripe_notification(signature_header, body)
+            .await
+    }
+
+    /// Discard cached Google keys, so that they are fetched again for the next
+    /// Google notification. Use this if verification starts failing after
+    /// Google rotated its keys, before the cache expired (see
+    /// 'IapUtilBuilder::google_jwks_cache_ttl').
+    pub async fn refresh_google_keys(&self) -> Result<(), IapError> {
+        self.iap_repository.refresh_google_keys().await
+    }
+}
+
+/// Allows application code to depend on 'IapRepository' instead of 'IapUtil',
+/// so that it can be tested against 'test_util::MockIapRepository' (with the
+/// 'test-util' feature).
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl IapRepository for IapUtil {
+    async fn verify_and_get_details<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+    ) -> Result<IapDetails<T::DetailsType>, IapError> {
+        self.iap_repository
+            .verify_and_get_details(product_id, purchase_id, include_price_info)
+            .await
+    }
+
+    async fn get_details_allow_inactive<T: TypedProductId>(
+        &self,
+        product_id: T,
+        purchase_id: IapPurchaseId,
+        include_price_info: bool,
+    ) -> Result<IapDetails<T::DetailsType>, IapError> {
+        self.iap_repository
+            .get_details_allow_inactive(product_id, purchase_id, include_price_info)
+            .await
+    }
+
+    async fn consume(
+        &self,
+        product_id: IapConsumableId,
+        purchase_id: IapPurchaseId,
+    ) -> Result<ConsumeResult, IapError> {
+        self.iap_repository.consume(product_id, purchase_id).await
+    }
+
+    async fn parse_apple_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        self.iap_repository.parse_apple_notification(body).await
+    }
+
+    async fn parse_google_notification(
+        &self,
+        authorization_header: &str,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        self.iap_repository
+            .parse_google_notification(authorization_header, body)
+            .await
+    }
+
+    async fn parse_google_developer_notification(
+        &self,
+        body: &str,
+    ) -> Result<IapUpdateNotification, IapError> {
+        self.iap_repository
+            .parse_google_developer_notification(body)
+            .await
+    }
+
+    async fn verify_apple_notification_signature(&self, body: &str) -> Result<(), IapError> {
+        self.iap_repository
+            .verify_apple_notification_signature(body)
+            .await
+    }
+
+    async fn verify_google_notification_auth(
+        &self,
+        authorization_header: &str,
+    ) -> Result<(), IapError> {
+        self.iap_repository
+            .verify_google_notification_auth(authorization_header)
+            .await
+    }
+
+    async fn request_apple_test_notification(&self, sandbox: bool) -> Result<String, IapError> {
         self.iap_repository
             .request_apple_test_notification(sandbox)
             .await
     }
+
+    async fn health_check(&self) -> IapHealthReport {
+        self.iap_repository.health_check().await
+    }
 }
 
 impl IapUtil {
@@ -114,17 +750,9 @@ impl IapUtil {
         application_id: impl Into<String>,
         aud_claim: impl Into<String>,
     ) -> Result<Self, ServerError> {
-        Ok(Self {
-            iap_repository: IapRepositoryImpl::new(
-                application_id,
-                aud_claim,
-                secrets.get(&IapSecretsConfig::AppleApiKey)?,
-                secrets.get(&IapSecretsConfig::AppleKeyId)?,
-                secrets.get(&IapSecretsConfig::AppleIssuerId)?,
-                secrets.get(&IapSecretsConfig::GoogleApiKey)?,
-            )
-            .await?,
-        })
+        IapUtilBuilder::from_secrets(secrets, application_id, aud_claim)?
+            .build()
+            .await
     }
 
     pub async fn from_values(
@@ -134,17 +762,769 @@ impl IapUtil {
         apple_key_id: &str,
         apple_issuer_id: &str,
         google_api_key: &str,
+    ) -> Result<Self, ServerError> {
+        IapUtilBuilder::from_values(
+            application_id,
+            expected_aud,
+            apple_api_key,
+            apple_key_id,
+            apple_issuer_id,
+            google_api_key,
+        )
+        .build()
+        .await
+    }
+
+    /// Read the application ID, audience and credentials from environment
+    /// variables (see 'IapUtilBuilder::from_env').
+    pub async fn from_env() -> Result<Self, ServerError> {
+        IapUtilBuilder::from_env()?.build().await
+    }
+
+    /// Same as 'from_env', with the variable names prefixed by 'prefix' (see
+    /// 'IapUtilBuilder::from_env_with_prefix').
+    pub async fn from_env_with_prefix(prefix: &str) -> Result<Self, ServerError> {
+        IapUtilBuilder::from_env_with_prefix(prefix)?.build().await
+    }
+
+    /// Load the credentials from a JSON secret in AWS Secrets Manager (see
+    /// 'IapUtilBuilder::from_aws_secrets').
+    #[cfg(feature = "aws-secrets")]
+    pub async fn from_aws_secrets(
+        secret_id: &str,
+        application_id: impl Into<String>,
+        expected_aud: impl Into<String>,
+    ) -> Result<Self, ServerError> {
+        IapUtilBuilder::from_aws_secrets(secret_id, application_id, expected_aud)
+            .await?
+            .build()
+            .await
+    }
+
+    /// Load the credentials from a JSON secret in GCP Secret Manager (see
+    /// 'IapUtilBuilder::from_gcp_secret').
+    #[cfg(feature = "gcp-secrets")]
+    pub async fn from_gcp_secret(
+        name: &str,
+        application_id: impl Into<String>,
+        expected_aud: impl Into<String>,
+    ) -> Result<Self, ServerError> {
+        IapUtilBuilder::from_gcp_secret(name, application_id, expected_aud)
+            .await?
+            .build()
+            .await
+    }
+}
+
+/// Builder for 'IapUtil', to customize behaviour beyond the defaults used by
+/// 'IapUtil::from_secrets' and 'IapUtil::from_values'.
+pub struct IapUtilBuilder {
+    application_id: String,
+    apple_expected_auds: Vec<String>,
+    google_expected_auds: Vec<String>,
+    apple_api_key: SecretString,
+    apple_key_id: String,
+    apple_issuer_id: String,
+    google_api_key: SecretString,
+    config: IapConfig,
+    /// GCP Secret Manager secret the credentials are fetched from on 'build'.
+    #[cfg(feature = "gcp-secrets")]
+    gcp_secret: Option<String>,
+}
+
+impl IapUtilBuilder {
+    pub fn from_secrets(
+        secrets: SecretValues<IapSecretsConfig>,
+        application_id: impl Into<String>,
+        aud_claim: impl Into<String>,
+    ) -> Result<Self, ServerError> {
+        Ok(Self::from_values(
+            application_id,
+            aud_claim,
+            secrets.get(&IapSecretsConfig::AppleApiKey)?,
+            secrets.get(&IapSecretsConfig::AppleKeyId)?,
+            secrets.get(&IapSecretsConfig::AppleIssuerId)?,
+            secrets.get(&IapSecretsConfig::GoogleApiKey)?,
+        ))
+    }
+
+    pub fn from_values(
+        application_id: impl Into<String>,
+        expected_aud: impl Into<String>,
+        apple_api_key: &str,
+        apple_key_id: &str,
+        apple_issuer_id: &str,
+        google_api_key: &str,
+    ) -> Self {
+        let expected_aud = expected_aud.into();
+        Self {
+            application_id: application_id.into(),
+            apple_expected_auds: vec![expected_aud.clone()],
+            google_expected_auds: vec![expected_aud],
+            apple_api_key: SecretString::new(apple_api_key),
+            apple_key_id: apple_key_id.to_owned(),
+            apple_issuer_id: apple_issuer_id.to_owned(),
+            google_api_key: SecretString::new(google_api_key),
+            config: IapConfig::default(),
+            #[cfg(feature = "gcp-secrets")]
+            gcp_secret: None,
+        }
+    }
+
+    /// Read the application ID, audience and credentials from the following
+    /// environment variables:
+    ///
+    /// - "APPLICATION_ID": the app's bundle ID / package name.
+    /// - "EXPECTED_AUD": the expected audience (see 'from_values'). Can be a
+    ///   comma-separated list, in which case the others are also accepted (see
+    ///   'accept_aud').
+    /// - "APPLE_API_KEY", "APPLE_KEY_ID", "APPLE_ISSUER_ID", "GOOGLE_API_KEY":
+    ///   the credentials, with the same contents as for 'from_secrets'.
+    ///
+    /// Fails with 'EnvVarMissing' if any of them is not set.
+    pub fn from_env() -> Result<Self, ServerError> {
+        Self::from_env_with_prefix("")
+    }
+
+    /// Same as 'from_env', with each variable name prefixed by 'prefix' (ex.
+    /// with "IAP_", the Apple API key is read from "IAP_APPLE_API_KEY").
+    pub fn from_env_with_prefix(prefix: &str) -> Result<Self, ServerError> {
+        let expected_auds = env_var(prefix, "EXPECTED_AUD")?;
+        let mut expected_auds = expected_auds
+            .split(',')
+            .map(str::trim)
+            .filter(|aud| !aud.is_empty());
+        let expected_aud = expected_auds.next().unwrap_or_default().to_owned();
+        let builder = Self::from_values(
+            env_var(prefix, "APPLICATION_ID")?,
+            expected_aud,
+            &env_var(prefix, "APPLE_API_KEY")?,
+            &env_var(prefix, "APPLE_KEY_ID")?,
+            &env_var(prefix, "APPLE_ISSUER_ID")?,
+            &env_var(prefix, "GOOGLE_API_KEY")?,
+        );
+        Ok(expected_auds.fold(builder, |builder, aud| builder.accept_aud(aud)))
+    }
+
+    /// Fetch the credentials from AWS Secrets Manager at startup, so private
+    /// keys do not need to be passed through environment variables.
+    ///
+    /// The secret must be a JSON object with the keys "GOOGLE_API_KEY",
+    /// "APPLE_API_KEY", "APPLE_KEY_ID", and "APPLE_ISSUER_ID". 'secret_id' can
+    /// be the secret's name or full ARN (in which case the ARN's region is
+    /// used). AWS credentials are resolved through the default provider chain.
+    #[cfg(feature = "aws-secrets")]
+    pub async fn from_aws_secrets(
+        secret_id: &str,
+        application_id: impl Into<String>,
+        expected_aud: impl Into<String>,
+    ) -> Result<Self, ServerError> {
+        let credentials = IapCredentials::from_aws_secrets_manager(secret_id).await?;
+        Ok(Self::from_credentials(
+            application_id,
+            expected_aud,
+            credentials,
+        ))
+    }
+
+    /// Fetch the credentials from GCP Secret Manager at startup, so private
+    /// keys do not need to be passed through environment variables.
+    ///
+    /// The secret must be a JSON object with the same keys as for
+    /// 'from_aws_secrets'. 'name' is the secret's resource name, ex.
+    /// "projects/my-project/secrets/iap-credentials" (optionally with
+    /// "/versions/<version>"; defaults to the latest version). Authenticates
+    /// as the runtime's service account through the GCP metadata server.
+    ///
+    /// The secret is fetched by 'build', so that the requests go through the
+    /// configured proxy and root certificates (see 'proxy'); until then,
+    /// 'validate' only checks the application ID.
+    #[cfg(feature = "gcp-secrets")]
+    pub async fn from_gcp_secret(
+        name: &str,
+        application_id: impl Into<String>,
+        expected_aud: impl Into<String>,
     ) -> Result<Self, ServerError> {
         Ok(Self {
-            iap_repository: IapRepositoryImpl::new(
-                application_id,
-                expected_aud,
-                apple_api_key,
-                apple_key_id,
-                apple_issuer_id,
-                google_api_key,
-            )
-            .await?,
+            gcp_secret: Some(name.to_owned()),
+            ..Self::from_values(application_id, expected_aud, "", "", "", "")
         })
     }
+
+    #[cfg(feature = "aws-secrets")]
+    fn from_credentials(
+        application_id: impl Into<String>,
+        expected_aud: impl Into<String>,
+        credentials: IapCredentials,
+    ) -> Self {
+        let expected_aud = expected_aud.into();
+        Self {
+            application_id: application_id.into(),
+            apple_expected_auds: vec![expected_aud.clone()],
+            google_expected_auds: vec![expected_aud],
+            apple_api_key: credentials.apple_api_key,
+            apple_key_id: credentials.apple_key_id,
+            apple_issuer_id: credentials.apple_issuer_id,
+            google_api_key: credentials.google_api_key,
+            config: IapConfig::default(),
+            #[cfg(feature = "gcp-secrets")]
+            gcp_secret: None,
+        }
+    }
+
+    /// Also accept tokens and payloads issued for the given audience, besides
+    /// the 'expected_aud' the builder was created with (ex. when staging and
+    /// production push subscriptions use different audiences, or while
+    /// migrating to a new one). Applies to both Google Pub/Sub tokens and
+    /// Apple JWS payloads.
+    pub fn accept_aud(mut self, aud: impl Into<String>) -> Self {
+        let aud = aud.into();
+        self.apple_expected_auds.push(aud.clone());
+        self.google_expected_auds.push(aud);
+        self
+    }
+
+    /// Audience(s) accepted for Apple JWS payloads (ex. the app's bundle ID),
+    /// replacing the 'expected_aud' the builder was created with (and any
+    /// previous 'accept_aud'). Google Pub/Sub tokens are not affected.
+    pub fn apple_expected_auds(
+        mut self,
+        auds: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.apple_expected_auds = auds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Audience(s) accepted for the OIDC tokens of Google Pub/Sub push
+    /// requests (the audience configured on the push subscription),
+    /// replacing the 'expected_aud' the builder was created with (and any
+    /// previous 'accept_aud'). Apple JWS payloads are not affected.
+    pub fn google_expected_auds(
+        mut self,
+        auds: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.google_expected_auds = auds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Grace window applied when checking whether a subscription has expired,
+    /// to tolerate clock skew and renewals which are still being processed by
+    /// the store. A subscription is only considered expired once its expiry
+    /// time plus this leeway has passed.
+    ///
+    /// Defaults to zero (no leeway). Values in the range of a minute to an
+    /// hour are typical.
+    pub fn expiry_leeway(mut self, leeway: Duration) -> Self {
+        self.config.set_expiry_leeway(leeway);
+        self
+    }
+
+    /// Retry policy applied to transient failures (5xx responses, 429
+    /// responses, and connection errors) when calling the App Store Server API
+    /// and Google Play Developer API.
+    ///
+    /// Defaults to 'RetryPolicy::none()' (no retries).
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    /// Prepare the platform API credentials on first use, rather than in
+    /// 'build' (which mints a Google access token over the network). Keeps
+    /// cold starts fast, and allows building instances without network
+    /// access (ex. in tests). Invalid credentials are then only reported by
+    /// the first operation using them; call 'IapUtil::warm_up' to prepare
+    /// them ahead of time. The offline checks of 'validate' are skipped as
+    /// well.
+    ///
+    /// Disabled by default.
+    pub fn lazy_credentials(mut self, enabled: bool) -> Self {
+        self.config.lazy_credentials = enabled;
+        self
+    }
+
+    /// Include the Google profile (ID, name, email address) of customers who
+    /// subscribed through Subscribe with Google in
+    /// 'PlatformExtras::SubscribeWithGoogle'. Only enable this if the app
+    /// needs it (ex. to link the subscription to an account on the web), as
+    /// it is personal data; the profile is redacted from 'Debug' output
+    /// either way.
+    ///
+    /// Disabled by default (only the fact that the subscription was bought
+    /// through Subscribe with Google is reported).
+    pub fn expose_subscribe_with_google_profile(mut self, enabled: bool) -> Self {
+        self.config.expose_subscribe_with_google_profile = enabled;
+        self
+    }
+
+    /// Which subscription states count as active (ex. whether access is cut
+    /// while a Google Play subscription is paused or on account hold). See
+    /// 'ActivePolicy'.
+    pub fn active_policy(mut self, policy: ActivePolicy) -> Self {
+        self.config.active_policy = policy;
+        self
+    }
+
+    /// Which App Store Server API environments purchases are looked up in.
+    ///
+    /// Defaults to 'Environment::ProductionWithSandboxFallback'. Use
+    /// 'Environment::SandboxOnly' for staging deployments, to skip the
+    /// production callout (which always fails for sandbox purchases). Can be
+    /// overridden for individual calls with 'config::with_environment'.
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.config.environment = environment;
+        self
+    }
+
+    /// Allow at most 'limit' simultaneous callouts to the App Store Server
+    /// API. Further callouts wait until one completes, so that bursts (ex.
+    /// bulk reconciliation) do not exhaust connections or trip Apple's rate
+    /// limits.
+    ///
+    /// Unlimited by default.
+    pub fn apple_max_concurrent_callouts(mut self, limit: usize) -> Self {
+        self.config.apple_max_concurrent_callouts = Some(limit);
+        self
+    }
+
+    /// Allow at most 'limit' simultaneous callouts to the Google Play
+    /// Developer API (see 'apple_max_concurrent_callouts').
+    ///
+    /// Unlimited by default.
+    pub fn google_max_concurrent_callouts(mut self, limit: usize) -> Self {
+        self.config.google_max_concurrent_callouts = Some(limit);
+        self
+    }
+
+    /// Reserve 'slots' of each platform's concurrency limit (see
+    /// 'apple_max_concurrent_callouts') for callouts of the given class. The
+    /// class can use its reserved slots as well as the unreserved ones, while
+    /// other classes can only use the unreserved ones.
+    ///
+    /// Callouts are classed as 'CalloutClass::Verification' unless made
+    /// within 'with_callout_class' (ex. wrap backfill jobs with
+    /// 'CalloutClass::Reconciliation'), or while parsing a notification
+    /// ('CalloutClass::Notification'). Reservations are capped so that at
+    /// least one slot remains unreserved, and are ignored for platforms
+    /// without a concurrency limit.
+    pub fn reserve_callouts(mut self, class: CalloutClass, slots: usize) -> Self {
+        self.config.reserved_callouts.push((class, slots));
+        self
+    }
+
+    /// Cache successful 'verify_and_get_details' results for the given
+    /// duration (in memory, unless a 'cache_store' is set), so that repeated verifications of the same purchase
+    /// (ex. client retries) do not each result in a callout to the store.
+    ///
+    /// Cached results for a purchase are discarded whenever a notification
+    /// for that purchase is parsed through this instance. Since the store is
+    /// not consulted while a result is cached, keep the TTL short (a few
+    /// seconds to a minute).
+    ///
+    /// Disabled by default.
+    pub fn verification_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.config.verification_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Cache Google Play product metadata (fetched for price info when
+    /// verifying one-time purchases) for the given duration. Product prices
+    /// rarely change, so longer TTLs (ex. an hour) are reasonable.
+    ///
+    /// Disabled by default.
+    pub fn product_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.config.product_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Back the caches with the given store instead of memory, ex. to share
+    /// them across instances.
+    pub fn cache_store(mut self, store: impl CacheStore + 'static) -> Self {
+        self.config.cache_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Reject purchases in the given deny list during verification, even if
+    /// the platform reports them active (see 'DenyListStore').
+    pub fn deny_list(mut self, store: impl DenyListStore + 'static) -> Self {
+        self.config.deny_list = Some(Arc::new(store));
+        self
+    }
+
+    /// Register a hook which is called for every callout to the App Store
+    /// Server API and Google Play Developer API (ex. for audit logging, or to
+    /// add headers). Can be called multiple times; interceptors run in the
+    /// order they were registered.
+    pub fn interceptor(mut self, interceptor: impl CalloutInterceptor + 'static) -> Self {
+        self.config.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Register a hook which is called with structured context whenever an
+    /// operation fails (ex. to route alerts by platform and failure class).
+    /// Can be called multiple times; observers run in the order they were
+    /// registered.
+    pub fn error_observer(mut self, observer: impl ErrorObserver + 'static) -> Self {
+        self.config.error_observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Key used to hash purchase IDs (HMAC-SHA256) in error contexts (see
+    /// 'IapErrorContext::purchase_id_hash'), so that failures for the same
+    /// purchase can be correlated without exposing the ID. Purchase IDs are
+    /// left out of error contexts if not set.
+    pub fn purchase_id_hash_key(mut self, key: &str) -> Self {
+        self.config.purchase_id_hash_key = Some(SecretString::new(key));
+        self
+    }
+
+    /// User-Agent sent with all platform API callouts, ex. to identify your
+    /// service's traffic to Apple / Google support.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.config.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Send an additional static header with all platform API callouts. Can
+    /// be called multiple times. Invalid header names or values cause 'build'
+    /// to fail.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send the current correlation ID (see
+    /// 'correlation::with_correlation_id') in the given header with every
+    /// platform API callout (ex. "X-Request-ID", for egress proxies or
+    /// gateways which log it). Callouts without a correlation ID are sent
+    /// without the header.
+    ///
+    /// Not sent by default, since neither platform API uses it.
+    pub fn correlation_id_header(mut self, name: impl Into<String>) -> Self {
+        self.config.correlation_id_header = Some(name.into());
+        self
+    }
+
+    /// Register a hook which receives the raw notification bodies, decoded JWS
+    /// payloads, and platform API responses processed by 'IapUtil', with
+    /// signatures and tokens redacted (ex. to archive them for investigating
+    /// failed verifications). Can be called multiple times.
+    pub fn payload_capture(mut self, capture: impl PayloadCapture + 'static) -> Self {
+        self.config.payload_captures.push(Arc::new(capture));
+        self
+    }
+
+    /// Send all platform API callouts through the given proxy.
+    #[cfg(feature = "native")]
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// Trust an additional root certificate (PEM-encoded) for platform API
+    /// callouts, ex. for a TLS-intercepting egress proxy. Invalid
+    /// certificates cause 'build' to fail.
+    #[cfg(feature = "native")]
+    pub fn add_root_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.config
+            .root_certificates
+            .push(RootCertificate::Pem(pem.into()));
+        self
+    }
+
+    /// Same as 'add_root_certificate_pem', but for DER-encoded certificates.
+    #[cfg(feature = "native")]
+    pub fn add_root_certificate_der(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.config
+            .root_certificates
+            .push(RootCertificate::Der(der.into()));
+        self
+    }
+
+    /// Whether the TLS backend's built-in root certificates are trusted
+    /// (default: true). Disable to only trust certificates added through
+    /// 'add_root_certificate_pem' / 'add_root_certificate_der'.
+    #[cfg(feature = "native")]
+    pub fn tls_built_in_root_certs(mut self, enabled: bool) -> Self {
+        self.config.tls_built_in_root_certs = enabled;
+        self
+    }
+
+    /// TLS implementation used for platform API callouts (default: rustls).
+    #[cfg(feature = "native")]
+    pub fn tls_backend(mut self, tls_backend: TlsBackend) -> Self {
+        self.config.tls_backend = tls_backend;
+        self
+    }
+
+    /// Send App Store Server API callouts to the given base URLs (ex. a local
+    /// simulator, or an API gateway), instead of
+    /// 'https://api.storekit.itunes.apple.com' (production) and
+    /// 'https://api.storekit-sandbox.itunes.apple.com' (sandbox).
+    pub fn apple_api_base_urls(
+        mut self,
+        production: impl Into<String>,
+        sandbox: impl Into<String>,
+    ) -> Self {
+        self.config.apple_production_base_url = trim_base_url(production.into());
+        self.config.apple_sandbox_base_url = trim_base_url(sandbox.into());
+        self
+    }
+
+    /// Send Google Play Developer API callouts to the given base URL, instead
+    /// of 'https://androidpublisher.googleapis.com'.
+    ///
+    /// NOTE: The OAuth token endpoint is taken from the service account key
+    /// ('token_uri'), and is not affected.
+    pub fn google_play_api_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.config.google_play_base_url = trim_base_url(base_url.into());
+        self
+    }
+
+    /// Fetch the keys used to verify Google OIDC tokens (Pub/Sub push
+    /// requests) from the given URL, instead of
+    /// 'https://www.googleapis.com/oauth2/v3/certs'.
+    ///
+    /// Only used by the default (native) signature verifier.
+    #[cfg(feature = "native")]
+    pub fn google_jwk_url(mut self, url: impl Into<String>) -> Self {
+        self.config.google_jwk_url = url.into();
+        self
+    }
+
+    /// How long fetched Google keys are cached for (default: 5 minutes). See
+    /// also 'IapUtil::refresh_google_keys'.
+    ///
+    /// Only used by the default (native) signature verifier.
+    #[cfg(feature = "native")]
+    pub fn google_jwks_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.config.google_jwks_cache_ttl = ttl;
+        self
+    }
+
+    /// Verify Google OIDC tokens against the given JWK set (JSON, in the
+    /// format served by the JWK URL), instead of fetching Google's keys. For
+    /// tests without network access.
+    ///
+    /// Only used by the default (native) signature verifier.
+    #[cfg(feature = "native")]
+    pub fn google_jwks(mut self, jwks_json: impl Into<String>) -> Self {
+        self.config.google_jwks = Some(jwks_json.into());
+        self
+    }
+
+    /// Look up App Store prices through the App Store Connect API, with an API
+    /// key generated in App Store Connect (Users and Access > Integrations >
+    /// App Store Connect API). The key needs at least the 'App Manager' or
+    /// 'Finance' role:
+    /// https://developer.apple.com/documentation/appstoreconnectapi/creating-api-keys-for-app-store-connect-api
+    ///
+    /// Used by 'IapUtil::get_apple_price_schedule', and to fill in price info
+    /// for App Store transactions which do not carry a price.
+    ///
+    /// app_apple_id:
+    ///   Apple ID of the app (ex. '1234567890'), as shown in App Store Connect
+    ///   (App Information).
+    #[cfg(feature = "app-store-connect")]
+    pub fn app_store_connect_credentials(
+        mut self,
+        api_key: &str,
+        key_id: &str,
+        issuer_id: &str,
+        app_apple_id: &str,
+    ) -> Self {
+        self.config.app_store_connect_credentials = Some(AppStoreConnectCredentials {
+            api_key: SecretString::new(api_key),
+            key_id: key_id.to_owned(),
+            issuer_id: issuer_id.to_owned(),
+            app_apple_id: app_apple_id.to_owned(),
+        });
+        self
+    }
+
+    /// Send App Store Connect API callouts to the given base URL, instead of
+    /// 'https://api.appstoreconnect.apple.com'.
+    #[cfg(feature = "app-store-connect")]
+    pub fn app_store_connect_api_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.config.app_store_connect_base_url = trim_base_url(base_url.into());
+        self
+    }
+
+    /// Verify Microsoft Store purchases ('IapPurchaseId::MicrosoftStoreIdKey')
+    /// through the collections API, authenticating as the given Azure AD
+    /// application. The application must be associated with the app in
+    /// Partner Center:
+    /// https://learn.microsoft.com/en-us/windows/uwp/monetize/view-and-grant-products-from-a-service#step-1
+    ///
+    /// Product IDs are the products' Store IDs (ex. '9NBLGGH4R315'). Price
+    /// info is not available for Microsoft Store purchases.
+    #[cfg(feature = "microsoft-store")]
+    pub fn microsoft_store_credentials(
+        mut self,
+        tenant_id: &str,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Self {
+        self.config.microsoft_store_credentials = Some(MicrosoftStoreCredentials {
+            tenant_id: tenant_id.to_owned(),
+            client_id: client_id.to_owned(),
+            client_secret: SecretString::new(client_secret),
+        });
+        self
+    }
+
+    /// Send Microsoft Store collections API and Azure AD token callouts to
+    /// the given base URLs, instead of 'https://collections.mp.microsoft.com'
+    /// and 'https://login.microsoftonline.com'.
+    #[cfg(feature = "microsoft-store")]
+    pub fn microsoft_store_api_base_urls(
+        mut self,
+        collections: impl Into<String>,
+        login: impl Into<String>,
+    ) -> Self {
+        self.config.microsoft_store_collections_base_url = trim_base_url(collections.into());
+        self.config.microsoft_login_base_url = trim_base_url(login.into());
+        self
+    }
+
+    /// Verify Steam microtransactions ('IapPurchaseId::SteamOrderId') through
+    /// the ISteamMicroTxn Web API, for the given app, authenticating with a
+    /// publisher Web API key.
+    ///
+    /// Product IDs are the developer-defined item IDs. Steam orders can be
+    /// verified as consumables or non-consumables, but not as subscriptions.
+    #[cfg(feature = "steam")]
+    pub fn steam_credentials(mut self, app_id: u32, publisher_key: &str) -> Self {
+        self.config.steam_credentials = Some(SteamCredentials {
+            app_id,
+            publisher_key: SecretString::new(publisher_key),
+        });
+        self
+    }
+
+    /// Look up Steam orders through the sandbox interface
+    /// (ISteamMicroTxnSandbox), for orders placed in sandbox mode. Disabled by
+    /// default.
+    #[cfg(feature = "steam")]
+    pub fn steam_sandbox(mut self, enabled: bool) -> Self {
+        self.config.steam_sandbox = enabled;
+        self
+    }
+
+    /// Send Steam Web API callouts to the given base URL, instead of
+    /// 'https://partner.steam-api.com'.
+    #[cfg(feature = "steam")]
+    pub fn steam_api_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.config.steam_base_url = trim_base_url(base_url.into());
+        self
+    }
+
+    /// Verify Paddle Billing webhook notifications (see
+    /// 'IapUtil::parse_paddle_notification') with the secret key of the
+    /// notification destination, as shown in the Paddle dashboard.
+    #[cfg(feature = "paddle")]
+    pub fn paddle_webhook_secret(mut self, secret: &str) -> Self {
+        self.config.paddle_webhook_secret = Some(SecretString::new(secret));
+        self
+    }
+
+    /// Report subscriptions from Paddle notifications as sandbox purchases,
+    /// for a notification destination in the Paddle sandbox environment.
+    /// Disabled by default.
+    #[cfg(feature = "paddle")]
+    pub fn paddle_sandbox(mut self, enabled: bool) -> Self {
+        self.config.paddle_sandbox = enabled;
+        self
+    }
+
+    /// Verify Stripe webhook events (see 'IapUtil::parse_stripe_notification')
+    /// with the signing secret of the webhook endpoint ('whsec_...'), as shown
+    /// in the Stripe dashboard.
+    #[cfg(feature = "stripe")]
+    pub fn stripe_webhook_secret(mut self, secret: &str) -> Self {
+        self.config.stripe_webhook_secret = Some(SecretString::new(secret));
+        self
+    }
+
+    /// Use custom signature verification primitives (ex. backed by WebCrypto),
+    /// instead of the default OpenSSL-based implementation.
+    ///
+    /// Required when the 'native' feature is disabled (ex. for WASM / edge
+    /// runtimes).
+    pub fn signature_verifier(mut self, verifier: impl SignatureVerifier + 'static) -> Self {
+        self.config.signature_verifier = Some(Arc::new(verifier));
+        self
+    }
+
+    /// Answer platform API callouts with the given transport instead of the
+    /// network.
+    #[cfg(feature = "store-simulator")]
+    pub(crate) fn transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.config.transport = Some(transport);
+        self
+    }
+
+    /// Check the application ID and credentials for common mistakes (ex. a
+    /// '.p8' key missing its PEM header, escaped newlines, a Key ID in place
+    /// of the Issuer ID), without any callouts. Errors name the offending
+    /// value and what is wrong with it.
+    ///
+    /// Run by 'build', unless credentials are prepared lazily (see
+    /// 'lazy_credentials').
+    pub fn validate(&self) -> Result<(), ServerError> {
+        #[cfg(feature = "gcp-secrets")]
+        if self.gcp_secret.is_some() {
+            return validate_application_id(&self.application_id);
+        }
+        validate_config(
+            &self.application_id,
+            &self.apple_api_key,
+            &self.apple_key_id,
+            &self.apple_issuer_id,
+            &self.google_api_key,
+        )
+    }
+
+    #[cfg_attr(not(feature = "gcp-secrets"), allow(unused_mut))]
+    pub async fn build(mut self) -> Result<IapUtil, ServerError> {
+        #[cfg(feature = "gcp-secrets")]
+        if let Some(name) = self.gcp_secret.take() {
+            let client = HttpClient::build_client(&self.config)?;
+            let credentials = IapCredentials::from_gcp_secret_manager(&name, &client).await?;
+            self.apple_api_key = credentials.apple_api_key;
+            self.apple_key_id = credentials.apple_key_id;
+            self.apple_issuer_id = credentials.apple_issuer_id;
+            self.google_api_key = credentials.google_api_key;
+        }
+        if !self.config.lazy_credentials {
+            self.validate()?;
+        }
+        Ok(IapUtil {
+            iap_repository: Arc::new(
+                IapRepositoryImpl::new(
+                    self.application_id,
+                    self.apple_expected_auds,
+                    self.google_expected_auds,
+                    &self.apple_api_key,
+                    &self.apple_key_id,
+                    &self.apple_issuer_id,
+                    &self.google_api_key,
+                    self.config,
+                )
+                .await?,
+            ),
+        })
+    }
+}
+
+fn env_var(prefix: &str, name: &str) -> Result<String, ServerError> {
+    let name = format!("{prefix}{name}");
+    std::env::var(&name).map_err(|e| match e {
+        std::env::VarError::NotPresent => EnvVarMissing::new(&name),
+        std::env::VarError::NotUnicode(_) => EnvVarInvalid::new(&name, "not valid unicode"),
+    })
+}
+
+fn trim_base_url(base_url: String) -> String {
+    base_url.trim_end_matches('/').to_owned()
 }
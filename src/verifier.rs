@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use fractic_server_error::ServerError;
+
+/// Platform-specific cryptographic primitives used to verify signed data
+/// received from Apple and Google.
+///
+/// With the default 'native' feature, an OpenSSL-based implementation is used
+/// automatically. On targets where that is not available (ex. WASM / edge
+/// runtimes), disable the 'native' feature and provide an implementation
+/// through 'IapUtilBuilder::signature_verifier' (ex. backed by WebCrypto).
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait SignatureVerifier: Send + Sync {
+    /// Validate the x5c certificate chain of an Apple-signed JWS against
+    /// Apple's root certificates (see the 'res/trust' directory of this
+    /// crate).
+    ///
+    /// 'x5c_chain' contains the DER-encoded certificates, leaf first. Returns
+    /// the leaf certificate's public key, PEM-encoded (SubjectPublicKeyInfo),
+    /// which is then used to verify the JWS signature.
+    async fn verify_apple_certificate_chain(
+        &self,
+        x5c_chain: &[Vec<u8>],
+    ) -> Result<Vec<u8>, ServerError>;
+
+    /// Verify the signature and expiry of a Google-signed OIDC token (sent
+    /// with Google Cloud Pub/Sub push messages), using Google's published
+    /// keys (https://www.googleapis.com/oauth2/v3/certs).
+    ///
+    /// Returns the token's audience(s), which are checked by the caller.
+    async fn verify_google_token(&self, token: &str) -> Result<Vec<String>, ServerError>;
+
+    /// Discard any cached Google keys, so that they are fetched again for the
+    /// next verification (ex. after Google rotated its keys before the cache
+    /// expired). Does nothing by default.
+    async fn refresh_google_keys(&self) -> Result<(), ServerError> {
+        Ok(())
+    }
+}
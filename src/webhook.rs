@@ -0,0 +1,551 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fractic_server_error::ServerError;
+use web_time::Instant;
+
+#[cfg(feature = "paddle")]
+use crate::errors::InvalidPaddleSignature;
+#[cfg(feature = "stripe")]
+use crate::errors::InvalidStripeSignature;
+use crate::{
+    archive::{ArchiveSink, ArchivedNotification},
+    capture::{collect_payloads, CapturedPayload, CapturedPayloadKind},
+    domain::entities::{
+        iap_notification_history::AppleNotificationHistoryEntry,
+        iap_update_notification::IapUpdateNotification,
+    },
+    error_observer::{IapError, IapPlatform},
+    errors::{InvalidGoogleSignature, NotificationAuditUnavailable, NotificationInFlight},
+    reporting::ProcessingReporter,
+    util::IapUtil,
+};
+
+/// Header carrying the signature of Paddle Billing notifications.
+#[cfg(feature = "paddle")]
+pub const PADDLE_SIGNATURE_HEADER: &str = "Paddle-Signature";
+
+/// Header carrying the signature of Stripe webhook events.
+#[cfg(feature = "stripe")]
+pub const STRIPE_SIGNATURE_HEADER: &str = "Stripe-Signature";
+
+/// Application logic run for each verified notification.
+///
+/// Implemented for any async closure taking an 'IapUpdateNotification'. If
+/// the callback fails, the notification is reported as a retryable failure,
+/// so that the store redelivers it.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait NotificationCallback: Send + Sync {
+    async fn handle(&self, notification: IapUpdateNotification) -> Result<(), ServerError>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<F, Fut> NotificationCallback for F
+where
+    F: Fn(IapUpdateNotification) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<(), ServerError>> + Send,
+{
+    async fn handle(&self, notification: IapUpdateNotification) -> Result<(), ServerError> {
+        self(notification).await
+    }
+}
+
+/// Records which notifications have been handled, so that redeliveries can be
+/// skipped by 'WebhookHandler'.
+///
+/// The default store keeps notification IDs in memory. Implement this trait
+/// (or use an adapter from 'integrations') to share the record across
+/// instances.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait NotificationDedupeStore: Send + Sync {
+    /// Atomically claim the notification for processing. Must only return
+    /// 'DedupeState::New' to a single caller, until 'finish' is called with
+    /// 'success = false' (or the claim is otherwise released).
+    async fn begin(&self, notification_id: &str) -> Result<DedupeState, ServerError>;
+
+    /// Record the outcome of processing a claimed notification. Successfully
+    /// handled notifications should be remembered (for the store's dedupe
+    /// window), and failed ones released so that redeliveries are processed.
+    async fn finish(&self, notification_id: &str, success: bool) -> Result<(), ServerError>;
+
+    /// Current state of the notification, without claiming it: 'InFlight' or
+    /// 'Completed', or None if it is not recorded (never claimed, released,
+    /// or forgotten after the dedupe window). Used by
+    /// 'WebhookHandler::audit_missed_notifications'.
+    async fn state(&self, notification_id: &str) -> Result<Option<DedupeState>, ServerError>;
+}
+
+/// State of a notification in a 'NotificationDedupeStore'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupeState {
+    /// Not seen before (or seen, but not handled successfully); now claimed
+    /// by the caller.
+    New,
+    /// Currently being processed by another delivery.
+    InFlight,
+    /// Already handled successfully.
+    Completed,
+}
+
+/// A notification Apple sent, but which was never handled successfully (see
+/// 'WebhookHandler::audit_missed_notifications').
+#[derive(Debug, Clone)]
+pub struct MissedNotification {
+    pub entry: AppleNotificationHistoryEntry,
+    /// 'InFlight' if a delivery was claimed but never finished (ex. the
+    /// instance crashed while processing it), None if it was never recorded
+    /// (not delivered, rejected, or its callback failed).
+    pub state: Option<DedupeState>,
+}
+
+/// Result of handling a webhook request.
+#[derive(Debug)]
+pub enum WebhookOutcome {
+    /// The notification was verified and handled by the callback.
+    Processed,
+    /// The notification was already handled recently, and was skipped.
+    Duplicate,
+    /// The request can never succeed (ex. invalid signature, malformed body,
+    /// or the platform API reports that the purchase does not exist), so
+    /// redelivering it is pointless. Acknowledged like processed
+    /// notifications (see 'response').
+    PermanentFailure(ServerError),
+    /// The request failed, but may succeed if redelivered (ex. a platform API
+    /// callout failed transiently, or the callback failed).
+    RetryableFailure(ServerError),
+}
+
+impl WebhookOutcome {
+    /// HTTP status code to respond with, as in 'response' (which the
+    /// framework integrations also answer with). The stores redeliver on any
+    /// non-2xx status, so only retryable failures are answered with an error
+    /// status; permanent failures are acknowledged, and should be told apart
+    /// through logs instead.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            WebhookOutcome::Processed
+            | WebhookOutcome::Duplicate
+            | WebhookOutcome::PermanentFailure(_) => 200,
+            WebhookOutcome::RetryableFailure(_) => 500,
+        }
+    }
+
+    /// Response to send to the platform the notification was received from.
+    ///
+    /// All platforms redeliver notifications until they receive a 2xx
+    /// response (a Pub/Sub push 'ack'; any other status is a 'nack'). Only
+    /// retryable failures are answered with a 5xx status to request
+    /// redelivery. Permanent failures are acknowledged, since redelivering
+    /// them would fail the same way; they should be logged instead, and
+    /// notifications rejected due to misconfiguration (ex. a wrong expected
+    /// audience) recovered after fixing it (ex. with
+    /// 'WebhookHandler::audit_missed_notifications').
+    ///
+    /// Retryable failures should not be retried within the request: the
+    /// platform redelivers them with a backoff (see
+    /// 'WebhookResponse::redelivery_window').
+    pub fn response(&self, platform: IapPlatform) -> WebhookResponse {
+        let redelivered = matches!(self, WebhookOutcome::RetryableFailure(_));
+        WebhookResponse {
+            status_code: self.status_code(),
+            redelivered,
+            redelivery_window: redelivered.then(|| redelivery_window(platform)).flatten(),
+        }
+    }
+}
+
+/// HTTP response to a webhook request, as recommended by
+/// 'WebhookOutcome::response'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebhookResponse {
+    pub status_code: u16,
+    /// Whether the platform will redeliver the notification.
+    pub redelivered: bool,
+    /// Approximately how long the platform keeps redelivering the
+    /// notification (with increasing delays) before giving up, with the
+    /// platform's default settings. None if unknown.
+    pub redelivery_window: Option<Duration>,
+}
+
+fn redelivery_window(platform: IapPlatform) -> Option<Duration> {
+    const HOUR: u64 = 60 * 60;
+    match platform {
+        // Retried 5 times, 1, 12, 24, 48 and 72 hours after the previous
+        // attempt.
+        IapPlatform::AppStore => Some(Duration::from_secs(157 * HOUR)),
+        // Retried until the message expires (subscription's message
+        // retention duration, 7 days by default).
+        IapPlatform::GooglePlay => Some(Duration::from_secs(7 * 24 * HOUR)),
+        #[cfg(feature = "microsoft-store")]
+        IapPlatform::MicrosoftStore => None,
+        #[cfg(feature = "steam")]
+        IapPlatform::Steam => None,
+        #[cfg(feature = "paddle")]
+        IapPlatform::Paddle => Some(Duration::from_secs(3 * 24 * HOUR)),
+        #[cfg(feature = "stripe")]
+        IapPlatform::Stripe => Some(Duration::from_secs(3 * 24 * HOUR)),
+    }
+}
+
+/// Framework-agnostic webhook handling: verifies and parses notifications,
+/// skips duplicates, and passes new notifications to a callback.
+///
+/// Used by the framework integrations in 'integrations', but can also be
+/// called directly from any other HTTP framework.
+pub struct WebhookHandler {
+    iap_util: IapUtil,
+    callback: Arc<dyn NotificationCallback>,
+    dedupe: Option<Arc<dyn NotificationDedupeStore>>,
+    archive: Option<Arc<dyn ArchiveSink>>,
+    reporter: Option<Arc<ProcessingReporter>>,
+}
+
+impl WebhookHandler {
+    /// Duplicates are detected for 24 hours by default (see
+    /// 'dedupe_window').
+    pub fn new(iap_util: IapUtil, callback: impl NotificationCallback + 'static) -> Self {
+        Self {
+            iap_util,
+            callback: Arc::new(callback),
+            dedupe: Some(Arc::new(RecentNotifications::new(Duration::from_secs(
+                24 * 60 * 60,
+            )))),
+            archive: None,
+            reporter: None,
+        }
+    }
+
+    /// How long notification IDs are remembered to detect redeliveries.
+    /// Claims of notifications which are still being processed expire after
+    /// 5 minutes, so that a failed callback does not block redeliveries.
+    ///
+    /// NOTE: IDs are only remembered in memory, so this does not detect
+    /// duplicates delivered to different instances (see 'dedupe_store').
+    pub fn dedupe_window(mut self, window: Duration) -> Self {
+        self.dedupe = Some(Arc::new(RecentNotifications::new(window)));
+        self
+    }
+
+    /// Record handled notifications in the given store instead of in memory,
+    /// ex. to detect duplicates delivered to different instances.
+    pub fn dedupe_store(mut self, store: impl NotificationDedupeStore + 'static) -> Self {
+        self.dedupe = Some(Arc::new(store));
+        self
+    }
+
+    /// Pass every delivery to the callback, including redeliveries.
+    pub fn without_dedupe(mut self) -> Self {
+        self.dedupe = None;
+        self
+    }
+
+    /// Pass the original body (and decoded payloads) of every new verified
+    /// notification to the given sink before running the callback, ex. to
+    /// retain signed originals for compliance (see 'ArchiveSink').
+    pub fn archive_sink(mut self, sink: impl ArchiveSink + 'static) -> Self {
+        self.archive = Some(Arc::new(sink));
+        self
+    }
+
+    /// Record the outcome of every request in the given reporter, which
+    /// periodically flushes a summary (see 'ProcessingReporter'). The reporter
+    /// is shared, so that it can also be flushed (or filled) by the caller.
+    pub fn processing_reporter(mut self, reporter: Arc<ProcessingReporter>) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// Compare the App Store notification history of the given period with
+    /// the notifications recorded in the dedupe store, and return those
+    /// Apple sent but which were never handled successfully, for reliability
+    /// audits. Missed notifications can be replayed with 'handle_apple'
+    /// (see 'AppleNotificationHistoryEntry::notification_body').
+    ///
+    /// NOTE: Only notifications still remembered by the store (see
+    /// 'dedupe_window') are recognized, so the period should not extend
+    /// further back than the window.
+    pub async fn audit_missed_notifications(
+        &self,
+        range: Range<DateTime<Utc>>,
+        sandbox: bool,
+    ) -> Result<Vec<MissedNotification>, IapError> {
+        let Some(dedupe) = &self.dedupe else {
+            return Err(NotificationAuditUnavailable::new().into());
+        };
+        let history = self
+            .iap_util
+            .get_apple_notification_history(range, sandbox)
+            .await?;
+        let mut missed = Vec::new();
+        for entry in history {
+            let state = dedupe.state(&entry.notification_id).await?;
+            if state != Some(DedupeState::Completed) {
+                missed.push(MissedNotification { entry, state });
+            }
+        }
+        Ok(missed)
+    }
+
+    /// Handle the raw POST body of an App Store Server Notification.
+    pub async fn handle_apple(&self, body: &str) -> WebhookOutcome {
+        let (result, payloads) = self
+            .parse(self.iap_util.parse_apple_notification(body))
+            .await;
+        self.dispatch(IapPlatform::AppStore, body, result, payloads)
+            .await
+    }
+
+    /// Handle a Google Cloud Pub/Sub push request carrying an RTDN
+    /// notification.
+    pub async fn handle_google(
+        &self,
+        authorization_header: Option<&str>,
+        body: &str,
+    ) -> WebhookOutcome {
+        let Some(authorization_header) = authorization_header else {
+            let outcome = WebhookOutcome::PermanentFailure(InvalidGoogleSignature::new(
+                "missing authorization header",
+            ));
+            return self.reported(None, outcome).await;
+        };
+        let (result, payloads) = self
+            .parse(
+                self.iap_util
+                    .parse_google_notification(authorization_header, body),
+            )
+            .await;
+        self.dispatch(IapPlatform::GooglePlay, body, result, payloads)
+            .await
+    }
+
+    /// Handle a Paddle Billing webhook notification, given the value of its
+    /// signature header (see 'PADDLE_SIGNATURE_HEADER').
+    #[cfg(feature = "paddle")]
+    pub async fn handle_paddle(
+        &self,
+        signature_header: Option<&str>,
+        body: &str,
+    ) -> WebhookOutcome {
+        let Some(signature_header) = signature_header else {
+            let outcome = WebhookOutcome::PermanentFailure(InvalidPaddleSignature::new(
+                "missing signature header",
+            ));
+            return self.reported(None, outcome).await;
+        };
+        let (result, payloads) = self
+            .parse(
+                self.iap_util
+                    .parse_paddle_notification(signature_header, body),
+            )
+            .await;
+        self.dispatch(IapPlatform::Paddle, body, result, payloads)
+            .await
+    }
+
+    /// Handle a Stripe webhook event, given the value of its signature header
+    /// (see 'STRIPE_SIGNATURE_HEADER').
+    #[cfg(feature = "stripe")]
+    pub async fn handle_stripe(
+        &self,
+        signature_header: Option<&str>,
+        body: &str,
+    ) -> WebhookOutcome {
+        let Some(signature_header) = signature_header else {
+            let outcome = WebhookOutcome::PermanentFailure(InvalidStripeSignature::new(
+                "missing signature header",
+            ));
+            return self.reported(None, outcome).await;
+        };
+        let (result, payloads) = self
+            .parse(
+                self.iap_util
+                    .parse_stripe_notification(signature_header, body),
+            )
+            .await;
+        self.dispatch(IapPlatform::Stripe, body, result, payloads)
+            .await
+    }
+
+    /// Run the parsing future, collecting the decoded payloads if they are
+    /// archived.
+    async fn parse(
+        &self,
+        future: impl Future<Output = Result<IapUpdateNotification, IapError>>,
+    ) -> (
+        Result<IapUpdateNotification, IapError>,
+        Vec<CapturedPayload>,
+    ) {
+        match self.archive {
+            Some(_) => collect_payloads(future).await,
+            None => (future.await, Vec::new()),
+        }
+    }
+
+    async fn dispatch(
+        &self,
+        platform: IapPlatform,
+        body: &str,
+        result: Result<IapUpdateNotification, IapError>,
+        payloads: Vec<CapturedPayload>,
+    ) -> WebhookOutcome {
+        let notification_type = result
+            .as_ref()
+            .ok()
+            .map(|notification| notification.details.type_name());
+        let outcome = self.process(platform, body, result, payloads).await;
+        self.reported(notification_type, outcome).await
+    }
+
+    /// Record the outcome in the reporter, if any. Failures to flush the
+    /// report do not affect the outcome.
+    async fn reported(
+        &self,
+        notification_type: Option<&str>,
+        outcome: WebhookOutcome,
+    ) -> WebhookOutcome {
+        if let Some(reporter) = &self.reporter {
+            let _ = reporter.record(notification_type, &outcome).await;
+        }
+        outcome
+    }
+
+    async fn process(
+        &self,
+        platform: IapPlatform,
+        body: &str,
+        result: Result<IapUpdateNotification, IapError>,
+        payloads: Vec<CapturedPayload>,
+    ) -> WebhookOutcome {
+        let notification = match result {
+            Ok(notification) => notification,
+            Err(e) if e.is_transient() => return WebhookOutcome::RetryableFailure(e.into_error()),
+            Err(e) => return WebhookOutcome::PermanentFailure(e.into_error()),
+        };
+        let notification_id = notification.notification_id.clone();
+        if let Some(dedupe) = &self.dedupe {
+            match dedupe.begin(&notification_id).await {
+                Ok(DedupeState::New) => {}
+                Ok(DedupeState::Completed) => return WebhookOutcome::Duplicate,
+                // Ask the store to redeliver later. If the in-flight delivery
+                // fails, the redelivery will then be processed.
+                Ok(DedupeState::InFlight) => {
+                    return WebhookOutcome::RetryableFailure(NotificationInFlight::new(
+                        &notification_id,
+                    ))
+                }
+                Err(e) => return WebhookOutcome::RetryableFailure(e),
+            }
+        }
+        if let Some(archive) = &self.archive {
+            let archived = ArchivedNotification {
+                platform,
+                notification_id: notification_id.clone(),
+                received_at: Utc::now(),
+                body: body.to_owned(),
+                decoded_payloads: payloads
+                    .into_iter()
+                    .filter(|payload| payload.kind != CapturedPayloadKind::ApiResponse)
+                    .collect(),
+            };
+            if let Err(e) = archive.archive(&archived).await {
+                // Released, so that the redelivery is archived and processed.
+                if let Some(dedupe) = &self.dedupe {
+                    let _ = dedupe.finish(&notification_id, false).await;
+                }
+                return WebhookOutcome::RetryableFailure(e);
+            }
+        }
+        let result = self.callback.handle(notification).await;
+        if let Some(dedupe) = &self.dedupe {
+            // The callback's outcome takes precedence. If it could not be
+            // recorded, the notification may be processed again on
+            // redelivery.
+            let _ = dedupe.finish(&notification_id, result.is_ok()).await;
+        }
+        match result {
+            Ok(()) => WebhookOutcome::Processed,
+            Err(e) => WebhookOutcome::RetryableFailure(e),
+        }
+    }
+}
+
+/// In-memory record of recently seen notification IDs.
+struct RecentNotifications {
+    window: Duration,
+    in_flight_timeout: Duration,
+    entries: Mutex<HashMap<String, (Instant, bool)>>,
+}
+
+impl RecentNotifications {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            in_flight_timeout: Duration::from_secs(5 * 60),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Entries are replaced as a whole, so they stay consistent even if a
+    /// holder of the lock panicked, and poisoning is ignored.
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, (Instant, bool)>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Whether the entry is still remembered: completed notifications for
+    /// the dedupe window, claims until they time out.
+    fn is_live(&self, (seen_at, completed): &(Instant, bool)) -> bool {
+        let ttl = match completed {
+            true => self.window,
+            false => self.in_flight_timeout,
+        };
+        seen_at.elapsed() < ttl
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl NotificationDedupeStore for RecentNotifications {
+    async fn begin(&self, notification_id: &str) -> Result<DedupeState, ServerError> {
+        let mut entries = self.entries();
+        entries.retain(|_, entry| self.is_live(entry));
+        Ok(match entries.get(notification_id) {
+            Some((_, true)) => DedupeState::Completed,
+            Some((_, false)) => DedupeState::InFlight,
+            None => {
+                entries.insert(notification_id.to_owned(), (Instant::now(), false));
+                DedupeState::New
+            }
+        })
+    }
+
+    async fn finish(&self, notification_id: &str, success: bool) -> Result<(), ServerError> {
+        let mut entries = self.entries();
+        if success {
+            entries.insert(notification_id.to_owned(), (Instant::now(), true));
+        } else {
+            entries.remove(notification_id);
+        }
+        Ok(())
+    }
+
+    async fn state(&self, notification_id: &str) -> Result<Option<DedupeState>, ServerError> {
+        Ok(self
+            .entries()
+            .get(notification_id)
+            .filter(|entry| self.is_live(entry))
+            .map(|(_, completed)| match completed {
+                true => DedupeState::Completed,
+                false => DedupeState::InFlight,
+            }))
+    }
+}